@@ -129,13 +129,13 @@ variable "performance_insights_enabled_retention" {
 
 variable "backup_retention_period" {
   description = "Backup retention period"
-  default = 14
+  default = {% if backup_retention_days %}{{ backup_retention_days }}{% else %}14{% endif %}
   type = number
 }
 
 variable "preferred_backup_window" {
   description = "Maintenance window"
-  default = "00:00-01:00"
+  default = "{% if backup_window %}{{ backup_window }}{% else %}00:00-01:00{% endif %}"
   type = string
 }
 
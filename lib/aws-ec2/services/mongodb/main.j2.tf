@@ -55,7 +55,7 @@ resource "aws_docdb_cluster" "documentdb_cluster" {
     delete = "60m"
   }
 
-  master_password = var.password
+  master_password = local.database_password
   {%- if snapshot is defined and snapshot["snapshot_id"] %}
   # Snapshot
   snapshot_identifier = var.snapshot_identifier
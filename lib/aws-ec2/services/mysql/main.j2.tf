@@ -70,7 +70,7 @@ resource "aws_db_instance" "mysql_instance" {
     update = "120m"
     delete = "60m"
   }
-  password = var.password
+  password = local.database_password
   db_name = var.database_name
   parameter_group_name = aws_db_parameter_group.mysql_parameter_group.name
   storage_encrypted = var.encrypt_disk
@@ -45,7 +45,7 @@ resource "aws_db_instance" "postgresql_instance" {
     update = "120m"
     delete = "60m"
   }
-  password = var.password
+  password = local.database_password
   storage_encrypted = var.encrypt_disk
   {%- if snapshot and snapshot["snapshot_id"] %}
   # Snapshot
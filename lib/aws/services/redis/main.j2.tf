@@ -93,7 +93,7 @@ resource "aws_elasticache_replication_group" "elasticache_cluster" {
 
   # Elasticache auth
   transit_encryption_enabled = true
-  auth_token = var.password
+  auth_token = local.database_password
 
   # Network
   # WARNING: this value can't get fetch from data sources and is linked to the bootstrap phase
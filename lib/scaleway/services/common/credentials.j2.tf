@@ -0,0 +1,24 @@
+{%- if database_generate_credentials %}
+# The password is generated at creation, the engine reads it back from this file to expose it to applications
+resource "random_password" "database_password" {
+  length = 32
+  special = false
+}
+
+resource "local_sensitive_file" "database_credentials" {
+  filename = "database-credentials.json"
+  content = jsonencode({
+    "database_login" = "{{ database_login }}",
+    "database_password" = random_password.database_password.result
+  })
+  file_permission = "0600"
+}
+{%- endif %}
+
+locals {
+  {%- if database_generate_credentials %}
+  database_password = random_password.database_password.result
+  {%- else %}
+  database_password = var.password
+  {%- endif %}
+}
@@ -8,6 +8,10 @@ terraform {
       source = "hashicorp/local"
       version = "2.2.3"
     }
+    random = {
+      source = "hashicorp/random"
+      version = "3.4.3"
+    }
     time = {
       source  = "hashicorp/time"
       version = "0.9.0"
@@ -49,8 +49,10 @@ resource "scaleway_rdb_instance" "mysql_instance" {
   is_ha_cluster     = var.activate_high_availability
   disable_backup    = !var.activate_backups
 
+  backup_schedule_retention = var.backup_retention_period
+
   user_name         = var.username
-  password          = var.password
+  password          = local.database_password
 
   region            = var.region
 
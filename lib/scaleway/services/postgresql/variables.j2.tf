@@ -154,7 +154,7 @@ variable "activate_backups" {
 
 variable "backup_retention_period" {
   description = "Backup rentention period"
-  default = 7
+  default = {% if backup_retention_days %}{{ backup_retention_days }}{% else %}7{% endif %}
   type = number
 }
 
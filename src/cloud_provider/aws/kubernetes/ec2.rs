@@ -27,6 +27,7 @@ use uuid::Uuid;
 pub struct EC2 {
     context: Context,
     id: String,
+    kube_context_name: String,
     long_id: Uuid,
     name: String,
     version: String,
@@ -82,6 +83,7 @@ impl EC2 {
         Ok(EC2 {
             context,
             id: id.to_string(),
+            kube_context_name: "default".to_string(),
             long_id,
             name: name.to_string(),
             version: version.to_string(),
@@ -135,6 +137,11 @@ impl Kubernetes for EC2 {
         self.id.as_str()
     }
 
+    // context name of the kubeconfig generated by k3s
+    fn kube_context_name(&self) -> &str {
+        self.kube_context_name.as_str()
+    }
+
     fn long_id(&self) -> &Uuid {
         &self.long_id
     }
@@ -159,6 +166,11 @@ impl Kubernetes for EC2 {
         Some(self.zones.clone())
     }
 
+    // the whole cluster runs on a single instance
+    fn zones_count(&self) -> usize {
+        1
+    }
+
     fn cloud_provider(&self) -> &dyn CloudProvider {
         (*self.cloud_provider).borrow()
     }
@@ -194,7 +206,7 @@ impl Kubernetes for EC2 {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Create, || {
+        send_progress_on_long_task(self, Action::Create, self.context().cancel_requested(), |_| {
             kubernetes::create(
                 self,
                 self.long_id,
@@ -217,7 +229,9 @@ impl Kubernetes for EC2 {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Create, || kubernetes::create_error(self))
+        send_progress_on_long_task(self, Action::Create, self.context().cancel_requested(), |_| {
+            kubernetes::create_error(self)
+        })
     }
 
     fn upgrade_with_status(&self, _kubernetes_upgrade_status: KubernetesUpgradeStatus) -> Result<(), Box<EngineError>> {
@@ -236,7 +250,7 @@ impl Kubernetes for EC2 {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Create, || self.upgrade())
+        send_progress_on_long_task(self, Action::Create, self.context().cancel_requested(), |_| self.upgrade())
     }
 
     #[named]
@@ -250,7 +264,9 @@ impl Kubernetes for EC2 {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Create, || kubernetes::upgrade_error(self))
+        send_progress_on_long_task(self, Action::Create, self.context().cancel_requested(), |_| {
+            kubernetes::upgrade_error(self)
+        })
     }
 
     #[named]
@@ -264,7 +280,7 @@ impl Kubernetes for EC2 {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Pause, || {
+        send_progress_on_long_task(self, Action::Pause, self.context().cancel_requested(), |_| {
             kubernetes::pause(self, self.template_directory.as_str(), &self.zones, &[], &self.options)
         })
     }
@@ -280,7 +296,9 @@ impl Kubernetes for EC2 {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Pause, || kubernetes::pause_error(self))
+        send_progress_on_long_task(self, Action::Pause, self.context().cancel_requested(), |_| {
+            kubernetes::pause_error(self)
+        })
     }
 
     #[named]
@@ -294,7 +312,7 @@ impl Kubernetes for EC2 {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Delete, || {
+        send_progress_on_long_task(self, Action::Delete, self.context().cancel_requested(), |_| {
             kubernetes::delete(
                 self,
                 self.template_directory.as_str(),
@@ -316,7 +334,9 @@ impl Kubernetes for EC2 {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Delete, || kubernetes::delete_error(self))
+        send_progress_on_long_task(self, Action::Delete, self.context().cancel_requested(), |_| {
+            kubernetes::delete_error(self)
+        })
     }
 
     fn advanced_settings(&self) -> &ClusterAdvancedSettings {
@@ -43,6 +43,7 @@ use super::{get_rusoto_eks_client, should_update_desired_nodes};
 pub struct EKS {
     context: Context,
     id: String,
+    kube_context_name: String,
     long_id: Uuid,
     name: String,
     version: String,
@@ -91,6 +92,7 @@ impl EKS {
         Ok(EKS {
             context,
             id: id.to_string(),
+            kube_context_name: format!("aws_{}", id.replace('-', "_")),
             long_id,
             name: name.to_string(),
             version: version.to_string(),
@@ -157,7 +159,7 @@ impl EKS {
         let namespace = "kube-system";
         kubectl_exec_scale_replicas(
             kubeconfig_path,
-            self.cloud_provider().credentials_environment_variables(),
+            self.kubectl_environment_variables(),
             namespace,
             ScalingKind::Deployment,
             selector,
@@ -198,6 +200,11 @@ impl Kubernetes for EKS {
         self.id.as_str()
     }
 
+    // context name set in the kubeconfig generated by terraform
+    fn kube_context_name(&self) -> &str {
+        self.kube_context_name.as_str()
+    }
+
     fn long_id(&self) -> &Uuid {
         &self.long_id
     }
@@ -222,6 +229,10 @@ impl Kubernetes for EKS {
         Some(self.zones.clone())
     }
 
+    fn zones_count(&self) -> usize {
+        self.zones.len()
+    }
+
     fn cloud_provider(&self) -> &dyn CloudProvider {
         (*self.cloud_provider).borrow()
     }
@@ -257,7 +268,7 @@ impl Kubernetes for EKS {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Create, || {
+        send_progress_on_long_task(self, Action::Create, self.context().cancel_requested(), |_| {
             kubernetes::create(
                 self,
                 self.long_id,
@@ -280,7 +291,9 @@ impl Kubernetes for EKS {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Create, || kubernetes::create_error(self))
+        send_progress_on_long_task(self, Action::Create, self.context().cancel_requested(), |_| {
+            kubernetes::create_error(self)
+        })
     }
 
     fn upgrade_with_status(&self, kubernetes_upgrade_status: KubernetesUpgradeStatus) -> Result<(), Box<EngineError>> {
@@ -448,7 +461,7 @@ impl Kubernetes for EKS {
             None,
             None,
             Some(3),
-            self.cloud_provider().credentials_environment_variables(),
+            self.kubectl_environment_variables(),
             Infrastructure(InfrastructureStep::Upgrade),
         ) {
             self.logger().log(EngineEvent::Error(*e.clone(), None));
@@ -456,7 +469,7 @@ impl Kubernetes for EKS {
         }
 
         if let Err(e) = self.delete_completed_jobs(
-            self.cloud_provider().credentials_environment_variables(),
+            self.kubectl_environment_variables(),
             Infrastructure(InfrastructureStep::Upgrade),
         ) {
             self.logger().log(EngineEvent::Error(*e.clone(), None));
@@ -493,7 +506,7 @@ impl Kubernetes for EKS {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Create, || self.upgrade())
+        send_progress_on_long_task(self, Action::Create, self.context().cancel_requested(), |_| self.upgrade())
     }
 
     #[named]
@@ -507,7 +520,9 @@ impl Kubernetes for EKS {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Create, || kubernetes::upgrade_error(self))
+        send_progress_on_long_task(self, Action::Create, self.context().cancel_requested(), |_| {
+            kubernetes::upgrade_error(self)
+        })
     }
 
     #[named]
@@ -521,7 +536,7 @@ impl Kubernetes for EKS {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Pause, || {
+        send_progress_on_long_task(self, Action::Pause, self.context().cancel_requested(), |_| {
             kubernetes::pause(
                 self,
                 self.template_directory.as_str(),
@@ -543,7 +558,9 @@ impl Kubernetes for EKS {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Pause, || kubernetes::pause_error(self))
+        send_progress_on_long_task(self, Action::Pause, self.context().cancel_requested(), |_| {
+            kubernetes::pause_error(self)
+        })
     }
 
     #[named]
@@ -557,7 +574,7 @@ impl Kubernetes for EKS {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Delete, || {
+        send_progress_on_long_task(self, Action::Delete, self.context().cancel_requested(), |_| {
             kubernetes::delete(
                 self,
                 self.template_directory.as_str(),
@@ -579,7 +596,9 @@ impl Kubernetes for EKS {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Delete, || kubernetes::delete_error(self))
+        send_progress_on_long_task(self, Action::Delete, self.context().cancel_requested(), |_| {
+            kubernetes::delete_error(self)
+        })
     }
 
     fn advanced_settings(&self) -> &ClusterAdvancedSettings {
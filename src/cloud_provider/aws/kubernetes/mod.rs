@@ -189,6 +189,7 @@ fn event_details(
         Stage::Infrastructure(InfrastructureStep::LoadConfiguration),
         Transmitter::Kubernetes(kubernetes_id, kubernetes_name),
     )
+    .with_transaction_id(*context.transaction_id())
 }
 
 fn aws_zones(
@@ -830,7 +831,7 @@ fn create(
             Ok((path, _)) => match is_kubernetes_upgrade_required(
                 path,
                 kubernetes.version(),
-                kubernetes.cloud_provider().credentials_environment_variables(),
+                kubernetes.kubectl_environment_variables(),
                 event_details.clone(),
                 kubernetes.logger(),
             ) {
@@ -1011,6 +1012,7 @@ fn create(
                 &TcpCheckSource::DnsName(qovery_terraform_config.aws_ec2_public_hostname.as_str()),
                 port,
                 600,
+                kubernetes.listen_probe_timeout(),
                 kubernetes.logger(),
                 event_details.clone(),
             )
@@ -1083,8 +1085,7 @@ fn create(
     let kubeconfig_path = Path::new(&kubeconfig_path);
 
     let credentials_environment_variables: Vec<(String, String)> = kubernetes
-        .cloud_provider()
-        .credentials_environment_variables()
+        .kubectl_environment_variables()
         .into_iter()
         .map(|x| (x.0.to_string(), x.1.to_string()))
         .collect();
@@ -1254,7 +1255,7 @@ fn create(
 fn create_error(kubernetes: &dyn Kubernetes) -> Result<(), Box<EngineError>> {
     let event_details = kubernetes.get_event_details(Stage::Infrastructure(InfrastructureStep::Create));
     let (kubeconfig_path, _) = kubernetes.get_kubeconfig_file()?;
-    let environment_variables = kubernetes.cloud_provider().credentials_environment_variables();
+    let environment_variables = kubernetes.kubectl_environment_variables();
 
     kubernetes.logger().log(EngineEvent::Warning(
         kubernetes.get_event_details(Stage::Infrastructure(InfrastructureStep::Create)),
@@ -1385,7 +1386,7 @@ fn pause(
                 let wait_engine_job_finish = retry::retry(Fixed::from_millis(60000).take(60), || {
                     return match kubectl_exec_api_custom_metrics(
                         &kubernetes_config_file_path,
-                        kubernetes.cloud_provider().credentials_environment_variables(),
+                        kubernetes.kubectl_environment_variables(),
                         "qovery",
                         None,
                         metric_name,
@@ -1597,10 +1598,8 @@ fn delete(
             .logger()
             .log(EngineEvent::Info(event_details.clone(), EventMessage::new_from_safe(message)));
 
-        let all_namespaces = kubectl_exec_get_all_namespaces(
-            &kubernetes_config_file_path,
-            kubernetes.cloud_provider().credentials_environment_variables(),
-        );
+        let all_namespaces =
+            kubectl_exec_get_all_namespaces(&kubernetes_config_file_path, kubernetes.kubectl_environment_variables());
 
         match all_namespaces {
             Ok(namespace_vec) => {
@@ -1616,7 +1615,7 @@ fn delete(
                     match cmd::kubectl::kubectl_exec_delete_namespace(
                         &kubernetes_config_file_path,
                         namespace_to_delete,
-                        kubernetes.cloud_provider().credentials_environment_variables(),
+                        kubernetes.kubectl_environment_variables(),
                     ) {
                         Ok(_) => kubernetes.logger().log(EngineEvent::Info(
                             event_details.clone(),
@@ -1679,7 +1678,7 @@ fn delete(
         // required to avoid namespace stuck on deletion
         if let Err(e) = uninstall_cert_manager(
             &kubernetes_config_file_path,
-            kubernetes.cloud_provider().credentials_environment_variables(),
+            kubernetes.kubectl_environment_variables(),
             event_details.clone(),
             kubernetes.logger(),
         ) {
@@ -1728,7 +1727,7 @@ fn delete(
             let deletion = cmd::kubectl::kubectl_exec_delete_namespace(
                 &kubernetes_config_file_path,
                 qovery_namespace,
-                kubernetes.cloud_provider().credentials_environment_variables(),
+                kubernetes.kubectl_environment_variables(),
             );
             match deletion {
                 Ok(_) => kubernetes.logger().log(EngineEvent::Info(
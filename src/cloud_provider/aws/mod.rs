@@ -187,6 +187,7 @@ impl CloudProvider for AWS {
             stage,
             self.to_transmitter(),
         )
+        .with_transaction_id(*context.transaction_id())
     }
 
     fn to_transmitter(&self) -> Transmitter {
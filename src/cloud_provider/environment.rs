@@ -1,4 +1,4 @@
-use crate::cloud_provider::service::Action;
+use crate::cloud_provider::service::{Action, Service};
 
 use crate::events::{EnvironmentStep, EventDetails, Stage, Transmitter};
 use crate::io_models::context::Context;
@@ -9,8 +9,45 @@ use crate::models::database::DatabaseService;
 use crate::models::job::JobService;
 use crate::models::router::RouterService;
 use crate::utilities::to_short_id;
+use lazy_static::lazy_static;
+use regex::Regex;
 use uuid::Uuid;
 
+lazy_static! {
+    // i.e: ${service.d3bd4c6e-53f1-4b5e-8b1f-9d8d0ac3e8a2.fqdn}
+    static ref SERVICE_REFERENCE_RE: Regex =
+        Regex::new(r"\$\{service\.(?P<long_id>[^.}]+)\.fqdn\}").expect("invalid service reference regex");
+}
+
+/// Endpoint of a kubernetes service, the namespace must be the one of the service and not of its consumer
+pub fn internal_service_fqdn(service_name: &str, namespace: &str) -> String {
+    format!("{}.{}.svc.cluster.local", service_name, namespace)
+}
+
+/// Replaces references to other services by the endpoint returned by `service_fqdn`.
+/// On failure, returns the first reference which cannot be resolved.
+pub fn resolve_service_references<F>(value: &str, service_fqdn: F) -> Result<String, String>
+where
+    F: Fn(&Uuid) -> Option<String>,
+{
+    let mut resolved = String::with_capacity(value.len());
+    let mut last_match_end = 0;
+    for captures in SERVICE_REFERENCE_RE.captures_iter(value) {
+        let reference = captures.get(0).expect("regex group 0 always exists");
+        let fqdn = Uuid::parse_str(&captures["long_id"])
+            .ok()
+            .and_then(|long_id| service_fqdn(&long_id))
+            .ok_or_else(|| reference.as_str().to_string())?;
+
+        resolved.push_str(&value[last_match_end..reference.start()]);
+        resolved.push_str(&fqdn);
+        last_match_end = reference.end();
+    }
+    resolved.push_str(&value[last_match_end..]);
+
+    Ok(resolved)
+}
+
 pub struct Environment {
     namespace: String,
     event_details: EventDetails,
@@ -80,4 +117,76 @@ impl Environment {
     pub fn event_details(&self) -> &EventDetails {
         &self.event_details
     }
+
+    /// Namespace a service of the environment is deployed into.
+    /// Services live in the environment namespace, unless they ask for their own one
+    pub fn service_namespace(&self, service_long_id: &Uuid) -> String {
+        if let Some(app) = self.applications.iter().find(|app| app.long_id() == service_long_id) {
+            return app.namespace(self);
+        }
+
+        self.containers
+            .iter()
+            .map(|container| container.as_service())
+            .chain(self.databases.iter().map(|db| db.as_service()))
+            .chain(self.jobs.iter().map(|job| job.as_service()))
+            .find(|service| service.long_id() == service_long_id)
+            .and_then(|service| service.namespace_override())
+            .unwrap_or_else(|| self.namespace.clone())
+    }
+
+    /// Endpoint of a service of the environment, reachable from within the cluster whatever the consumer namespace
+    pub fn service_fqdn(&self, service_long_id: &Uuid) -> Option<String> {
+        let namespace = self.service_namespace(service_long_id);
+        if let Some(app) = self.applications.iter().find(|app| app.long_id() == service_long_id) {
+            return Some(internal_service_fqdn(&app.sanitized_name(), &namespace));
+        }
+
+        if let Some(container) = self.containers.iter().find(|c| c.long_id() == service_long_id) {
+            return Some(internal_service_fqdn(&container.kube_service_name(), &namespace));
+        }
+
+        self.databases
+            .iter()
+            .find(|db| db.long_id() == service_long_id)
+            .map(|db| db.internal_fqdn(&namespace))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cloud_provider::environment::resolve_service_references;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_resolve_service_references() {
+        // setup:
+        let known_service = Uuid::new_v4();
+        let unknown_service = Uuid::new_v4();
+        let service_fqdn = |long_id: &Uuid| match long_id == &known_service {
+            true => Some("my-app.my-namespace.svc.cluster.local".to_string()),
+            false => None,
+        };
+
+        // execute & verify:
+        assert_eq!(
+            Ok("no reference".to_string()),
+            resolve_service_references("no reference", service_fqdn)
+        );
+        assert_eq!(
+            Ok("http://my-app.my-namespace.svc.cluster.local:8080/api".to_string()),
+            resolve_service_references(&format!("http://${{service.{}.fqdn}}:8080/api", known_service), service_fqdn)
+        );
+        assert_eq!(
+            Err(format!("${{service.{}.fqdn}}", unknown_service)),
+            resolve_service_references(
+                &format!("${{service.{}.fqdn}},${{service.{}.fqdn}}", known_service, unknown_service),
+                service_fqdn
+            )
+        );
+        assert_eq!(
+            Err("${service.not-an-id.fqdn}".to_string()),
+            resolve_service_references("${service.not-an-id.fqdn}", service_fqdn)
+        );
+    }
 }
@@ -60,12 +60,50 @@ pub struct ChartSetValue {
     pub value: String,
 }
 
+impl ChartSetValue {
+    /// Helm splits `--set` values on commas, the raw value is escaped to be set as a whole
+    pub fn escaped(key: String, value: &str) -> ChartSetValue {
+        ChartSetValue {
+            key,
+            value: escape_helm_set_value(value),
+        }
+    }
+}
+
+fn escape_helm_set_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | ',' | '=') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
 #[derive(Clone)]
 pub struct ChartValuesGenerated {
     pub filename: String,
     pub yaml_content: String,
 }
 
+/// Where an additional values file is inserted among the values of a chart.
+///
+/// Helm merges values with this precedence, from lowest to highest:
+/// 1. `values.yaml` of the chart
+/// 2. `values_files`, in order, the last one wins
+/// 3. `yaml_files_content`, in order
+/// 4. `values` (--set)
+/// 5. `values_string` (--set-string)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HelmValuesLayer {
+    /// Right above the chart defaults, every other override wins over it
+    AfterChartDefaults,
+    /// Above all values files, only generated yaml and set values win over it
+    AfterValuesFiles,
+}
+
 #[derive(Clone)]
 pub struct ChartInfo {
     pub name: String,
@@ -89,6 +127,8 @@ pub struct ChartInfo {
     pub k8s_selector: Option<String>,
     pub backup_resources: Option<Vec<String>>,
     pub crds_update: Option<CRDSUpdate>,
+    /// Tag the revision created by a successful upgrade, to rollback to it by name later
+    pub release_tag: Option<String>,
 }
 
 impl ChartInfo {
@@ -127,6 +167,51 @@ impl ChartInfo {
         }
     }
 
+    pub fn insert_values_file(&mut self, layer: HelmValuesLayer, values_file: String) {
+        match layer {
+            HelmValuesLayer::AfterChartDefaults => self.values_files.insert(0, values_file),
+            HelmValuesLayer::AfterValuesFiles => self.values_files.push(values_file),
+        }
+    }
+
+    /// Values the chart will be deployed with, once all layers are merged following helm precedence.
+    /// Only meant to debug overrides: `--set` keys are split on dots, list indexes are not supported.
+    pub fn merged_values(&self) -> Result<serde_json::Value, CommandError> {
+        let read_values_file = |path: &str| -> Result<serde_json::Value, CommandError> {
+            let content = fs::read_to_string(path).map_err(|e| {
+                CommandError::new(
+                    format!("Can't read values file `{}` of chart `{}`", path, self.name),
+                    Some(e.to_string()),
+                    None,
+                )
+            })?;
+            parse_values(&content, path)
+        };
+
+        let mut merged = serde_json::Value::Object(serde_json::Map::new());
+
+        let chart_defaults = format!("{}/values.yaml", self.path);
+        if Path::new(&chart_defaults).exists() {
+            merge_values(&mut merged, read_values_file(&chart_defaults)?);
+        }
+        for values_file in &self.values_files {
+            merge_values(&mut merged, read_values_file(values_file)?);
+        }
+        for generated in &self.yaml_files_content {
+            merge_values(&mut merged, parse_values(&generated.yaml_content, &generated.filename)?);
+        }
+        for value in &self.values {
+            let typed_value =
+                serde_yaml::from_str(&value.value).unwrap_or_else(|_| serde_json::Value::String(value.value.clone()));
+            set_value(&mut merged, &value.key, typed_value);
+        }
+        for value in &self.values_string {
+            set_value(&mut merged, &value.key, serde_json::Value::String(value.value.clone()));
+        }
+
+        Ok(merged)
+    }
+
     pub fn get_namespace_string(&self) -> String {
         match self.namespace {
             HelmChartNamespaces::Custom => self
@@ -138,6 +223,71 @@ impl ChartInfo {
     }
 }
 
+fn parse_values(content: &str, origin: &str) -> Result<serde_json::Value, CommandError> {
+    // empty values files are valid for helm
+    match serde_yaml::from_str::<Option<serde_json::Value>>(content) {
+        Ok(values) => Ok(values.unwrap_or(serde_json::Value::Null)),
+        Err(e) => Err(CommandError::new(
+            format!("Values `{}` are not valid yaml", origin),
+            Some(e.to_string()),
+            None,
+        )),
+    }
+}
+
+/// Deep merge like helm does: maps are merged key by key, anything else is replaced and null removes the key
+fn merge_values(base: &mut serde_json::Value, overrides: serde_json::Value) {
+    let overrides = match overrides {
+        serde_json::Value::Object(overrides) => overrides,
+        serde_json::Value::Null => return,
+        other => {
+            *base = other;
+            return;
+        }
+    };
+
+    if !base.is_object() {
+        *base = serde_json::Value::Object(serde_json::Map::new());
+    }
+    let base = base.as_object_mut().expect("base has just been set to an object");
+
+    for (key, value) in overrides {
+        if value.is_null() {
+            base.remove(&key);
+            continue;
+        }
+
+        match base.get_mut(&key) {
+            Some(existing) if existing.is_object() && value.is_object() => merge_values(existing, value),
+            _ => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+// keys use dots as separator, escaped dots belong to the key name i.e: annotations.service\.beta\.kubernetes\.io/foo
+fn set_value(values: &mut serde_json::Value, key: &str, value: serde_json::Value) {
+    let mut path: Vec<String> = vec![String::new()];
+    let mut chars = key.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'.') => path.last_mut().unwrap().push(chars.next().unwrap()),
+            '.' => path.push(String::new()),
+            c => path.last_mut().unwrap().push(c),
+        }
+    }
+
+    let mut override_value = value;
+    for segment in path.into_iter().rev() {
+        let mut map = serde_json::Map::new();
+        map.insert(segment, override_value);
+        override_value = serde_json::Value::Object(map);
+    }
+
+    merge_values(values, override_value);
+}
+
 impl Default for ChartInfo {
     fn default() -> ChartInfo {
         ChartInfo {
@@ -161,6 +311,7 @@ impl Default for ChartInfo {
             k8s_selector: None,
             backup_resources: None,
             crds_update: None,
+            release_tag: None,
         }
     }
 }
@@ -742,7 +893,10 @@ pub fn get_chart_for_cluster_agent(
 
 #[cfg(test)]
 mod tests {
-    use crate::cloud_provider::helm::get_latest_successful_deployment;
+    use crate::cloud_provider::helm::{
+        escape_helm_set_value, get_latest_successful_deployment, ChartInfo, ChartSetValue, ChartValuesGenerated,
+        HelmValuesLayer,
+    };
     use crate::cmd::structs::HelmHistoryRow;
 
     #[test]
@@ -780,4 +934,66 @@ mod tests {
         let final_succeed = get_latest_successful_deployment(&results).unwrap();
         assert_eq!(results[1].updated, final_succeed.updated);
     }
+
+    #[test]
+    fn test_merged_values_precedence() {
+        // setup:
+        let chart_dir = tempfile::tempdir().expect("cannot create temp dir");
+        let chart_path = chart_dir.path().to_str().unwrap().to_string();
+        let write = |name: &str, content: &str| -> String {
+            let path = format!("{}/{}", chart_path, name);
+            std::fs::write(&path, content).expect("cannot write values file");
+            path
+        };
+        write(
+            "values.yaml",
+            "replicas: 1\nimage:\n  tag: latest\n  pullPolicy: Always\nlabels:\n  team: core\n",
+        );
+        let overrides = write("overrides.yaml", "image:\n  tag: \"1.0\"\nlabels: null\n");
+        let defaults = write("defaults.yaml", "replicas: 3\nimage:\n  tag: \"0.9\"\n");
+        let mut chart = ChartInfo {
+            name: "my-chart".to_string(),
+            path: chart_path.clone(),
+            values_files: vec![overrides],
+            yaml_files_content: vec![ChartValuesGenerated {
+                filename: "generated.yaml".to_string(),
+                yaml_content: "replicas: 2\n".to_string(),
+            }],
+            values: vec![ChartSetValue {
+                key: "annotations.qovery\\.com/id".to_string(),
+                value: "42".to_string(),
+            }],
+            values_string: vec![ChartSetValue {
+                key: "image.pullPolicy".to_string(),
+                value: "IfNotPresent".to_string(),
+            }],
+            ..Default::default()
+        };
+
+        // execute:
+        chart.insert_values_file(HelmValuesLayer::AfterChartDefaults, defaults);
+        let merged = chart.merged_values().expect("cannot merge values");
+
+        // verify:
+        assert_eq!(
+            merged,
+            serde_json::json!({
+                "replicas": 2,
+                "image": { "tag": "1.0", "pullPolicy": "IfNotPresent" },
+                "annotations": { "qovery.com/id": 42 },
+            })
+        );
+    }
+
+    #[test]
+    fn test_escape_helm_set_value() {
+        assert_eq!(escape_helm_set_value("true"), "true");
+        assert_eq!(escape_helm_set_value("a,b"), "a\\,b");
+        assert_eq!(escape_helm_set_value("key=value"), "key\\=value");
+        assert_eq!(escape_helm_set_value("C:\\path"), "C:\\\\path");
+        assert_eq!(
+            ChartSetValue::escaped("featureFlags.beta".to_string(), "x=1,y=2").value,
+            "x\\=1\\,y\\=2"
+        );
+    }
 }
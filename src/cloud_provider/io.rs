@@ -1,3 +1,4 @@
+use crate::cloud_provider::utilities::DEFAULT_TCP_CHECK_TIMEOUT;
 use crate::cloud_provider::Kind as KindModel;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -34,6 +35,10 @@ pub struct ClusterAdvancedSettings {
     pub aws_iam_user_mapper_group_name: String,
     #[serde(alias = "cloud_provider.container_registry.tags")]
     pub cloud_provider_container_registry_tags: HashMap<String, String>,
+    #[serde(alias = "cluster.listen_probe_timeout")]
+    pub cluster_listen_probe_timeout_sec: u32,
+    #[serde(alias = "database.zone_anti_affinity_required")]
+    pub database_zone_anti_affinity_required: bool,
 }
 
 impl Default for ClusterAdvancedSettings {
@@ -45,6 +50,8 @@ impl Default for ClusterAdvancedSettings {
             loki_log_retention_in_week: 12,
             aws_iam_user_mapper_group_name: "Admins".to_string(),
             cloud_provider_container_registry_tags: HashMap::new(),
+            cluster_listen_probe_timeout_sec: DEFAULT_TCP_CHECK_TIMEOUT.as_secs() as u32,
+            database_zone_anti_affinity_required: false,
         }
     }
 }
@@ -12,8 +12,10 @@ use std::fs::File;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
-use std::sync::mpsc::TryRecvError;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 use uuid::Uuid;
@@ -44,6 +46,7 @@ use crate::object_storage::ObjectStorage;
 use crate::runtime::block_on;
 use crate::unit_conversion::{any_to_mi, cpu_string_to_float};
 use crate::utilities::create_kube_client;
+use kube::config::Kubeconfig;
 
 use super::models::NodeGroupsWithDesiredState;
 
@@ -62,21 +65,35 @@ pub trait Kubernetes {
         format!("qovery-{}", self.id())
     }
     fn version(&self) -> &str;
+    /// Version the cluster is configured with, parsed to be compared.
+    /// `DeploymentTarget::cluster_version` returns the version actually running
+    fn version_number(&self) -> Result<VersionsNumber, CommandError> {
+        VersionsNumber::from_str(self.version())
+    }
     fn region(&self) -> &str;
     fn zone(&self) -> &str;
     fn aws_zones(&self) -> Option<Vec<AwsZones>>;
+    /// Number of zones the nodes of the cluster are spread across
+    fn zones_count(&self) -> usize;
     fn cloud_provider(&self) -> &dyn CloudProvider;
     fn dns_provider(&self) -> &dyn DnsProvider;
     fn logger(&self) -> &dyn Logger;
     fn config_file_store(&self) -> &dyn ObjectStorage;
     fn is_valid(&self) -> Result<(), Box<EngineError>>;
     fn is_network_managed_by_user(&self) -> bool;
+    /// Name of the cluster context in the kubeconfig, always passed explicitly to kubectl
+    fn kube_context_name(&self) -> &str;
+    /// Cloud provider credentials along with the expected kubeconfig context, to give to kubectl calls
+    fn kubectl_environment_variables(&self) -> Vec<(&str, &str)> {
+        let mut envs = self.cloud_provider().credentials_environment_variables();
+        envs.push((KUBECTL_CONTEXT, self.kube_context_name()));
+        envs
+    }
     fn kube_client(&self) -> Result<kube::Client, Box<EngineError>> {
         // FIXME: Create only 1 kube client per Kubernetes object instead every time this function is called
         let kubeconfig_path = self.get_kubeconfig_file_path().unwrap_or_default();
         let kube_credentials: Vec<(String, String)> = self
-            .cloud_provider()
-            .credentials_environment_variables()
+            .kubectl_environment_variables()
             .into_iter()
             .map(|(k, v)| (k.to_string(), v.to_string()))
             .collect();
@@ -99,6 +116,7 @@ pub trait Kubernetes {
             stage,
             Transmitter::Kubernetes(*self.long_id(), self.name().to_string()),
         )
+        .with_transaction_id(*context.transaction_id())
     }
 
     fn get_kubeconfig_filename(&self) -> String {
@@ -252,6 +270,23 @@ pub trait Kubernetes {
             )));
         };
 
+        // the kubeconfig may hold several clusters, ensure ours is there as we never rely on its current-context
+        let available_contexts: Vec<String> = Kubeconfig::read_from(string_path.as_str())
+            .map(|kubeconfig| kubeconfig.contexts.into_iter().map(|context| context.name).collect())
+            .unwrap_or_default();
+        if !available_contexts
+            .iter()
+            .any(|context| context == self.kube_context_name())
+        {
+            let error = EngineError::new_kube_context_not_found(
+                self.get_event_details(stage),
+                self.kube_context_name().to_string(),
+                available_contexts,
+            );
+            self.logger().log(EngineEvent::Error(error.clone(), None));
+            return Err(Box::new(error));
+        }
+
         let mut permissions = metadata.permissions();
         permissions.set_mode(0o400);
         if let Err(err) = std::fs::set_permissions(string_path.as_str(), permissions) {
@@ -306,10 +341,7 @@ pub trait Kubernetes {
         let kubernetes_config_file_path = self.get_kubeconfig_file_path()?;
         let stage = Infrastructure(InfrastructureStep::RetrieveClusterResources);
 
-        let nodes = match kubectl_exec_get_node(
-            kubernetes_config_file_path,
-            self.cloud_provider().credentials_environment_variables(),
-        ) {
+        let nodes = match kubectl_exec_get_node(kubernetes_config_file_path, self.kubectl_environment_variables()) {
             Ok(k) => k,
             Err(err) => {
                 let error = EngineError::new_cannot_get_cluster_nodes(self.get_event_details(stage), err);
@@ -358,14 +390,14 @@ pub trait Kubernetes {
 
             return match is_kubernetes_upgradable(
                 kubeconfig.clone(),
-                self.cloud_provider().credentials_environment_variables(),
+                self.kubectl_environment_variables(),
                 event_details.clone(),
             ) {
                 Err(e) => Err(e),
                 Ok(..) => match is_kubernetes_upgrade_required(
                     kubeconfig,
                     self.version(),
-                    self.cloud_provider().credentials_environment_variables(),
+                    self.kubectl_environment_variables(),
                     event_details,
                     self.logger(),
                 ) {
@@ -382,11 +414,12 @@ pub trait Kubernetes {
     where
         Self: Sized,
     {
-        send_progress_on_long_task(self, Action::Create, || {
+        send_progress_on_long_task(self, Action::Create, self.context().cancel_requested(), |cancel| {
             check_workers_upgrade_status(
                 self.get_kubeconfig_file_path().expect("Unable to get Kubeconfig"),
-                self.cloud_provider().credentials_environment_variables(),
+                self.kubectl_environment_variables(),
                 targeted_version.clone(),
+                cancel,
             )
         })
     }
@@ -400,8 +433,8 @@ pub trait Kubernetes {
             Err(e) => return Err(e.underlying_error().unwrap_or_default()),
         };
 
-        send_progress_on_long_task(self, Action::Create, || {
-            check_workers_status(&kubeconfig, self.cloud_provider().credentials_environment_variables())
+        send_progress_on_long_task(self, Action::Create, self.context().cancel_requested(), |cancel| {
+            check_workers_status(&kubeconfig, self.kubectl_environment_variables(), cancel)
         })
     }
 
@@ -414,8 +447,8 @@ pub trait Kubernetes {
             Err(e) => return Err(e.underlying_error().unwrap_or_default()),
         };
 
-        send_progress_on_long_task(self, Action::Create, || {
-            check_workers_pause(&kubeconfig, self.cloud_provider().credentials_environment_variables())
+        send_progress_on_long_task(self, Action::Create, self.context().cancel_requested(), |cancel| {
+            check_workers_pause(&kubeconfig, self.kubectl_environment_variables(), cancel)
         })
     }
     fn upgrade_with_status(&self, kubernetes_upgrade_status: KubernetesUpgradeStatus) -> Result<(), Box<EngineError>>;
@@ -499,6 +532,12 @@ pub trait Kubernetes {
     }
 
     fn advanced_settings(&self) -> &ClusterAdvancedSettings;
+
+    /// How long a TCP connection to the cluster endpoints may take before they are considered unreachable.
+    /// Without it, a dropped SYN blocks for the OS default timeout, often more than 2 minutes
+    fn listen_probe_timeout(&self) -> Duration {
+        Duration::from_secs(self.advanced_settings().cluster_listen_probe_timeout_sec as u64)
+    }
 }
 
 pub trait KubernetesNode {
@@ -534,6 +573,41 @@ impl Display for Kind {
     }
 }
 
+/// Kubernetes features the engine relies on, which are only available starting from a given version
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KubernetesFeature {
+    ServerSideApply,
+    SchedulingGates,
+    NativeSidecars,
+}
+
+impl KubernetesFeature {
+    /// First version where the feature is enabled by default
+    pub fn min_version(&self) -> VersionsNumber {
+        let minor = match self {
+            KubernetesFeature::ServerSideApply => "22",
+            KubernetesFeature::SchedulingGates => "27",
+            KubernetesFeature::NativeSidecars => "29",
+        };
+
+        VersionsNumber::new("1".to_string(), Some(minor.to_string()), None, None)
+    }
+
+    pub fn is_supported_by(&self, cluster_version: &VersionsNumber) -> bool {
+        cluster_version.is_at_least_major_minor(&self.min_version())
+    }
+}
+
+impl Display for KubernetesFeature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            KubernetesFeature::ServerSideApply => "server side apply",
+            KubernetesFeature::SchedulingGates => "pod scheduling gates",
+            KubernetesFeature::NativeSidecars => "native sidecar containers",
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct Resources {
     pub free_cpu: f32,
@@ -760,15 +834,23 @@ where
     }
 }
 
+fn cancelled_by_user_error() -> CommandError {
+    CommandError::new_from_safe_message("Nodes check cancelled by user.".to_string())
+}
+
 pub fn check_workers_upgrade_status<P>(
     kubernetes_config: P,
     envs: Vec<(&str, &str)>,
     target_version: String,
+    cancel: &AtomicBool,
 ) -> Result<(), CommandError>
 where
     P: AsRef<Path>,
 {
     let result = retry::retry(Fixed::from_millis(10000).take(360), || {
+        if cancel.load(Ordering::Relaxed) {
+            return OperationResult::Err(cancelled_by_user_error());
+        }
         match kubectl_exec_get_node(kubernetes_config.as_ref(), envs.clone()) {
             Err(e) => OperationResult::Retry(e),
             Ok(nodes) => {
@@ -785,7 +867,7 @@ where
     });
 
     return match result {
-        Ok(_) => match check_workers_status(kubernetes_config.as_ref(), envs.clone()) {
+        Ok(_) => match check_workers_status(kubernetes_config.as_ref(), envs.clone(), cancel) {
             Ok(_) => Ok(()),
             Err(e) => Err(e),
         },
@@ -794,11 +876,18 @@ where
     };
 }
 
-pub fn check_workers_status<P>(kubernetes_config: P, envs: Vec<(&str, &str)>) -> Result<(), CommandError>
+pub fn check_workers_status<P>(
+    kubernetes_config: P,
+    envs: Vec<(&str, &str)>,
+    cancel: &AtomicBool,
+) -> Result<(), CommandError>
 where
     P: AsRef<Path>,
 {
     let result = retry::retry(Fixed::from_millis(10000).take(60), || {
+        if cancel.load(Ordering::Relaxed) {
+            return OperationResult::Err(cancelled_by_user_error());
+        }
         match kubectl_exec_get_node(kubernetes_config.as_ref(), envs.clone()) {
             Err(e) => OperationResult::Retry(e),
             Ok(nodes) => {
@@ -826,11 +915,18 @@ where
     }
 }
 
-pub fn check_workers_pause<P>(kubernetes_config: P, envs: Vec<(&str, &str)>) -> Result<(), CommandError>
+pub fn check_workers_pause<P>(
+    kubernetes_config: P,
+    envs: Vec<(&str, &str)>,
+    cancel: &AtomicBool,
+) -> Result<(), CommandError>
 where
     P: AsRef<Path>,
 {
     let result = retry::retry(Fixed::from_millis(10000).take(60), || {
+        if cancel.load(Ordering::Relaxed) {
+            return OperationResult::Err(cancelled_by_user_error());
+        }
         match kubectl_exec_get_node(kubernetes_config.as_ref(), envs.clone()) {
             //TODO: handle error properly
             Err(_) => OperationResult::Ok(()),
@@ -1134,10 +1230,11 @@ impl InstanceEc2 {
 /// TODO(benjaminch): to be refactored with similar function in services.rs
 /// This function call (start|pause|delete)_in_progress function every 10 seconds when a
 /// long blocking task is running.
-pub fn send_progress_on_long_task<K, R, F>(kubernetes: &K, action: Action, long_task: F) -> R
+/// Setting `cancel` stops the progress notifications, `long_task` receives the same flag to abort itself
+pub fn send_progress_on_long_task<K, R, F>(kubernetes: &K, action: Action, cancel: Arc<AtomicBool>, long_task: F) -> R
 where
     K: Kubernetes,
-    F: Fn() -> R,
+    F: Fn(&AtomicBool) -> R,
 {
     let waiting_message = match action {
         Action::Create => Some(format!(
@@ -1154,70 +1251,71 @@ where
         )),
     };
 
-    send_progress_on_long_task_with_message(kubernetes, waiting_message, action, long_task)
+    send_progress_on_long_task_with_message(kubernetes, waiting_message, action, cancel, long_task)
 }
 
 /// TODO(benjaminch): to be refactored with similar function in services.rs
 /// This function call (start|pause|delete)_in_progress function every 10 seconds when a
 /// long blocking task is running.
+/// Setting `cancel` stops the progress notifications, `long_task` receives the same flag to abort itself
 pub fn send_progress_on_long_task_with_message<K, R, F>(
     kubernetes: &K,
     waiting_message: Option<String>,
     action: Action,
+    cancel: Arc<AtomicBool>,
     long_task: F,
 ) -> R
 where
     K: Kubernetes,
-    F: Fn() -> R,
+    F: Fn(&AtomicBool) -> R,
 {
     let logger = kubernetes.logger().clone_dyn();
     let event_details = kubernetes.get_event_details(Infrastructure(InfrastructureStep::Create));
+    let cancelled_message = format!("Infrastructure '{}' deployment cancelled by user", kubernetes.name_with_id());
 
     let (tx, rx) = mpsc::channel();
+    let task_cancel = cancel.clone();
 
     // monitor thread to notify user while the blocking task is executed
     let handle = thread::Builder::new().name("task-monitor".to_string()).spawn(move || {
         // stop the thread when the blocking task is done
-        let action = action;
         let waiting_message = waiting_message.unwrap_or_else(|| "no message ...".to_string());
+        let event_details = match action {
+            Action::Create => {
+                EventDetails::clone_changing_stage(event_details, Infrastructure(InfrastructureStep::Create))
+            }
+            Action::Pause => {
+                EventDetails::clone_changing_stage(event_details, Infrastructure(InfrastructureStep::Pause))
+            }
+            Action::Delete => {
+                EventDetails::clone_changing_stage(event_details, Infrastructure(InfrastructureStep::Delete))
+            }
+        };
 
         loop {
             // do notify users here
-            let event_details = Clone::clone(&event_details);
-            let event_message = EventMessage::new_from_safe(waiting_message.to_string());
-
-            match action {
-                Action::Create => {
-                    logger.log(EngineEvent::Info(
-                        EventDetails::clone_changing_stage(event_details, Infrastructure(InfrastructureStep::Create)),
-                        event_message,
-                    ));
-                }
-                Action::Pause => {
-                    logger.log(EngineEvent::Info(
-                        EventDetails::clone_changing_stage(event_details, Infrastructure(InfrastructureStep::Pause)),
-                        event_message,
-                    ));
-                }
-                Action::Delete => {
-                    logger.log(EngineEvent::Info(
-                        EventDetails::clone_changing_stage(event_details, Infrastructure(InfrastructureStep::Delete)),
-                        event_message,
-                    ));
-                }
-            };
+            logger.log(EngineEvent::Info(
+                event_details.clone(),
+                EventMessage::new_from_safe(waiting_message.to_string()),
+            ));
 
-            thread::sleep(Duration::from_secs(30));
+            // watch for thread termination or cancellation, every second not to notice them 30s late
+            for _ in 0..30 {
+                if cancel.load(Ordering::Relaxed) {
+                    logger.log(EngineEvent::Info(event_details, EventMessage::new_from_safe(cancelled_message)));
+                    return;
+                }
 
-            // watch for thread termination
-            match rx.try_recv() {
-                Ok(_) | Err(TryRecvError::Disconnected) => break,
-                Err(TryRecvError::Empty) => {}
+                match rx.recv_timeout(Duration::from_secs(1)) {
+                    Ok(_) | Err(RecvTimeoutError::Disconnected) => return,
+                    Err(RecvTimeoutError::Timeout) => {}
+                }
             }
         }
     });
 
-    let blocking_task_result = long_task();
+    let blocking_task_result = long_task(&task_cancel);
+    // the monitor may have already exited on cancellation, closing the channel
     let _ = tx.send(());
     let _ = handle.map(|it| it.join());
 
@@ -1242,6 +1340,27 @@ pub fn validate_k8s_required_cpu_and_burstable(
     })
 }
 
+/// Cpu requested to kubernetes for `total_cpus`, lowered by the overcommit ratio.
+/// Rounded down to the millicore, but never below 1m
+pub fn overcommitted_cpu_request(total_cpus: String, overcommit_ratio_percent: u32) -> String {
+    if overcommit_ratio_percent >= 100 {
+        return total_cpus;
+    }
+
+    let millis = (cpu_string_to_float(total_cpus) * 1000.0 * (overcommit_ratio_percent as f32 / 100.0)).floor();
+    format!("{}m", (millis as u32).max(1))
+}
+
+/// Memory requested to kubernetes for `total_ram_in_mib`, lowered by the overcommit ratio.
+/// Rounded down to the MiB, but never below 1MiB
+pub fn overcommitted_ram_request_in_mib(total_ram_in_mib: u32, overcommit_ratio_percent: u32) -> u32 {
+    if overcommit_ratio_percent >= 100 {
+        return total_ram_in_mib;
+    }
+
+    ((total_ram_in_mib as f32 * overcommit_ratio_percent as f32 / 100.0).floor() as u32).max(1)
+}
+
 /// TODO(benjaminch): deprecate this function and use plain KubernetesCpuRessourceUnit
 pub fn convert_k8s_cpu_value_to_f32(value: String) -> Result<f32, CommandError> {
     if value.ends_with('m') {
@@ -1280,6 +1399,17 @@ pub async fn kube_does_secret_exists(kube: &kube::Client, name: &str, namespace:
     }
 }
 
+pub async fn kube_does_service_exists(kube: &kube::Client, name: &str, namespace: &str) -> Result<bool, Error> {
+    let item: Api<Service> = Api::namespaced(kube.clone(), namespace);
+    match item.get(name).await {
+        Ok(_) => Ok(true),
+        Err(e) => match e {
+            Error::Api(api_err) if api_err.code == 404 => Ok(false),
+            _ => Err(e),
+        },
+    }
+}
+
 pub async fn kube_list_services(
     kube: &kube::Client,
     namespace_name: Option<&str>,
@@ -1385,7 +1515,8 @@ mod tests {
     use crate::cloud_provider::kubernetes::{
         check_kubernetes_upgrade_status, compare_kubernetes_cluster_versions_for_upgrade, convert_k8s_cpu_value_to_f32,
         filter_svc_loadbalancers, kube_create_namespace_if_not_exists, kube_does_secret_exists, kube_list_services,
-        validate_k8s_required_cpu_and_burstable, KubernetesNodesType,
+        overcommitted_cpu_request, overcommitted_ram_request_in_mib, validate_k8s_required_cpu_and_burstable,
+        KubernetesFeature, KubernetesNodesType,
     };
     use crate::cloud_provider::models::CpuLimits;
     use crate::cmd::structs::{KubernetesList, KubernetesNode, KubernetesVersion};
@@ -1437,6 +1568,21 @@ mod tests {
         }
     }
 
+    #[test]
+    pub fn test_kubernetes_feature_support() {
+        let version = |raw: &str| -> VersionsNumber { VersionsNumber::from_str(raw).unwrap() };
+
+        assert!(KubernetesFeature::SchedulingGates.is_supported_by(&version("1.27")));
+        assert!(KubernetesFeature::SchedulingGates.is_supported_by(&version("1.28+")));
+        assert!(KubernetesFeature::ServerSideApply.is_supported_by(&version("2.0")));
+        // minor versions are compared as numbers
+        assert!(!KubernetesFeature::SchedulingGates.is_supported_by(&version("1.3")));
+        assert!(!KubernetesFeature::SchedulingGates.is_supported_by(&version("1.26")));
+        assert!(!KubernetesFeature::ServerSideApply.is_supported_by(&version("1")));
+        assert!(KubernetesFeature::NativeSidecars.is_supported_by(&version("1.29")));
+        assert!(!KubernetesFeature::NativeSidecars.is_supported_by(&version("1.28")));
+    }
+
     #[test]
     #[cfg(feature = "test-local-kube")]
     pub fn k8s_get_services() {
@@ -1906,4 +2052,31 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    pub fn test_overcommitted_cpu_request() {
+        // no overcommit, the request is left as is
+        assert_eq!(overcommitted_cpu_request("2".to_string(), 100), "2");
+        assert_eq!(overcommitted_cpu_request("250m".to_string(), 150), "250m");
+        // cores and millicores are both parsed
+        assert_eq!(overcommitted_cpu_request("2".to_string(), 50), "1000m");
+        assert_eq!(overcommitted_cpu_request("0.5".to_string(), 50), "250m");
+        assert_eq!(overcommitted_cpu_request("250m".to_string(), 50), "125m");
+        // rounded down to the millicore, never below 1m
+        assert_eq!(overcommitted_cpu_request("255m".to_string(), 50), "127m");
+        assert_eq!(overcommitted_cpu_request("1m".to_string(), 10), "1m");
+        assert_eq!(overcommitted_cpu_request("500m".to_string(), 0), "1m");
+    }
+
+    #[test]
+    pub fn test_overcommitted_ram_request_in_mib() {
+        // no overcommit, the request is left as is
+        assert_eq!(overcommitted_ram_request_in_mib(512, 100), 512);
+        assert_eq!(overcommitted_ram_request_in_mib(512, 150), 512);
+        assert_eq!(overcommitted_ram_request_in_mib(512, 50), 256);
+        // rounded down to the MiB, never below 1MiB
+        assert_eq!(overcommitted_ram_request_in_mib(513, 50), 256);
+        assert_eq!(overcommitted_ram_request_in_mib(1, 50), 1);
+        assert_eq!(overcommitted_ram_request_in_mib(512, 0), 1);
+    }
 }
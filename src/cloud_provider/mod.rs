@@ -1,16 +1,19 @@
 use std::any::Any;
 use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use aws_config::SdkConfig;
 use serde::{Deserialize, Serialize};
 
 use crate::cloud_provider::environment::Environment;
-use crate::cloud_provider::kubernetes::Kubernetes;
+use crate::cloud_provider::kubernetes::{Kubernetes, KubernetesFeature};
 use crate::cloud_provider::service::Service;
+use crate::cmd::cluster_executor::{BinaryClusterExecutor, ClusterExecutor, KubectlApplyClusterExecutor};
 use crate::cmd::docker::Docker;
 use crate::cmd::helm::{to_engine_error, Helm};
+use crate::cmd::kubectl::kubectl_exec_version;
 use crate::container_registry::ContainerRegistry;
 use crate::deployment_report::logger::EnvLogger;
 use crate::dns_provider::DnsProvider;
@@ -19,6 +22,7 @@ use crate::errors::EngineError;
 use crate::events::{EnvironmentStep, EventDetails, Stage, Transmitter};
 use crate::io_models::context::Context;
 use crate::logger::Logger;
+use crate::models::types::VersionsNumber;
 use crate::runtime::block_on;
 use crate::utilities::create_kube_client;
 
@@ -117,11 +121,16 @@ pub struct DeploymentTarget<'a> {
     pub environment: &'a Environment,
     pub docker: &'a Docker,
     pub kube: kube::Client,
-    pub helm: Helm,
+    pub cluster_executor: Box<dyn ClusterExecutor>,
     pub should_abort: &'a dyn Fn() -> bool,
     logger: Arc<Box<dyn Logger>>,
     pub is_dry_run_deploy: bool,
     pub is_test_cluster: bool,
+    // Retrieved once per deployment, the cluster is not upgraded while deploying an environment
+    cluster_version: Mutex<Option<VersionsNumber>>,
+    // Some providers fetch the kubeconfig from their API, it is resolved once per deployment
+    kubeconfig_path: Mutex<Option<PathBuf>>,
+    kubectl_credentials: Vec<(String, String)>,
 }
 
 impl<'a> DeploymentTarget<'a> {
@@ -132,10 +141,13 @@ impl<'a> DeploymentTarget<'a> {
     ) -> Result<DeploymentTarget<'a>, Box<EngineError>> {
         let event_details = environment.event_details();
         let kubernetes = infra_ctx.kubernetes();
-        let kubeconfig_path = kubernetes.get_kubeconfig_file_path().unwrap_or_default();
+        let cached_kubeconfig_path = kubernetes.get_kubeconfig_file_path().ok().map(PathBuf::from);
+        let kubeconfig_path = cached_kubeconfig_path
+            .as_ref()
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_default();
         let kube_credentials: Vec<(String, String)> = kubernetes
-            .cloud_provider()
-            .credentials_environment_variables()
+            .kubectl_environment_variables()
             .into_iter()
             .map(|(k, v)| (k.to_string(), v.to_string()))
             .collect();
@@ -149,6 +161,22 @@ impl<'a> DeploymentTarget<'a> {
         )
         .map_err(|e| to_engine_error(event_details, e))?;
 
+        // helm stays the default, applying rendered manifests is only a fallback when helm can't be used
+        let cluster_executor: Box<dyn ClusterExecutor> = if kubernetes.context().is_deploy_without_helm() {
+            Box::new(KubectlApplyClusterExecutor::new(
+                helm,
+                PathBuf::from(kubeconfig_path),
+                kube_credentials.clone(),
+                kubernetes.context().rendered_charts_directory(),
+            ))
+        } else {
+            Box::new(BinaryClusterExecutor::new(
+                helm,
+                PathBuf::from(kubeconfig_path),
+                kube_credentials.clone(),
+            ))
+        };
+
         Ok(DeploymentTarget {
             kubernetes,
             container_registry: infra_ctx.container_registry(),
@@ -157,14 +185,87 @@ impl<'a> DeploymentTarget<'a> {
             environment,
             docker: &infra_ctx.context().docker,
             kube: kube_client,
-            helm,
+            cluster_executor,
             should_abort,
             logger: Arc::new(infra_ctx.kubernetes().logger().clone_dyn()),
             is_dry_run_deploy: kubernetes.context().is_dry_run_deploy(),
             is_test_cluster: kubernetes.context().is_test_cluster(),
+            cluster_version: Mutex::new(None),
+            kubeconfig_path: Mutex::new(cached_kubeconfig_path),
+            kubectl_credentials: kube_credentials,
         })
     }
 
+    /// Kubeconfig of the cluster, retrieved once and cached for the whole deployment
+    pub fn kubeconfig_path(&self) -> Result<PathBuf, Box<EngineError>> {
+        let mut kubeconfig_path = self.kubeconfig_path.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(path) = kubeconfig_path.as_ref() {
+            return Ok(path.clone());
+        }
+
+        let path = PathBuf::from(self.kubernetes.get_kubeconfig_file_path()?);
+        *kubeconfig_path = Some(path.clone());
+        Ok(path)
+    }
+
+    /// Environment variables kubectl needs to reach the cluster, resolved when the target is created
+    pub fn kubectl_credentials(&self) -> Vec<(&str, &str)> {
+        self.kubectl_credentials
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect()
+    }
+
+    /// Version of Kubernetes actually running on the cluster, retrieved once and cached for the whole deployment
+    pub fn cluster_version(&self, event_details: EventDetails) -> Result<VersionsNumber, Box<EngineError>> {
+        let mut cluster_version = self.cluster_version.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(version) = cluster_version.as_ref() {
+            return Ok(version.clone());
+        }
+
+        let server_version = kubectl_exec_version(self.kubeconfig_path()?, self.kubectl_credentials())
+            .map_err(|err| EngineError::new_cannot_execute_k8s_exec_version(event_details.clone(), err))?
+            .server_version;
+        let raw_version = format!("{}.{}", server_version.major, server_version.minor);
+        let version = VersionsNumber::from_str(&raw_version)
+            .map_err(|_| EngineError::new_cannot_determine_k8s_master_version(event_details, raw_version))?;
+
+        *cluster_version = Some(version.clone());
+        Ok(version)
+    }
+
+    /// Whether the cluster provides the feature, so behaviors relying on it can be skipped otherwise.
+    /// When the running version cannot be retrieved, the configured one is used instead
+    pub fn supports(&self, feature: KubernetesFeature, event_details: EventDetails) -> bool {
+        let version = match self.cluster_version(event_details) {
+            Ok(version) => version,
+            Err(err) => {
+                warn!("cannot retrieve cluster version, relying on the configured one: {}", err);
+                match self.kubernetes.version_number() {
+                    Ok(version) => version,
+                    Err(_) => return true,
+                }
+            }
+        };
+
+        feature.is_supported_by(&version)
+    }
+
+    /// Fail for features the deployment cannot do without
+    pub fn require(&self, feature: KubernetesFeature, event_details: EventDetails) -> Result<(), Box<EngineError>> {
+        let version = self.cluster_version(event_details.clone())?;
+        if feature.is_supported_by(&version) {
+            return Ok(());
+        }
+
+        Err(Box::new(EngineError::new_unsupported_kubernetes_version(
+            event_details,
+            feature.to_string(),
+            feature.min_version(),
+            version,
+        )))
+    }
+
     pub fn env_logger(&self, service: &impl Service, step: EnvironmentStep) -> EnvLogger {
         EnvLogger::new(service, step, self.logger.clone())
     }
@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
 use uuid::Uuid;
 
@@ -14,6 +16,20 @@ pub struct EnvironmentVariableDataTemplate {
     pub value: String,
 }
 
+/// Sha256 of the variables stored in the service secret, whatever their order.
+/// Set as the `checksum/config` pod annotation, so pods are rolled out when a value changes and only then
+pub fn environment_variables_checksum<'a>(variables: impl Iterator<Item = (&'a str, &'a str)>) -> String {
+    let variables: BTreeMap<&str, &str> = variables.collect();
+    let mut hasher = Sha256::new();
+    for (key, value) in variables {
+        hasher.update(key.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(value.as_bytes());
+        hasher.update([0u8]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
 #[derive(Clone, Eq, PartialEq, Hash)]
 pub struct Storage<T> {
     pub id: String,
@@ -164,7 +180,9 @@ impl Display for KubernetesMemoryResourceUnit {
 
 #[cfg(test)]
 mod tests {
-    use crate::cloud_provider::models::{KubernetesCpuResourceUnit, KubernetesMemoryResourceUnit};
+    use crate::cloud_provider::models::{
+        environment_variables_checksum, KubernetesCpuResourceUnit, KubernetesMemoryResourceUnit,
+    };
 
     #[test]
     fn test_kubernetes_cpu_resource_unit_to_string() {
@@ -239,4 +257,26 @@ mod tests {
             assert_eq!(tc.output, tc.input.to_string());
         }
     }
+
+    #[test]
+    fn test_environment_variables_checksum() {
+        let checksum = |variables: &[(&'static str, &'static str)]| {
+            environment_variables_checksum(variables.iter().map(|(key, value)| (*key, *value)))
+        };
+
+        assert_eq!(
+            checksum(&[("DB_HOST", "ZGI="), ("DB_PASSWORD", "c2VjcmV0")]),
+            checksum(&[("DB_PASSWORD", "c2VjcmV0"), ("DB_HOST", "ZGI=")])
+        );
+        assert_ne!(
+            checksum(&[("DB_HOST", "ZGI="), ("DB_PASSWORD", "c2VjcmV0")]),
+            checksum(&[("DB_HOST", "ZGI="), ("DB_PASSWORD", "cm90YXRlZA==")])
+        );
+        assert_ne!(
+            checksum(&[("DB_HOST", "ZGI=")]),
+            checksum(&[("DB_HOST", "ZGI="), ("DEBUG", "MQ==")])
+        );
+        assert_ne!(checksum(&[("AB", "C")]), checksum(&[("A", "BC")]));
+        assert_eq!(checksum(&[("DB_HOST", "ZGI=")]).len(), 64);
+    }
 }
@@ -136,6 +136,7 @@ impl KapsuleOptions {
 pub struct Kapsule {
     context: Context,
     id: String,
+    kube_context_name: String,
     long_id: Uuid,
     name: String,
     version: String,
@@ -177,7 +178,8 @@ impl Kapsule {
                             context.execution_id().to_string(),
                             Infrastructure(InfrastructureStep::LoadConfiguration),
                             Transmitter::Kubernetes(long_id, name),
-                        ),
+                        )
+                        .with_transaction_id(*context.transaction_id()),
                         node_group.instance_type.as_str(),
                         e,
                     );
@@ -195,7 +197,8 @@ impl Kapsule {
                                 context.execution_id().to_string(),
                                 Infrastructure(InfrastructureStep::LoadConfiguration),
                                 Transmitter::Kubernetes(long_id, name),
-                            ),
+                            )
+                            .with_transaction_id(*context.transaction_id()),
                             node_group.instance_type.as_str(),
                             CommandError::new_from_safe_message(format!(
                                 "`{}` instance type is not supported",
@@ -224,6 +227,7 @@ impl Kapsule {
         Ok(Kapsule {
             context,
             id: to_short_id(&long_id),
+            kube_context_name: format!("admin@qovery-{}", to_short_id(&long_id)),
             long_id,
             name,
             version,
@@ -612,7 +616,7 @@ impl Kapsule {
             Ok((path, _)) => match is_kubernetes_upgrade_required(
                 path,
                 &self.version,
-                self.cloud_provider.credentials_environment_variables(),
+                self.kubectl_environment_variables(),
                 event_details.clone(),
                 self.logger(),
             ) {
@@ -908,8 +912,7 @@ impl Kapsule {
 
         // kubernetes helm deployments on the cluster
         let credentials_environment_variables: Vec<(String, String)> = self
-            .cloud_provider
-            .credentials_environment_variables()
+            .kubectl_environment_variables()
             .into_iter()
             .map(|x| (x.0.to_string(), x.1.to_string()))
             .collect();
@@ -975,7 +978,7 @@ impl Kapsule {
     fn create_error(&self) -> Result<(), Box<EngineError>> {
         let event_details = self.get_event_details(Infrastructure(InfrastructureStep::Create));
         let (kubeconfig_path, _) = self.get_kubeconfig_file()?;
-        let environment_variables: Vec<(&str, &str)> = self.cloud_provider.credentials_environment_variables();
+        let environment_variables: Vec<(&str, &str)> = self.kubectl_environment_variables();
 
         self.logger().log(EngineEvent::Warning(
             self.get_event_details(Infrastructure(InfrastructureStep::Create)),
@@ -1089,7 +1092,7 @@ impl Kapsule {
                     let wait_engine_job_finish = retry::retry(Fixed::from_millis(60000).take(60), || {
                         return match kubectl_exec_api_custom_metrics(
                             &kubernetes_config_file_path,
-                            self.cloud_provider().credentials_environment_variables(),
+                            self.kubectl_environment_variables(),
                             "qovery",
                             None,
                             metric_name,
@@ -1241,10 +1244,7 @@ impl Kapsule {
             self.logger()
                 .log(EngineEvent::Info(event_details.clone(), EventMessage::new_from_safe(message)));
 
-            let all_namespaces = kubectl_exec_get_all_namespaces(
-                kubeconfig_path,
-                self.cloud_provider().credentials_environment_variables(),
-            );
+            let all_namespaces = kubectl_exec_get_all_namespaces(kubeconfig_path, self.kubectl_environment_variables());
 
             match all_namespaces {
                 Ok(namespace_vec) => {
@@ -1260,7 +1260,7 @@ impl Kapsule {
                         match cmd::kubectl::kubectl_exec_delete_namespace(
                             kubeconfig_path,
                             namespace_to_delete,
-                            self.cloud_provider().credentials_environment_variables(),
+                            self.kubectl_environment_variables(),
                         ) {
                             Ok(_) => self.logger().log(EngineEvent::Info(
                                 event_details.clone(),
@@ -1307,7 +1307,7 @@ impl Kapsule {
                 .log(EngineEvent::Info(event_details.clone(), EventMessage::new_from_safe(message)));
 
             // delete custom metrics api to avoid stale namespaces on deletion
-            let helm = Helm::new(kubeconfig_path, &self.cloud_provider.credentials_environment_variables())
+            let helm = Helm::new(kubeconfig_path, &self.cloud_provider().credentials_environment_variables())
                 .map_err(|e| to_engine_error(&event_details, e))?;
             let chart = ChartInfo::new_from_release_name("metrics-server", "kube-system");
 
@@ -1322,7 +1322,7 @@ impl Kapsule {
             // required to avoid namespace stuck on deletion
             if let Err(e) = uninstall_cert_manager(
                 kubeconfig_path,
-                self.cloud_provider().credentials_environment_variables(),
+                self.kubectl_environment_variables(),
                 event_details.clone(),
                 self.logger(),
             ) {
@@ -1374,7 +1374,7 @@ impl Kapsule {
                 let deletion = cmd::kubectl::kubectl_exec_delete_namespace(
                     kubeconfig_path,
                     qovery_namespace,
-                    self.cloud_provider().credentials_environment_variables(),
+                    self.kubectl_environment_variables(),
                 );
                 match deletion {
                     Ok(_) => self.logger().log(EngineEvent::Info(
@@ -1478,6 +1478,11 @@ impl Kubernetes for Kapsule {
         self.id.as_str()
     }
 
+    // context name of the kubeconfig delivered by Scaleway, based on the cluster name
+    fn kube_context_name(&self) -> &str {
+        self.kube_context_name.as_str()
+    }
+
     fn long_id(&self) -> &Uuid {
         &self.long_id
     }
@@ -1502,6 +1507,11 @@ impl Kubernetes for Kapsule {
         None
     }
 
+    // kapsule clusters live in a single zone
+    fn zones_count(&self) -> usize {
+        1
+    }
+
     fn cloud_provider(&self) -> &dyn CloudProvider {
         self.cloud_provider.as_ref().borrow()
     }
@@ -1537,7 +1547,7 @@ impl Kubernetes for Kapsule {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Create, || self.create())
+        send_progress_on_long_task(self, Action::Create, self.context().cancel_requested(), |_| self.create())
     }
 
     #[named]
@@ -1551,7 +1561,7 @@ impl Kubernetes for Kapsule {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Create, || self.create_error())
+        send_progress_on_long_task(self, Action::Create, self.context().cancel_requested(), |_| self.create_error())
     }
 
     fn upgrade_with_status(&self, kubernetes_upgrade_status: KubernetesUpgradeStatus) -> Result<(), Box<EngineError>> {
@@ -1614,7 +1624,7 @@ impl Kubernetes for Kapsule {
             None,
             None,
             Some(3),
-            self.cloud_provider().credentials_environment_variables(),
+            self.kubectl_environment_variables(),
             Infrastructure(InfrastructureStep::Upgrade),
         ) {
             self.logger().log(EngineEvent::Error(*e.clone(), None));
@@ -1622,7 +1632,7 @@ impl Kubernetes for Kapsule {
         }
 
         if let Err(e) = self.delete_completed_jobs(
-            self.cloud_provider().credentials_environment_variables(),
+            self.kubectl_environment_variables(),
             Infrastructure(InfrastructureStep::Upgrade),
         ) {
             self.logger().log(EngineEvent::Error(*e.clone(), None));
@@ -1664,7 +1674,7 @@ impl Kubernetes for Kapsule {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Create, || self.upgrade())
+        send_progress_on_long_task(self, Action::Create, self.context().cancel_requested(), |_| self.upgrade())
     }
 
     #[named]
@@ -1678,7 +1688,9 @@ impl Kubernetes for Kapsule {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Create, || self.upgrade_error())
+        send_progress_on_long_task(self, Action::Create, self.context().cancel_requested(), |_| {
+            self.upgrade_error()
+        })
     }
 
     #[named]
@@ -1692,7 +1704,7 @@ impl Kubernetes for Kapsule {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Pause, || self.pause())
+        send_progress_on_long_task(self, Action::Pause, self.context().cancel_requested(), |_| self.pause())
     }
 
     #[named]
@@ -1706,7 +1718,7 @@ impl Kubernetes for Kapsule {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Pause, || self.pause_error())
+        send_progress_on_long_task(self, Action::Pause, self.context().cancel_requested(), |_| self.pause_error())
     }
 
     #[named]
@@ -1720,7 +1732,7 @@ impl Kubernetes for Kapsule {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Delete, || self.delete())
+        send_progress_on_long_task(self, Action::Delete, self.context().cancel_requested(), |_| self.delete())
     }
 
     #[named]
@@ -1734,7 +1746,7 @@ impl Kubernetes for Kapsule {
             event_details,
             self.logger(),
         );
-        send_progress_on_long_task(self, Action::Delete, || self.delete_error())
+        send_progress_on_long_task(self, Action::Delete, self.context().cancel_requested(), |_| self.delete_error())
     }
 
     fn advanced_settings(&self) -> &ClusterAdvancedSettings {
@@ -137,6 +137,7 @@ impl CloudProvider for Scaleway {
             stage,
             self.to_transmitter(),
         )
+        .with_transaction_id(*context.transaction_id())
     }
 
     fn to_transmitter(&self) -> Transmitter {
@@ -1,8 +1,13 @@
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
+use std::{env, thread};
 
 use crate::build_platform::Build;
 use tera::Context as TeraContext;
@@ -11,10 +16,11 @@ use uuid::Uuid;
 use crate::cloud_provider::environment::Environment;
 use crate::cloud_provider::kubernetes::Kubernetes;
 use crate::cmd::kubectl::{kubectl_exec_delete_pod, kubectl_exec_get_pods};
-use crate::cmd::structs::KubernetesPodStatusPhase;
+use crate::cmd::structs::{KubernetesPod, KubernetesPodStatusPhase};
 use crate::cmd::terraform::TerraformError;
 use crate::errors::{CommandError, EngineError};
-use crate::events::{EnvironmentStep, EventDetails, Stage};
+use crate::events::{EngineEvent, EnvironmentStep, EventDetails, EventMessage, Stage};
+use crate::logger::Logger;
 use crate::models;
 use crate::models::database::{Database, DatabaseMode};
 
@@ -30,17 +36,56 @@ pub trait Service {
     fn action(&self) -> &Action;
     // used to retrieve logs by using Kubernetes labels (selector)
     fn selector(&self) -> Option<String>;
+    // services made of several workloads, not sharing the same labels, have one selector per workload
+    fn selectors(&self) -> Vec<String> {
+        self.selector().into_iter().collect()
+    }
+    // namespace the service is deployed into instead of the environment one, if any
+    fn namespace_override(&self) -> Option<String> {
+        None
+    }
+    // terraform workspace holding the state of the service, to isolate it per environment. See `get_tfstate_workspace`
+    fn tfstate_workspace(&self) -> String {
+        TFSTATE_DEFAULT_WORKSPACE.to_string()
+    }
+    // ad-hoc `--set key=value` overrides of the service chart, values are escaped before reaching helm
+    fn helm_set_values(&self) -> Vec<(String, String)> {
+        vec![]
+    }
+    // values files of the service chart, applied over its defaults. Database charts get the qovery values rendered in the workspace
+    fn helm_value_files(&self, workspace_dir: &str) -> Vec<String> {
+        match self.service_type() {
+            ServiceType::Database(_) => vec![format!("{}/qovery-values.yaml", workspace_dir)],
+            _ => vec![],
+        }
+    }
     fn as_service(&self) -> &dyn Service;
     fn as_service_mut(&mut self) -> &mut dyn Service;
     fn build(&self) -> Option<&Build>;
     fn build_mut(&mut self) -> Option<&mut Build>;
 }
 
+/// HTTP endpoint which must answer the expected status before a service is considered ready
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ReadinessProbe {
+    pub path: String,
+    pub port: u16,
+    pub expected_status: u16,
+    pub timeout: Duration,
+}
+
+/// Implemented by services declaring a readiness check on top of their pods readiness,
+/// for the ones whose endpoint is not able to serve yet while their pods are already ready
+pub trait HealthCheck {
+    fn readiness_probe(&self) -> Option<ReadinessProbe>;
+}
+
 #[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
 pub enum Action {
     Create,
     Pause,
     Delete,
+    Restart,
 }
 
 impl Action {
@@ -49,6 +94,7 @@ impl Action {
             Action::Create => EnvironmentStep::Deploy,
             Action::Pause => EnvironmentStep::Pause,
             Action::Delete => EnvironmentStep::Delete,
+            Action::Restart => EnvironmentStep::Restart,
         }
     }
 }
@@ -139,12 +185,41 @@ pub struct DatabaseTerraformConfig {
 pub fn get_database_terraform_config(
     database_terraform_config_file: &str,
 ) -> Result<DatabaseTerraformConfig, TerraformError> {
-    let file_content = match File::open(database_terraform_config_file) {
+    read_database_terraform_file(database_terraform_config_file, "database config")
+}
+
+/// Credentials generated by the cloud provider for a managed database
+#[derive(Clone, Deserialize)]
+pub struct DatabaseCredentials {
+    #[serde(rename = "database_login")]
+    pub login: String,
+    #[serde(rename = "database_password")]
+    pub password: String,
+}
+
+pub fn get_database_terraform_credentials(
+    database_terraform_credentials_file: &str,
+) -> Result<DatabaseCredentials, TerraformError> {
+    let credentials: DatabaseCredentials =
+        read_database_terraform_file(database_terraform_credentials_file, "generated database credentials")?;
+
+    if credentials.login.is_empty() || credentials.password.is_empty() {
+        return Err(TerraformError::ConfigFileInvalidContent {
+            path: database_terraform_credentials_file.to_string(),
+            raw_message: "Terraform config error, generated database credentials are empty.".to_string(),
+        });
+    }
+
+    Ok(credentials)
+}
+
+fn read_database_terraform_file<T: DeserializeOwned>(file_path: &str, description: &str) -> Result<T, TerraformError> {
+    let file_content = match File::open(file_path) {
         Ok(f) => f,
         Err(e) => {
             return Err(TerraformError::ConfigFileNotFound {
-                path: database_terraform_config_file.to_string(),
-                raw_message: format!("Terraform config error, database config cannot be found.\n{}", e),
+                path: file_path.to_string(),
+                raw_message: format!("Terraform config error, {} cannot be found.\n{}", description, e),
             });
         }
     };
@@ -153,8 +228,8 @@ pub fn get_database_terraform_config(
     match serde_json::from_reader(reader) {
         Ok(config) => Ok(config),
         Err(e) => Err(TerraformError::ConfigFileInvalidContent {
-            path: database_terraform_config_file.to_string(),
-            raw_message: format!("Terraform config error, database config cannot be parsed.\n{}", e),
+            path: file_path.to_string(),
+            raw_message: format!("Terraform config error, {} cannot be parsed.\n{}", description, e),
         }),
     }
 }
@@ -185,6 +260,39 @@ impl ServiceVersionCheckResult {
     pub fn message(&self) -> Option<String> {
         self.message.clone()
     }
+
+    /// The matched version is strictly lower than the requested one
+    pub fn is_downgrade(&self) -> bool {
+        self.matched_version.cmp_precedence(&self.requested_version) == Ordering::Less
+    }
+
+    /// Events telling the requested version has been remapped, if it has been.
+    /// Besides the plain message, the versions are sent as data on the `VersionMismatch` step to be alerted on
+    pub fn mismatch_events(&self, event_details: EventDetails) -> Vec<EngineEvent> {
+        let message = match &self.message {
+            Some(message) => message,
+            None => return vec![],
+        };
+
+        let versions = serde_json::json!({
+            "requested_version": self.requested_version.to_string(),
+            "matched_version": self.matched_version.to_string(),
+        });
+
+        vec![
+            EngineEvent::Info(event_details.clone(), EventMessage::new_from_safe(message.clone())),
+            EngineEvent::Info(
+                EventDetails::clone_changing_stage(event_details, Stage::Environment(EnvironmentStep::VersionMismatch)),
+                EventMessage::new_for_sending_core_data(message.clone(), versions.to_string()),
+            ),
+        ]
+    }
+
+    pub fn log_mismatch(&self, logger: &dyn Logger, event_details: EventDetails) {
+        for event in self.mismatch_events(event_details) {
+            logger.log(event);
+        }
+    }
 }
 
 pub fn check_service_version<C: CloudProvider, M: DatabaseMode, T: models::database::DatabaseType<C, M>>(
@@ -205,7 +313,7 @@ where
                     version.as_str()
                 );
 
-                return Ok(ServiceVersionCheckResult::new(
+                let result = ServiceVersionCheckResult::new(
                     VersionsNumber::from_str(&srv_version).map_err(|e| {
                         EngineError::new_version_number_parsing_error(event_details.clone(), srv_version.clone(), e)
                     })?,
@@ -213,7 +321,19 @@ where
                         EngineError::new_version_number_parsing_error(event_details.clone(), srv_version, e)
                     })?,
                     Some(message),
-                ));
+                );
+
+                // the data of a database may not be readable by an older version
+                if result.is_downgrade() && !service.allow_version_downgrade {
+                    return Err(Box::new(EngineError::new_database_version_downgrade(
+                        event_details,
+                        service.service_type().name(),
+                        result.requested_version.to_string(),
+                        result.matched_version.to_string(),
+                    )));
+                }
+
+                return Ok(result);
             }
 
             Ok(ServiceVersionCheckResult::new(
@@ -238,40 +358,294 @@ pub fn get_tfstate_suffix(service: &dyn Service) -> String {
     service.id().to_string()
 }
 
+pub const TFSTATE_DEFAULT_WORKSPACE: &str = "default";
+// Kubernetes secret names are DNS-1123 subdomains, limited to 253 characters
+const TFSTATE_NAME_MAX_LENGTH: usize = 253;
+
+// The workspace is part of the secret name, so it must only contain characters allowed in it
+fn sanitize_tfstate_workspace(workspace: &str, secret_suffix: &str) -> String {
+    let max_length = TFSTATE_NAME_MAX_LENGTH - format!("tfstate--{}", secret_suffix).len();
+    let workspace: String = workspace
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '-' | '.' => c,
+            _ => '-',
+        })
+        .take(max_length)
+        .collect();
+    let workspace = workspace.trim_matches(|c: char| !c.is_ascii_alphanumeric());
+
+    match workspace.is_empty() {
+        true => TFSTATE_DEFAULT_WORKSPACE.to_string(),
+        false => workspace.to_string(),
+    }
+}
+
+/// Workspace terraform must run into for the state to be stored in the secret named by `get_tfstate_name`
+pub fn get_tfstate_workspace(service: &dyn Service) -> String {
+    sanitize_tfstate_workspace(&service.tfstate_workspace(), &get_tfstate_suffix(service))
+}
+
 // Name generated from TF secret suffix
 // https://www.terraform.io/docs/backends/types/kubernetes.html#secret_suffix
 // As mention the doc: Secrets will be named in the format: tfstate-{workspace}-{secret_suffix}.
 pub fn get_tfstate_name(service: &dyn Service) -> String {
-    format!("tfstate-default-{}", service.id())
+    format!("tfstate-{}-{}", get_tfstate_workspace(service), get_tfstate_suffix(service))
+}
+
+// A pod stays pending for a few seconds while the scheduler places it, deleting it then only restarts the cycle
+const PENDING_POD_DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(60);
+const PENDING_POD_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+pub fn default_pending_pod_grace_period() -> Duration {
+    match env::var("PENDING_POD_GRACE_PERIOD_IN_SECS") {
+        Ok(env_var) => match env_var.parse::<u64>() {
+            Ok(grace_period) => Duration::from_secs(grace_period),
+            Err(_) => PENDING_POD_DEFAULT_GRACE_PERIOD,
+        },
+        Err(_) => PENDING_POD_DEFAULT_GRACE_PERIOD,
+    }
 }
 
+/// How long the pod has been pending, since it started or since its creation if it has not started yet
+fn pending_duration(pod: &KubernetesPod, now: DateTime<Utc>) -> Option<Duration> {
+    if pod.status.phase != KubernetesPodStatusPhase::Pending {
+        return None;
+    }
+
+    let pending_since = pod
+        .status
+        .start_time
+        .as_deref()
+        .or(pod.metadata.creation_timestamp.as_deref())
+        .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok());
+
+    match pending_since {
+        Some(pending_since) => Some((now - pending_since.with_timezone(&Utc)).to_std().unwrap_or_default()),
+        // Without timestamp, the pod cannot be told apart from one pending for long
+        None => Some(Duration::MAX),
+    }
+}
+
+/// Why the scheduler cannot place the pod, i.e: not enough resources left on the nodes
+fn unschedulable_message(pod: &KubernetesPod) -> Option<String> {
+    pod.status
+        .conditions
+        .iter()
+        .flatten()
+        .find(|condition| condition.typee == "PodScheduled" && condition.status == "False")
+        .map(|condition| {
+            format!(
+                "Pod {} cannot be scheduled: {}",
+                pod.metadata.name,
+                condition.message.as_deref().unwrap_or("no node can host it")
+            )
+        })
+}
+
+/// Deletes the pods of the service pending for longer than the grace period, waiting for the younger ones to either
+/// be scheduled or exceed it. Returns why the deleted pods could not be scheduled, for the user to learn about it
 pub fn delete_pending_service<P>(
     kubernetes_config: P,
     namespace: &str,
     selector: &str,
     envs: Vec<(&str, &str)>,
     event_details: EventDetails,
-) -> Result<(), Box<EngineError>>
+) -> Result<Vec<String>, Box<EngineError>>
 where
     P: AsRef<Path>,
 {
-    match kubectl_exec_get_pods(&kubernetes_config, Some(namespace), Some(selector), envs.clone()) {
-        Ok(pods) => {
-            for pod in pods.items {
-                if pod.status.phase == KubernetesPodStatusPhase::Pending {
-                    if let Err(e) = kubectl_exec_delete_pod(
+    let grace_period = default_pending_pod_grace_period();
+    let started_at = Instant::now();
+    let mut unschedulable_messages = vec![];
+
+    loop {
+        let pods = kubectl_exec_get_pods(&kubernetes_config, Some(namespace), Some(selector), envs.clone())
+            .map_err(|e| EngineError::new_k8s_service_issue(event_details.clone(), e))?;
+
+        let now = Utc::now();
+        let mut has_pods_within_grace_period = false;
+        for pod in pods.items {
+            match pending_duration(&pod, now) {
+                None => {}
+                Some(pending_duration) if pending_duration < grace_period => has_pods_within_grace_period = true,
+                Some(_) => {
+                    unschedulable_messages.extend(unschedulable_message(&pod));
+                    kubectl_exec_delete_pod(
                         &kubernetes_config,
                         pod.metadata.namespace.as_str(),
                         pod.metadata.name.as_str(),
                         envs.clone(),
-                    ) {
-                        return Err(Box::new(EngineError::new_k8s_service_issue(event_details, e)));
-                    }
+                    )
+                    .map_err(|e| EngineError::new_k8s_service_issue(event_details.clone(), e))?;
                 }
             }
+        }
 
-            Ok(())
+        // Deleted pods are replaced by new pending ones, which are not waited for
+        if !has_pods_within_grace_period || started_at.elapsed() >= grace_period {
+            return Ok(unschedulable_messages);
         }
-        Err(e) => Err(Box::new(EngineError::new_k8s_service_issue(event_details, e))),
+
+        thread::sleep(PENDING_POD_CHECK_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cloud_provider::service::{
+        get_database_terraform_credentials, pending_duration, sanitize_tfstate_workspace, unschedulable_message,
+        DatabaseType, ServiceType, ServiceVersionCheckResult, TFSTATE_DEFAULT_WORKSPACE, TFSTATE_NAME_MAX_LENGTH,
+    };
+    use crate::cmd::structs::KubernetesPod;
+    use crate::cmd::terraform::TerraformError;
+    use crate::events::{EngineEvent, EnvironmentStep, EventDetails, EventMessageVerbosity, Stage, Transmitter};
+    use crate::io_models::QoveryIdentifier;
+    use crate::models::types::VersionsNumber;
+    use chrono::{DateTime, Utc};
+    use std::fs;
+    use std::str::FromStr;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_get_database_terraform_credentials() {
+        let workspace = tempfile::tempdir().expect("cannot create temp dir");
+        let credentials_file = workspace.path().join("database-credentials.json");
+        let credentials_path = credentials_file.to_string_lossy().to_string();
+
+        assert!(matches!(
+            get_database_terraform_credentials(&credentials_path),
+            Err(TerraformError::ConfigFileNotFound { .. })
+        ));
+
+        fs::write(&credentials_file, r#"{"database_login": "superuser", "database_password": ""}"#).unwrap();
+        assert!(matches!(
+            get_database_terraform_credentials(&credentials_path),
+            Err(TerraformError::ConfigFileInvalidContent { .. })
+        ));
+
+        fs::write(
+            &credentials_file,
+            r#"{"database_login": "superuser", "database_password": "p4ss"}"#,
+        )
+        .unwrap();
+        let credentials = get_database_terraform_credentials(&credentials_path).expect("valid credentials");
+        assert_eq!(credentials.login, "superuser");
+        assert_eq!(credentials.password, "p4ss");
+    }
+
+    #[test]
+    fn test_database_service_type_name() {
+        assert_eq!(DatabaseType::Redis.to_string(), "Redis");
+        assert_eq!(ServiceType::Database(DatabaseType::Redis).name(), "Redis database");
+        assert_eq!(
+            ServiceType::Database(DatabaseType::PostgreSQL).to_string(),
+            "PostgreSQL database"
+        );
+    }
+
+    #[test]
+    fn test_version_mismatch_events() {
+        let event_details = EventDetails::new(
+            None,
+            QoveryIdentifier::new_random(),
+            QoveryIdentifier::new_random(),
+            "".to_string(),
+            Stage::Environment(EnvironmentStep::LoadConfiguration),
+            Transmitter::Database(Uuid::new_v4(), "my-redis".to_string()),
+        );
+        let matching = ServiceVersionCheckResult::new(
+            VersionsNumber::from_str("7.0.5").unwrap(),
+            VersionsNumber::from_str("7.0.5").unwrap(),
+            None,
+        );
+        assert!(matching.mismatch_events(event_details.clone()).is_empty());
+
+        let remapped = ServiceVersionCheckResult::new(
+            VersionsNumber::from_str("6").unwrap(),
+            VersionsNumber::from_str("6.2.7").unwrap(),
+            Some("remapped".to_string()),
+        );
+        let events = remapped.mismatch_events(event_details);
+        assert_eq!(events.len(), 2);
+        match &events[1] {
+            EngineEvent::Info(details, message) => {
+                assert_eq!(details.stage(), &Stage::Environment(EnvironmentStep::VersionMismatch));
+                let data = message.message(EventMessageVerbosity::FullDetailsWithoutEnvVars);
+                assert!(data.contains(r#""requested_version":"6""#));
+                assert!(data.contains(r#""matched_version":"6.2.7""#));
+            }
+            _ => panic!("version mismatch should be an info event"),
+        }
+    }
+
+    #[test]
+    fn test_pending_pods() {
+        let pod = |phase: &str, start_time: Option<&str>| -> KubernetesPod {
+            serde_json::from_value(serde_json::json!({
+                "metadata": {
+                    "name": "app-7d9f",
+                    "namespace": "z1234",
+                    "creationTimestamp": "2022-10-10T10:00:00Z"
+                },
+                "status": {
+                    "phase": phase,
+                    "startTime": start_time,
+                    "conditions": [{
+                        "type": "PodScheduled",
+                        "status": "False",
+                        "reason": "Unschedulable",
+                        "message": "0/3 nodes are available: 3 Insufficient cpu."
+                    }]
+                }
+            }))
+            .unwrap()
+        };
+        let now = DateTime::parse_from_rfc3339("2022-10-10T10:01:30Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(pending_duration(&pod("Running", None), now), None);
+        // not started yet, pending since its creation
+        assert_eq!(pending_duration(&pod("Pending", None), now), Some(Duration::from_secs(90)));
+        assert_eq!(
+            pending_duration(&pod("Pending", Some("2022-10-10T10:01:00Z")), now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            unschedulable_message(&pod("Pending", None)),
+            Some("Pod app-7d9f cannot be scheduled: 0/3 nodes are available: 3 Insufficient cpu.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_version_check_downgrade() {
+        let check = |requested: &str, matched: &str| {
+            ServiceVersionCheckResult::new(
+                VersionsNumber::from_str(requested).unwrap(),
+                VersionsNumber::from_str(matched).unwrap(),
+                None,
+            )
+        };
+
+        assert!(check("13.9", "13.4").is_downgrade());
+        assert!(check("1.10", "1.9").is_downgrade());
+        assert!(!check("13", "13.4").is_downgrade());
+        assert!(!check("13", "13.0.0").is_downgrade());
+    }
+
+    #[test]
+    fn test_sanitize_tfstate_workspace() {
+        let suffix = "z1234abcd";
+
+        assert_eq!(sanitize_tfstate_workspace("default", suffix), "default");
+        assert_eq!(sanitize_tfstate_workspace("Staging_EU", suffix), "staging-eu");
+        assert_eq!(sanitize_tfstate_workspace("_preview/42_", suffix), "preview-42");
+        assert_eq!(sanitize_tfstate_workspace("__", suffix), TFSTATE_DEFAULT_WORKSPACE);
+
+        let workspace = sanitize_tfstate_workspace(&"a".repeat(300), suffix);
+        assert_eq!(format!("tfstate-{}-{}", workspace, suffix).len(), TFSTATE_NAME_MAX_LENGTH);
     }
 }
@@ -8,6 +8,7 @@ use core::result::Result;
 use core::result::Result::{Err, Ok};
 use retry::delay::Fixed;
 use retry::{Error, OperationResult};
+use std::io;
 use std::net::ToSocketAddrs;
 use std::net::{SocketAddr, TcpStream as NetTcpStream};
 use std::time::Duration;
@@ -125,10 +126,24 @@ pub fn managed_db_name_sanitizer(max_size: usize, prefix: &str, name: &str) -> S
 #[derive(PartialEq, Eq, Debug)]
 pub enum TcpCheckErrors {
     DomainNotResolvable,
+    /// The host answered but refused the connection: it is up, nothing listens on the port yet
     PortNotOpen,
+    /// Nothing answered before the timeout: the host is down or packets are dropped on the way
+    PortUnreachable,
     UnknownError,
 }
 
+impl fmt::Display for TcpCheckErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TcpCheckErrors::DomainNotResolvable => write!(f, "domain cannot be resolved"),
+            TcpCheckErrors::PortNotOpen => write!(f, "connection refused, host is up but port is closed"),
+            TcpCheckErrors::PortUnreachable => write!(f, "connection timed out, host is unreachable"),
+            TcpCheckErrors::UnknownError => write!(f, "unknown error"),
+        }
+    }
+}
+
 pub enum TcpCheckSource<'a> {
     SocketAddr(SocketAddr),
     DnsName(&'a str),
@@ -143,9 +158,18 @@ impl fmt::Display for TcpCheckSource<'_> {
     }
 }
 
+/// Default time we allow a TCP connection to be established before considering the port as not open
+pub const DEFAULT_TCP_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
 pub fn check_tcp_port_is_open(address: &TcpCheckSource, port: u16) -> Result<(), TcpCheckErrors> {
-    let timeout = core::time::Duration::from_secs(1);
+    check_tcp_port_is_open_with_timeout(address, port, DEFAULT_TCP_CHECK_TIMEOUT)
+}
 
+pub fn check_tcp_port_is_open_with_timeout(
+    address: &TcpCheckSource,
+    port: u16,
+    timeout: Duration,
+) -> Result<(), TcpCheckErrors> {
     let ip = match address {
         TcpCheckSource::SocketAddr(x) => *x,
         TcpCheckSource::DnsName(x) => {
@@ -162,7 +186,8 @@ pub fn check_tcp_port_is_open(address: &TcpCheckSource, port: u16) -> Result<(),
 
     match NetTcpStream::connect_timeout(&ip, timeout) {
         Ok(_) => Ok(()),
-        Err(_) => Err(TcpCheckErrors::PortNotOpen),
+        Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => Err(TcpCheckErrors::PortNotOpen),
+        Err(_) => Err(TcpCheckErrors::PortUnreachable),
     }
 }
 
@@ -170,18 +195,21 @@ pub fn wait_until_port_is_open(
     address: &TcpCheckSource,
     port: u16,
     max_timeout: usize,
+    probe_timeout: Duration,
     logger: &dyn Logger,
     event_details: EventDetails,
 ) -> Result<(), TcpCheckErrors> {
     let fixed_iterable = Fixed::from(core::time::Duration::from_secs(1)).take(max_timeout);
-    let check_result = retry::retry(fixed_iterable, || match check_tcp_port_is_open(address, port) {
-        Ok(_) => OperationResult::Ok(()),
-        Err(e) => {
-            logger.log(EngineEvent::Info(
-                event_details.clone(),
-                EventMessage::new_from_safe(format!("{}:{} is still not ready: {:?}. retrying...", address, port, e)),
-            ));
-            OperationResult::Retry(e)
+    let check_result = retry::retry(fixed_iterable, || {
+        match check_tcp_port_is_open_with_timeout(address, port, probe_timeout) {
+            Ok(_) => OperationResult::Ok(()),
+            Err(e) => {
+                logger.log(EngineEvent::Info(
+                    event_details.clone(),
+                    EventMessage::new_from_safe(format!("{}:{} is still not ready: {}. retrying...", address, port, e)),
+                ));
+                OperationResult::Retry(e)
+            }
         }
     });
 
@@ -212,11 +240,13 @@ pub fn print_action(
 #[cfg(test)]
 mod tests {
     use crate::cloud_provider::utilities::{
-        await_domain_resolve_cname, check_tcp_port_is_open, TcpCheckErrors, TcpCheckSource,
+        await_domain_resolve_cname, check_tcp_port_is_open, check_tcp_port_is_open_with_timeout, TcpCheckErrors,
+        TcpCheckSource,
     };
     use crate::cmd::command::CommandKiller;
     use crate::errors::CommandError;
     use crate::models::types::VersionsNumber;
+    use std::net::TcpListener;
     use std::str::FromStr;
     use std::time::Duration;
 
@@ -232,8 +262,33 @@ mod tests {
             check_tcp_port_is_open(&TcpCheckSource::DnsName(address_nok), port_ok).unwrap_err(),
             TcpCheckErrors::DomainNotResolvable
         );
-        assert_eq!(
+        // depending on the host firewall, the connection is either refused or dropped
+        assert!(matches!(
             check_tcp_port_is_open(&TcpCheckSource::DnsName(address_ok), port_nok).unwrap_err(),
+            TcpCheckErrors::PortNotOpen | TcpCheckErrors::PortUnreachable
+        ));
+    }
+
+    #[test]
+    pub fn test_port_open_with_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("cannot bind local listener");
+        let address = listener.local_addr().expect("cannot get local listener address");
+
+        assert!(check_tcp_port_is_open_with_timeout(
+            &TcpCheckSource::SocketAddr(address),
+            address.port(),
+            Duration::from_millis(500)
+        )
+        .is_ok());
+
+        drop(listener);
+        assert_eq!(
+            check_tcp_port_is_open_with_timeout(
+                &TcpCheckSource::SocketAddr(address),
+                address.port(),
+                Duration::from_millis(500)
+            )
+            .unwrap_err(),
             TcpCheckErrors::PortNotOpen
         );
     }
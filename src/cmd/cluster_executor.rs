@@ -0,0 +1,580 @@
+use crate::cloud_provider::helm::ChartInfo;
+use crate::cmd::command::CommandKiller;
+use crate::cmd::helm::{Helm, HelmCommand, HelmError};
+use crate::cmd::kubectl::{
+    kubectl_apply_with_path, kubectl_exec_adopt_resource_in_helm_release, kubectl_exec_delete_by_selector,
+    kubectl_exec_delete_secret, kubectl_exec_wait_for_pods_condition, PodCondition,
+};
+use crate::cmd::terraform::{
+    terraform_apply_saved_plan, terraform_init_validate_destroy, terraform_init_validate_plan,
+    terraform_init_validate_plan_apply, terraform_init_validate_plan_destroy, TerraformError,
+};
+use crate::errors::CommandError;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+/// Abstraction over the tools (helm, kubectl, terraform) used to act on the cluster during a deployment.
+/// It allows to test deployment orchestration without a real cluster.
+pub trait ClusterExecutor: Send + Sync {
+    fn helm_upgrade_diff(&self, chart: &ChartInfo) -> Result<(), HelmError>;
+    fn helm_dependency_build(&self, chart: &ChartInfo) -> Result<(), HelmError>;
+    fn helm_upgrade(&self, chart: &ChartInfo, cmd_killer: &CommandKiller) -> Result<(), HelmError>;
+    fn helm_uninstall(&self, chart: &ChartInfo) -> Result<(), HelmError>;
+    fn helm_rollback(&self, chart: &ChartInfo, revision: u32) -> Result<(), HelmError>;
+    fn kubectl_delete_secret(&self, namespace: &str, secret_name: &str) -> Result<(), CommandError>;
+    fn kubectl_adopt_resource_in_helm_release(
+        &self,
+        namespace: &str,
+        resource_kind: &str,
+        resource_name: &str,
+        chart: &ChartInfo,
+    ) -> Result<(), CommandError>;
+    fn terraform_plan(&self, root_dir: &str) -> Result<Vec<String>, TerraformError>;
+    fn terraform_apply(&self, root_dir: &str, dry_run: bool) -> Result<Vec<String>, TerraformError>;
+    /// Apply the plan saved by the last terraform_plan of root_dir
+    fn terraform_apply_plan(&self, root_dir: &str) -> Result<Vec<String>, TerraformError>;
+    fn terraform_plan_destroy(&self, root_dir: &str) -> Result<Vec<String>, TerraformError>;
+    fn terraform_destroy(&self, root_dir: &str, run_apply_before_destroy: bool) -> Result<Vec<String>, TerraformError>;
+}
+
+/// Executor calling the real binaries against the cluster
+pub struct BinaryClusterExecutor {
+    helm: Helm,
+    kubernetes_config: PathBuf,
+    envs: Vec<(String, String)>,
+}
+
+impl BinaryClusterExecutor {
+    pub fn new(helm: Helm, kubernetes_config: PathBuf, envs: Vec<(String, String)>) -> BinaryClusterExecutor {
+        BinaryClusterExecutor {
+            helm,
+            kubernetes_config,
+            envs,
+        }
+    }
+}
+
+impl ClusterExecutor for BinaryClusterExecutor {
+    fn helm_upgrade_diff(&self, chart: &ChartInfo) -> Result<(), HelmError> {
+        self.helm.upgrade_diff(chart, &[])
+    }
+
+    fn helm_dependency_build(&self, chart: &ChartInfo) -> Result<(), HelmError> {
+        self.helm.dependency_build(chart, &[])
+    }
+
+    fn helm_upgrade(&self, chart: &ChartInfo, cmd_killer: &CommandKiller) -> Result<(), HelmError> {
+        self.helm.upgrade(chart, &[], cmd_killer)
+    }
+
+    fn helm_uninstall(&self, chart: &ChartInfo) -> Result<(), HelmError> {
+        self.helm.uninstall(chart, &[])
+    }
+
+    fn helm_rollback(&self, chart: &ChartInfo, revision: u32) -> Result<(), HelmError> {
+        self.helm.rollback_to_revision(chart, revision, &[])
+    }
+
+    fn kubectl_delete_secret(&self, namespace: &str, secret_name: &str) -> Result<(), CommandError> {
+        kubectl_exec_delete_secret(
+            &self.kubernetes_config,
+            namespace,
+            secret_name,
+            self.envs.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect(),
+        )
+    }
+
+    fn kubectl_adopt_resource_in_helm_release(
+        &self,
+        namespace: &str,
+        resource_kind: &str,
+        resource_name: &str,
+        chart: &ChartInfo,
+    ) -> Result<(), CommandError> {
+        kubectl_exec_adopt_resource_in_helm_release(
+            &self.kubernetes_config,
+            namespace,
+            resource_kind,
+            resource_name,
+            &chart.name,
+            &chart.get_namespace_string(),
+            self.envs.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect(),
+        )
+    }
+
+    fn terraform_plan(&self, root_dir: &str) -> Result<Vec<String>, TerraformError> {
+        terraform_init_validate_plan(root_dir)
+    }
+
+    fn terraform_apply(&self, root_dir: &str, dry_run: bool) -> Result<Vec<String>, TerraformError> {
+        terraform_init_validate_plan_apply(root_dir, dry_run)
+    }
+
+    fn terraform_apply_plan(&self, root_dir: &str) -> Result<Vec<String>, TerraformError> {
+        terraform_apply_saved_plan(root_dir)
+    }
+
+    fn terraform_plan_destroy(&self, root_dir: &str) -> Result<Vec<String>, TerraformError> {
+        terraform_init_validate_plan_destroy(root_dir)
+    }
+
+    fn terraform_destroy(&self, root_dir: &str, run_apply_before_destroy: bool) -> Result<Vec<String>, TerraformError> {
+        terraform_init_validate_destroy(root_dir, run_apply_before_destroy)
+    }
+}
+
+// kinds created by our charts, deleted by selector as there is no helm release to uninstall
+const KUBECTL_APPLY_MANAGED_KINDS: &str =
+    "deployment,statefulset,service,configmap,secret,ingress,horizontalpodautoscaler,poddisruptionbudget,serviceaccount";
+
+fn is_helm_installed() -> bool {
+    Command::new("helm")
+        .arg("version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+/// Executor bypassing helm: charts are applied with kubectl, from the manifests rendered beforehand
+/// (i.e: by `helm template --output-dir`) when given, otherwise rendered locally with helm.
+/// Helm hooks, release history and rollback are not supported, it is only meant for clusters where helm can't be used.
+pub struct KubectlApplyClusterExecutor {
+    helm: Helm,
+    kubernetes_config: PathBuf,
+    envs: Vec<(String, String)>,
+    // one directory of manifests per release name
+    rendered_charts_directory: Option<PathBuf>,
+}
+
+impl KubectlApplyClusterExecutor {
+    pub fn new(
+        helm: Helm,
+        kubernetes_config: PathBuf,
+        envs: Vec<(String, String)>,
+        rendered_charts_directory: Option<PathBuf>,
+    ) -> KubectlApplyClusterExecutor {
+        KubectlApplyClusterExecutor {
+            helm,
+            kubernetes_config,
+            envs,
+            rendered_charts_directory,
+        }
+    }
+
+    fn envs(&self) -> Vec<(&str, &str)> {
+        self.envs.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect()
+    }
+
+    fn render_directory(chart: &ChartInfo) -> String {
+        format!("{}-rendered", chart.path.trim_end_matches('/'))
+    }
+
+    fn helm_not_installed_error(chart: &ChartInfo, command: HelmCommand) -> HelmError {
+        HelmError::CmdError(
+            chart.name.clone(),
+            command,
+            CommandError::new_from_safe_message(format!(
+                "Cannot render `{}`: helm is not installed, its manifests must be rendered beforehand",
+                chart.name
+            )),
+        )
+    }
+
+    /// Directory holding the manifests to apply, rendering them with helm when they have not been given
+    fn manifests_directory(&self, chart: &ChartInfo) -> Result<String, HelmError> {
+        let to_helm_error = |err: CommandError| HelmError::CmdError(chart.name.clone(), HelmCommand::UPGRADE, err);
+
+        if let Some(rendered_charts_directory) = &self.rendered_charts_directory {
+            let manifests_directory = rendered_charts_directory.join(&chart.name);
+            if !manifests_directory.is_dir() {
+                return Err(to_helm_error(CommandError::new_from_safe_message(format!(
+                    "Cannot deploy `{}` without helm: no rendered manifests in `{}`",
+                    chart.name,
+                    manifests_directory.to_string_lossy()
+                ))));
+            }
+            return Ok(manifests_directory.to_string_lossy().to_string());
+        }
+
+        if !is_helm_installed() {
+            return Err(Self::helm_not_installed_error(chart, HelmCommand::TEMPLATE));
+        }
+
+        let render_directory = Self::render_directory(chart);
+        // remove manifests of a previous render, templates may have been removed since
+        if let Err(err) = std::fs::remove_dir_all(&render_directory) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                return Err(to_helm_error(CommandError::new(
+                    format!("Cannot clean render directory `{}`", render_directory),
+                    Some(err.to_string()),
+                    None,
+                )));
+            }
+        }
+
+        self.helm.template_validate(chart, &[], Some(&render_directory))?;
+        Ok(render_directory)
+    }
+}
+
+impl ClusterExecutor for KubectlApplyClusterExecutor {
+    fn helm_upgrade_diff(&self, _chart: &ChartInfo) -> Result<(), HelmError> {
+        // nothing to diff against without a helm release
+        Ok(())
+    }
+
+    fn helm_dependency_build(&self, chart: &ChartInfo) -> Result<(), HelmError> {
+        // rendered manifests already include the subcharts
+        if self.rendered_charts_directory.is_some() {
+            return Ok(());
+        }
+
+        if !is_helm_installed() {
+            return Err(Self::helm_not_installed_error(chart, HelmCommand::DEPENDENCY));
+        }
+
+        // subcharts are rendered along the chart, so they must be fetched as well
+        self.helm.dependency_build(chart, &[])
+    }
+
+    fn helm_upgrade(&self, chart: &ChartInfo, _cmd_killer: &CommandKiller) -> Result<(), HelmError> {
+        let to_helm_error = |err: CommandError| HelmError::CmdError(chart.name.clone(), HelmCommand::UPGRADE, err);
+        let render_directory = self.manifests_directory(chart)?;
+
+        let namespace = chart.get_namespace_string();
+        let mut apply_args = vec!["--recursive", "-n", &namespace];
+        // the api server still validates the manifests, without persisting them
+        if chart.dry_run {
+            apply_args.push("--dry-run=server");
+        }
+        kubectl_apply_with_path(&self.kubernetes_config, self.envs(), &render_directory, Some(apply_args))
+            .map_err(to_helm_error)?;
+        if chart.dry_run {
+            return Ok(());
+        }
+
+        // helm --wait is not available anymore, do the readiness check ourselves
+        if let Some(selector) = &chart.k8s_selector {
+            kubectl_exec_wait_for_pods_condition(
+                &self.kubernetes_config,
+                self.envs(),
+                &namespace,
+                selector,
+                PodCondition::Ready,
+            )
+            .map_err(to_helm_error)?;
+        }
+
+        Ok(())
+    }
+
+    fn helm_uninstall(&self, chart: &ChartInfo) -> Result<(), HelmError> {
+        let selector = match &chart.k8s_selector {
+            Some(selector) => selector,
+            None => {
+                return Err(HelmError::CmdError(
+                    chart.name.clone(),
+                    HelmCommand::UNINSTALL,
+                    CommandError::new_from_safe_message(format!(
+                        "Cannot delete `{}` without helm: no selector to find its resources",
+                        chart.name
+                    )),
+                ))
+            }
+        };
+
+        kubectl_exec_delete_by_selector(
+            &self.kubernetes_config,
+            &chart.get_namespace_string(),
+            KUBECTL_APPLY_MANAGED_KINDS,
+            selector,
+            self.envs(),
+        )
+        .map_err(|err| HelmError::CmdError(chart.name.clone(), HelmCommand::UNINSTALL, err))
+    }
+
+    fn helm_rollback(&self, chart: &ChartInfo, _revision: u32) -> Result<(), HelmError> {
+        Err(HelmError::CmdError(
+            chart.name.clone(),
+            HelmCommand::ROLLBACK,
+            CommandError::new_from_safe_message(format!(
+                "Cannot rollback `{}` without helm: there is no release history",
+                chart.name
+            )),
+        ))
+    }
+
+    fn kubectl_delete_secret(&self, namespace: &str, secret_name: &str) -> Result<(), CommandError> {
+        kubectl_exec_delete_secret(&self.kubernetes_config, namespace, secret_name, self.envs())
+    }
+
+    fn kubectl_adopt_resource_in_helm_release(
+        &self,
+        _namespace: &str,
+        _resource_kind: &str,
+        _resource_name: &str,
+        _chart: &ChartInfo,
+    ) -> Result<(), CommandError> {
+        // there is no helm release to adopt the resource, kubectl apply takes over existing resources
+        Ok(())
+    }
+
+    fn terraform_plan(&self, root_dir: &str) -> Result<Vec<String>, TerraformError> {
+        terraform_init_validate_plan(root_dir)
+    }
+
+    fn terraform_apply(&self, root_dir: &str, dry_run: bool) -> Result<Vec<String>, TerraformError> {
+        terraform_init_validate_plan_apply(root_dir, dry_run)
+    }
+
+    fn terraform_apply_plan(&self, root_dir: &str) -> Result<Vec<String>, TerraformError> {
+        terraform_apply_saved_plan(root_dir)
+    }
+
+    fn terraform_plan_destroy(&self, root_dir: &str) -> Result<Vec<String>, TerraformError> {
+        terraform_init_validate_plan_destroy(root_dir)
+    }
+
+    fn terraform_destroy(&self, root_dir: &str, run_apply_before_destroy: bool) -> Result<Vec<String>, TerraformError> {
+        terraform_init_validate_destroy(root_dir, run_apply_before_destroy)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClusterCallKind {
+    HelmUpgradeDiff,
+    HelmDependencyBuild,
+    HelmUpgrade,
+    HelmUninstall,
+    HelmRollback,
+    KubectlDeleteSecret,
+    KubectlAdoptResource,
+    TerraformPlan,
+    TerraformApply,
+    TerraformApplyPlan,
+    TerraformPlanDestroy,
+    TerraformDestroy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterCall {
+    pub kind: ClusterCallKind,
+    // helm release name, secret name or terraform root directory
+    pub target: String,
+    pub namespace: Option<String>,
+}
+
+/// Executor recording every call it receives, without touching any cluster.
+/// All calls succeed, unless they have been configured to fail.
+/// Clones share the recorded calls, so one can be kept to inspect what a deployment target has run.
+#[derive(Default, Clone)]
+pub struct MockClusterExecutor {
+    calls: Arc<Mutex<Vec<ClusterCall>>>,
+    failing_calls: HashSet<ClusterCallKind>,
+}
+
+impl MockClusterExecutor {
+    pub fn new() -> MockClusterExecutor {
+        MockClusterExecutor::default()
+    }
+
+    pub fn with_failing_call(mut self, kind: ClusterCallKind) -> MockClusterExecutor {
+        self.failing_calls.insert(kind);
+        self
+    }
+
+    pub fn calls(&self) -> Vec<ClusterCall> {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn record(&self, kind: ClusterCallKind, target: &str, namespace: Option<String>) -> bool {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).push(ClusterCall {
+            kind,
+            target: target.to_string(),
+            namespace,
+        });
+
+        !self.failing_calls.contains(&kind)
+    }
+
+    fn helm_result(&self, kind: ClusterCallKind, chart: &ChartInfo, command: HelmCommand) -> Result<(), HelmError> {
+        match self.record(kind, &chart.name, Some(chart.get_namespace_string())) {
+            true => Ok(()),
+            false => Err(HelmError::CmdError(
+                chart.name.clone(),
+                command,
+                CommandError::new_from_safe_message(format!("Mocked {:?} failure", kind)),
+            )),
+        }
+    }
+
+    fn terraform_result(&self, kind: ClusterCallKind, root_dir: &str) -> Result<Vec<String>, TerraformError> {
+        match self.record(kind, root_dir, None) {
+            true => Ok(vec![]),
+            false => Err(TerraformError::Unknown {
+                terraform_args: vec![],
+                raw_message: format!("Mocked {:?} failure", kind),
+            }),
+        }
+    }
+}
+
+impl ClusterExecutor for MockClusterExecutor {
+    fn helm_upgrade_diff(&self, chart: &ChartInfo) -> Result<(), HelmError> {
+        self.helm_result(ClusterCallKind::HelmUpgradeDiff, chart, HelmCommand::DIFF)
+    }
+
+    fn helm_dependency_build(&self, chart: &ChartInfo) -> Result<(), HelmError> {
+        self.helm_result(ClusterCallKind::HelmDependencyBuild, chart, HelmCommand::DEPENDENCY)
+    }
+
+    fn helm_upgrade(&self, chart: &ChartInfo, _cmd_killer: &CommandKiller) -> Result<(), HelmError> {
+        self.helm_result(ClusterCallKind::HelmUpgrade, chart, HelmCommand::UPGRADE)
+    }
+
+    fn helm_uninstall(&self, chart: &ChartInfo) -> Result<(), HelmError> {
+        self.helm_result(ClusterCallKind::HelmUninstall, chart, HelmCommand::UNINSTALL)
+    }
+
+    fn helm_rollback(&self, chart: &ChartInfo, _revision: u32) -> Result<(), HelmError> {
+        self.helm_result(ClusterCallKind::HelmRollback, chart, HelmCommand::ROLLBACK)
+    }
+
+    fn kubectl_delete_secret(&self, namespace: &str, secret_name: &str) -> Result<(), CommandError> {
+        match self.record(ClusterCallKind::KubectlDeleteSecret, secret_name, Some(namespace.to_string())) {
+            true => Ok(()),
+            false => Err(CommandError::new_from_safe_message(
+                "Mocked KubectlDeleteSecret failure".to_string(),
+            )),
+        }
+    }
+
+    fn kubectl_adopt_resource_in_helm_release(
+        &self,
+        namespace: &str,
+        resource_kind: &str,
+        resource_name: &str,
+        _chart: &ChartInfo,
+    ) -> Result<(), CommandError> {
+        match self.record(
+            ClusterCallKind::KubectlAdoptResource,
+            &format!("{}/{}", resource_kind, resource_name),
+            Some(namespace.to_string()),
+        ) {
+            true => Ok(()),
+            false => Err(CommandError::new_from_safe_message(
+                "Mocked KubectlAdoptResource failure".to_string(),
+            )),
+        }
+    }
+
+    fn terraform_plan(&self, root_dir: &str) -> Result<Vec<String>, TerraformError> {
+        self.terraform_result(ClusterCallKind::TerraformPlan, root_dir)
+    }
+
+    fn terraform_apply(&self, root_dir: &str, _dry_run: bool) -> Result<Vec<String>, TerraformError> {
+        self.terraform_result(ClusterCallKind::TerraformApply, root_dir)
+    }
+
+    fn terraform_apply_plan(&self, root_dir: &str) -> Result<Vec<String>, TerraformError> {
+        self.terraform_result(ClusterCallKind::TerraformApplyPlan, root_dir)
+    }
+
+    fn terraform_plan_destroy(&self, root_dir: &str) -> Result<Vec<String>, TerraformError> {
+        self.terraform_result(ClusterCallKind::TerraformPlanDestroy, root_dir)
+    }
+
+    fn terraform_destroy(
+        &self,
+        root_dir: &str,
+        _run_apply_before_destroy: bool,
+    ) -> Result<Vec<String>, TerraformError> {
+        self.terraform_result(ClusterCallKind::TerraformDestroy, root_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cloud_provider::helm::{ChartInfo, HelmChartNamespaces};
+    use crate::cmd::cluster_executor::{
+        ClusterCall, ClusterCallKind, ClusterExecutor, KubectlApplyClusterExecutor, MockClusterExecutor,
+    };
+    use crate::cmd::command::CommandKiller;
+    use crate::cmd::helm::{Helm, HelmCommand, HelmError};
+
+    #[test]
+    fn test_mock_cluster_executor_records_calls() {
+        // setup:
+        let executor = MockClusterExecutor::new().with_failing_call(ClusterCallKind::HelmUninstall);
+        let chart = ChartInfo {
+            name: "my-release".to_string(),
+            namespace: HelmChartNamespaces::Custom,
+            custom_namespace: Some("my-namespace".to_string()),
+            ..Default::default()
+        };
+
+        // execute:
+        let upgrade_result = executor.helm_upgrade(&chart, &CommandKiller::never());
+        let uninstall_result = executor.helm_uninstall(&chart);
+        let terraform_result = executor.terraform_apply("/tmp/terraform", false);
+
+        // verify:
+        assert!(upgrade_result.is_ok());
+        assert!(uninstall_result.is_err());
+        assert!(terraform_result.is_ok());
+        assert_eq!(
+            executor.calls(),
+            vec![
+                ClusterCall {
+                    kind: ClusterCallKind::HelmUpgrade,
+                    target: "my-release".to_string(),
+                    namespace: Some("my-namespace".to_string()),
+                },
+                ClusterCall {
+                    kind: ClusterCallKind::HelmUninstall,
+                    target: "my-release".to_string(),
+                    namespace: Some("my-namespace".to_string()),
+                },
+                ClusterCall {
+                    kind: ClusterCallKind::TerraformApply,
+                    target: "/tmp/terraform".to_string(),
+                    namespace: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_kubectl_apply_cluster_executor_requires_rendered_manifests() {
+        // setup:
+        let workspace = tempfile::tempdir().expect("cannot create temp dir");
+        let kubeconfig = workspace.path().join("kubeconfig");
+        std::fs::write(&kubeconfig, "").unwrap();
+        let rendered_charts_directory = workspace.path().join("rendered");
+        std::fs::create_dir_all(rendered_charts_directory.join("other-release")).unwrap();
+        let executor = KubectlApplyClusterExecutor::new(
+            Helm::new(&kubeconfig, &[]).unwrap(),
+            kubeconfig,
+            vec![],
+            Some(rendered_charts_directory),
+        );
+        let chart = ChartInfo {
+            name: "my-release".to_string(),
+            ..Default::default()
+        };
+
+        // execute:
+        let dependency_build_result = executor.helm_dependency_build(&chart);
+        let upgrade_result = executor.helm_upgrade(&chart, &CommandKiller::never());
+
+        // verify:
+        assert!(dependency_build_result.is_ok());
+        assert!(matches!(
+            upgrade_result,
+            Err(HelmError::CmdError(name, HelmCommand::UPGRADE, _)) if name == "my-release"
+        ));
+    }
+}
@@ -6,7 +6,9 @@ use std::process::{Child, Command, ExitStatus, Stdio};
 
 use crate::cmd::command::CommandError::{ExecutionError, ExitStatusError, Killed, TimeoutError};
 
+use crate::logger::LogTee;
 use itertools::Itertools;
+use std::cell::RefCell;
 use std::time::{Duration, Instant};
 use timeout_readwrite::TimeoutReader;
 
@@ -127,9 +129,38 @@ pub trait ExecutableCommand {
         STDERR: FnMut(String);
 }
 
+thread_local! {
+    // Log tee of the task running on this thread, the output of the commands it spawns is also streamed into it.
+    // Tasks run concurrently on their own thread, so each of them only gets the output of its own commands.
+    static COMMAND_LOG_TEE: RefCell<Option<LogTee>> = RefCell::new(None);
+}
+
+/// Stream the output of the commands spawned by the current thread into the log tee, until the guard is dropped
+pub fn set_command_log_tee(log_tee: Option<LogTee>) -> CommandLogTeeGuard {
+    let previous = COMMAND_LOG_TEE.with(|tee| tee.replace(log_tee));
+    CommandLogTeeGuard { previous }
+}
+
+/// Log tee of the current thread, to propagate it to the threads spawned on behalf of the task
+pub fn current_command_log_tee() -> Option<LogTee> {
+    COMMAND_LOG_TEE.with(|tee| tee.borrow().clone())
+}
+
+pub struct CommandLogTeeGuard {
+    previous: Option<LogTee>,
+}
+
+impl Drop for CommandLogTeeGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        COMMAND_LOG_TEE.with(|tee| *tee.borrow_mut() = previous);
+    }
+}
+
 pub struct QoveryCommand {
     command: Command,
     kill_grace_period: Duration,
+    log_tee: Option<LogTee>,
 }
 
 impl QoveryCommand {
@@ -144,6 +175,7 @@ impl QoveryCommand {
         QoveryCommand {
             command,
             kill_grace_period: Duration::from_secs(60 * 5),
+            log_tee: current_command_log_tee(),
         }
     }
 
@@ -219,6 +251,20 @@ impl ExecutableCommand for QoveryCommand {
         STDERR: FnMut(String),
     {
         info!("command: {:?}", self.command);
+        let log_tee = self.log_tee.clone();
+        let mut stdout_output = |line: String| {
+            if let Some(tee) = &log_tee {
+                tee.send(line.clone());
+            }
+            stdout_output(line)
+        };
+        let mut stderr_output = |line: String| {
+            if let Some(tee) = &log_tee {
+                tee.send(line.clone());
+            }
+            stderr_output(line)
+        };
+
         let mut cmd_handle = self
             .command
             .stdout(Stdio::piped())
@@ -11,10 +11,14 @@ use crate::cmd::structs::{HelmChart, HelmChartVersions, HelmListItem};
 use crate::errors;
 use crate::errors::EngineError;
 use crate::events::EventDetails;
+use regex::Regex;
 use semver::Version;
 use serde_derive::Deserialize;
+use sha2::{Digest, Sha256};
 use std::fs::File;
 use std::str::FromStr;
+use std::{env, fs};
+use uuid::Uuid;
 
 const HELM_DEFAULT_TIMEOUT_IN_SECONDS: u32 = 600;
 const HELM_MAX_HISTORY: &str = "50";
@@ -58,6 +62,15 @@ pub enum HelmError {
 
     #[error("Helm command `{1:?}` for release {0} terminated with an error: {2:?}")]
     CmdError(String, HelmCommand, errors::CommandError),
+
+    #[error("Helm release `{0}` cannot adopt existing {1} `{2}` in namespace `{3}`: invalid ownership metadata")]
+    InvalidOwnershipMetadata(String, String, String, String, Option<String>),
+
+    #[error("Helm chart `{0}` dependency `{1}` cannot be fetched: {2:?}")]
+    DependencyFailed(String, String, errors::CommandError),
+
+    #[error("Helm release `{0}` has been denied by admission webhook `{1}`: {2}")]
+    AdmissionRejected(String, String, String),
 }
 
 #[derive(Debug)]
@@ -75,6 +88,147 @@ pub enum HelmCommand {
     LIST,
     DIFF,
     TEMPLATE,
+    DEPENDENCY,
+}
+
+/// Subchart declared in the `dependencies` of a Chart.yaml
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct ChartDependency {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub repository: String,
+}
+
+impl ChartDependency {
+    fn archive_name(&self) -> String {
+        format!("{}-{}.tgz", self.name, self.version)
+    }
+
+    // the same name and version can be published by different repositories, each one has its own cache directory
+    fn cached_archive(&self, cache_dir: &Path) -> PathBuf {
+        let repository_hash = format!("{:x}", Sha256::digest(self.repository.as_bytes()));
+        cache_dir.join(&repository_hash[..16]).join(self.archive_name())
+    }
+
+    // helm stores fetched subcharts as `<name>-<version>.tgz`, but they can also be vendored unpacked
+    fn is_fetched(&self, charts_dir: &Path) -> bool {
+        if charts_dir.join(&self.name).join("Chart.yaml").exists() {
+            return true;
+        }
+
+        let archive_prefix = format!("{}-", self.name);
+        match fs::read_dir(charts_dir) {
+            Ok(entries) => entries.filter_map(|entry| entry.ok()).any(|entry| {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                file_name.ends_with(".tgz")
+                    && file_name
+                        .strip_prefix(&archive_prefix)
+                        .map(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+                        .unwrap_or(false)
+            }),
+            Err(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+struct ChartMetadata {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    dependencies: Vec<ChartDependency>,
+}
+
+fn chart_metadata(chart_dir: &Path) -> Result<ChartMetadata, errors::CommandError> {
+    let chart_file = chart_dir.join("Chart.yaml");
+    let content = fs::read_to_string(&chart_file).map_err(|e| {
+        errors::CommandError::new(
+            format!("Cannot read chart file `{}`", chart_file.display()),
+            Some(e.to_string()),
+            None,
+        )
+    })?;
+    serde_yaml::from_str(&content).map_err(|e| {
+        errors::CommandError::new(
+            format!("Cannot parse chart file `{}`", chart_file.display()),
+            Some(e.to_string()),
+            None,
+        )
+    })
+}
+
+/// Dependencies declared in the Chart.yaml of the chart directory
+pub fn chart_dependencies(chart_dir: &Path) -> Result<Vec<ChartDependency>, errors::CommandError> {
+    Ok(chart_metadata(chart_dir)?.dependencies)
+}
+
+/// Name declared in the Chart.yaml of the chart directory
+pub fn chart_name(chart_dir: &Path) -> Result<String, errors::CommandError> {
+    Ok(chart_metadata(chart_dir)?.name)
+}
+
+// A failed copy must not leave a partial archive behind, helm would take it for a fetched dependency
+fn restore_cached_dependencies(
+    dependencies: &[ChartDependency],
+    charts_dir: &Path,
+    cache_dir: &Path,
+) -> Result<(), errors::CommandError> {
+    for dependency in dependencies.iter().filter(|d| !d.is_fetched(charts_dir)) {
+        let cached_archive = dependency.cached_archive(cache_dir);
+        if !cached_archive.exists() {
+            continue;
+        }
+
+        let archive = charts_dir.join(dependency.archive_name());
+        if let Err(err) = fs::create_dir_all(charts_dir).and_then(|_| fs::copy(&cached_archive, &archive)) {
+            let _ = fs::remove_file(&archive);
+            return Err(errors::CommandError::new_from_safe_message(format!(
+                "Cannot copy cached dependency {} into {}: {}",
+                cached_archive.display(),
+                charts_dir.display(),
+                err
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// Copy then rename, so concurrent deployments never read a partially written archive
+fn cache_fetched_dependencies(
+    dependencies: &[ChartDependency],
+    charts_dir: &Path,
+    cache_dir: &Path,
+) -> Result<(), std::io::Error> {
+    for dependency in dependencies {
+        let archive = charts_dir.join(dependency.archive_name());
+        if !archive.exists() {
+            continue;
+        }
+
+        let cached_archive = dependency.cached_archive(cache_dir);
+        let cached_archive_dir = cached_archive.parent().unwrap_or(cache_dir);
+        let tmp_archive = cached_archive_dir.join(format!(".{}.{}", dependency.archive_name(), Uuid::new_v4()));
+        let ret = fs::create_dir_all(cached_archive_dir)
+            .and_then(|_| fs::copy(&archive, &tmp_archive))
+            .and_then(|_| fs::rename(&tmp_archive, &cached_archive));
+        if let Err(err) = ret {
+            let _ = fs::remove_file(&tmp_archive);
+            return Err(err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Directory where fetched subcharts are kept between deployments
+pub fn default_helm_dependencies_cache_dir() -> PathBuf {
+    match env::var("HELM_DEPENDENCIES_CACHE_DIR") {
+        Ok(cache_dir) => PathBuf::from(cache_dir),
+        Err(_) => env::temp_dir().join("qovery-helm-dependencies"),
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -164,11 +318,35 @@ impl Helm {
             return Err(CannotRollback(chart.name.clone()));
         }
 
+        self.rollback_release(chart, None, envs)
+    }
+
+    /// Rollback the release to the given revision, which must still be part of the release history
+    pub fn rollback_to_revision(
+        &self,
+        chart: &ChartInfo,
+        revision: u32,
+        envs: &[(&str, &str)],
+    ) -> Result<(), HelmError> {
+        self.check_release_exist(chart, envs)?;
+        self.rollback_release(chart, Some(revision), envs)
+    }
+
+    fn rollback_release(
+        &self,
+        chart: &ChartInfo,
+        revision: Option<u32>,
+        envs: &[(&str, &str)],
+    ) -> Result<(), HelmError> {
         let timeout = format!("{}s", &chart.timeout_in_seconds);
         let namespace = chart.get_namespace_string();
-        let args = vec![
-            "rollback",
-            &chart.name,
+        // without revision, helm rollbacks to the previous one
+        let revision = revision.map(|revision| revision.to_string());
+        let mut args = vec!["rollback", &chart.name];
+        if let Some(revision) = &revision {
+            args.push(revision);
+        }
+        args.extend([
             "--kubeconfig",
             self.kubernetes_config.to_str().unwrap_or_default(),
             "--namespace",
@@ -180,7 +358,7 @@ impl Helm {
             "--cleanup-on-fail",
             "--force",
             "--wait",
-        ];
+        ]);
 
         let mut stderr = String::new();
         match helm_exec_with_output(
@@ -351,6 +529,58 @@ impl Helm {
         Ok(None)
     }
 
+    /// Fetch the subcharts declared in the chart dependencies, unless they are already in its `charts` directory.
+    /// Fetched archives are cached between deployments, only dependencies pinned to an exact version hit the cache
+    pub fn dependency_build(&self, chart: &ChartInfo, envs: &[(&str, &str)]) -> Result<(), HelmError> {
+        let chart_dir = Path::new(&chart.path);
+        let charts_dir = chart_dir.join("charts");
+        let dependencies =
+            chart_dependencies(chart_dir).map_err(|e| CmdError(chart.name.clone(), HelmCommand::DEPENDENCY, e))?;
+        let cache_dir = default_helm_dependencies_cache_dir();
+
+        restore_cached_dependencies(&dependencies, &charts_dir, &cache_dir)
+            .map_err(|e| CmdError(chart.name.clone(), HelmCommand::DEPENDENCY, e))?;
+        if dependencies.iter().all(|d| d.is_fetched(&charts_dir)) {
+            return Ok(());
+        }
+
+        // build fetches the versions pinned in Chart.lock, update resolves them when there is no lock file yet
+        let action = match chart_dir.join("Chart.lock").exists() {
+            true => "build",
+            false => "update",
+        };
+        let helm_ret = helm_exec_with_output(
+            &["dependency", action, chart.path.as_str()],
+            &self.get_all_envs(envs),
+            &mut |line| {
+                info!("{}", line);
+            },
+            &mut |line| {
+                warn!("chart {}: {}", chart.name, line);
+            },
+            &CommandKiller::never(),
+        );
+
+        if let Some(missing) = dependencies.iter().find(|d| !d.is_fetched(&charts_dir)) {
+            let raw_error = match helm_ret {
+                Err(err) => err.into(),
+                Ok(_) => errors::CommandError::new_from_safe_message(format!(
+                    "helm dependency {} did not fetch `{}` from `{}`",
+                    action, missing.name, missing.repository
+                )),
+            };
+            return Err(HelmError::DependencyFailed(chart.name.clone(), missing.name.clone(), raw_error));
+        }
+        helm_ret.map_err(|err| CmdError(chart.name.clone(), HelmCommand::DEPENDENCY, err.into()))?;
+
+        // the dependencies are fetched, failing to cache them only costs a download next time
+        if let Err(err) = cache_fetched_dependencies(&dependencies, &charts_dir, &cache_dir) {
+            warn!("chart {}: cannot cache its dependencies: {}", chart.name, err);
+        }
+
+        Ok(())
+    }
+
     pub fn upgrade_diff(&self, chart: &ChartInfo, envs: &[(&str, &str)]) -> Result<(), HelmError> {
         let mut args_string: Vec<String> = vec![
             "diff".to_string(),
@@ -566,6 +796,10 @@ impl Helm {
                 HelmError::Rollbacked(chart.name.clone(), UPGRADE)
             } else if stderr_msg.contains("timed out waiting") {
                 HelmError::Timeout(chart.name.clone(), UPGRADE, stderr_msg)
+            } else if let Some(error) = to_invalid_ownership_metadata_error(&chart.name, &stderr_msg) {
+                error
+            } else if let Some(error) = to_admission_rejected_error(&chart.name, &stderr_msg) {
+                error
             } else {
                 CmdError(
                     chart.name.clone(),
@@ -722,6 +956,54 @@ where
     }
 }
 
+// Helm refuses to manage a resource it has not created, i.e:
+// Service "my-svc" in namespace "my-ns" exists and cannot be imported into the current release: invalid ownership metadata
+// The release owning the resource, if any, is reported in the annotation validation error, i.e:
+// annotation validation error: key "meta.helm.sh/release-name" must equal "my-release": current value is "other-release"
+fn to_invalid_ownership_metadata_error(release_name: &str, stderr_msg: &str) -> Option<HelmError> {
+    let re = Regex::new(concat!(
+        r#"(?P<kind>\w+) "(?P<name>[^"]+)" in namespace "(?P<namespace>[^"]*)" exists and cannot be imported "#,
+        r#"into the current release: invalid ownership metadata"#
+    ))
+    .ok()?;
+    let captures = re.captures(stderr_msg)?;
+
+    let owner_re =
+        Regex::new(r#"key "meta\.helm\.sh/release-name" must equal "[^"]*": current value is "(?P<owner>[^"]*)""#)
+            .ok()?;
+    let owner = owner_re
+        .captures(stderr_msg)
+        .map(|owner_captures| owner_captures["owner"].to_string());
+
+    Some(HelmError::InvalidOwnershipMetadata(
+        release_name.to_string(),
+        captures["kind"].to_string(),
+        captures["name"].to_string(),
+        captures["namespace"].to_string(),
+        owner,
+    ))
+}
+
+// An admission webhook refusing a resource of the release, i.e:
+// admission webhook "validation.gatekeeper.sh" denied the request: [host-path] HostPath volume is not allowed
+fn to_admission_rejected_error(release_name: &str, stderr_msg: &str) -> Option<HelmError> {
+    let re = Regex::new(r#"admission webhook "(?P<webhook>[^"]+)" denied the request: (?P<reason>.+)"#).ok()?;
+    let captures = re.captures(stderr_msg)?;
+
+    Some(HelmError::AdmissionRejected(
+        release_name.to_string(),
+        captures["webhook"].to_string(),
+        captures["reason"].trim().to_string(),
+    ))
+}
+
+/// Whether the admission webhook belongs to a policy engine (OPA Gatekeeper, Kyverno)
+pub fn is_policy_admission_webhook(webhook: &str) -> bool {
+    ["gatekeeper.sh", "kyverno"]
+        .iter()
+        .any(|engine| webhook.contains(engine))
+}
+
 pub fn to_command_error(error: HelmError) -> errors::CommandError {
     errors::CommandError::new("Error while executing Helm command.".to_string(), Some(error.to_string()), None)
 }
@@ -735,9 +1017,13 @@ pub fn to_engine_error(event_details: &EventDetails, error: HelmError) -> Engine
 mod tests {
     use crate::cloud_provider::helm::{ChartInfo, ChartSetValue};
     use crate::cmd::command::{CommandKiller, ExecutableCommand, QoveryCommand};
-    use crate::cmd::helm::{helm_exec_with_output, Helm, HelmError};
+    use crate::cmd::helm::{
+        cache_fetched_dependencies, helm_exec_with_output, restore_cached_dependencies,
+        to_invalid_ownership_metadata_error, ChartDependency, Helm, HelmError,
+    };
     use crate::deployment_action::deploy_helm::default_helm_timeout;
     use semver::Version;
+    use std::fs;
     use std::sync::{Arc, Barrier};
     use std::thread;
     use std::time::Duration;
@@ -1048,4 +1334,101 @@ mod tests {
         let releases = helm.list_release(Some(&charts[0].get_namespace_string()), &[]).unwrap();
         assert_eq!(releases[0].clone().chart_version.unwrap(), Version::new(0, 1, 0))
     }
+
+    #[test]
+    fn test_dependencies_cache_is_keyed_by_repository() {
+        // setup:
+        let tmp_dir = tempdir::TempDir::new("helm_dependencies").expect("error creating temporary dir");
+        let cache_dir = tmp_dir.path().join("cache");
+        let dependency = |repository: &str| ChartDependency {
+            name: "redis".to_string(),
+            version: "17.3.7".to_string(),
+            repository: repository.to_string(),
+        };
+        let bitnami = dependency("https://charts.bitnami.com/bitnami");
+        let mirror = dependency("https://charts.example.com/mirror");
+        let fetched_dir = tmp_dir.path().join("fetched");
+        fs::create_dir_all(&fetched_dir).unwrap();
+        fs::write(fetched_dir.join(bitnami.archive_name()), "bitnami archive").unwrap();
+
+        // execute:
+        cache_fetched_dependencies(&[bitnami.clone()], &fetched_dir, &cache_dir).unwrap();
+        let bitnami_charts_dir = tmp_dir.path().join("bitnami").join("charts");
+        let mirror_charts_dir = tmp_dir.path().join("mirror").join("charts");
+        restore_cached_dependencies(&[bitnami.clone()], &bitnami_charts_dir, &cache_dir).unwrap();
+        restore_cached_dependencies(&[mirror.clone()], &mirror_charts_dir, &cache_dir).unwrap();
+
+        // verify:
+        assert_eq!(
+            fs::read_to_string(bitnami_charts_dir.join(bitnami.archive_name())).unwrap(),
+            "bitnami archive"
+        );
+        assert!(!mirror.is_fetched(&mirror_charts_dir));
+    }
+
+    #[test]
+    fn test_restore_cached_dependencies_fails_when_charts_dir_cannot_be_written() {
+        // setup:
+        let tmp_dir = tempdir::TempDir::new("helm_dependencies").expect("error creating temporary dir");
+        let cache_dir = tmp_dir.path().join("cache");
+        let dependency = ChartDependency {
+            name: "redis".to_string(),
+            version: "17.3.7".to_string(),
+            repository: "https://charts.bitnami.com/bitnami".to_string(),
+        };
+        let fetched_dir = tmp_dir.path().join("fetched");
+        fs::create_dir_all(&fetched_dir).unwrap();
+        fs::write(fetched_dir.join(dependency.archive_name()), "archive").unwrap();
+        cache_fetched_dependencies(&[dependency.clone()], &fetched_dir, &cache_dir).unwrap();
+        // a file where the charts directory should be
+        let charts_dir = tmp_dir.path().join("charts");
+        fs::write(&charts_dir, "").unwrap();
+
+        // execute:
+        let ret = restore_cached_dependencies(&[dependency], &charts_dir, &cache_dir);
+
+        // verify:
+        assert!(ret.is_err());
+    }
+
+    #[test]
+    fn test_invalid_ownership_metadata_error() {
+        // resource created outside of helm
+        let stderr_msg = concat!(
+            r#"Error: UPGRADE FAILED: rendered manifests contain a resource that already exists. "#,
+            r#"Unable to continue with update: Service "my-svc" in namespace "my-ns" exists and cannot be imported "#,
+            r#"into the current release: invalid ownership metadata; label validation error: missing key "#,
+            r#""app.kubernetes.io/managed-by": must be set to "Helm"; annotation validation error: missing key "#,
+            r#""meta.helm.sh/release-name": must be set to "my-release""#
+        );
+        match to_invalid_ownership_metadata_error("my-release", stderr_msg) {
+            Some(HelmError::InvalidOwnershipMetadata(release, kind, name, namespace, owner)) => {
+                assert_eq!(release, "my-release");
+                assert_eq!(kind, "Service");
+                assert_eq!(name, "my-svc");
+                assert_eq!(namespace, "my-ns");
+                assert_eq!(owner, None);
+            }
+            _ => panic!("ownership metadata error not recognized"),
+        }
+
+        // resource owned by another release
+        let stderr_msg = concat!(
+            r#"Error: UPGRADE FAILED: rendered manifests contain a resource that already exists. "#,
+            r#"Unable to continue with update: ConfigMap "my-cm" in namespace "my-ns" exists and cannot be imported "#,
+            r#"into the current release: invalid ownership metadata; annotation validation error: key "#,
+            r#""meta.helm.sh/release-name" must equal "my-release": current value is "other-release""#
+        );
+        match to_invalid_ownership_metadata_error("my-release", stderr_msg) {
+            Some(HelmError::InvalidOwnershipMetadata(_, kind, name, _, owner)) => {
+                assert_eq!(kind, "ConfigMap");
+                assert_eq!(name, "my-cm");
+                assert_eq!(owner, Some("other-release".to_string()));
+            }
+            _ => panic!("ownership metadata error not recognized"),
+        }
+
+        // unrelated error
+        assert!(to_invalid_ownership_metadata_error("my-release", "Error: UPGRADE FAILED: timed out").is_none());
+    }
 }
@@ -1,7 +1,9 @@
+use std::env;
 use std::fmt::Debug;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
+use std::time::Duration;
 
 use retry::delay::Fibonacci;
 use retry::OperationResult;
@@ -15,9 +17,10 @@ use crate::cmd::structs::{
     KubernetesPod, KubernetesPodStatusPhase, KubernetesPodStatusReason, KubernetesService, KubernetesStatefulSet,
     KubernetesVersion, MetricsServer, Namespace, Secrets, HPA, PDB, PVC, SVC,
 };
-use crate::constants::KUBECONFIG;
+use crate::constants::{KUBECONFIG, KUBECTL_CONTEXT};
 use crate::errors::{CommandError, ErrorMessageVerbosity};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ScalingKind {
     Deployment,
     Statefulset,
@@ -30,6 +33,27 @@ pub enum PodCondition {
     Delete,
 }
 
+// Generous on purpose, it only prevents a kubectl call from hanging forever on an unresponsive api server
+const KUBECTL_DEFAULT_REQUEST_TIMEOUT_IN_SECS: u64 = 5 * 60;
+
+pub fn default_kubectl_request_timeout() -> Duration {
+    match env::var("KUBECTL_REQUEST_TIMEOUT_IN_SECS") {
+        Ok(env_var) => match env_var.parse::<u64>() {
+            Ok(timeout) => Duration::from_secs(timeout),
+            Err(_) => Duration::from_secs(KUBECTL_DEFAULT_REQUEST_TIMEOUT_IN_SECS),
+        },
+        Err(_) => Duration::from_secs(KUBECTL_DEFAULT_REQUEST_TIMEOUT_IN_SECS),
+    }
+}
+
+// Server side apply is not subject to the last-applied-configuration annotation size limit
+pub fn default_kubectl_server_side_apply() -> bool {
+    match env::var("KUBECTL_SERVER_SIDE_APPLY") {
+        Ok(env_var) => env_var.parse::<bool>().unwrap_or(false),
+        Err(_) => false,
+    }
+}
+
 pub fn kubectl_exec_with_output<F, X>(
     args: Vec<&str>,
     envs: Vec<(&str, &str)>,
@@ -40,6 +64,25 @@ where
     F: FnMut(String),
     X: FnMut(String),
 {
+    // wait commands are watching resources and manage their own timeout
+    let request_timeout = format!("--request-timeout={}s", default_kubectl_request_timeout().as_secs());
+    let mut args = args;
+    if !args.contains(&"wait") && !args.iter().any(|arg| arg.starts_with("--request-timeout")) {
+        // global flag set first, so it never ends up after a `--` separator
+        args.insert(0, request_timeout.as_str());
+    }
+
+    // never rely on the kubeconfig current-context, it could target another cluster
+    let context = envs
+        .iter()
+        .find(|(k, _)| *k == KUBECTL_CONTEXT)
+        .map(|(_, context)| format!("--context={}", context));
+    if let Some(context) = &context {
+        if !args.iter().any(|arg| arg.starts_with("--context")) {
+            args.insert(0, context.as_str());
+        }
+    }
+
     let mut cmd = QoveryCommand::new("kubectl", &args, &envs);
 
     if let Err(err) = cmd.exec_with_output(stdout_output, stderr_output) {
@@ -396,6 +439,91 @@ where
     Ok(())
 }
 
+/// Delete every resource of the given kinds matching the selector, used when resources are not managed by helm
+pub fn kubectl_exec_delete_by_selector<P>(
+    kubernetes_config: P,
+    namespace: &str,
+    resource_kinds: &str,
+    selector: &str,
+    envs: Vec<(&str, &str)>,
+) -> Result<(), CommandError>
+where
+    P: AsRef<Path>,
+{
+    let mut _envs = Vec::with_capacity(envs.len() + 1);
+    _envs.push((KUBECONFIG, kubernetes_config.as_ref().to_str().unwrap()));
+    _envs.extend(envs);
+
+    kubectl_exec_with_output(
+        vec![
+            "-n",
+            namespace,
+            "delete",
+            resource_kinds,
+            "-l",
+            selector,
+            "--ignore-not-found",
+        ],
+        _envs,
+        &mut |line| info!("{}", line),
+        &mut |line| error!("{}", line),
+    )?;
+
+    Ok(())
+}
+
+/// Set helm ownership metadata on a resource created outside of helm, so the release can manage it
+pub fn kubectl_exec_adopt_resource_in_helm_release<P>(
+    kubernetes_config: P,
+    namespace: &str,
+    resource_kind: &str,
+    resource_name: &str,
+    release_name: &str,
+    release_namespace: &str,
+    envs: Vec<(&str, &str)>,
+) -> Result<(), CommandError>
+where
+    P: AsRef<Path>,
+{
+    let mut _envs = Vec::with_capacity(envs.len() + 1);
+    _envs.push((KUBECONFIG, kubernetes_config.as_ref().to_str().unwrap()));
+    _envs.extend(envs);
+
+    let resource = format!("{}/{}", resource_kind.to_lowercase(), resource_name);
+    kubectl_exec_with_output(
+        vec![
+            "-n",
+            namespace,
+            "label",
+            "--overwrite",
+            resource.as_str(),
+            "app.kubernetes.io/managed-by=Helm",
+        ],
+        _envs.clone(),
+        &mut |line| info!("{}", line),
+        &mut |line| error!("{}", line),
+    )?;
+
+    let release_name_annotation = format!("meta.helm.sh/release-name={}", release_name);
+    let release_namespace_annotation = format!("meta.helm.sh/release-namespace={}", release_namespace);
+    kubectl_exec_with_output(
+        vec![
+            "-n",
+            namespace,
+            "annotate",
+            "--overwrite",
+            resource.as_str(),
+            release_name_annotation.as_str(),
+            release_namespace_annotation.as_str(),
+        ],
+        _envs,
+        &mut |line| info!("{}", line),
+        &mut |line| error!("{}", line),
+    )?;
+
+    Ok(())
+}
+
 pub fn kubectl_exec_logs<P>(
     kubernetes_config: P,
     namespace: &str,
@@ -444,6 +572,30 @@ where
     Ok(output_vec.join("\n"))
 }
 
+pub fn kubectl_exec_describe_pod_by_name<P>(
+    kubernetes_config: P,
+    namespace: &str,
+    pod_name: &str,
+    envs: Vec<(&str, &str)>,
+) -> Result<String, CommandError>
+where
+    P: AsRef<Path>,
+{
+    let mut _envs = Vec::with_capacity(envs.len() + 1);
+    _envs.push((KUBECONFIG, kubernetes_config.as_ref().to_str().unwrap()));
+    _envs.extend(envs);
+
+    let mut output_vec: Vec<String> = Vec::with_capacity(50);
+    kubectl_exec_with_output(
+        vec!["describe", "pod", "-n", namespace, pod_name],
+        _envs,
+        &mut |line| output_vec.push(line),
+        &mut |line| error!("{}", line),
+    )?;
+
+    Ok(output_vec.join("\n"))
+}
+
 pub fn kubectl_exec_version<P>(kubernetes_config: P, envs: Vec<(&str, &str)>) -> Result<KubernetesVersion, CommandError>
 where
     P: AsRef<Path>,
@@ -1215,6 +1367,10 @@ where
 {
     let mut cmd_args = vec!["apply"];
 
+    if default_kubectl_server_side_apply() {
+        cmd_args.push("--server-side");
+    }
+
     if let Some(args) = args {
         for arg in args {
             cmd_args.push(arg)
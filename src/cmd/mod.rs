@@ -1,3 +1,4 @@
+pub mod cluster_executor;
 pub mod command;
 pub mod docker;
 pub mod helm;
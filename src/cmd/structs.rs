@@ -1,7 +1,7 @@
 use crate::cmd::structs::KubernetesPodStatusReason::Unknown;
 use semver::Version;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Deserialize, Clone, Eq, PartialEq)]
 #[serde(rename_all = "camelCase")]
@@ -178,6 +178,24 @@ pub struct KubernetesIngressStatusLoadBalancerIngress {
 pub struct KubernetesPod {
     pub status: KubernetesPodStatus,
     pub metadata: KubernetesPodMetadata,
+    #[serde(default)]
+    pub spec: KubernetesPodSpec,
+}
+
+#[derive(Deserialize, Clone, Eq, PartialEq, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct KubernetesPodSpec {
+    // Empty until the pod is scheduled
+    pub node_name: Option<String>,
+    // The pod is not considered for scheduling until all its gates are removed
+    #[serde(default)]
+    pub scheduling_gates: Vec<KubernetesPodSchedulingGate>,
+}
+
+#[derive(Deserialize, Clone, Eq, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct KubernetesPodSchedulingGate {
+    pub name: String,
 }
 
 #[derive(Deserialize, Clone, Eq, PartialEq, Debug)]
@@ -185,6 +203,7 @@ pub struct KubernetesPod {
 pub struct KubernetesPodMetadata {
     pub name: String,
     pub namespace: String,
+    pub creation_timestamp: Option<String>,
 }
 
 #[derive(Deserialize, Clone, Eq, PartialEq, Debug)]
@@ -195,6 +214,8 @@ pub struct KubernetesPodStatus {
     // read the doc: https://kubernetes.io/docs/concepts/workloads/pods/pod-lifecycle/
     // phase can be Pending, Running, Succeeded, Failed, Unknown
     pub phase: KubernetesPodStatusPhase,
+    // Set once the pod has been acknowledged by the kubelet
+    pub start_time: Option<String>,
 }
 
 #[derive(Deserialize, Clone, Eq, PartialEq, Debug)]
@@ -304,9 +325,16 @@ pub struct KubernetesJobStatus {
 #[derive(Deserialize, Clone, Eq, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct KubernetesNode {
+    pub metadata: KubernetesNodeMetadata,
     pub status: KubernetesNodeStatus,
 }
 
+#[derive(Deserialize, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KubernetesNodeMetadata {
+    pub name: String,
+}
+
 #[derive(Deserialize, Clone, Eq, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct KubernetesNodeStatus {
@@ -456,6 +484,8 @@ pub struct PVCItem {
 pub struct PVCMetadata {
     pub resource_version: String,
     pub name: String,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Deserialize)]
@@ -593,12 +623,24 @@ pub struct MetricsServer {
 #[serde(rename_all = "camelCase")]
 pub struct KubernetesDeployment {
     pub kind: String,
+    #[serde(default)]
+    pub metadata: KubernetesWorkloadMetadata,
 }
 
 #[derive(Deserialize, Clone, Eq, PartialEq, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct KubernetesStatefulSet {
     pub kind: String,
+    #[serde(default)]
+    pub metadata: KubernetesWorkloadMetadata,
+}
+
+#[derive(Default, Deserialize, Clone, Eq, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct KubernetesWorkloadMetadata {
+    pub name: String,
+    #[serde(default)]
+    pub annotations: HashMap<String, String>,
 }
 
 #[cfg(test)]
@@ -1,14 +1,17 @@
 use bitflags::bitflags;
 use dirs::home_dir;
-use retry::delay::Fixed;
+use retry::delay::{Fibonacci, Fixed};
 use retry::OperationResult;
 
 use crate::cmd::command::{ExecutableCommand, QoveryCommand};
 use crate::constants::TF_PLUGIN_CACHE_DIR;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use rand::Rng;
 use regex::Regex;
 use retry::Error::Operation;
+use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
+use std::time::Duration;
 use std::{env, fs, thread, time};
 
 bitflags! {
@@ -16,10 +19,13 @@ bitflags! {
     struct TerraformAction: u32 {
         const INIT = 0b00000001;
         const VALIDATE = 0b00000010;
-        // const PLAN = 0b00000100; Not needed, apply and destroy should call plan on their end
+        // Apply and destroy call plan on their end, this one only previews the changes
+        const PLAN = 0b00000100;
         const APPLY = 0b00001000;
         const DESTROY = 0b00010000;
         const STATE_LIST = 0b00100000;
+        // Only previews what destroy would remove
+        const PLAN_DESTROY = 0b01000000;
     }
 }
 
@@ -145,6 +151,12 @@ pub enum TerraformError {
         /// raw_message: raw Terraform error message with all details.
         raw_message: String,
     },
+    IncompatibleVersion {
+        required_version: String,
+        installed_version: String,
+        /// raw_message: raw Terraform error message with all details.
+        raw_message: String,
+    },
 }
 
 impl TerraformError {
@@ -593,6 +605,9 @@ impl TerraformError {
             TerraformError::StateLocked { lock_id, .. } => {
                 format!("Error, terraform state is locked (lock_id: {})", lock_id)
             }
+            TerraformError::IncompatibleVersion { required_version, installed_version, .. } => {
+                format!("Error, installed terraform version `{}` doesn't satisfy required version `{}`.", installed_version, required_version)
+            }
         }
     }
 }
@@ -660,6 +675,9 @@ impl Display for TerraformError {
             TerraformError::StateLocked { raw_message, .. } => {
                 format!("{}\n{}", self.to_safe_message(), raw_message)
             }
+            TerraformError::IncompatibleVersion { raw_message, .. } => {
+                format!("{}\n{}", self.to_safe_message(), raw_message)
+            }
         };
 
         f.write_str(&message)
@@ -672,10 +690,71 @@ fn manage_common_issues(
     err: &TerraformError,
 ) -> Result<Vec<String>, TerraformError> {
     terraform_plugins_failed_load(root_dir, err, terraform_provider_lock)?;
+    terraform_force_unlock_stale_state_lock(root_dir, err)?;
 
     Ok(vec![])
 }
 
+/// Age after which a state lock is considered as stale and can be force-unlocked.
+/// Disabled unless explicitly set, force-unlocking a lock still held by a running terraform corrupts the state.
+pub fn default_terraform_stale_lock_threshold() -> Option<Duration> {
+    env::var("TERRAFORM_FORCE_UNLOCK_STALE_LOCK_AFTER_IN_SECS")
+        .ok()?
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Returns the state lock creation date, from terraform lock info (i.e: `Created: 2022-11-14 13:59:21.540636643 +0000 UTC`)
+fn terraform_state_lock_created_at(raw_message: &str) -> Option<DateTime<Utc>> {
+    let created_re =
+        Regex::new(r"Created:\s+(?P<created>\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}(\.\d+)?) \+0000 UTC").ok()?;
+    let created = created_re.captures(raw_message)?.name("created")?.as_str();
+    let created = NaiveDateTime::parse_from_str(created, "%Y-%m-%d %H:%M:%S%.f").ok()?;
+
+    Some(DateTime::<Utc>::from_utc(created, Utc))
+}
+
+fn terraform_force_unlock_stale_state_lock(
+    root_dir: &str,
+    error: &TerraformError,
+) -> Result<Vec<String>, TerraformError> {
+    let (lock_id, raw_message) = match error {
+        TerraformError::StateLocked { lock_id, raw_message } => (lock_id, raw_message),
+        _ => return Ok(vec![]),
+    };
+    let stale_lock_threshold = match default_terraform_stale_lock_threshold() {
+        Some(threshold) => threshold,
+        None => return Ok(vec![]),
+    };
+    // lock info doesn't tell whether the holder process is still alive, so only the lock age can be relied on
+    let created_at = match terraform_state_lock_created_at(raw_message) {
+        Some(created_at) => created_at,
+        None => {
+            warn!(
+                "Cannot read creation date of terraform state lock {}, not unlocking it",
+                lock_id
+            );
+            return Ok(vec![]);
+        }
+    };
+    let lock_age = Utc::now()
+        .signed_duration_since(created_at)
+        .to_std()
+        .unwrap_or_default();
+    if lock_age < stale_lock_threshold {
+        return Ok(vec![]);
+    }
+
+    error!(
+        "Terraform state lock {} created at {} is older than {}s, considering it stale and FORCING UNLOCK",
+        lock_id,
+        created_at,
+        stale_lock_threshold.as_secs()
+    );
+    terraform_exec(root_dir, vec!["force-unlock", "-force", lock_id])
+}
+
 fn terraform_plugins_failed_load(
     root_dir: &str,
     error: &TerraformError,
@@ -758,6 +837,27 @@ fn terraform_init(root_dir: &str) -> Result<Vec<String>, TerraformError> {
     }
 }
 
+/// File of the terraform root folder holding the workspace to run into, the default workspace is used without it
+pub const TERRAFORM_WORKSPACE_FILE: &str = "qovery-terraform-workspace";
+// plan saved by terraform plan, so apply runs exactly what has been planned
+const TERRAFORM_PLAN_FILE: &str = "tf_plan";
+
+// Terraform 1.3 cannot create a workspace while selecting it, and refuses to init in a workspace which does not exist
+fn terraform_select_workspace(root_dir: &str) -> Result<Vec<String>, TerraformError> {
+    let workspace = match fs::read_to_string(format!("{}/{}", root_dir, TERRAFORM_WORKSPACE_FILE)) {
+        Ok(workspace) => workspace.trim().to_string(),
+        Err(_) => return Ok(vec![]),
+    };
+    if workspace.is_empty() || workspace == "default" {
+        return Ok(vec![]);
+    }
+
+    match terraform_exec(root_dir, vec!["workspace", "select", "-no-color", &workspace]) {
+        Ok(output) => Ok(output),
+        Err(_) => terraform_exec(root_dir, vec!["workspace", "new", "-no-color", &workspace]),
+    }
+}
+
 fn terraform_validate(root_dir: &str) -> Result<Vec<String>, TerraformError> {
     let terraform_args = vec!["validate", "-no-color"];
     let terraform_provider_lock = format!("{}/.terraform.lock.hcl", &root_dir);
@@ -812,7 +912,7 @@ pub fn terraform_state_list(root_dir: &str) -> Result<Vec<String>, TerraformErro
 
 pub fn terraform_plan(root_dir: &str) -> Result<Vec<String>, TerraformError> {
     // plan
-    let terraform_args = vec!["plan", "-no-color", "-out", "tf_plan"];
+    let terraform_args = vec!["plan", "-no-color", "-out", TERRAFORM_PLAN_FILE];
     // Retry is not needed, fixing it to 1 only for the time being
     let result = retry::retry(Fixed::from_millis(3000).take(1), || {
         match terraform_exec(root_dir, terraform_args.clone()) {
@@ -836,8 +936,151 @@ pub fn terraform_plan(root_dir: &str) -> Result<Vec<String>, TerraformError> {
     }
 }
 
+pub fn terraform_plan_destroy(root_dir: &str) -> Result<Vec<String>, TerraformError> {
+    // plan destroy, nothing is saved as the plan is never applied
+    let terraform_args = vec!["plan", "-destroy", "-no-color"];
+    match terraform_exec(root_dir, terraform_args) {
+        Ok(output) => Ok(output),
+        Err(err) => {
+            let _ = manage_common_issues(root_dir, "", &err);
+            Err(err)
+        }
+    }
+}
+
+/// What a terraform plan is about to change, parsed from its human readable output
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TerraformPlanSummary {
+    pub to_add: u32,
+    pub to_change: u32,
+    pub to_destroy: u32,
+    /// Address of each changed resource along with what happens to it, i.e: `aws_db_instance.db will be destroyed`
+    pub resource_changes: Vec<String>,
+}
+
+impl TerraformPlanSummary {
+    /// None when the output holds no plan, a plan without changes has all its counts to 0
+    pub fn from_plan_output(output: &[String]) -> Option<TerraformPlanSummary> {
+        let counts_re = Regex::new(r"^Plan: (\d+) to add, (\d+) to change, (\d+) to destroy").ok()?;
+        let resource_re = Regex::new(r"^\s*# (?P<address>\S+) (?P<action>(will be|must be) .+)$").ok()?;
+
+        let mut summary = TerraformPlanSummary::default();
+        let mut has_plan = false;
+        for line in output.iter().map(|line| line.trim()) {
+            if line.starts_with("No changes.") {
+                has_plan = true;
+            } else if let Some(counts) = counts_re.captures(line) {
+                has_plan = true;
+                summary.to_add = counts[1].parse().unwrap_or_default();
+                summary.to_change = counts[2].parse().unwrap_or_default();
+                summary.to_destroy = counts[3].parse().unwrap_or_default();
+            } else if let Some(resource) = resource_re.captures(line) {
+                // data sources are read, nothing changes on the provider side
+                if !resource["action"].starts_with("will be read") {
+                    summary
+                        .resource_changes
+                        .push(format!("{} {}", &resource["address"], &resource["action"]));
+                }
+            }
+        }
+
+        has_plan.then(|| summary)
+    }
+
+    /// Replaced resources are counted as destroyed too
+    pub fn has_destructive_changes(&self) -> bool {
+        self.to_destroy > 0
+    }
+}
+
+impl Display for TerraformPlanSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} to add, {} to change, {} to destroy",
+            self.to_add, self.to_change, self.to_destroy
+        )
+    }
+}
+
+/// Provider errors known to be transient (eventual consistency, rate limits, network hiccups)
+const TERRAFORM_DEFAULT_TRANSIENT_ERROR_PATTERNS: [&str; 10] = [
+    r"RequestLimitExceeded",
+    r"Throttling",
+    r"TooManyRequests",
+    r"(?i)rate exceeded",
+    r"429 Too Many Requests",
+    r"connection reset by peer",
+    r"i/o timeout",
+    r"TLS handshake timeout",
+    r"InvalidParameterValue: Invalid IAM Instance Profile",
+    r"(?i)service unavailable",
+];
+
+/// How many times the whole apply is re-run on a transient provider error, apply being idempotent it is safe to
+pub fn default_terraform_transient_error_max_retries() -> usize {
+    match env::var("TERRAFORM_TRANSIENT_ERROR_MAX_RETRIES") {
+        Ok(env_var) => env_var.parse::<usize>().unwrap_or(3),
+        Err(_) => 3,
+    }
+}
+
+/// Regexes matched against the raw terraform error, `;` separated. Replaces the default ones when set
+pub fn default_terraform_transient_error_patterns() -> Vec<Regex> {
+    let patterns = match env::var("TERRAFORM_TRANSIENT_ERROR_PATTERNS") {
+        Ok(env_var) => env_var.split(';').map(|pattern| pattern.to_string()).collect(),
+        Err(_) => TERRAFORM_DEFAULT_TRANSIENT_ERROR_PATTERNS
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .collect::<Vec<String>>(),
+    };
+
+    patterns
+        .iter()
+        .filter(|pattern| !pattern.trim().is_empty())
+        .filter_map(|pattern| match Regex::new(pattern.trim()) {
+            Ok(regex) => Some(regex),
+            Err(e) => {
+                warn!("Ignoring invalid terraform transient error pattern `{}`: {}", pattern, e);
+                None
+            }
+        })
+        .collect()
+}
+
+// Errors already identified (credentials, quotas, invalid config...) are never transient
+fn is_transient_terraform_error(error: &TerraformError, patterns: &[Regex]) -> bool {
+    match error {
+        TerraformError::Unknown { raw_message, .. } => patterns.iter().any(|pattern| pattern.is_match(raw_message)),
+        _ => false,
+    }
+}
+
 fn terraform_apply(root_dir: &str) -> Result<Vec<String>, TerraformError> {
-    let terraform_args = vec!["apply", "-no-color", "-auto-approve", "tf_plan"];
+    let transient_error_patterns = default_terraform_transient_error_patterns();
+    let max_retries = default_terraform_transient_error_max_retries();
+
+    let result = retry::retry(
+        Fibonacci::from_millis(10_000).take(max_retries),
+        || match terraform_plan_apply(root_dir) {
+            Ok(out) => OperationResult::Ok(out),
+            Err(err) if is_transient_terraform_error(&err, &transient_error_patterns) => {
+                warn!("Transient provider error while running terraform apply, retrying: {}", err);
+                OperationResult::Retry(err)
+            }
+            Err(err) => OperationResult::Err(err),
+        },
+    );
+
+    match result {
+        Ok(output) => Ok(output),
+        Err(Operation { error, .. }) => Err(error),
+        Err(retry::Error::Internal(e)) => Err(TerraformError::new(vec!["apply".to_string()], "".to_string(), e)),
+    }
+}
+
+fn terraform_plan_apply(root_dir: &str) -> Result<Vec<String>, TerraformError> {
+    let terraform_args = vec!["apply", "-no-color", "-auto-approve", TERRAFORM_PLAN_FILE];
     let result = retry::retry(Fixed::from_millis(3000).take(1), || {
         // ensure we do plan before apply otherwise apply could crash.
         if let Err(e) = terraform_plan(root_dir) {
@@ -866,6 +1109,16 @@ fn terraform_apply(root_dir: &str) -> Result<Vec<String>, TerraformError> {
     }
 }
 
+/// Apply the plan saved by a previous terraform plan, without planning again.
+/// Not retried, a saved plan is stale once part of it has been applied
+pub fn terraform_apply_saved_plan(root_dir: &str) -> Result<Vec<String>, TerraformError> {
+    let terraform_args = vec!["apply", "-no-color", "-auto-approve", TERRAFORM_PLAN_FILE];
+    terraform_exec(root_dir, terraform_args).map_err(|err| {
+        let _ = manage_common_issues(root_dir, "", &err);
+        err
+    })
+}
+
 pub fn terraform_apply_with_tf_workers_resources(
     root_dir: &str,
     tf_workers_resources: Vec<String>,
@@ -1016,11 +1269,134 @@ pub fn terraform_remove_resource_from_tf_state(root_dir: &str, resource: &str) -
     }
 }
 
+/// Returns the terraform version constraint declared in the `required_version` of the root dir files, if any.
+fn terraform_required_version(root_dir: &str) -> Option<String> {
+    let required_version_re = Regex::new(r#"required_version\s*=\s*"(?P<constraint>[^"]+)""#).ok()?;
+    let entries = fs::read_dir(root_dir).ok()?;
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map(|ext| ext == "tf").unwrap_or(false))
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .find_map(|content| {
+            required_version_re
+                .captures(&content)
+                .and_then(|cap| cap.name("constraint"))
+                .map(|constraint| constraint.as_str().to_string())
+        })
+}
+
+fn terraform_installed_version(root_dir: &str) -> Result<String, TerraformError> {
+    let terraform_args = vec!["version", "-json"];
+    let output = terraform_exec(root_dir, terraform_args.clone())?.join("");
+
+    serde_json::from_str::<serde_json::Value>(&output)
+        .ok()
+        .and_then(|version| version["terraform_version"].as_str().map(|v| v.to_string()))
+        .ok_or_else(|| TerraformError::Unknown {
+            terraform_args: terraform_args.iter().map(|e| e.to_string()).collect(),
+            raw_message: format!("Cannot read terraform version from output: {}", output),
+        })
+}
+
+fn parse_terraform_version(version: &str) -> Option<Vec<u64>> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split('-')
+        .next()?
+        .split('.')
+        .map(|part| part.parse::<u64>().ok())
+        .collect()
+}
+
+fn compare_terraform_versions(left: &[u64], right: &[u64]) -> Ordering {
+    let len = left.len().max(right.len());
+    let pad = |version: &[u64]| {
+        let mut padded = version.to_vec();
+        padded.resize(len, 0);
+        padded
+    };
+
+    pad(left).cmp(&pad(right))
+}
+
+/// Checks the installed version against a terraform version constraint (i.e: `>= 1.3, < 2.0.0` or `~> 1.3.0`).
+/// An unreadable constraint is considered as satisfied, terraform will complain by itself if needed.
+fn is_terraform_version_satisfying(installed_version: &str, constraint: &str) -> bool {
+    let installed = match parse_terraform_version(installed_version) {
+        Some(installed) => installed,
+        None => return true,
+    };
+
+    constraint.split(',').all(|requirement| {
+        let requirement = requirement.trim();
+        let (operator, version) = match ["~>", ">=", "<=", "!=", ">", "<", "="]
+            .iter()
+            .find(|operator| requirement.starts_with(*operator))
+        {
+            Some(operator) => (*operator, &requirement[operator.len()..]),
+            None => ("=", requirement),
+        };
+        let required = match parse_terraform_version(version) {
+            Some(required) => required,
+            None => return true,
+        };
+
+        let ordering = compare_terraform_versions(&installed, &required);
+        match operator {
+            "~>" => {
+                // only the right-most version component is allowed to increase
+                let mut upper_bound = required[..required.len().saturating_sub(1).max(1)].to_vec();
+                if let Some(last) = upper_bound.last_mut() {
+                    *last += match required.len() {
+                        1 => 0,
+                        _ => 1,
+                    };
+                }
+                ordering != Ordering::Less
+                    && (required.len() == 1 || compare_terraform_versions(&installed, &upper_bound) == Ordering::Less)
+            }
+            ">=" => ordering != Ordering::Less,
+            "<=" => ordering != Ordering::Greater,
+            "!=" => ordering != Ordering::Equal,
+            ">" => ordering == Ordering::Greater,
+            "<" => ordering == Ordering::Less,
+            _ => ordering == Ordering::Equal,
+        }
+    })
+}
+
+/// Ensures the installed terraform binary satisfies the version required by the templates,
+/// instead of letting terraform init fail with an opaque error.
+pub fn terraform_check_version(root_dir: &str) -> Result<(), TerraformError> {
+    let required_version = match terraform_required_version(root_dir) {
+        Some(required_version) => required_version,
+        None => return Ok(()),
+    };
+    let installed_version = terraform_installed_version(root_dir)?;
+
+    match is_terraform_version_satisfying(&installed_version, &required_version) {
+        true => Ok(()),
+        false => Err(TerraformError::IncompatibleVersion {
+            raw_message: format!(
+                "Terraform {} is installed, but templates require `{}`",
+                installed_version, required_version
+            ),
+            required_version,
+            installed_version,
+        }),
+    }
+}
+
 fn terraform_run(actions: TerraformAction, root_dir: &str, dry_run: bool) -> Result<Vec<String>, TerraformError> {
     let mut output = vec![];
 
     if actions.contains(TerraformAction::INIT) {
+        terraform_check_version(root_dir)?;
         output.extend(terraform_init(root_dir)?);
+        output.extend(terraform_select_workspace(root_dir)?);
     }
 
     if actions.contains(TerraformAction::VALIDATE) {
@@ -1031,6 +1407,14 @@ fn terraform_run(actions: TerraformAction, root_dir: &str, dry_run: bool) -> Res
         output.extend(terraform_state_list(root_dir)?);
     }
 
+    if actions.contains(TerraformAction::PLAN) {
+        output.extend(terraform_plan(root_dir)?);
+    }
+
+    if actions.contains(TerraformAction::PLAN_DESTROY) {
+        output.extend(terraform_plan_destroy(root_dir)?);
+    }
+
     if actions.contains(TerraformAction::APPLY) && !dry_run {
         output.extend(terraform_apply(root_dir)?);
     }
@@ -1051,6 +1435,15 @@ pub fn terraform_init_validate_plan_apply(root_dir: &str, dry_run: bool) -> Resu
     )
 }
 
+pub fn terraform_init_validate_plan(root_dir: &str) -> Result<Vec<String>, TerraformError> {
+    // Terraform init, validate and plan, without applying anything
+    terraform_run(
+        TerraformAction::INIT | TerraformAction::VALIDATE | TerraformAction::PLAN,
+        root_dir,
+        false,
+    )
+}
+
 pub fn terraform_init_validate(root_dir: &str) -> Result<Vec<String>, TerraformError> {
     // Terraform init & validate
     terraform_run(TerraformAction::INIT | TerraformAction::VALIDATE, root_dir, false)
@@ -1070,6 +1463,15 @@ pub fn terraform_init_validate_destroy(
     terraform_run(terraform_actions_to_be_performed | TerraformAction::DESTROY, root_dir, false)
 }
 
+pub fn terraform_init_validate_plan_destroy(root_dir: &str) -> Result<Vec<String>, TerraformError> {
+    // Terraform init, validate and plan destroy, without destroying anything
+    terraform_run(
+        TerraformAction::INIT | TerraformAction::VALIDATE | TerraformAction::PLAN_DESTROY,
+        root_dir,
+        false,
+    )
+}
+
 pub fn terraform_init_validate_state_list(root_dir: &str) -> Result<Vec<String>, TerraformError> {
     // Terraform init, validate and statelist
     terraform_run(
@@ -1126,9 +1528,13 @@ fn terraform_exec(root_dir: &str, args: Vec<&str>) -> Result<Vec<String>, Terraf
 mod tests {
     use crate::cmd::command::{CommandError, CommandKiller, ExecutableCommand};
     use crate::cmd::terraform::{
-        manage_common_issues, terraform_exec_from_command, terraform_init, terraform_init_validate, QuotaExceededError,
-        TerraformError,
+        is_terraform_version_satisfying, is_transient_terraform_error, manage_common_issues,
+        terraform_exec_from_command, terraform_init, terraform_init_validate, terraform_required_version,
+        terraform_state_lock_created_at, QuotaExceededError, TerraformError, TerraformPlanSummary,
+        TERRAFORM_DEFAULT_TRANSIENT_ERROR_PATTERNS,
     };
+    use rand::Rng;
+    use regex::Regex;
     use std::fs;
     use std::process::Child;
 
@@ -1679,4 +2085,162 @@ flag, but this is not recommended."#;
             result
         );
     }
+
+    #[test]
+    fn test_terraform_version_constraints() {
+        // setup:
+        let test_cases = vec![
+            ("1.3.3", "1.3.3", true),
+            ("1.3.4", "1.3.3", false),
+            ("1.3.4", "= 1.3.3", false),
+            ("1.3.4", ">= 1.3", true),
+            ("1.2.9", ">= 1.3", false),
+            ("1.3.4", ">= 1.3, < 2.0.0", true),
+            ("2.0.0", ">= 1.3, < 2.0.0", false),
+            ("1.3.9", "~> 1.3.0", true),
+            ("1.4.0", "~> 1.3.0", false),
+            ("1.9.0", "~> 1.3", true),
+            ("2.0.0", "~> 1.3", false),
+            ("1.3.3", "!= 1.3.3", false),
+            ("v1.3.3-beta1", "> 1.3.2", true),
+            ("1.3.3", "not a version", true),
+        ];
+
+        for (installed_version, constraint, expected) in test_cases {
+            // execute & verify:
+            assert_eq!(
+                expected,
+                is_terraform_version_satisfying(installed_version, constraint),
+                "installed: {}, constraint: {}",
+                installed_version,
+                constraint
+            );
+        }
+    }
+
+    #[test]
+    fn test_terraform_state_lock_created_at() {
+        // setup:
+        let raw_message = r#"Error: Error acquiring the state lock
+Lock Info:
+  ID:        ecd9f287-8d29-4331-1683-48028be7aaba
+  Operation: OperationTypeApply
+  Version:   1.3.3
+  Created:   2022-11-14 13:59:21.540636643 +0000 UTC
+  Info:
+"#;
+
+        // execute:
+        let created_at = terraform_state_lock_created_at(raw_message);
+
+        // verify:
+        assert_eq!(
+            Some("2022-11-14T13:59:21.540636643Z".to_string()),
+            created_at.map(|c| c.to_rfc3339_opts(chrono::SecondsFormat::Nanos, true))
+        );
+        assert_eq!(None, terraform_state_lock_created_at("Error: something else"));
+    }
+
+    #[test]
+    fn test_terraform_required_version() {
+        // setup:
+        let root_dir = format!("/tmp/terraform-required-version-{}", rand::thread_rng().gen::<u32>());
+        fs::create_dir_all(&root_dir).expect("cannot create test directory");
+        fs::write(
+            format!("{}/providers.tf", root_dir),
+            "terraform {\n  required_version = \">= 1.3.3\"\n}\n",
+        )
+        .expect("cannot write test file");
+        fs::write(format!("{}/values.yaml", root_dir), "required_version = \"0.1\"").expect("cannot write test file");
+
+        // execute:
+        let required_version = terraform_required_version(&root_dir);
+        let _ = fs::remove_dir_all(&root_dir);
+
+        // verify:
+        assert_eq!(Some(">= 1.3.3".to_string()), required_version);
+    }
+
+    #[test]
+    fn test_terraform_transient_errors() {
+        // setup:
+        let patterns: Vec<Regex> = TERRAFORM_DEFAULT_TRANSIENT_ERROR_PATTERNS
+            .iter()
+            .map(|pattern| Regex::new(pattern).expect("invalid pattern"))
+            .collect();
+        let unknown = |raw_message: &str| TerraformError::Unknown {
+            terraform_args: vec!["apply".to_string()],
+            raw_message: raw_message.to_string(),
+        };
+
+        // execute & verify:
+        assert!(is_transient_terraform_error(
+            &unknown("Error: error creating EC2 Instance: RequestLimitExceeded: Request limit exceeded."),
+            &patterns
+        ));
+        assert!(is_transient_terraform_error(
+            &unknown("Error: reading EKS Cluster: dial tcp 52.94.1.1:443: i/o timeout"),
+            &patterns
+        ));
+        assert!(!is_transient_terraform_error(
+            &unknown("Error: Unsupported argument \"foo\""),
+            &patterns
+        ));
+        assert!(!is_transient_terraform_error(
+            &TerraformError::InvalidCredentials {
+                raw_message: "Error: Throttling: Rate exceeded, invalid credentials".to_string(),
+            },
+            &patterns
+        ));
+        assert!(!is_transient_terraform_error(&unknown("Error: RequestLimitExceeded"), &[]));
+    }
+
+    #[test]
+    fn test_terraform_plan_summary() {
+        let to_lines = |output: &str| output.lines().map(str::to_string).collect::<Vec<String>>();
+
+        let output = to_lines(
+            r#"Terraform will perform the following actions:
+
+  # data.aws_vpc.selected will be read during apply
+ <= data "aws_vpc" "selected" {
+  # aws_db_instance.postgresql_instance must be replaced
+-/+ resource "aws_db_instance" "postgresql_instance" {
+      ~ engine_version = "13.4" -> "14.2" # forces replacement
+        # (12 unchanged attributes hidden)
+    }
+
+  # aws_db_parameter_group.postgresql will be updated in-place
+  ~ resource "aws_db_parameter_group" "postgresql" {
+
+Plan: 1 to add, 1 to change, 1 to destroy."#,
+        );
+        let summary = TerraformPlanSummary::from_plan_output(&output).unwrap();
+        assert_eq!(
+            summary,
+            TerraformPlanSummary {
+                to_add: 1,
+                to_change: 1,
+                to_destroy: 1,
+                resource_changes: vec![
+                    "aws_db_instance.postgresql_instance must be replaced".to_string(),
+                    "aws_db_parameter_group.postgresql will be updated in-place".to_string(),
+                ],
+            }
+        );
+        assert!(summary.has_destructive_changes());
+        assert_eq!(summary.to_string(), "1 to add, 1 to change, 1 to destroy");
+
+        let no_changes = TerraformPlanSummary::from_plan_output(&to_lines(
+            "No changes. Your infrastructure matches the configuration.",
+        ))
+        .unwrap();
+        assert_eq!(no_changes, TerraformPlanSummary::default());
+        assert!(!no_changes.has_destructive_changes());
+
+        assert_eq!(
+            TerraformPlanSummary::from_plan_output(&to_lines("Terraform has been successfully initialized!")),
+            None
+        );
+    }
 }
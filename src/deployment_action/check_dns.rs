@@ -5,6 +5,9 @@ use crate::cmd::command::CommandKiller;
 use crate::deployment_action::DeploymentAction;
 use crate::errors::EngineError;
 use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
 use std::time::Duration;
 
 pub struct CheckDnsForDomains<'a> {
@@ -14,8 +17,24 @@ pub struct CheckDnsForDomains<'a> {
 }
 
 const DEFAULT_CHECK_FREQUENCY: Duration = Duration::from_secs(30);
+// Each check can wait several minutes for the propagation, they are run in parallel with this bound
+const MAX_CONCURRENT_CHECKS: usize = 8;
 
-fn check_domain_resolve_ip(domain: &str, log: &impl Fn(String), should_abort: &dyn Fn() -> bool) {
+#[derive(thiserror::Error, Debug)]
+#[error("💥 Unable to check {} domain(s): {}. Note: this is not critical.", .0.len(), .0.join(", "))]
+pub struct DomainsCheckError(pub Vec<String>);
+
+enum DomainCheck<'a> {
+    Ip(&'a str),
+    Cname(&'a CustomDomain),
+}
+
+enum DomainCheckEvent {
+    Log(String),
+    Done { check_idx: usize, resolved: bool },
+}
+
+fn check_domain_resolve_ip(domain: &str, log: &impl Fn(String), should_abort: &dyn Fn() -> bool) -> bool {
     // We use send_success because if on_check is called it means the DB is already correctly deployed
     (log)(format!(
         "🌍 Checking DNS Ip resolution for domain {}. Please wait, it can take some time...",
@@ -37,6 +56,7 @@ fn check_domain_resolve_ip(domain: &str, log: &impl Fn(String), should_abort: &d
                 domain,
                 ip.iter().next().unwrap_or_else(|| IpAddr::from([0_u8, 0, 0, 0]))
             ));
+            true
         }
         Err(_) => {
             let message = format!(
@@ -45,11 +65,16 @@ fn check_domain_resolve_ip(domain: &str, log: &impl Fn(String), should_abort: &d
                 &domain
             );
             (log)(message);
+            false
         }
     }
 }
 
-fn check_domain_resolve_cname(custom_domain: &CustomDomain, log: &impl Fn(String), should_abort: &dyn Fn() -> bool) {
+fn check_domain_resolve_cname(
+    custom_domain: &CustomDomain,
+    log: &impl Fn(String),
+    should_abort: &dyn Fn() -> bool,
+) -> bool {
     // We use send_success because if on_check is called it means the DB is already correctly deployed
     (log)(format!(
         "🌍 Checking DNS CNAME resolution for domain {}. Please wait, it can take some time...",
@@ -74,6 +99,7 @@ fn check_domain_resolve_cname(custom_domain: &CustomDomain, log: &impl Fn(String
                 custom_domain.domain,
                 cname.to_utf8()
             ));
+            true
         }
         Err(_) => {
             let message = format!(
@@ -81,18 +107,90 @@ fn check_domain_resolve_cname(custom_domain: &CustomDomain, log: &impl Fn(String
                 &custom_domain.domain
             );
             (log)(message);
+            false
         }
     }
 }
 
-impl<'a> DeploymentAction for CheckDnsForDomains<'a> {
-    fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
-        for domain in &self.resolve_to_ip {
-            check_domain_resolve_ip(domain, &self.log, target.should_abort);
+impl<'a> CheckDnsForDomains<'a> {
+    /// Check all domains concurrently, returning every domain that did not resolve.
+    /// Logs are forwarded from the current thread as the logger may not be shareable between threads.
+    pub fn check_domains(&self, should_abort: &dyn Fn() -> bool) -> Result<(), DomainsCheckError> {
+        let checks: Vec<DomainCheck> = self
+            .resolve_to_ip
+            .iter()
+            .map(|domain| DomainCheck::Ip(domain.as_str()))
+            .chain(self.resolve_to_cname.iter().map(DomainCheck::Cname))
+            .collect();
+        let next_check = AtomicUsize::new(0);
+        let is_aborted = AtomicBool::new(false);
+        let (tx, rx) = mpsc::channel();
+
+        let mut failed_checks: Vec<usize> = thread::scope(|s| {
+            for _ in 0..MAX_CONCURRENT_CHECKS.min(checks.len()) {
+                let tx = tx.clone();
+                let (checks, next_check, is_aborted) = (&checks, &next_check, &is_aborted);
+                s.spawn(move || {
+                    let log = |msg: String| {
+                        let _ = tx.send(DomainCheckEvent::Log(msg));
+                    };
+                    let should_abort = || is_aborted.load(Ordering::Relaxed);
+
+                    loop {
+                        let check_idx = next_check.fetch_add(1, Ordering::Relaxed);
+                        let resolved = match checks.get(check_idx) {
+                            Some(DomainCheck::Ip(domain)) => check_domain_resolve_ip(domain, &log, &should_abort),
+                            Some(DomainCheck::Cname(domain)) => check_domain_resolve_cname(domain, &log, &should_abort),
+                            None => break,
+                        };
+                        let _ = tx.send(DomainCheckEvent::Done { check_idx, resolved });
+                    }
+                });
+            }
+            drop(tx);
+
+            let mut failed_checks = vec![];
+            loop {
+                match rx.recv_timeout(Duration::from_secs(1)) {
+                    Ok(DomainCheckEvent::Log(msg)) => (self.log)(msg),
+                    Ok(DomainCheckEvent::Done { check_idx, resolved }) => {
+                        if !resolved {
+                            failed_checks.push(check_idx);
+                        }
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+
+                if should_abort() {
+                    is_aborted.store(true, Ordering::Relaxed);
+                }
+            }
+
+            failed_checks
+        });
+
+        if failed_checks.is_empty() {
+            return Ok(());
         }
 
-        for domain in &self.resolve_to_cname {
-            check_domain_resolve_cname(domain, &self.log, target.should_abort);
+        failed_checks.sort_unstable();
+        Err(DomainsCheckError(
+            failed_checks
+                .into_iter()
+                .map(|check_idx| match &checks[check_idx] {
+                    DomainCheck::Ip(domain) => domain.to_string(),
+                    DomainCheck::Cname(custom_domain) => custom_domain.domain.clone(),
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl<'a> DeploymentAction for CheckDnsForDomains<'a> {
+    fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        if let Err(err) = self.check_domains(target.should_abort) {
+            (self.log)(err.to_string());
         }
 
         Ok(())
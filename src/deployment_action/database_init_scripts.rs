@@ -0,0 +1,353 @@
+use crate::cloud_provider::service::{DatabaseType, Service};
+use crate::cloud_provider::DeploymentTarget;
+use crate::deployment_action::deploy_job::{job_status, JobStatus};
+use crate::deployment_action::DeploymentAction;
+use crate::errors::EngineError;
+use crate::events::EventDetails;
+use crate::io_models::database::InitScript;
+use crate::models::database::DatabaseService;
+use crate::runtime::block_on;
+use k8s_openapi::api::batch::v1::Job;
+use k8s_openapi::api::core::v1::{Pod, Secret};
+use kube::api::{DeleteParams, ListParams, LogParams, PostParams, PropagationPolicy};
+use kube::Api;
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const INIT_SCRIPTS_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+const INIT_SCRIPTS_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const INIT_SCRIPTS_OUTPUT_MAX_LINES: i64 = 100;
+const INIT_SCRIPTS_MOUNT_PATH: &str = "/qovery/init-scripts";
+
+/// Run the initialization scripts of a database once, with a job connecting to it.
+/// A marker secret is created once they succeeded, so they are not run again on next deployments.
+pub struct DatabaseInitScripts {
+    database_name: String,
+    database_id: String,
+    resource_name: String,
+    db_type: DatabaseType,
+    host: String,
+    port: u16,
+    login: String,
+    password: String,
+    scripts: Vec<InitScript>,
+    event_details: EventDetails,
+}
+
+impl DatabaseInitScripts {
+    pub fn new(
+        database: &dyn DatabaseService,
+        host: String,
+        port: u16,
+        login: String,
+        password: String,
+        event_details: EventDetails,
+    ) -> DatabaseInitScripts {
+        DatabaseInitScripts {
+            database_name: database.name().to_string(),
+            database_id: database.id().to_string(),
+            resource_name: format!("{}-init-scripts", database.sanitized_name()),
+            db_type: database.db_type(),
+            host,
+            port,
+            login,
+            password,
+            scripts: database.init_scripts(),
+            event_details,
+        }
+    }
+
+    /// Connect with other credentials than the ones of the database options, i.e: generated by the cloud provider
+    pub fn with_credentials(mut self, login: String, password: String) -> DatabaseInitScripts {
+        self.login = login;
+        self.password = password;
+        self
+    }
+
+    fn marker_name(&self) -> String {
+        format!("{}-done", self.resource_name)
+    }
+
+    fn error(&self, reason: String, output: Option<String>) -> Box<EngineError> {
+        Box::new(EngineError::new_database_init_scripts_failed(
+            self.event_details.clone(),
+            self.database_name.clone(),
+            reason,
+            output,
+        ))
+    }
+
+    fn scripts_secret(&self) -> Result<Secret, serde_json::Error> {
+        let mut data = script_files(&self.scripts);
+        data.insert("DB_PASSWORD".to_string(), self.password.clone());
+
+        serde_json::from_value(serde_json::json!({
+            "metadata": {
+                "name": self.resource_name,
+                "labels": { "databaseId": self.database_id },
+            },
+            "stringData": data,
+        }))
+    }
+
+    fn marker_secret(&self) -> Result<Secret, serde_json::Error> {
+        serde_json::from_value(serde_json::json!({
+            "metadata": {
+                "name": self.marker_name(),
+                "labels": { "databaseId": self.database_id },
+            },
+            "stringData": {
+                "scripts": self.scripts.iter().map(|script| script.name.as_str()).collect::<Vec<_>>().join("\n"),
+            },
+        }))
+    }
+
+    fn job(&self) -> Result<Job, serde_json::Error> {
+        let (image, command) = init_scripts_runner(&self.db_type);
+        let script_items: Vec<serde_json::Value> = script_files(&self.scripts)
+            .keys()
+            .map(|file| serde_json::json!({ "key": file, "path": file }))
+            .collect();
+
+        serde_json::from_value(serde_json::json!({
+            "metadata": {
+                "name": self.resource_name,
+                "labels": { "databaseId": self.database_id },
+            },
+            "spec": {
+                "backoffLimit": 0,
+                "template": {
+                    "metadata": {
+                        "labels": { "databaseId": self.database_id },
+                    },
+                    "spec": {
+                        "restartPolicy": "Never",
+                        "automountServiceAccountToken": false,
+                        "containers": [{
+                            "name": "init-scripts",
+                            "image": image,
+                            "command": ["/bin/sh", "-c", format!(
+                                "set -e; for script in {}/*; do echo \"Running $script\"; {}; done",
+                                INIT_SCRIPTS_MOUNT_PATH, command
+                            )],
+                            "env": [
+                                { "name": "DB_HOST", "value": self.host },
+                                { "name": "DB_PORT", "value": self.port.to_string() },
+                                { "name": "DB_USER", "value": self.login },
+                                {
+                                    "name": "DB_PASSWORD",
+                                    "valueFrom": {
+                                        "secretKeyRef": { "name": self.resource_name, "key": "DB_PASSWORD" },
+                                    },
+                                },
+                            ],
+                            "volumeMounts": [{
+                                "name": "init-scripts",
+                                "mountPath": INIT_SCRIPTS_MOUNT_PATH,
+                                "readOnly": true,
+                            }],
+                        }],
+                        "volumes": [{
+                            "name": "init-scripts",
+                            "secret": { "secretName": self.resource_name, "items": script_items },
+                        }],
+                    },
+                },
+            },
+        }))
+    }
+
+    fn cleanup(&self, jobs: &Api<Job>, secrets: &Api<Secret>) {
+        let delete_params = DeleteParams {
+            propagation_policy: Some(PropagationPolicy::Background),
+            ..Default::default()
+        };
+        let _ = block_on(jobs.delete(&self.resource_name, &delete_params));
+        let _ = block_on(secrets.delete(&self.resource_name, &DeleteParams::default()));
+    }
+
+    fn output(&self, pods: &Api<Pod>) -> Option<String> {
+        let list_params = ListParams::default().labels(&format!("job-name={}", self.resource_name));
+        let pod_name = block_on(pods.list(&list_params))
+            .ok()?
+            .items
+            .into_iter()
+            .find_map(|pod| pod.metadata.name)?;
+        let log_params = LogParams {
+            tail_lines: Some(INIT_SCRIPTS_OUTPUT_MAX_LINES),
+            ..Default::default()
+        };
+
+        block_on(pods.logs(&pod_name, &log_params)).ok()
+    }
+}
+
+impl DeploymentAction for DatabaseInitScripts {
+    fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        if self.scripts.is_empty() {
+            return Ok(());
+        }
+
+        let namespace = target.environment.namespace();
+        let secrets: Api<Secret> = Api::namespaced(target.kube.clone(), namespace);
+        let jobs: Api<Job> = Api::namespaced(target.kube.clone(), namespace);
+        let pods: Api<Pod> = Api::namespaced(target.kube.clone(), namespace);
+
+        match block_on(secrets.get(&self.marker_name())) {
+            Ok(_) => {
+                info!("Initialization scripts of database {} already ran", self.database_name);
+                return Ok(());
+            }
+            Err(kube::Error::Api(err)) if err.code == 404 => {}
+            Err(err) => return Err(self.error(format!("cannot check if scripts already ran: {}", err), None)),
+        }
+
+        // leftovers of a previous failed run, the job must be gone before being created again
+        self.cleanup(&jobs, &secrets);
+        let cleanup_started = Instant::now();
+        while block_on(jobs.get(&self.resource_name)).is_ok() && cleanup_started.elapsed() < INIT_SCRIPTS_TIMEOUT {
+            thread::sleep(INIT_SCRIPTS_POLL_INTERVAL);
+        }
+
+        let scripts_secret = self
+            .scripts_secret()
+            .map_err(|err| self.error(format!("cannot build scripts secret: {}", err), None))?;
+        let job = self
+            .job()
+            .map_err(|err| self.error(format!("cannot build job: {}", err), None))?;
+        block_on(secrets.create(&PostParams::default(), &scripts_secret))
+            .map_err(|err| self.error(format!("cannot create scripts secret: {}", err), None))?;
+        if let Err(err) = block_on(jobs.create(&PostParams::default(), &job)) {
+            self.cleanup(&jobs, &secrets);
+            return Err(self.error(format!("cannot create job: {}", err), None));
+        }
+
+        let started = Instant::now();
+        let result = loop {
+            match block_on(jobs.get(&self.resource_name))
+                .as_ref()
+                .map(|job| job_status(&Some(job)))
+            {
+                Ok(JobStatus::Success) => break Ok(()),
+                Ok(JobStatus::Failure { reason, message }) => {
+                    break Err(self.error(format!("{} {}", reason, message), self.output(&pods)))
+                }
+                Ok(JobStatus::NotRunning | JobStatus::Running) | Err(_) => {}
+            }
+
+            if (target.should_abort)() {
+                break Err(self.error("deployment has been aborted".to_string(), self.output(&pods)));
+            }
+
+            if started.elapsed() > INIT_SCRIPTS_TIMEOUT {
+                break Err(self.error(
+                    format!("scripts did not complete after {} seconds", INIT_SCRIPTS_TIMEOUT.as_secs()),
+                    self.output(&pods),
+                ));
+            }
+
+            thread::sleep(INIT_SCRIPTS_POLL_INTERVAL);
+        };
+
+        let result = result.and_then(|_| {
+            let marker = self
+                .marker_secret()
+                .map_err(|err| self.error(format!("cannot build marker secret: {}", err), None))?;
+            block_on(secrets.create(&PostParams::default(), &marker))
+                .map_err(|err| self.error(format!("cannot mark scripts as done: {}", err), None))?;
+            Ok(())
+        });
+
+        // the scripts secret holds the database password, never keep it around
+        self.cleanup(&jobs, &secrets);
+
+        result
+    }
+
+    fn on_pause(&self, _target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        Ok(())
+    }
+
+    fn on_delete(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        let secrets: Api<Secret> = Api::namespaced(target.kube.clone(), target.environment.namespace());
+        let jobs: Api<Job> = Api::namespaced(target.kube.clone(), target.environment.namespace());
+
+        self.cleanup(&jobs, &secrets);
+        let _ = block_on(secrets.delete(&self.marker_name(), &DeleteParams::default()));
+
+        Ok(())
+    }
+}
+
+// Secret keys and mounted file names, prefixed by their position as they are run in alphabetical order
+fn script_files(scripts: &[InitScript]) -> BTreeMap<String, String> {
+    scripts
+        .iter()
+        .enumerate()
+        .map(|(idx, script)| {
+            let name: String = script
+                .name
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            (format!("{:03}-{}", idx, name), script.content.clone())
+        })
+        .collect()
+}
+
+// Client image and command running a single `$script` against the database
+fn init_scripts_runner(db_type: &DatabaseType) -> (&'static str, &'static str) {
+    match db_type {
+        DatabaseType::PostgreSQL => (
+            "postgres:15-alpine",
+            concat!(
+                "PGPASSWORD=\"$DB_PASSWORD\" psql -v ON_ERROR_STOP=1 ",
+                "-h \"$DB_HOST\" -p \"$DB_PORT\" -U \"$DB_USER\" -d postgres -f \"$script\""
+            ),
+        ),
+        DatabaseType::MySQL => (
+            "mysql:8.0",
+            "MYSQL_PWD=\"$DB_PASSWORD\" mysql -h \"$DB_HOST\" -P \"$DB_PORT\" -u \"$DB_USER\" < \"$script\"",
+        ),
+        DatabaseType::MongoDB => (
+            "mongo:6.0",
+            concat!(
+                "mongosh --quiet --host \"$DB_HOST\" --port \"$DB_PORT\" ",
+                "-u \"$DB_USER\" -p \"$DB_PASSWORD\" --authenticationDatabase admin --file \"$script\""
+            ),
+        ),
+        DatabaseType::Redis => (
+            "redis:7-alpine",
+            "redis-cli -h \"$DB_HOST\" -p \"$DB_PORT\" -a \"$DB_PASSWORD\" --no-auth-warning < \"$script\"",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_script_files_keep_declared_order() {
+        let script = |name: &str| InitScript {
+            name: name.to_string(),
+            content: format!("-- {}", name),
+        };
+        let scripts = vec![script("schemas.sql"), script("b roles.sql"), script("a/seed.sql")];
+
+        let files = script_files(&scripts);
+
+        assert_eq!(
+            files.keys().cloned().collect::<Vec<_>>(),
+            vec!["000-schemas.sql", "001-b_roles.sql", "002-a_seed.sql"]
+        );
+        assert_eq!(files["001-b_roles.sql"], "-- b roles.sql");
+    }
+}
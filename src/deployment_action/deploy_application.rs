@@ -1,67 +1,243 @@
-use crate::cloud_provider::helm::{ChartInfo, HelmAction, HelmChartNamespaces};
-use crate::cloud_provider::service::{delete_pending_service, Action, Service};
+use crate::cloud_provider::environment::internal_service_fqdn;
+use crate::cloud_provider::helm::{ChartInfo, ChartSetValue, HelmAction, HelmChartNamespaces};
+use crate::cloud_provider::kubernetes::KubernetesFeature;
+use crate::cloud_provider::service::{delete_pending_service, Action, HealthCheck, Service};
 use crate::cloud_provider::DeploymentTarget;
 use crate::deployment_action::deploy_helm::HelmDeployment;
+use crate::deployment_action::deploy_namespace::NamespaceDeployment;
 use crate::deployment_action::pause_service::PauseServiceAction;
+use crate::deployment_action::pre_deploy_job::PreDeployJob;
+use crate::deployment_action::readiness_probe::wait_for_readiness_probe;
+use crate::deployment_action::utils::{
+    check_node_pool_capacity, is_api_group_available, is_crd_installed, is_mutating_webhook_installed,
+    log_policy_exemption, mirror_image, verify_cleanup, wait_for_readiness_annotation, KubeObjectKind,
+};
+use crate::deployment_action::warmup::warmup_pods;
 use crate::deployment_action::DeploymentAction;
 use crate::deployment_report::application::reporter::ApplicationDeploymentReporter;
 use crate::deployment_report::execute_long_deployment;
 use crate::errors::{CommandError, EngineError};
-use crate::events::{EnvironmentStep, Stage};
+use crate::events::{EnvironmentStep, EventDetails, Stage};
+use crate::io_models::application::AutoscalingMetric;
+use crate::io_models::container::Registry;
 use crate::kubers_utils::kube_delete_all_from_selector;
-use crate::models::application::{Application, ApplicationService};
+use crate::models::application::{Application, ApplicationService, LOG_SHIPPING_IMAGE, LOG_SHIPPING_IMAGE_TAG};
 use crate::models::types::{CloudProvider, ToTeraContext};
 use crate::runtime::block_on;
-use k8s_openapi::api::core::v1::PersistentVolumeClaim;
+use k8s_openapi::api::core::v1::{Namespace, PersistentVolumeClaim};
+use kube::api::{Patch, PatchParams};
+use kube::Api;
 
 use crate::deployment_report::logger::EnvProgressLogger;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::time::Duration;
 use tera::Context;
+use url::Url;
+use uuid::Uuid;
+
+const CUSTOM_METRICS_API_GROUP: &str = "custom.metrics.k8s.io";
+const VPA_CRD_NAME: &str = "verticalpodautoscalers.autoscaling.k8s.io";
 
 impl<T: CloudProvider> DeploymentAction for Application<T>
 where
     Application<T>: ToTeraContext,
 {
+    fn helm_release(&self) -> Option<String> {
+        Some(self.helm_release_name())
+    }
+
+    // services with slow probes need more time to be ready
+    fn helm_timeout(&self) -> Duration {
+        self.startup_timeout()
+    }
+
+    fn service_workspace_directory(&self) -> Option<&str> {
+        Some(self.workspace_directory())
+    }
+
     fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
-        let long_task = |_logger: &EnvProgressLogger| -> Result<(), Box<EngineError>> {
+        let long_task = |logger: &EnvProgressLogger| -> Result<(), Box<EngineError>> {
             let event_details = self.get_event_details(Stage::Environment(EnvironmentStep::Deploy));
+            if let Some((env_var_name, allowed_pattern)) = self.invalid_environment_variable_name() {
+                return Err(Box::new(EngineError::new_invalid_env_var_name(
+                    event_details,
+                    env_var_name.to_string(),
+                    allowed_pattern.to_string(),
+                )));
+            }
+
+            // Referenced services endpoints are computed from their ids, so they are known even if not deployed yet
+            if let Err((env_var_name, service_reference)) = self.resolved_environment_variables(target.environment) {
+                return Err(Box::new(EngineError::new_unresolved_service_reference(
+                    event_details,
+                    env_var_name,
+                    service_reference,
+                )));
+            }
+
+            // A dry run only renders and validates the chart, the cluster is left untouched
+            let namespace = self.namespace(target.environment);
+            if !target.is_dry_run_deploy {
+                if self.has_dedicated_namespace() {
+                    dedicated_namespace(self, target, event_details.clone()).on_create(target)?;
+                }
+                enable_service_mesh_injection(self, target, &namespace, logger, event_details.clone())?;
+            }
+            check_metrics_adapter(self, target, logger);
+            log_policy_exemption(self.policy_exemption().as_ref(), logger);
+            if !target.is_dry_run_deploy {
+                check_node_pool_capacity(&target.kube, self.node_selector(), self.tolerations(), logger);
+            }
+
             // If the service have been paused, we must ensure we un-pause it first as hpa will not kick in
-            let _ = PauseServiceAction::new(
-                self.selector(),
-                self.is_stateful(),
-                Duration::from_secs(5 * 60),
-                event_details.clone(),
-            )
-            .unpause_if_needed(target);
+            if !target.is_dry_run_deploy {
+                let _ = PauseServiceAction::new(
+                    self.selector(),
+                    self.is_stateful(),
+                    Duration::from_secs(5 * 60),
+                    event_details.clone(),
+                )
+                .with_namespace(namespace.clone())
+                .unpause_if_needed(target);
+            }
+
+            // The new version may require the job to complete before being healthy
+            if !target.is_dry_run_deploy && self.pre_deploy_job_command().is_some() {
+                logger.info("⏳ Running the pre-deploy job of the application".to_string());
+                pre_deploy_job(self, target, event_details.clone()).on_create(target)?;
+            }
+
+            // Older clusters would silently drop the restart policy and block the pod on its init container
+            if self.log_shipping_destination().is_some() && self.advanced_settings().logging_sidecar_native {
+                target.require(KubernetesFeature::NativeSidecars, event_details.clone())?;
+            }
+
+            // The logging sidecar image is pulled from the cluster registry, as the application one
+            if !target.is_dry_run_deploy && self.log_shipping_destination().is_some() {
+                let docker_hub = Registry::DockerHub {
+                    long_id: Uuid::nil(),
+                    url: Url::parse("https://docker.io").expect("docker hub url is not valid"),
+                    credentials: None,
+                };
+                mirror_image(
+                    &docker_hub,
+                    LOG_SHIPPING_IMAGE,
+                    LOG_SHIPPING_IMAGE_TAG,
+                    self.log_shipping_tag_for_mirror(),
+                    target,
+                    logger,
+                    event_details.clone(),
+                )?;
+            }
 
             let chart = ChartInfo {
                 name: self.helm_release_name(),
                 path: self.workspace_directory().to_string(),
                 namespace: HelmChartNamespaces::Custom,
-                custom_namespace: Some(target.environment.namespace().to_string()),
-                timeout_in_seconds: self.startup_timeout().as_secs() as i64,
+                custom_namespace: Some(namespace.clone()),
+                timeout_in_seconds: self.helm_timeout().as_secs() as i64,
+                values_files: self.helm_value_files(self.workspace_directory()),
+                values: self
+                    .helm_set_values()
+                    .into_iter()
+                    .map(|(key, value)| ChartSetValue::escaped(key, &value))
+                    .collect(),
                 k8s_selector: Some(self.selector()),
+                release_tag: self.release_tag(),
+                dry_run: target.is_dry_run_deploy,
                 ..Default::default()
             };
 
-            let helm = HelmDeployment::new(
+            let mut tera_context = self.to_tera_context(target)?;
+            // the chart cannot be installed if it contains a VerticalPodAutoscaler without its CRD
+            if self.vpa_update_mode().is_some() && !is_vpa_installed(target, logger) {
+                tera_context.insert("vpa_update_mode", &None::<&str>);
+            }
+
+            let mut helm = HelmDeployment::new(
                 event_details.clone(),
-                self.to_tera_context(target)?,
+                tera_context,
                 PathBuf::from(self.helm_chart_dir()),
                 None,
                 chart,
             );
+            if self.pvc_binding_fail_fast() {
+                helm = helm.with_pvc_binding_check();
+            }
+            if self.node_not_ready_fail_fast() {
+                helm = helm.with_node_readiness_check();
+            }
+            // pods cannot be gated on older clusters, no need to look for them
+            if target.supports(KubernetesFeature::SchedulingGates, event_details.clone()) {
+                helm = helm.with_scheduling_gate_max_wait(self.scheduling_gate_max_wait());
+            }
+            // helm --atomic rolls back a failed rollout, unless the engine is asked to wait for it instead
+            if self.engine_readiness_check() {
+                helm = helm.with_engine_readiness_check(self.helm_timeout());
+            }
 
             helm.on_create(target)?;
+            if target.is_dry_run_deploy {
+                logger.info(format!(
+                    "Dry run of application {}: the chart has been rendered and validated, nothing has been deployed",
+                    self.name()
+                ));
+                return Ok(());
+            }
+
+            // Pods are ready, but an external controller may still have to flag the application as ready
+            if let Some((annotation, expected_value)) = self.readiness_annotation() {
+                let kind = match self.is_stateful() {
+                    true => KubeObjectKind::Statefulset,
+                    false => KubeObjectKind::Deployment,
+                };
+                wait_for_readiness_annotation(
+                    target,
+                    &namespace,
+                    &self.selector(),
+                    kind,
+                    (&annotation, &expected_value),
+                    self.startup_timeout(),
+                    event_details.clone(),
+                )?;
+            }
 
-            delete_pending_service(
-                target.kubernetes.get_kubeconfig_file_path()?.as_str(),
-                target.environment.namespace(),
+            // Pods are ready, but the application endpoint may not be able to serve requests yet
+            if let Some(probe) = self.readiness_probe() {
+                logger.info(format!(
+                    "⏳ Waiting for the application endpoint {} to answer status {}",
+                    probe.path, probe.expected_status
+                ));
+                wait_for_readiness_probe(&target.kube, &namespace, &self.selector(), &probe, target.should_abort)
+                    .map_err(|reason| {
+                        EngineError::new_k8s_pod_not_ready(
+                            event_details.clone(),
+                            self.selector(),
+                            namespace.clone(),
+                            CommandError::new_from_safe_message(reason),
+                        )
+                    })?;
+            }
+
+            warmup_application(self, target, &namespace, logger, event_details.clone())?;
+
+            // Expose the application inside the environment namespace, so router and other services can reach it
+            if self.has_dedicated_namespace() {
+                dedicated_namespace_service(self, target, event_details.clone(), HelmAction::Deploy)
+                    .on_create(target)?;
+            }
+
+            let unschedulable_messages = delete_pending_service(
+                target.kubeconfig_path()?,
+                &namespace,
                 self.selector().as_str(),
-                target.kubernetes.cloud_provider().credentials_environment_variables(),
+                target.kubectl_credentials(),
                 event_details,
             )?;
+            for message in unschedulable_messages {
+                logger.warning(format!("⚠️ {}", message));
+            }
 
             Ok(())
         };
@@ -78,8 +254,42 @@ where
                     self.is_stateful(),
                     Duration::from_secs(5 * 60),
                     self.get_event_details(Stage::Environment(EnvironmentStep::Pause)),
-                );
-                pause_service.on_pause(target)
+                )
+                .with_hpa_suspension(self.pause_suspend_hpa())
+                .with_graceful_drain_timeout(self.pause_graceful_drain_timeout())
+                .with_namespace(self.namespace(target.environment));
+                pause_service.on_pause(target)?;
+                pause_service.on_pause_check(target)?;
+
+                // standby pods only make sense while the application is running
+                if let Some(standby_selector) = self.standby_selector() {
+                    PauseServiceAction::new(
+                        standby_selector,
+                        false,
+                        Duration::from_secs(5 * 60),
+                        self.get_event_details(Stage::Environment(EnvironmentStep::Pause)),
+                    )
+                    .with_namespace(self.namespace(target.environment))
+                    .on_pause(target)?;
+                }
+
+                Ok(())
+            },
+        )
+    }
+
+    fn on_restart(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        execute_long_deployment(
+            ApplicationDeploymentReporter::new(self, target, Action::Restart),
+            |_logger: &EnvProgressLogger| -> Result<(), Box<EngineError>> {
+                PauseServiceAction::new(
+                    self.selector(),
+                    self.is_stateful(),
+                    Duration::from_secs(5 * 60),
+                    self.get_event_details(Stage::Environment(EnvironmentStep::Restart)),
+                )
+                .with_namespace(self.namespace(target.environment))
+                .on_restart(target)
             },
         )
     }
@@ -89,10 +299,11 @@ where
         execute_long_deployment(
             ApplicationDeploymentReporter::new(self, target, Action::Delete),
             |logger: &EnvProgressLogger| {
+                let namespace = self.namespace(target.environment);
                 let chart = ChartInfo {
                     name: self.helm_release_name(),
                     namespace: HelmChartNamespaces::Custom,
-                    custom_namespace: Some(target.environment.namespace().to_string()),
+                    custom_namespace: Some(namespace.clone()),
                     action: HelmAction::Destroy,
                     k8s_selector: Some(self.selector()),
                     ..Default::default()
@@ -114,7 +325,7 @@ where
                     if let Err(err) = block_on(kube_delete_all_from_selector::<PersistentVolumeClaim>(
                         &target.kube,
                         &self.selector(),
-                        target.environment.namespace(),
+                        &namespace,
                     )) {
                         return Err(Box::new(EngineError::new_k8s_cannot_delete_pvcs(
                             event_details.clone(),
@@ -123,6 +334,8 @@ where
                         )));
                     }
                 }
+                pre_deploy_job(self, target, event_details.clone()).on_delete(target)?;
+                verify_cleanup(target, &namespace, &[&self.selector()], event_details.clone())?;
 
                 // Delete container repository created for this application
                 logger.info("🪓 Terminating container registry of the application".to_string());
@@ -139,8 +352,259 @@ where
                     return Err(Box::new(user_error));
                 }
 
+                if self.has_dedicated_namespace() {
+                    logger.info("🪓 Terminating dedicated namespace of the application".to_string());
+                    dedicated_namespace_service(self, target, event_details.clone(), HelmAction::Destroy)
+                        .on_delete(target)?;
+                    dedicated_namespace(self, target, event_details.clone()).on_delete(target)?;
+                }
+
                 Ok(())
             },
         )
     }
 }
+
+// The job runs next to the application, with its image and environment variables
+fn pre_deploy_job<T: CloudProvider>(
+    app: &Application<T>,
+    target: &DeploymentTarget,
+    event_details: EventDetails,
+) -> PreDeployJob {
+    PreDeployJob::new(
+        app.name().to_string(),
+        app.long_id().to_string(),
+        &app.sanitized_name(),
+        app.namespace(target.environment),
+        app.build().image.full_image_name_with_tag(),
+        app.pre_deploy_job_command().unwrap_or_default(),
+        app.pre_deploy_job_run_key(),
+        app.pre_deploy_job_timeout(),
+        event_details,
+    )
+    .with_environment_variables(
+        app.resolved_environment_variables(target.environment)
+            .unwrap_or_default(),
+    )
+    .with_image_pull_secret(
+        app.build()
+            .image
+            .registry_secret_name(target.kubernetes.kind())
+            .to_string(),
+    )
+}
+
+// The mesh sidecar is injected by a webhook, without it pods are silently deployed outside of the mesh
+fn enable_service_mesh_injection<T: CloudProvider>(
+    app: &Application<T>,
+    target: &DeploymentTarget,
+    namespace: &str,
+    logger: &EnvProgressLogger,
+    event_details: EventDetails,
+) -> Result<(), Box<EngineError>> {
+    let service_mesh = app.advanced_settings().deployment_service_mesh;
+    let webhook_prefix = match service_mesh.injector_webhook_prefix() {
+        Some(webhook_prefix) => webhook_prefix,
+        None => return Ok(()),
+    };
+
+    match is_mutating_webhook_installed(&target.kube, webhook_prefix) {
+        Ok(true) => {}
+        Ok(false) => logger.warning(format!(
+            "⚠️ Service mesh {:?} does not seem to be installed, the application will not join the mesh",
+            service_mesh
+        )),
+        Err(err) => logger.warning(format!(
+            "⚠️ Cannot check that service mesh {:?} is installed: {}",
+            service_mesh, err
+        )),
+    }
+
+    if !app.advanced_settings().deployment_service_mesh_label_namespace {
+        return Ok(());
+    }
+
+    if let Some(patch) = service_mesh.namespace_injection_patch() {
+        let namespaces: Api<Namespace> = Api::all(target.kube.clone());
+        block_on(namespaces.patch(namespace, &PatchParams::default(), &Patch::Merge(patch))).map_err(|err| {
+            EngineError::new_k8s_cannot_enable_service_mesh_injection(
+                event_details,
+                namespace.to_string(),
+                CommandError::new_from_safe_message(err.to_string()),
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+// Without a metrics adapter, the autoscaler cannot read custom metrics and never scales the application
+fn check_metrics_adapter<T: CloudProvider>(
+    app: &Application<T>,
+    target: &DeploymentTarget,
+    logger: &EnvProgressLogger,
+) {
+    let custom_metrics: Vec<String> = app
+        .autoscaling_metrics()
+        .into_iter()
+        .filter_map(|metric| match metric {
+            AutoscalingMetric::Custom { name, .. } => Some(name),
+            _ => None,
+        })
+        .collect();
+    if custom_metrics.is_empty() {
+        return;
+    }
+
+    match is_api_group_available(&target.kube, CUSTOM_METRICS_API_GROUP) {
+        Ok(true) => {}
+        Ok(false) => logger.warning(format!(
+            "⚠️ No metrics adapter serves {} in the cluster, the application will not scale on {}",
+            CUSTOM_METRICS_API_GROUP,
+            custom_metrics.join(", ")
+        )),
+        Err(err) => logger.warning(format!("⚠️ Cannot check that a metrics adapter is installed: {}", err)),
+    }
+}
+
+// Warmup only reduces the cold start of the first users, so by default its failures do not fail the deployment
+fn warmup_application<T: CloudProvider>(
+    app: &Application<T>,
+    target: &DeploymentTarget,
+    namespace: &str,
+    logger: &EnvProgressLogger,
+    event_details: EventDetails,
+) -> Result<(), Box<EngineError>> {
+    if app.warmup_requests().is_empty() || target.is_dry_run_deploy {
+        return Ok(());
+    }
+
+    let port = match app.warmup_port() {
+        Some(port) => port,
+        None => {
+            logger.warning("⚠️ Application does not expose any port, warmup requests are skipped".to_string());
+            return Ok(());
+        }
+    };
+
+    logger.info(format!(
+        "🔥 Warming up application with {} requests",
+        app.warmup_requests().len()
+    ));
+    let failures = warmup_pods(
+        &target.kube,
+        namespace,
+        &app.selector(),
+        port,
+        app.warmup_requests(),
+        app.warmup_timeout(),
+    );
+    if failures.is_empty() {
+        return Ok(());
+    }
+
+    if app.advanced_settings().deployment_warmup_fail_on_error {
+        return Err(Box::new(EngineError::new_application_warmup_failed(
+            event_details,
+            app.name().to_string(),
+            failures,
+        )));
+    }
+
+    logger.warning(format!("⚠️ Some warmup requests failed: {}", failures.join(", ")));
+    Ok(())
+}
+
+fn is_vpa_installed(target: &DeploymentTarget, logger: &EnvProgressLogger) -> bool {
+    match is_crd_installed(&target.kube, VPA_CRD_NAME) {
+        Ok(true) => true,
+        Ok(false) => {
+            logger.warning(
+                "⚠️ Vertical pod autoscaler is not installed in the cluster, no resources recommendation computed"
+                    .to_string(),
+            );
+            false
+        }
+        Err(err) => {
+            logger.warning(format!("⚠️ Cannot check that vertical pod autoscaler is installed: {}", err));
+            false
+        }
+    }
+}
+
+fn dedicated_namespace<T: CloudProvider>(
+    app: &Application<T>,
+    target: &DeploymentTarget,
+    event_details: EventDetails,
+) -> NamespaceDeployment {
+    NamespaceDeployment::new(
+        target,
+        Some(app.namespace(target.environment)),
+        BTreeMap::from([
+            ("envId".to_string(), target.environment.id.to_string()),
+            ("appId".to_string(), app.id().to_string()),
+        ]),
+        event_details,
+    )
+}
+
+// An ExternalName service living in the environment namespace and pointing to the application one.
+// It allows the router and other services of the environment to keep using the application service name
+fn dedicated_namespace_service<T: CloudProvider>(
+    app: &Application<T>,
+    target: &DeploymentTarget,
+    event_details: EventDetails,
+    action: HelmAction,
+) -> HelmDeployment {
+    let values = vec![
+        ChartSetValue {
+            key: "target_hostname".to_string(),
+            value: internal_service_fqdn(&app.sanitized_name(), &app.namespace(target.environment)),
+        },
+        ChartSetValue {
+            key: "app_id".to_string(),
+            value: app.id().to_string(),
+        },
+        ChartSetValue {
+            key: "app_long_id".to_string(),
+            value: app.long_id().to_string(),
+        },
+        ChartSetValue {
+            key: "environment_id".to_string(),
+            value: target.environment.id.to_string(),
+        },
+        ChartSetValue {
+            key: "environment_long_id".to_string(),
+            value: target.environment.long_id.to_string(),
+        },
+        ChartSetValue {
+            key: "project_long_id".to_string(),
+            value: target.environment.project_long_id.to_string(),
+        },
+        ChartSetValue {
+            key: "service_name".to_string(),
+            value: app.sanitized_name(),
+        },
+    ];
+
+    let chart = ChartInfo {
+        name: format!("{}-externalname", app.sanitized_name()),
+        path: format!("{}/{}", app.workspace_directory(), "service-chart"),
+        namespace: HelmChartNamespaces::Custom,
+        custom_namespace: Some(target.environment.namespace().to_string()),
+        action,
+        values,
+        ..Default::default()
+    };
+
+    let mut tera_context = Context::default();
+    tera_context.insert("publicly_accessible", &false);
+
+    HelmDeployment::new(
+        event_details,
+        tera_context,
+        PathBuf::from(app.helm_chart_external_name_service_dir()),
+        None,
+        chart,
+    )
+}
@@ -1,20 +1,26 @@
-use crate::cloud_provider::helm::{ChartInfo, HelmAction, HelmChartNamespaces};
-use crate::cloud_provider::service::{delete_pending_service, Action, Service};
+use crate::cloud_provider::helm::{ChartInfo, ChartSetValue, HelmAction, HelmChartNamespaces};
+use crate::cloud_provider::kubernetes::KubernetesFeature;
+use crate::cloud_provider::service::{delete_pending_service, Action, HealthCheck, Service};
 use crate::cloud_provider::DeploymentTarget;
 use crate::deployment_action::deploy_helm::HelmDeployment;
 use crate::deployment_action::pause_service::PauseServiceAction;
+use crate::deployment_action::pre_deploy_job::PreDeployJob;
+use crate::deployment_action::readiness_probe::wait_for_readiness_probe;
 use crate::deployment_action::DeploymentAction;
 use crate::deployment_report::application::reporter::ApplicationDeploymentReporter;
 use crate::deployment_report::{execute_long_deployment, DeploymentTaskImpl};
 use crate::errors::{CommandError, EngineError};
-use crate::events::{EnvironmentStep, Stage};
+use crate::events::{EnvironmentStep, EventDetails, Stage};
 use crate::kubers_utils::kube_delete_all_from_selector;
 use crate::models::container::{Container, ContainerService};
 use crate::models::types::{CloudProvider, ToTeraContext};
 use crate::runtime::block_on;
 use k8s_openapi::api::core::v1::PersistentVolumeClaim;
 
-use crate::deployment_action::utils::{delete_cached_image, get_last_deployed_image, mirror_image, KubeObjectKind};
+use crate::deployment_action::utils::{
+    check_node_pool_capacity, combined_change_drift, delete_cached_image, get_last_deployed_image,
+    get_last_deployed_resources, log_policy_exemption, mirror_image, verify_cleanup, KubeObjectKind,
+};
 use crate::deployment_report::logger::{EnvProgressLogger, EnvSuccessLogger};
 use std::path::PathBuf;
 use std::time::Duration;
@@ -23,6 +29,19 @@ impl<T: CloudProvider> DeploymentAction for Container<T>
 where
     Container<T>: ToTeraContext,
 {
+    fn helm_release(&self) -> Option<String> {
+        Some(self.helm_release_name())
+    }
+
+    // services with slow probes need more time to be ready
+    fn helm_timeout(&self) -> Duration {
+        self.startup_timeout()
+    }
+
+    fn service_workspace_directory(&self) -> Option<&str> {
+        Some(self.workspace_directory())
+    }
+
     fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
         let event_details = self.get_event_details(Stage::Environment(EnvironmentStep::Deploy));
         struct TaskContext {
@@ -31,6 +50,14 @@ where
 
         // We first mirror the image if needed
         let pre_task = |logger: &EnvProgressLogger| -> Result<TaskContext, Box<EngineError>> {
+            // A dry run only renders and validates the chart, the cluster and its registry are left untouched
+            if target.is_dry_run_deploy {
+                log_policy_exemption(self.policy_exemption().as_ref(), logger);
+                return Ok(TaskContext {
+                    last_deployed_image: None,
+                });
+            }
+
             mirror_image(
                 &self.registry,
                 &self.image,
@@ -52,53 +79,122 @@ where
                 target.environment.namespace(),
             ));
 
+            check_combined_change(self, target, last_image.as_deref(), logger, event_details.clone())?;
+            log_policy_exemption(self.policy_exemption().as_ref(), logger);
+            check_node_pool_capacity(&target.kube, self.node_selector(), self.tolerations(), logger);
+
             Ok(TaskContext {
                 last_deployed_image: last_image,
             })
         };
 
-        let long_task = |_logger: &EnvProgressLogger, state: TaskContext| -> Result<TaskContext, Box<EngineError>> {
+        let long_task = |logger: &EnvProgressLogger, state: TaskContext| -> Result<TaskContext, Box<EngineError>> {
             // If the service have been paused, we must ensure we un-pause it first as hpa will not kick in
-            let _ = PauseServiceAction::new(
-                self.selector(),
-                self.is_stateful(),
-                Duration::from_secs(5 * 60),
-                event_details.clone(),
-            )
-            .unpause_if_needed(target);
+            if !target.is_dry_run_deploy {
+                let _ = PauseServiceAction::new(
+                    self.selector(),
+                    self.is_stateful(),
+                    Duration::from_secs(5 * 60),
+                    event_details.clone(),
+                )
+                .unpause_if_needed(target);
+            }
+
+            // The new version may require the job to complete before being healthy
+            if !target.is_dry_run_deploy && self.pre_deploy_job_command().is_some() {
+                logger.info("⏳ Running the pre-deploy job of the container".to_string());
+                pre_deploy_job(self, target, event_details.clone()).on_create(target)?;
+            }
 
             let chart = ChartInfo {
                 name: self.helm_release_name(),
                 path: self.workspace_directory().to_string(),
                 namespace: HelmChartNamespaces::Custom,
                 custom_namespace: Some(target.environment.namespace().to_string()),
-                timeout_in_seconds: self.startup_timeout().as_secs() as i64,
+                timeout_in_seconds: self.helm_timeout().as_secs() as i64,
+                values_files: self.helm_value_files(self.workspace_directory()),
+                values: self
+                    .helm_set_values()
+                    .into_iter()
+                    .map(|(key, value)| ChartSetValue::escaped(key, &value))
+                    .collect(),
                 k8s_selector: Some(self.selector()),
+                release_tag: self.release_tag(),
+                dry_run: target.is_dry_run_deploy,
                 ..Default::default()
             };
 
-            let helm = HelmDeployment::new(
+            let mut helm = HelmDeployment::new(
                 event_details.clone(),
                 self.to_tera_context(target)?,
                 PathBuf::from(self.helm_chart_dir()),
                 None,
                 chart,
             );
+            if self.node_not_ready_fail_fast() {
+                helm = helm.with_node_readiness_check();
+            }
+            // pods cannot be gated on older clusters, no need to look for them
+            if target.supports(KubernetesFeature::SchedulingGates, event_details.clone()) {
+                helm = helm.with_scheduling_gate_max_wait(self.scheduling_gate_max_wait());
+            }
+            // helm --atomic rolls back a failed rollout, unless the engine is asked to wait for it instead
+            if self.engine_readiness_check() {
+                helm = helm.with_engine_readiness_check(self.helm_timeout());
+            }
 
             helm.on_create(target)?;
+            if target.is_dry_run_deploy {
+                logger.info(format!(
+                    "Dry run of container {}: the chart has been rendered and validated, nothing has been deployed",
+                    self.name()
+                ));
+                return Ok(state);
+            }
+
+            // Pods are ready, but the container endpoint may not be able to serve requests yet
+            if let Some(probe) = self.readiness_probe() {
+                logger.info(format!(
+                    "⏳ Waiting for the container endpoint {} to answer status {}",
+                    probe.path, probe.expected_status
+                ));
+                wait_for_readiness_probe(
+                    &target.kube,
+                    target.environment.namespace(),
+                    &self.selector(),
+                    &probe,
+                    target.should_abort,
+                )
+                .map_err(|reason| {
+                    EngineError::new_k8s_pod_not_ready(
+                        event_details.clone(),
+                        self.selector(),
+                        target.environment.namespace().to_string(),
+                        CommandError::new_from_safe_message(reason),
+                    )
+                })?;
+            }
 
-            delete_pending_service(
-                target.kubernetes.get_kubeconfig_file_path()?.as_str(),
+            let unschedulable_messages = delete_pending_service(
+                target.kubeconfig_path()?,
                 target.environment.namespace(),
                 self.selector().as_str(),
-                target.kubernetes.cloud_provider().credentials_environment_variables(),
+                target.kubectl_credentials(),
                 event_details.clone(),
             )?;
+            for message in unschedulable_messages {
+                logger.warning(format!("⚠️ {}", message));
+            }
 
             Ok(state)
         };
 
         let post_task = |logger: &EnvSuccessLogger, state: TaskContext| {
+            // nothing has been deployed by a dry run, the previous image is still in use
+            if target.is_dry_run_deploy {
+                return;
+            }
+
             // Delete previous image from cache to cleanup resources
             let _ = delete_cached_image(self.tag_for_mirror(), state.last_deployed_image, false, target, logger)
                 .map_err(|err| {
@@ -127,8 +223,38 @@ where
                     self.is_stateful(),
                     Duration::from_secs(5 * 60),
                     self.get_event_details(Stage::Environment(EnvironmentStep::Pause)),
-                );
-                pause_service.on_pause(target)
+                )
+                .with_hpa_suspension(self.pause_suspend_hpa());
+                pause_service.on_pause(target)?;
+                pause_service.on_pause_check(target)?;
+
+                // standby pods only make sense while the container is running
+                if let Some(standby_selector) = self.standby_selector() {
+                    PauseServiceAction::new(
+                        standby_selector,
+                        false,
+                        Duration::from_secs(5 * 60),
+                        self.get_event_details(Stage::Environment(EnvironmentStep::Pause)),
+                    )
+                    .on_pause(target)?;
+                }
+
+                Ok(())
+            },
+        )
+    }
+
+    fn on_restart(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        execute_long_deployment(
+            ApplicationDeploymentReporter::new_for_container(self, target, Action::Restart),
+            |_logger: &EnvProgressLogger| -> Result<(), Box<EngineError>> {
+                PauseServiceAction::new(
+                    self.selector(),
+                    self.is_stateful(),
+                    Duration::from_secs(5 * 60),
+                    self.get_event_details(Stage::Environment(EnvironmentStep::Restart)),
+                )
+                .on_restart(target)
             },
         )
     }
@@ -192,6 +318,13 @@ where
                     )));
                 }
             }
+            pre_deploy_job(self, target, event_details.clone()).on_delete(target)?;
+            verify_cleanup(
+                target,
+                target.environment.namespace(),
+                &[&self.selector()],
+                event_details.clone(),
+            )?;
 
             Ok(state)
         };
@@ -223,3 +356,79 @@ where
         )
     }
 }
+
+// The job runs next to the container, with its mirrored image and environment variables
+fn pre_deploy_job<T: CloudProvider>(
+    container: &Container<T>,
+    target: &DeploymentTarget,
+    event_details: EventDetails,
+) -> PreDeployJob {
+    let registry_info = target.container_registry.registry_info();
+    let job = PreDeployJob::new(
+        container.name().to_string(),
+        container.long_id().to_string(),
+        &container.sanitized_name(),
+        target.environment.namespace().to_string(),
+        container.mirrored_image_full(registry_info),
+        container.pre_deploy_job_command().unwrap_or_default(),
+        container.pre_deploy_job_run_key(),
+        container.pre_deploy_job_timeout(),
+        event_details,
+    )
+    .with_environment_variables(container.environment_variables().to_vec());
+
+    match &registry_info.registry_docker_json_config {
+        Some(docker_json_config) => job.with_registry_docker_json_config(docker_json_config.to_string()),
+        None => job,
+    }
+}
+
+/// Changing the version and the resources in the same deployment makes it hard to know which one caused
+/// a regression, so warn about it or reject the deployment if requested by the advanced settings
+fn check_combined_change<T: CloudProvider>(
+    container: &Container<T>,
+    target: &DeploymentTarget,
+    last_image: Option<&str>,
+    logger: &EnvProgressLogger,
+    event_details: EventDetails,
+) -> Result<(), Box<EngineError>> {
+    let last_image = match last_image {
+        Some(image) => image,
+        None => return Ok(()),
+    };
+
+    let last_resources = match block_on(get_last_deployed_resources(
+        target.kube.clone(),
+        &container.selector(),
+        if container.is_stateful() {
+            KubeObjectKind::Statefulset
+        } else {
+            KubeObjectKind::Deployment
+        },
+        target.environment.namespace(),
+    )) {
+        Some(resources) => resources,
+        None => return Ok(()),
+    };
+
+    let drift = match combined_change_drift(
+        last_image,
+        &container.tag_for_mirror(),
+        last_resources,
+        container.resources_requests(),
+        container.combined_change_threshold_percent(),
+    ) {
+        Some(drift) => drift,
+        None => return Ok(()),
+    };
+
+    if container.reject_combined_changes() {
+        return Err(Box::new(EngineError::new_combined_change_rejected(event_details, drift)));
+    }
+
+    logger.warning(format!(
+        "⚠️ This deployment changes both the version and the resources ({}), consider deploying them separately",
+        drift.join(", ")
+    ));
+    Ok(())
+}
@@ -1,35 +1,87 @@
+use crate::cloud_provider::environment::internal_service_fqdn;
 use crate::cloud_provider::helm::{ChartInfo, ChartSetValue, HelmAction, HelmChartNamespaces};
-use crate::cloud_provider::service::{delete_pending_service, get_database_terraform_config, Action, Service};
+use crate::cloud_provider::kubernetes::kube_does_service_exists;
+use crate::cloud_provider::service::{
+    delete_pending_service, get_database_terraform_config, get_database_terraform_credentials, Action,
+    DatabaseCredentials, Service,
+};
+use crate::cloud_provider::utilities::{check_tcp_port_is_open, TcpCheckErrors, TcpCheckSource};
 use crate::cloud_provider::Kind::Aws;
 use crate::cloud_provider::{service, DeploymentTarget};
 use crate::cmd;
 use crate::cmd::command::{ExecutableCommand, QoveryCommand};
 use crate::constants::AWS_DEFAULT_REGION;
 use crate::deployment_action::check_dns::CheckDnsForDomains;
+use crate::deployment_action::database_init_scripts::DatabaseInitScripts;
 use crate::deployment_action::deploy_helm::HelmDeployment;
+use crate::deployment_action::deploy_namespace::NamespaceDeployment;
 use crate::deployment_action::deploy_terraform::TerraformDeployment;
 use crate::deployment_action::pause_service::PauseServiceAction;
+use crate::deployment_action::utils::{check_statefulset_rollout, verify_cleanup};
 use crate::deployment_action::DeploymentAction;
 use crate::deployment_report::database::reporter::DatabaseDeploymentReporter;
 use crate::deployment_report::{execute_long_deployment, DeploymentTaskImpl};
 use crate::errors::{CommandError, EngineError};
 use crate::events::{EnvironmentStep, EventDetails, Stage};
+use crate::io_models::database::DatabaseOptions;
 use crate::kubers_utils::kube_delete_all_from_selector;
-use crate::models::database::{Container, Database, DatabaseService, DatabaseType, Managed};
+use crate::models::database::{Container, Database, DatabaseMode, DatabaseService, DatabaseType, Managed};
 use crate::models::types::{CloudProvider, ToTeraContext};
 use crate::runtime::block_on;
-use k8s_openapi::api::core::v1::PersistentVolumeClaim;
+use crate::template::generate_and_copy_all_files_into_dir;
+use k8s_openapi::api::core::v1::{PersistentVolumeClaim, Secret, Service as KubeService};
+use kube::api::{DeleteParams, Patch, PatchParams};
+use kube::Api;
 use serde::Deserialize;
+use tera::Context as TeraContext;
 
-use crate::cmd::kubectl::kubectl_get_pvc;
+use crate::cmd::kubectl::{kubectl_get_pvc, ScalingKind};
 use crate::cmd::structs::PVCItem;
 use crate::deployment_report::logger::{EnvProgressLogger, EnvSuccessLogger};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
 use std::thread;
 use std::time::{Duration, Instant};
 
 const DB_READY_STATE: &str = "available";
 const DB_STOPPED_STATE: &str = "stopped";
+// A fresh endpoint can take a few minutes to be resolvable and to accept connections
+const DB_CONNECTION_CHECK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const DB_CONNECTION_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Check that a managed database accepts connections once provisioned. Private databases are only reachable
+/// when the engine runs in the same network, so the check is opt-in
+fn managed_database_connection_check() -> bool {
+    match env::var("MANAGED_DATABASE_CONNECTION_CHECK") {
+        Ok(value) => value.eq_ignore_ascii_case("true"),
+        Err(_) => false,
+    }
+}
+
+// Only relational databases are probed, the other ones have their own way to expose themselves
+fn check_managed_database_connection(
+    db_type: service::DatabaseType,
+    hostname: &str,
+    port: u16,
+    target: &DeploymentTarget,
+) -> Result<(), TcpCheckErrors> {
+    if !matches!(db_type, service::DatabaseType::PostgreSQL | service::DatabaseType::MySQL) {
+        return Ok(());
+    }
+
+    let address = TcpCheckSource::DnsName(hostname);
+    let started_at = Instant::now();
+    loop {
+        match check_tcp_port_is_open(&address, port) {
+            Ok(()) => return Ok(()),
+            Err(err) if started_at.elapsed() >= DB_CONNECTION_CHECK_TIMEOUT || (target.should_abort)() => {
+                return Err(err)
+            }
+            Err(_) => thread::sleep(DB_CONNECTION_CHECK_INTERVAL),
+        }
+    }
+}
 
 #[derive(Deserialize, Default)]
 struct CacheCluster {
@@ -249,10 +301,50 @@ fn await_db_state(
     }
 }
 
-fn on_create_managed_impl<C: CloudProvider, T: DatabaseType<C, Managed>>(
+/// Terraform files and the external name chart are rendered into distinct directories of the workspace,
+/// so they are generated concurrently. Terraform common and resource files share theirs and stay ordered
+fn prepare_managed_database_files(
+    terraform_deploy: &TerraformDeployment,
+    chart_original_dir: &Path,
+    chart_dir: &str,
+    tera_context: &TeraContext,
+    event_details: &EventDetails,
+) -> Result<(), Box<EngineError>> {
+    let chart_copy_error = |e: CommandError| {
+        Box::new(EngineError::new_cannot_copy_files_from_one_directory_to_another(
+            event_details.clone(),
+            chart_original_dir.to_string_lossy().to_string(),
+            chart_dir.to_string(),
+            e,
+        ))
+    };
+
+    thread::scope(|s| {
+        let current_span = tracing::Span::current();
+        let chart_files = s.spawn(move || {
+            // making sure to pass the current span to the new thread not to lose any tracing info
+            let _span = current_span.enter();
+            generate_and_copy_all_files_into_dir(chart_original_dir, chart_dir, tera_context.clone())
+                .map_err(chart_copy_error)
+        });
+
+        let terraform_files = terraform_deploy.prepare_terraform_files();
+        let chart_files = chart_files.join().unwrap_or_else(|_| {
+            Err(chart_copy_error(CommandError::new_from_safe_message(
+                "Thread panicked while rendering the chart.".to_string(),
+            )))
+        });
+
+        // terraform files come first, as they are the ones the deployment cannot go without
+        terraform_files.and(chart_files)
+    })
+}
+
+fn on_create_managed_impl<C: CloudProvider, T: DatabaseType<C, Managed, DatabaseOptions = DatabaseOptions>>(
     db: &Database<C, Managed, T>,
     event_details: EventDetails,
     target: &DeploymentTarget,
+    logger: &EnvProgressLogger,
 ) -> Result<(), Box<EngineError>>
 where
     Database<C, Managed, T>: DatabaseService,
@@ -269,18 +361,52 @@ where
         event_details.clone(),
         target.is_dry_run_deploy,
     );
-    terraform_deploy.on_create(target)?;
+    let chart_original_dir = PathBuf::from(db.helm_chart_external_name_service_dir());
+    let chart_dir = format!("{}/{}", &workspace_dir, "service-chart");
+    prepare_managed_database_files(
+        &terraform_deploy,
+        &chart_original_dir,
+        &chart_dir,
+        &tera_context,
+        &event_details,
+    )?;
+
+    // Show what is going to change on the provider side, the data of the database may not survive it
+    if let Some(plan) = terraform_deploy.plan(target)? {
+        logger.info(format!("📝 Database {} infrastructure plan: {}", db.name(), plan));
+        for resource_change in &plan.resource_changes {
+            logger.info(format!("📝 {}", resource_change));
+        }
+        if plan.has_destructive_changes() {
+            logger.warning(format!(
+                "⚠️ Database {} infrastructure changes will destroy {} resource(s), its data may be lost",
+                db.name(),
+                plan.to_destroy
+            ));
+        }
+    }
+
+    // Nothing is applied, so there is nothing else to deploy
+    if target.is_dry_run_deploy {
+        return Ok(());
+    }
+    // the reviewed plan is applied as is, planning again could apply changes nobody has seen
+    terraform_deploy.apply_plan(target)?;
 
     // Our terrraform give us back a file with all the info we need to deploy the remaining stuff
     let database_config =
         get_database_terraform_config(format!("{}/database-tf-config.json", &workspace_dir,).as_str())
             .map_err(|err| EngineError::new_terraform_error(event_details.clone(), err))?;
 
+    if let Some(credentials) = generated_credentials(db, event_details.clone())? {
+        store_generated_credentials(db, target, &credentials, event_details.clone())?;
+    }
+
     // Deploy the external service name
     let values = vec![
         ChartSetValue {
             key: "target_hostname".to_string(),
-            value: database_config.target_hostname,
+            value: database_config.target_hostname.clone(),
         },
         ChartSetValue {
             key: "source_fqdn".to_string(),
@@ -318,28 +444,50 @@ where
 
     let chart = ChartInfo {
         name: format!("{}-externalname", db.fqdn_id), // here it is the fqdn id :O
-        path: format!("{}/{}", &workspace_dir, "service-chart"),
+        path: chart_dir,
         namespace: HelmChartNamespaces::Custom,
         custom_namespace: Some(target.environment.namespace().to_string()),
         values,
         ..Default::default()
     };
 
-    let helm = HelmDeployment::new(
-        event_details.clone(),
-        tera_context,
-        PathBuf::from(db.helm_chart_external_name_service_dir()),
-        None,
-        chart,
-    );
+    let helm =
+        HelmDeployment::new(event_details.clone(), tera_context, chart_original_dir, None, chart).with_prepared_chart();
 
     helm.on_create(target)?;
 
     // We don't manage START/PAUSE for managed database elsewhere than for AWS
-    if target.kubernetes.cloud_provider().kind() != Aws {
-        return Ok(());
+    if target.kubernetes.cloud_provider().kind() == Aws {
+        await_managed_database_available(db, event_details.clone(), target)?;
     }
 
+    if managed_database_connection_check() {
+        logger.info(format!("🔌 Checking database {} accepts connections", db.name()));
+        check_managed_database_connection(db.db_type(), &database_config.target_hostname, db.private_port, target)
+            .map_err(|err| {
+                EngineError::new_database_failed_to_start_after_several_retries(
+                    event_details.clone(),
+                    db.id.to_string(),
+                    db.db_type().to_string(),
+                    Some(CommandError::new_from_safe_message(format!(
+                        "Cannot connect to {}:{}: {}",
+                        database_config.target_hostname, db.private_port, err
+                    ))),
+                )
+            })?;
+    }
+
+    Ok(())
+}
+
+fn await_managed_database_available<C: CloudProvider, T: DatabaseType<C, Managed, DatabaseOptions = DatabaseOptions>>(
+    db: &Database<C, Managed, T>,
+    event_details: EventDetails,
+    target: &DeploymentTarget,
+) -> Result<(), Box<EngineError>>
+where
+    Database<C, Managed, T>: DatabaseService,
+{
     // Terraform does not ensure that the database is correctly started
     // So we must force it ourselves in case
     let credentials = {
@@ -386,16 +534,114 @@ where
     }
 }
 
+// Scripts connect to the database through its in-cluster service, whatever its mode
+fn database_init_scripts<C: CloudProvider, M: DatabaseMode, T: DatabaseType<C, M, DatabaseOptions = DatabaseOptions>>(
+    db: &Database<C, M, T>,
+    target: &DeploymentTarget,
+    event_details: EventDetails,
+) -> DatabaseInitScripts
+where
+    Database<C, M, T>: DatabaseService,
+{
+    DatabaseInitScripts::new(
+        db,
+        DatabaseService::internal_fqdn(db, &target.environment.service_namespace(db.long_id())),
+        db.private_port,
+        db.options.login.clone(),
+        db.options.password.clone(),
+        event_details,
+    )
+}
+
+// Credentials generated by terraform, read back from the file it wrote in the workspace
+fn generated_credentials<C: CloudProvider, T: DatabaseType<C, Managed, DatabaseOptions = DatabaseOptions>>(
+    db: &Database<C, Managed, T>,
+    event_details: EventDetails,
+) -> Result<Option<DatabaseCredentials>, Box<EngineError>> {
+    if !db.options.generated_credentials {
+        return Ok(None);
+    }
+
+    get_database_terraform_credentials(&format!("{}/database-credentials.json", db.workspace_directory()))
+        .map(Some)
+        .map_err(|err| Box::new(EngineError::new_terraform_error(event_details, err)))
+}
+
+fn generated_credentials_secret_name<C: CloudProvider, M: DatabaseMode, T: DatabaseType<C, M>>(
+    db: &Database<C, M, T>,
+) -> String {
+    format!("{}-credentials", db.sanitized_name())
+}
+
+// Applications of the environment can only consume the generated credentials from this secret
+fn store_generated_credentials<C: CloudProvider, T: DatabaseType<C, Managed, DatabaseOptions = DatabaseOptions>>(
+    db: &Database<C, Managed, T>,
+    target: &DeploymentTarget,
+    credentials: &DatabaseCredentials,
+    event_details: EventDetails,
+) -> Result<(), Box<EngineError>>
+where
+    Database<C, Managed, T>: DatabaseService,
+{
+    let secret_name = generated_credentials_secret_name(db);
+    let secret = serde_json::json!({
+        "apiVersion": "v1",
+        "kind": "Secret",
+        "metadata": {
+            "name": secret_name,
+            "labels": { "databaseId": db.id(), "databaseLongId": db.long_id().to_string() },
+        },
+        "stringData": {
+            "login": credentials.login,
+            "password": credentials.password,
+            "host": DatabaseService::internal_fqdn(db, &target.environment.service_namespace(db.long_id())),
+            "port": db.private_port.to_string(),
+        },
+    });
+
+    let secrets: Api<Secret> = Api::namespaced(target.kube.clone(), target.environment.namespace());
+    block_on(secrets.patch(
+        &secret_name,
+        &PatchParams::apply("qovery-engine").force(),
+        &Patch::Apply(secret),
+    ))
+    .map_err(|err| {
+        Box::new(EngineError::new_database_credentials_cannot_be_stored(
+            event_details,
+            db.name().to_string(),
+            CommandError::new_from_safe_message(err.to_string()),
+        ))
+    })?;
+
+    Ok(())
+}
+
 // For Managed database
-impl<C: CloudProvider, T: DatabaseType<C, Managed>> DeploymentAction for Database<C, Managed, T>
+impl<C: CloudProvider, T: DatabaseType<C, Managed, DatabaseOptions = DatabaseOptions>> DeploymentAction
+    for Database<C, Managed, T>
 where
     Database<C, Managed, T>: ToTeraContext,
 {
+    fn service_workspace_directory(&self) -> Option<&str> {
+        Some(self.workspace_directory())
+    }
+
     fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
         let event_details = self.get_event_details(Stage::Environment(EnvironmentStep::Deploy));
         let pre_run = |_: &EnvProgressLogger| -> Result<(), Box<EngineError>> { Ok(()) };
-        let run = |_logger: &EnvProgressLogger, _: ()| -> Result<(), Box<EngineError>> {
-            on_create_managed_impl(self, event_details.clone(), target)
+        let run = |logger: &EnvProgressLogger, _: ()| -> Result<(), Box<EngineError>> {
+            on_create_managed_impl(self, event_details.clone(), target, logger)?;
+            // the database has only been planned, there is nothing to run the scripts against
+            if target.is_dry_run_deploy {
+                return Ok(());
+            }
+
+            let mut init_scripts = database_init_scripts(self, target, event_details.clone());
+            if let Some(credentials) = generated_credentials(self, event_details.clone())? {
+                init_scripts = init_scripts.with_credentials(credentials.login, credentials.password);
+            }
+
+            init_scripts.on_create(target)
         };
         let post_run = |logger: &EnvSuccessLogger, _: ()| {
             if self.publicly_accessible {
@@ -483,10 +729,10 @@ where
         let event_details = self.get_event_details(Stage::Environment(EnvironmentStep::Delete));
         execute_long_deployment(
             DatabaseDeploymentReporter::new(self, target, Action::Delete),
-            |_logger: &EnvProgressLogger| -> Result<(), Box<EngineError>> {
+            |logger: &EnvProgressLogger| -> Result<(), Box<EngineError>> {
                 // First we must ensure the DB is created and in a ready state
                 // because if not, the deletion is going to fail (i.e: cannot snapshot paused db)
-                on_create_managed_impl(self, event_details.clone(), target)?;
+                on_create_managed_impl(self, event_details.clone(), target, logger)?;
 
                 let workspace_dir = self.workspace_directory();
 
@@ -499,6 +745,24 @@ where
                     event_details.clone(),
                     target.is_dry_run_deploy,
                 );
+
+                // Only show what would be destroyed, the database and its data must survive a dry run
+                if target.is_dry_run_deploy {
+                    terraform_deploy.prepare_terraform_files()?;
+                    if let Some(plan) = terraform_deploy.plan_destroy(target)? {
+                        logger.info(format!(
+                            "📝 Database {} deletion would destroy {} resource(s)",
+                            self.name(),
+                            plan.to_destroy
+                        ));
+                        for resource_change in &plan.resource_changes {
+                            logger.info(format!("📝 {}", resource_change));
+                        }
+                    }
+                    logger.info(format!("📝 Dry run, database {} is not deleted", self.name()));
+                    return Ok(());
+                }
+
                 terraform_deploy.on_delete(target)?;
 
                 // Delete the service attached
@@ -518,7 +782,14 @@ where
                     chart,
                 );
 
-                helm.on_delete(target)
+                helm.on_delete(target)?;
+                if self.options.generated_credentials {
+                    let secrets: Api<Secret> = Api::namespaced(target.kube.clone(), target.environment.namespace());
+                    let _ =
+                        block_on(secrets.delete(&generated_credentials_secret_name(self), &DeleteParams::default()));
+                }
+
+                database_init_scripts(self, target, event_details.clone()).on_delete(target)
             },
         )
     }
@@ -527,12 +798,12 @@ where
 // For Container database
 fn is_pvc_bound(
     target: &DeploymentTarget,
+    namespace: &str,
     event_details: EventDetails,
     db_sanitized_name: String,
 ) -> Result<(), Box<EngineError>> {
-    let kubeconfig_path = target.kubernetes.get_kubeconfig_file_path()?;
-    let namespace = target.environment.namespace();
-    let creds = target.kubernetes.cloud_provider().credentials_environment_variables();
+    let kubeconfig_path = target.kubeconfig_path()?;
+    let creds = target.kubectl_credentials();
     match kubectl_get_pvc(kubeconfig_path, namespace, creds.clone()) {
         Ok(pvcs) => match pvcs.items {
             None => Err(Box::new(EngineError::new_k8s_enable_to_get_pvc_for_database(
@@ -569,23 +840,161 @@ fn is_pvc_bound(
     }
 }
 
-impl<C: CloudProvider, T: DatabaseType<C, Container>> DeploymentAction for Database<C, Container, T>
+fn container_external_name_service<C: CloudProvider, T: DatabaseType<C, Container>>(
+    db: &Database<C, Container, T>,
+    alias: &str,
+    event_details: EventDetails,
+    target: &DeploymentTarget,
+    action: HelmAction,
+) -> Result<HelmDeployment, Box<EngineError>>
+where
+    Database<C, Container, T>: ToTeraContext,
+{
+    let values = vec![
+        ChartSetValue {
+            key: "target_hostname".to_string(),
+            value: internal_service_fqdn(&db.sanitized_name(), &target.environment.service_namespace(db.long_id())),
+        },
+        ChartSetValue {
+            key: "database_id".to_string(),
+            value: db.id().to_string(),
+        },
+        ChartSetValue {
+            key: "database_long_id".to_string(),
+            value: db.long_id().to_string(),
+        },
+        ChartSetValue {
+            key: "environment_id".to_string(),
+            value: target.environment.id.to_string(),
+        },
+        ChartSetValue {
+            key: "environment_long_id".to_string(),
+            value: target.environment.long_id.to_string(),
+        },
+        ChartSetValue {
+            key: "project_long_id".to_string(),
+            value: target.environment.project_long_id.to_string(),
+        },
+        ChartSetValue {
+            key: "service_name".to_string(),
+            value: alias.to_string(),
+        },
+        // the alias is only meant to be resolved from within the cluster
+        ChartSetValue {
+            key: "publicly_accessible".to_string(),
+            value: "false".to_string(),
+        },
+    ];
+
+    let chart = ChartInfo {
+        name: format!("{}-externalname", db.fqdn_id),
+        path: format!("{}/{}", db.workspace_directory(), "service-chart"),
+        namespace: HelmChartNamespaces::Custom,
+        custom_namespace: Some(target.environment.namespace().to_string()),
+        action,
+        values,
+        ..Default::default()
+    };
+
+    Ok(HelmDeployment::new(
+        event_details,
+        db.to_tera_context(target)?,
+        PathBuf::from(db.helm_chart_external_name_service_dir()),
+        None,
+        chart,
+    ))
+}
+
+// A database deployed outside of the environment namespace owns its namespace, which expires with the environment.
+// The namespace is labeled with the database id, so a namespace the database did not create is never deleted
+fn dedicated_namespace<C: CloudProvider, T: DatabaseType<C, Container>>(
+    db: &Database<C, Container, T>,
+    target: &DeploymentTarget,
+    event_details: EventDetails,
+) -> Option<NamespaceDeployment> {
+    Some(
+        NamespaceDeployment::new(
+            target,
+            Some(db.namespace_override.clone()?),
+            BTreeMap::from([("envId".to_string(), target.environment.id.to_string())]),
+            event_details,
+        )
+        .with_owner_label("databaseId", db.id()),
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExposureTransition {
+    PublicToPrivate,
+    PrivateToPublic,
+}
+
+fn is_load_balancer(service: &KubeService) -> bool {
+    service
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.type_.as_deref())
+        .map_or(false, |type_| type_ == "LoadBalancer")
+}
+
+/// Exposure change between the deployed service and the desired one, None on first deployment or without change
+fn exposure_transition(
+    deployed_service: Option<&KubeService>,
+    publicly_accessible: bool,
+) -> Option<ExposureTransition> {
+    match (is_load_balancer(deployed_service?), publicly_accessible) {
+        (true, false) => Some(ExposureTransition::PublicToPrivate),
+        (false, true) => Some(ExposureTransition::PrivateToPublic),
+        _ => None,
+    }
+}
+
+impl<C: CloudProvider, T: DatabaseType<C, Container, DatabaseOptions = DatabaseOptions>> DeploymentAction
+    for Database<C, Container, T>
 where
     Database<C, Container, T>: ToTeraContext,
 {
+    fn helm_release(&self) -> Option<String> {
+        Some(self.helm_release_name())
+    }
+
+    fn service_workspace_directory(&self) -> Option<&str> {
+        Some(self.workspace_directory())
+    }
+
     fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
         let event_details = self.get_event_details(Stage::Environment(EnvironmentStep::Deploy));
+        let namespace = target.environment.service_namespace(self.long_id());
         let pre_run = |_: &EnvProgressLogger| -> Result<(), Box<EngineError>> { Ok(()) };
-        let run = |_logger: &EnvProgressLogger, _: ()| -> Result<(), Box<EngineError>> {
+        let run = |logger: &EnvProgressLogger, _: ()| -> Result<(), Box<EngineError>> {
+            if let Some(dedicated_namespace) = dedicated_namespace(self, target, event_details.clone()) {
+                dedicated_namespace.on_create(target)?;
+            }
+
+            // The chart switches the service type, but the records published for the load balancer are not ours
+            let services: Api<KubeService> = Api::namespaced(target.kube.clone(), &namespace);
+            let deployed_service = block_on(services.get_opt(&self.fqdn_id)).ok().flatten();
+            let exposure_transition = exposure_transition(deployed_service.as_ref(), self.publicly_accessible);
+            if exposure_transition == Some(ExposureTransition::PublicToPrivate) {
+                logger.warning(format!(
+                    "⚠️ Database {} is not publicly accessible anymore, its load balancer service {} becomes internal. Review the DNS record {} which may still point to it",
+                    self.name(),
+                    self.fqdn_id,
+                    self.fqdn
+                ));
+            }
+
             let chart = ChartInfo {
                 name: self.helm_release_name(),
                 path: self.workspace_directory().to_string(),
                 namespace: HelmChartNamespaces::Custom,
-                custom_namespace: Some(target.environment.namespace().to_string()),
+                custom_namespace: Some(namespace.clone()),
+                timeout_in_seconds: self.helm_timeout().as_secs() as i64,
+                values_files: self.helm_value_files(self.workspace_directory()),
                 k8s_selector: Some(self.selector()),
-                values_files: vec![format!("{}/qovery-values.yaml", self.workspace_directory())],
                 ..Default::default()
             };
+            // qovery values are rendered from helm_chart_values_dir and override the chart defaults
             let helm = HelmDeployment::new(
                 event_details.clone(),
                 self.to_tera_context(target)?,
@@ -595,21 +1004,68 @@ where
             );
 
             if let Err(e) = helm.on_create(target) {
-                return match is_pvc_bound(target, event_details.clone(), self.as_service().sanitized_name()) {
-                    Ok(_) => Err(e),
-                    Err(err) => Err(err),
-                };
+                is_pvc_bound(target, &namespace, event_details.clone(), self.as_service().sanitized_name())?;
+                // replicas of a database cluster are rolled one by one, report the one blocking the rollout
+                check_statefulset_rollout(target, &namespace, &self.selector(), event_details.clone())?;
+                return Err(e);
             };
+            check_statefulset_rollout(target, &namespace, &self.selector(), event_details.clone())?;
 
-            delete_pending_service(
-                target.kubernetes.get_kubeconfig_file_path()?.as_str(),
-                target.environment.namespace(),
+            if exposure_transition == Some(ExposureTransition::PrivateToPublic) {
+                let exposed_service = block_on(services.get_opt(&self.fqdn_id)).ok().flatten();
+                if exposed_service.as_ref().map_or(false, is_load_balancer) {
+                    logger.info(format!(
+                        "🌐 Database {} is now publicly accessible through {}",
+                        self.name(),
+                        self.fqdn
+                    ));
+                } else {
+                    logger.warning(format!(
+                        "⚠️ Database {} should be publicly accessible, but its service {} is not exposed by a load balancer",
+                        self.name(),
+                        self.fqdn_id
+                    ));
+                }
+            }
+
+            let unschedulable_messages = delete_pending_service(
+                target.kubeconfig_path()?,
+                &namespace,
                 self.selector().as_str(),
-                target.kubernetes.cloud_provider().credentials_environment_variables(),
+                target.kubectl_credentials(),
                 event_details.clone(),
             )?;
+            for message in unschedulable_messages {
+                logger.warning(format!("⚠️ {}", message));
+            }
 
-            Ok(())
+            if let Some(alias) = &self.options.external_name_alias {
+                let service_name = self.sanitized_name();
+                match block_on(kube_does_service_exists(&target.kube, &service_name, &namespace)) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        return Err(Box::new(EngineError::new_database_external_name_target_not_found(
+                            event_details.clone(),
+                            self.name().to_string(),
+                            service_name,
+                            None,
+                        )))
+                    }
+                    Err(err) => {
+                        return Err(Box::new(EngineError::new_database_external_name_target_not_found(
+                            event_details.clone(),
+                            self.name().to_string(),
+                            service_name,
+                            Some(CommandError::new_from_safe_message(err.to_string())),
+                        )))
+                    }
+                }
+
+                container_external_name_service(self, alias, event_details.clone(), target, HelmAction::Deploy)?
+                    .on_create(target)?;
+            }
+
+            database_init_scripts(self, target, event_details.clone()).on_create(target)
         };
 
         let post_run = |logger: &EnvSuccessLogger, _: ()| {
@@ -641,25 +1097,56 @@ where
             |_logger: &EnvProgressLogger| -> Result<(), Box<EngineError>> {
                 let pause_service = PauseServiceAction::new(
                     self.selector(),
-                    true,
+                    self.scaling_kind() == ScalingKind::Statefulset,
                     Duration::from_secs(5 * 60),
                     self.get_event_details(Stage::Environment(EnvironmentStep::Pause)),
-                );
-                pause_service.on_pause(target)
+                )
+                .with_namespace(target.environment.service_namespace(self.long_id()))
+                .with_workload_required(true);
+                pause_service.on_pause(target)?;
+                pause_service.on_pause_check(target)
+            },
+        )
+    }
+
+    fn on_restart(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        execute_long_deployment(
+            DatabaseDeploymentReporter::new(self, target, Action::Restart),
+            |_logger: &EnvProgressLogger| -> Result<(), Box<EngineError>> {
+                PauseServiceAction::new(
+                    self.selector(),
+                    self.scaling_kind() == ScalingKind::Statefulset,
+                    Duration::from_secs(5 * 60),
+                    self.get_event_details(Stage::Environment(EnvironmentStep::Restart)),
+                )
+                .with_namespace(target.environment.service_namespace(self.long_id()))
+                .on_restart(target)
             },
         )
     }
 
     fn on_delete(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
         let event_details = self.get_event_details(Stage::Environment(EnvironmentStep::Delete));
+        let namespace = target.environment.service_namespace(self.long_id());
         execute_long_deployment(
             DatabaseDeploymentReporter::new(self, target, Action::Delete),
             |logger: &EnvProgressLogger| {
+                if target.is_dry_run_deploy {
+                    logger.info(format!(
+                        "📝 Dry run, database {} is not deleted. Helm release {} and the volumes selected by {} in namespace {} would be removed",
+                        self.name(),
+                        self.helm_release_name(),
+                        self.selector(),
+                        namespace
+                    ));
+                    return Ok(());
+                }
+
                 let chart = ChartInfo {
                     name: self.helm_release_name(),
                     action: HelmAction::Destroy,
                     namespace: HelmChartNamespaces::Custom,
-                    custom_namespace: Some(target.environment.namespace().to_string()),
+                    custom_namespace: Some(namespace.clone()),
                     k8s_selector: Some(self.selector()),
                     ..Default::default()
                 };
@@ -673,12 +1160,18 @@ where
 
                 helm.on_delete(target)?;
 
+                if let Some(alias) = &self.options.external_name_alias {
+                    container_external_name_service(self, alias, event_details.clone(), target, HelmAction::Destroy)?
+                        .on_delete(target)?;
+                }
+
                 // TODO: Remove once we migrate to kube 1.23, it will done automatically
                 logger.info("🪓 Terminating network volume of the database".to_string());
+                let legacy_selector = format!("app={}", self.sanitized_name()); //FIXME: legacy labels ;(
                 if let Err(err) = block_on(kube_delete_all_from_selector::<PersistentVolumeClaim>(
                     &target.kube,
-                    &format!("app={}", self.sanitized_name()), //FIXME: legacy labels ;(
-                    target.environment.namespace(),
+                    &legacy_selector,
+                    &namespace,
                 )) {
                     return Err(Box::new(EngineError::new_k8s_cannot_delete_pvcs(
                         event_details.clone(),
@@ -687,8 +1180,46 @@ where
                     )));
                 }
 
+                database_init_scripts(self, target, event_details.clone()).on_delete(target)?;
+
+                // Volumes are never retained on delete, they must be gone as well
+                verify_cleanup(target, &namespace, &[&self.selector(), &legacy_selector], event_details.clone())?;
+
+                if let Some(dedicated_namespace) = dedicated_namespace(self, target, event_details.clone()) {
+                    dedicated_namespace.on_delete(target)?;
+                }
+
                 Ok(())
             },
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::deployment_action::deploy_database::{exposure_transition, ExposureTransition};
+    use k8s_openapi::api::core::v1::Service;
+
+    #[test]
+    fn test_exposure_transition() {
+        let service = |type_: &str| -> Service {
+            serde_json::from_value(serde_json::json!({
+                "metadata": { "name": "zabcd1234" },
+                "spec": { "type": type_ }
+            }))
+            .unwrap()
+        };
+
+        assert_eq!(exposure_transition(None, true), None);
+        assert_eq!(exposure_transition(Some(&service("LoadBalancer")), true), None);
+        assert_eq!(exposure_transition(Some(&service("ClusterIP")), false), None);
+        assert_eq!(
+            exposure_transition(Some(&service("LoadBalancer")), false),
+            Some(ExposureTransition::PublicToPrivate)
+        );
+        assert_eq!(
+            exposure_transition(Some(&service("ClusterIP")), true),
+            Some(ExposureTransition::PrivateToPublic)
+        );
+    }
+}
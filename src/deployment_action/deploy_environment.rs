@@ -4,16 +4,49 @@ use crate::cloud_provider::service::Action;
 use crate::cloud_provider::DeploymentTarget;
 use crate::cmd::kubectl::kubectl_exec_is_namespace_present;
 use crate::deployment_action::deploy_namespace::NamespaceDeployment;
+use crate::deployment_action::deploy_terraform::TerraformDeployment;
+use crate::deployment_action::summary::DeploymentSummary;
 use crate::deployment_action::DeploymentAction;
 use crate::engine::InfrastructureContext;
 use crate::errors::EngineError;
-use crate::events::{EnvironmentStep, EventDetails};
-use std::collections::HashSet;
-use std::time::Duration;
+use crate::events::{EngineEvent, EnvironmentStep, EventDetails, EventMessage};
+use crate::fs::{cleanup_service_workspace_directory, default_workspace_cleanup_after_deploy};
+use crate::io_models::router::CustomDomain;
+use crate::utilities::to_short_id;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Instant;
 use uuid::Uuid;
 
+/// Returns the first custom domain claimed by two different routers, with the names of both routers.
+/// Domains are compared case insensitively and without their trailing dot
+fn find_duplicate_custom_domain<'a>(
+    routers: impl Iterator<Item = (Uuid, &'a str, &'a [CustomDomain])>,
+) -> Option<(String, String, String)> {
+    let mut claimed_domains: HashMap<String, (Uuid, &str)> = HashMap::new();
+    for (router_id, router_name, custom_domains) in routers {
+        for custom_domain in custom_domains {
+            let domain = custom_domain.domain.trim_end_matches('.').to_lowercase();
+            match claimed_domains.get(&domain) {
+                Some((claimed_by_id, claimed_by_name)) if *claimed_by_id != router_id => {
+                    return Some((
+                        custom_domain.domain.clone(),
+                        claimed_by_name.to_string(),
+                        router_name.to_string(),
+                    ));
+                }
+                _ => {
+                    claimed_domains.insert(domain, (router_id, router_name));
+                }
+            }
+        }
+    }
+
+    None
+}
+
 pub struct EnvironmentDeployment<'a> {
     pub deployed_services: HashSet<Uuid>,
+    pub summaries: Vec<DeploymentSummary>,
     deployment_target: DeploymentTarget<'a>,
 }
 
@@ -26,46 +59,131 @@ impl<'a> EnvironmentDeployment<'a> {
         let deployment_target = DeploymentTarget::new(infra_ctx, environment, should_abort)?;
         Ok(EnvironmentDeployment {
             deployed_services: HashSet::with_capacity(Self::services_iter(environment).count()),
+            summaries: Vec::with_capacity(Self::services_iter(environment).count()),
             deployment_target,
         })
     }
 
     fn services_iter(
         environment: &Environment,
-    ) -> impl DoubleEndedIterator<Item = (Uuid, &dyn DeploymentAction, Action)> {
+    ) -> impl DoubleEndedIterator<Item = (Uuid, &dyn DeploymentAction, Action, Option<String>)> {
         std::iter::empty()
             .chain(
                 environment
                     .databases
                     .iter()
-                    .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action())),
+                    .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action(), s.selector())),
             )
             .chain(
                 environment
                     .jobs
                     .iter()
-                    .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action())),
+                    .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action(), s.selector())),
             )
             .chain(
                 environment
                     .containers
                     .iter()
-                    .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action())),
+                    .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action(), s.selector())),
             )
             .chain(
                 environment
                     .applications
                     .iter()
-                    .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action())),
+                    .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action(), s.selector())),
             )
             .chain(
                 environment
                     .routers
                     .iter()
-                    .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action())),
+                    .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action(), s.selector())),
             )
     }
 
+    // Services grouped by kind, in deployment order. A tier only depends on the previous ones
+    fn services_tiers(environment: &Environment) -> Vec<Vec<(Uuid, &dyn DeploymentAction, Action)>> {
+        vec![
+            environment
+                .databases
+                .iter()
+                .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action()))
+                .collect(),
+            environment
+                .jobs
+                .iter()
+                .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action()))
+                .collect(),
+            environment
+                .containers
+                .iter()
+                .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action()))
+                .chain(
+                    environment
+                        .applications
+                        .iter()
+                        .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action())),
+                )
+                .collect(),
+            environment
+                .routers
+                .iter()
+                .map(|s| (*s.long_id(), s.as_deployment_action(), *s.action()))
+                .collect(),
+        ]
+    }
+
+    // Several routers claiming the same custom domain lead to nondeterministic ingress routing
+    fn check_duplicate_custom_domains(
+        environment: &Environment,
+        event_details: &EventDetails,
+    ) -> Result<(), Box<EngineError>> {
+        let routers = environment
+            .routers
+            .iter()
+            .filter(|r| *r.action() == Action::Create)
+            .map(|router| (*router.long_id(), router.name(), router.custom_domains()));
+
+        match find_duplicate_custom_domain(routers) {
+            Some((domain, first_router_name, second_router_name)) => {
+                Err(Box::new(EngineError::new_duplicate_custom_domain(
+                    event_details.clone(),
+                    domain,
+                    first_router_name,
+                    second_router_name,
+                )))
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn summary(
+        target: &DeploymentTarget,
+        service_id: Uuid,
+        service: &dyn DeploymentAction,
+        action: Action,
+        selector: Option<String>,
+        result: &Result<(), Box<EngineError>>,
+        started_at: Instant,
+    ) -> DeploymentSummary {
+        let summary = DeploymentSummary::new(service_id, action, result, started_at.elapsed());
+        match action {
+            Action::Create | Action::Restart => {
+                // databases and applications may live in their own namespace
+                let namespace = target.environment.service_namespace(&service_id);
+                summary.with_runtime_status(&target.kube, &namespace, service.helm_release(), selector)
+            }
+            Action::Pause | Action::Delete => summary,
+        }
+    }
+
+    fn cleanup_service_workspace(service: &dyn DeploymentAction) {
+        if let Some(workspace_dir) = service.service_workspace_directory() {
+            if let Err(err) = cleanup_service_workspace_directory(workspace_dir) {
+                warn!("Cannot remove service workspace directory {}: {}", workspace_dir, err);
+            }
+        }
+    }
+
     fn should_abort_wrapper<'b>(
         target: &'b DeploymentTarget,
         event_details: &'b EventDetails,
@@ -85,26 +203,58 @@ impl<'a> EnvironmentDeployment<'a> {
             .deployment_target
             .environment
             .event_details_with_step(EnvironmentStep::Deploy);
-        let resource_expiration = target
-            .kubernetes
-            .context()
-            .resource_expiration_in_seconds()
-            .map(|ttl| Duration::from_secs(ttl as u64));
         let should_abort = Self::should_abort_wrapper(target, &event_details);
+        Self::check_duplicate_custom_domains(target.environment, &event_details)?;
 
         // deploy namespace first
         should_abort()?;
-        let ns = NamespaceDeployment {
-            resource_expiration,
-            event_details: event_details.clone(),
-        };
+        let ns = NamespaceDeployment::new(target, None, BTreeMap::new(), event_details.clone());
         ns.exec_action(target, target.environment.action)?;
 
+        let cleanup_workspaces = default_workspace_cleanup_after_deploy();
+        let max_attempts = target.kubernetes.context().deployment_max_attempts();
+        let retry_backoff = target.kubernetes.context().deployment_retry_backoff();
         let services = Self::services_iter(target.environment);
-        for (service_id, service, service_action) in services {
+        for (service_id, service, service_action, selector) in services {
             should_abort()?;
             self.deployed_services.insert(service_id);
-            service.exec_action(target, service_action)?;
+            let started_at = Instant::now();
+            let ret = service.exec_action_with_retry(target, service_action, max_attempts, retry_backoff);
+            self.summaries.push(Self::summary(
+                target,
+                service_id,
+                service,
+                service_action,
+                selector,
+                &ret,
+                started_at,
+            ));
+            ret?;
+
+            // only once deployed, the rendered files of a failed deployment help debugging it
+            if cleanup_workspaces {
+                Self::cleanup_service_workspace(service);
+            }
+        }
+
+        // services removed from the environment may have left their terraform state behind.
+        // Only on explicit request, as the environment may not be deployed with all its services
+        if target.kubernetes.context().cleanup_orphaned_tfstate_secrets() && !target.is_dry_run_deploy {
+            let live_service_ids: HashSet<String> = Self::services_iter(target.environment)
+                .map(|(service_id, _, _, _)| to_short_id(&service_id))
+                .collect();
+            let deleted_secrets = TerraformDeployment::cleanup_orphaned_tfstate_secrets(
+                target,
+                target.environment.namespace(),
+                &live_service_ids,
+            )?;
+            if !deleted_secrets.is_empty() {
+                info!(
+                    "orphaned tfstates {} of environment {} deleted",
+                    deleted_secrets.join(", "),
+                    target.environment.namespace()
+                );
+            }
         }
 
         // clean up nlb
@@ -123,25 +273,58 @@ impl<'a> EnvironmentDeployment<'a> {
 
         // reverse order of the deployment
         let services = Self::services_iter(target.environment).rev();
-        for (service_id, service, _) in services {
+        for (service_id, service, _, selector) in services {
             should_abort()?;
             self.deployed_services.insert(service_id);
-            service.on_pause(target)?;
+            let started_at = Instant::now();
+            let ret = service.on_pause(target);
+            self.summaries.push(Self::summary(
+                target,
+                service_id,
+                service,
+                Action::Pause,
+                selector,
+                &ret,
+                started_at,
+            ));
+            ret?;
         }
 
-        let ns = NamespaceDeployment {
-            resource_expiration: target
-                .kubernetes
-                .context()
-                .resource_expiration_in_seconds()
-                .map(|ttl| Duration::from_secs(ttl as u64)),
-            event_details: event_details.clone(),
-        };
+        let ns = NamespaceDeployment::new(target, None, BTreeMap::new(), event_details.clone());
         ns.on_pause(target)?;
 
         Ok(())
     }
 
+    pub fn on_restart(&mut self) -> Result<(), Box<EngineError>> {
+        let event_details = self
+            .deployment_target
+            .environment
+            .event_details_with_step(EnvironmentStep::Restart);
+        let target = &self.deployment_target;
+        let should_abort = Self::should_abort_wrapper(target, &event_details);
+
+        // same order as the deployment, dependencies are up again before their dependents restart
+        for (service_id, service, _, selector) in Self::services_iter(target.environment) {
+            should_abort()?;
+            self.deployed_services.insert(service_id);
+            let started_at = Instant::now();
+            let ret = service.on_restart(target);
+            self.summaries.push(Self::summary(
+                target,
+                service_id,
+                service,
+                Action::Restart,
+                selector,
+                &ret,
+                started_at,
+            ));
+            ret?;
+        }
+
+        Ok(())
+    }
+
     pub fn on_delete(&mut self) -> Result<(), Box<EngineError>> {
         let target = &self.deployment_target;
         let environment = &target.environment;
@@ -153,36 +336,126 @@ impl<'a> EnvironmentDeployment<'a> {
         // check if environment is not already deleted
         // speed up delete env because of terraform requiring apply + destroy
         if !kubectl_exec_is_namespace_present(
-            target.kubernetes.get_kubeconfig_file_path()?,
+            target.kubeconfig_path()?,
             environment.namespace(),
-            target.kubernetes.cloud_provider().credentials_environment_variables(),
+            target.kubectl_credentials(),
         ) {
             info!("no need to delete environment {}, already absent", environment.namespace());
-            Self::services_iter(target.environment).for_each(|(id, _, _)| {
+            Self::services_iter(target.environment).for_each(|(id, _, _, _)| {
                 self.deployed_services.insert(id);
             });
             return Ok(());
         };
 
-        // reverse order of the deployment
+        // reverse order of the deployment, tier by tier, so dependents are always deleted before their dependencies
+        // A failure does not prevent the deletion of the other services of the same tier
         let should_abort = Self::should_abort_wrapper(target, &event_details);
-        let services = Self::services_iter(target.environment).rev();
-        for (service_id, service, _) in services {
-            should_abort()?;
-            self.deployed_services.insert(service_id);
-            service.on_delete(target)?;
+        for services in Self::services_tiers(target.environment).into_iter().rev() {
+            let mut first_error: Option<Box<EngineError>> = None;
+            for (service_id, service, _) in services.into_iter().rev() {
+                should_abort()?;
+                self.deployed_services.insert(service_id);
+                let started_at = Instant::now();
+                let ret = service.on_delete(target);
+                self.summaries.push(Self::summary(
+                    target,
+                    service_id,
+                    service,
+                    Action::Delete,
+                    None,
+                    &ret,
+                    started_at,
+                ));
+                // reported to the user, a failure must not hide which services were deleted
+                match ret {
+                    Ok(()) => target.kubernetes.logger().log(EngineEvent::Info(
+                        event_details.clone(),
+                        EventMessage::new_from_safe(format!("Service {} deleted", service_id)),
+                    )),
+                    Err(err) => {
+                        target.kubernetes.logger().log(EngineEvent::Warning(
+                            event_details.clone(),
+                            EventMessage::new_from_safe(format!(
+                                "Service {} cannot be deleted: {}",
+                                service_id,
+                                err.user_log_message()
+                            )),
+                        ));
+                        first_error.get_or_insert(err);
+                    }
+                }
+            }
+
+            if let Some(err) = first_error {
+                return Err(err);
+            }
         }
 
-        let ns = NamespaceDeployment {
-            resource_expiration: target
-                .kubernetes
-                .context()
-                .resource_expiration_in_seconds()
-                .map(|ttl| Duration::from_secs(ttl as u64)),
-            event_details: event_details.clone(),
-        };
-        ns.on_delete(target)?;
+        // a dry run only renders the services, the namespace and what it still holds must stay
+        if !target.is_dry_run_deploy {
+            let ns = NamespaceDeployment::new(target, None, BTreeMap::new(), event_details.clone());
+            ns.on_delete(target)?;
+        }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::deployment_action::deploy_environment::find_duplicate_custom_domain;
+    use crate::io_models::router::CustomDomain;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_find_duplicate_custom_domain() {
+        let custom_domains = |domains: &[&str]| -> Vec<CustomDomain> {
+            domains
+                .iter()
+                .map(|domain| CustomDomain {
+                    domain: domain.to_string(),
+                    target_domain: "router.qovery.io".to_string(),
+                })
+                .collect()
+        };
+        let (front_id, api_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+        // no duplicates
+        let front_domains = custom_domains(&["www.example.com", "example.com"]);
+        let api_domains = custom_domains(&["api.example.com"]);
+        let routers = vec![
+            (front_id, "front", front_domains.as_slice()),
+            (api_id, "api", api_domains.as_slice()),
+        ];
+        assert_eq!(find_duplicate_custom_domain(routers.into_iter()), None);
+
+        // same domain on two routers
+        let front_domains = custom_domains(&["www.example.com"]);
+        let api_domains = custom_domains(&["api.example.com", "www.example.com"]);
+        let routers = vec![
+            (front_id, "front", front_domains.as_slice()),
+            (api_id, "api", api_domains.as_slice()),
+        ];
+        assert_eq!(
+            find_duplicate_custom_domain(routers.into_iter()),
+            Some(("www.example.com".to_string(), "front".to_string(), "api".to_string()))
+        );
+
+        // case and trailing dot variants of the same domain
+        let front_domains = custom_domains(&["www.example.com"]);
+        let api_domains = custom_domains(&["WWW.Example.com."]);
+        let routers = vec![
+            (front_id, "front", front_domains.as_slice()),
+            (api_id, "api", api_domains.as_slice()),
+        ];
+        assert_eq!(
+            find_duplicate_custom_domain(routers.into_iter()),
+            Some(("WWW.Example.com.".to_string(), "front".to_string(), "api".to_string()))
+        );
+
+        // a router listing the same domain twice does not conflict with itself
+        let front_domains = custom_domains(&["www.example.com", "www.example.com."]);
+        let routers = vec![(front_id, "front", front_domains.as_slice())];
+        assert_eq!(find_duplicate_custom_domain(routers.into_iter()), None);
+    }
+}
@@ -1,15 +1,28 @@
-use crate::cloud_provider::helm::ChartInfo;
+use crate::cloud_provider::helm::{ChartInfo, HelmValuesLayer};
+use crate::cloud_provider::kubernetes::KubernetesFeature;
 use crate::cloud_provider::DeploymentTarget;
 use crate::cmd::command::CommandKiller;
+use crate::cmd::helm::{chart_name, HelmCommand, HelmError};
+use crate::cmd::kubectl::{
+    default_kubectl_server_side_apply, kubectl_exec_get_json_events, kubectl_exec_get_node, kubectl_exec_get_pods,
+    kubectl_get_pvc,
+};
+use crate::cmd::structs::{KubernetesEvent, KubernetesList, KubernetesNode, KubernetesPod, PVCItem};
+use crate::deployment_action::release_ownership::{mark_release_managed, release_ownership, ReleaseOwnership};
+use crate::deployment_action::release_tag::tag_release;
 use crate::deployment_action::DeploymentAction;
-use crate::errors::EngineError;
+use crate::errors::{CommandError, EngineError};
 use crate::events::EventDetails;
 use crate::runtime::block_on;
 use crate::template::generate_and_copy_all_files_into_dir;
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
 use k8s_openapi::api::core::v1::Pod;
 use kube::api::ListParams;
 use kube::Api;
+use std::cell::{Cell, RefCell};
 use std::env;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tera::Context as TeraContext;
@@ -24,6 +37,66 @@ pub fn default_helm_timeout() -> Duration {
         Err(_) => Duration::from_secs(10 * 60),
     }
 }
+
+// Helm stores the whole release (chart + values) into a k8s secret which is limited to 1MB
+// We keep some room for the chart itself and the secret metadata
+const HELM_VALUES_DEFAULT_MAX_SIZE_IN_BYTES: u64 = 900 * 1024;
+// Safeguard against looping forever when adopting existing resources
+const HELM_MAX_ADOPTED_RESOURCES: usize = 20;
+// Volume provisioners retry by themselves, so leave them some time before looking for binding failures
+const PVC_BINDING_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+// Nodes are flagged NotReady after ~40s without heartbeat, no need to look for them more often
+const NODE_READINESS_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+// Gates are removed by external controllers, which usually take a while to approve a pod
+const SCHEDULING_GATE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+// Without --wait, helm only has to apply the manifests
+const HELM_OPERATION_DEFAULT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const WORKLOAD_READINESS_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+// Registries usually lift their rate limit within a minute, attempts are spaced by 15s then 30s
+const HELM_UPGRADE_MAX_ATTEMPTS: u32 = 3;
+const HELM_UPGRADE_RETRY_BACKOFF: Duration = Duration::from_secs(15);
+const HELM_UPGRADE_DEFAULT_TRANSIENT_ERROR_PATTERNS: [&str; 3] =
+    ["TLS handshake timeout", "toomanyrequests", "429 Too Many Requests"];
+
+/// Timeout of the helm upgrade itself, when the engine waits for the workloads readiness instead of helm.
+/// See `HelmDeployment::with_engine_readiness_check` for how it interacts with the readiness timeout
+pub fn default_helm_operation_timeout() -> Duration {
+    match env::var("HELM_OPERATION_TIMEOUT_IN_SECS") {
+        Ok(env_var) => match env_var.parse::<u64>() {
+            Ok(timeout) => Duration::from_secs(timeout),
+            Err(_) => HELM_OPERATION_DEFAULT_TIMEOUT,
+        },
+        Err(_) => HELM_OPERATION_DEFAULT_TIMEOUT,
+    }
+}
+
+/// Patterns of helm upgrade errors worth retrying, as a comma separated list.
+/// Anything else fails the deployment on the first attempt
+pub fn default_helm_transient_error_patterns() -> Vec<String> {
+    match env::var("HELM_UPGRADE_TRANSIENT_ERROR_PATTERNS") {
+        Ok(env_var) => env_var
+            .split(',')
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(str::to_string)
+            .collect(),
+        Err(_) => HELM_UPGRADE_DEFAULT_TRANSIENT_ERROR_PATTERNS
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .collect(),
+    }
+}
+
+pub fn default_helm_values_max_size_in_bytes() -> u64 {
+    match env::var("HELM_VALUES_MAX_SIZE_IN_BYTES") {
+        Ok(env_var) => match env_var.parse::<u64>() {
+            Ok(max_size) => max_size,
+            Err(_) => HELM_VALUES_DEFAULT_MAX_SIZE_IN_BYTES,
+        },
+        Err(_) => HELM_VALUES_DEFAULT_MAX_SIZE_IN_BYTES,
+    }
+}
+
 /// Helm Deployment manages Helm + jinja support
 pub struct HelmDeployment {
     event_details: EventDetails,
@@ -34,6 +107,204 @@ pub struct HelmDeployment {
     pub render_custom_values_file: Option<PathBuf>,
     /// Path should be inside the workspace directory because it will be copied there
     pub helm_chart: ChartInfo,
+    /// Fail as soon as a volume claim of the chart cannot be bound, instead of waiting for helm timeout
+    pvc_binding_check: bool,
+    /// Fail as soon as a pod of the chart is stuck on a NotReady node, instead of waiting for helm timeout
+    node_readiness_check: bool,
+    /// How long pods of the chart can be held by scheduling gates before failing, instead of waiting for helm timeout
+    scheduling_gate_max_wait: Option<Duration>,
+    /// Wait for the workloads of the chart to be rolled out ourselves for this long, instead of relying on helm --wait
+    readiness_timeout: Option<Duration>,
+    /// Retry the helm upgrade when its error contains one of these patterns
+    transient_error_patterns: Vec<String>,
+    /// The chart has already been rendered into the workspace by the caller
+    chart_prepared: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PvcBindingFailure {
+    pvc_name: String,
+    storage_class: String,
+    requested_size: String,
+    reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NodeNotReady {
+    node_name: String,
+    pod_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SchedulingGated {
+    pod_name: String,
+    gates: Vec<String>,
+}
+
+/// Returns the deployments and statefulsets which are not rolled out yet, with their ready replicas
+fn find_not_ready_workloads(deployments: &[Deployment], statefulsets: &[StatefulSet]) -> Vec<String> {
+    let not_ready_deployments = deployments.iter().filter_map(|deployment| {
+        let replicas = deployment.spec.as_ref().and_then(|spec| spec.replicas).unwrap_or(1);
+        let status = deployment.status.clone().unwrap_or_default();
+        // same conditions as kubectl rollout status: old replicas must be gone and the new ones available
+        let rolled_out = status.observed_generation.unwrap_or(0) >= deployment.metadata.generation.unwrap_or(0)
+            && status.updated_replicas.unwrap_or(0) >= replicas
+            && status.replicas.unwrap_or(0) <= replicas
+            && status.available_replicas.unwrap_or(0) >= replicas;
+        match rolled_out {
+            true => None,
+            false => Some(format!(
+                "deployment/{} {}/{} ready",
+                deployment.metadata.name.as_deref().unwrap_or_default(),
+                status.available_replicas.unwrap_or(0),
+                replicas
+            )),
+        }
+    });
+    let not_ready_statefulsets = statefulsets.iter().filter_map(|statefulset| {
+        let replicas = statefulset.spec.as_ref().and_then(|spec| spec.replicas).unwrap_or(1);
+        let status = statefulset.status.clone().unwrap_or_default();
+        let rolled_out = status.observed_generation.unwrap_or(0) >= statefulset.metadata.generation.unwrap_or(0)
+            && status.updated_replicas.unwrap_or(0) >= replicas
+            && status.ready_replicas.unwrap_or(0) >= replicas;
+        match rolled_out {
+            true => None,
+            false => Some(format!(
+                "statefulset/{} {}/{} ready",
+                statefulset.metadata.name.as_deref().unwrap_or_default(),
+                status.ready_replicas.unwrap_or(0),
+                replicas
+            )),
+        }
+    });
+
+    not_ready_deployments.chain(not_ready_statefulsets).collect()
+}
+
+/// Returns the first of `patterns` found in the error of a helm upgrade, if it is worth retrying.
+// Only the errors carrying the helm output can be matched, the other ones are never retried
+fn transient_helm_error<'a>(err: &HelmError, patterns: &'a [String]) -> Option<&'a str> {
+    let message = match err {
+        HelmError::CmdError(_, HelmCommand::UPGRADE, cmd_err) => cmd_err.message_raw().unwrap_or_default(),
+        HelmError::Timeout(_, HelmCommand::UPGRADE, message) => message.clone(),
+        _ => return None,
+    };
+
+    patterns
+        .iter()
+        .find(|pattern| message.contains(pattern.as_str()))
+        .map(|pattern| pattern.as_str())
+}
+
+/// Returns the pods which are not scheduled yet because some of their scheduling gates have not been removed
+fn find_scheduling_gated_pods(pods: &[KubernetesPod]) -> Vec<SchedulingGated> {
+    pods.iter()
+        .filter(|pod| pod.spec.node_name.is_none() && !pod.spec.scheduling_gates.is_empty())
+        .map(|pod| SchedulingGated {
+            pod_name: pod.metadata.name.clone(),
+            gates: pod.spec.scheduling_gates.iter().map(|gate| gate.name.clone()).collect(),
+        })
+        .collect()
+}
+
+/// Returns the first pod which is scheduled on a node whose Ready condition is not True
+fn find_pod_on_not_ready_node(pods: &[KubernetesPod], nodes: &[KubernetesNode]) -> Option<NodeNotReady> {
+    let not_ready_nodes: Vec<&str> = nodes
+        .iter()
+        .filter(|node| {
+            node.status
+                .conditions
+                .iter()
+                .any(|condition| condition.condition_type == "Ready" && condition.status != "True")
+        })
+        .map(|node| node.metadata.name.as_str())
+        .collect();
+
+    pods.iter().find_map(|pod| match &pod.spec.node_name {
+        Some(node_name) if not_ready_nodes.contains(&node_name.as_str()) => Some(NodeNotReady {
+            node_name: node_name.clone(),
+            pod_name: pod.metadata.name.clone(),
+        }),
+        _ => None,
+    })
+}
+
+/// Returns the first pending volume claim matching the selector, for which kubernetes reported a binding failure
+/// since `since`. Older events may have been raised for a previous deployment of the same claim
+fn find_pvc_binding_failure(
+    pvcs: &[PVCItem],
+    events: &[KubernetesEvent],
+    selector: &str,
+    since: DateTime<Utc>,
+) -> Option<PvcBindingFailure> {
+    let labels: Vec<(&str, &str)> = selector.split(',').filter_map(|label| label.split_once('=')).collect();
+
+    pvcs.iter()
+        .filter(|pvc| pvc.status.phase == "Pending")
+        .filter(|pvc| {
+            labels
+                .iter()
+                .all(|(key, value)| pvc.metadata.labels.get(*key).map(|v| v == value).unwrap_or(false))
+        })
+        .find_map(|pvc| {
+            events
+                .iter()
+                .find(|event| {
+                    event.involved_object.kind == "PersistentVolumeClaim"
+                        && event.involved_object.name == pvc.metadata.name
+                        && (event.reason == "FailedBinding" || event.reason == "ProvisioningFailed")
+                        && event
+                            .last_timestamp
+                            .as_deref()
+                            .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+                            .map(|timestamp| timestamp.with_timezone(&Utc) >= since)
+                            .unwrap_or(false)
+                })
+                .map(|event| PvcBindingFailure {
+                    pvc_name: pvc.metadata.name.clone(),
+                    storage_class: pvc.spec.storage_class_name.clone(),
+                    requested_size: pvc.spec.resources.requests.storage.clone(),
+                    reason: format!("{}: {}", event.reason, event.message.clone().unwrap_or_default()),
+                })
+        })
+}
+
+/// What is known about the workloads of the chart while they are being deployed, to abort the deployment as soon as
+/// they will never be ready and report why
+struct ReadinessWatch {
+    started_at: DateTime<Utc>,
+    pvc_binding_failure: RefCell<Option<PvcBindingFailure>>,
+    last_pvc_binding_check: Cell<Instant>,
+    node_not_ready: RefCell<Option<NodeNotReady>>,
+    last_node_readiness_check: Cell<Instant>,
+    scheduling_gated: RefCell<Option<SchedulingGated>>,
+    last_scheduling_gate_check: Cell<Instant>,
+    scheduling_gated_since: Cell<Option<Instant>>,
+    not_ready_workloads: RefCell<Option<Vec<String>>>,
+}
+
+impl ReadinessWatch {
+    fn new() -> ReadinessWatch {
+        ReadinessWatch {
+            started_at: Utc::now(),
+            pvc_binding_failure: RefCell::new(None),
+            last_pvc_binding_check: Cell::new(Instant::now()),
+            node_not_ready: RefCell::new(None),
+            last_node_readiness_check: Cell::new(Instant::now()),
+            scheduling_gated: RefCell::new(None),
+            last_scheduling_gate_check: Cell::new(Instant::now()),
+            scheduling_gated_since: Cell::new(None),
+            not_ready_workloads: RefCell::new(None),
+        }
+    }
+
+    /// Whether a failure preventing the workloads from ever being ready has been found
+    fn has_failure(&self) -> bool {
+        self.pvc_binding_failure.borrow().is_some()
+            || self.node_not_ready.borrow().is_some()
+            || self.scheduling_gated.borrow().is_some()
+            || self.not_ready_workloads.borrow().is_some()
+    }
 }
 
 impl HelmDeployment {
@@ -50,7 +321,145 @@ impl HelmDeployment {
             chart_orginal_dir,
             render_custom_values_file,
             helm_chart,
+            pvc_binding_check: false,
+            node_readiness_check: false,
+            scheduling_gate_max_wait: None,
+            readiness_timeout: None,
+            transient_error_patterns: default_helm_transient_error_patterns(),
+            chart_prepared: false,
+        }
+    }
+
+    pub fn with_pvc_binding_check(mut self) -> HelmDeployment {
+        self.pvc_binding_check = true;
+        self
+    }
+
+    pub fn with_node_readiness_check(mut self) -> HelmDeployment {
+        self.node_readiness_check = true;
+        self
+    }
+
+    pub fn with_scheduling_gate_max_wait(mut self, max_wait: Duration) -> HelmDeployment {
+        self.scheduling_gate_max_wait = Some(max_wait);
+        self
+    }
+
+    /// Wait for the workloads readiness ourselves, to report which of them are not ready instead of an opaque
+    /// helm timeout. helm upgrade then runs without --wait (nor --atomic which implies it) and is bounded by
+    /// `default_helm_operation_timeout`, the engine waits for the rollout during what remains of `timeout`,
+    /// so an upgrade attempt never exceeds it.
+    /// As helm does not wait anymore, a failed rollout is not rolled back by helm
+    pub fn with_engine_readiness_check(mut self, timeout: Duration) -> HelmDeployment {
+        self.readiness_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_transient_error_patterns(mut self, patterns: Vec<String>) -> HelmDeployment {
+        self.transient_error_patterns = patterns;
+        self
+    }
+
+    /// Skip the chart rendering on create, for callers generating it alongside other files
+    pub fn with_prepared_chart(mut self) -> HelmDeployment {
+        self.chart_prepared = true;
+        self
+    }
+
+    /// Add a values file at the given precedence, see `HelmValuesLayer` for the full ordering
+    pub fn with_values_layer(mut self, layer: HelmValuesLayer, values_file: String) -> HelmDeployment {
+        self.helm_chart.insert_values_file(layer, values_file);
+        self
+    }
+
+    /// Write the values the chart is deployed with next to it, to debug overrides not taking effect.
+    /// This is best effort and never fails the deployment
+    pub fn export_merged_values(&self) -> Option<PathBuf> {
+        let export_path = PathBuf::from(format!("{}.merged-values.json", self.helm_chart.path.trim_end_matches('/')));
+        let merged_values = match self.helm_chart.merged_values() {
+            Ok(values) => values,
+            Err(err) => {
+                warn!("cannot merge values of chart {}: {}", self.helm_chart.name, err.message_safe());
+                return None;
+            }
+        };
+
+        match serde_json::to_string_pretty(&merged_values).map(|json| fs::write(&export_path, json)) {
+            Ok(Ok(())) => Some(export_path),
+            _ => {
+                warn!("cannot export merged values of chart {}", self.helm_chart.name);
+                None
+            }
+        }
+    }
+
+    fn pvc_binding_failure(&self, target: &DeploymentTarget, since: DateTime<Utc>) -> Option<PvcBindingFailure> {
+        let selector = self.helm_chart.k8s_selector.as_ref()?;
+        let namespace = self.helm_chart.get_namespace_string();
+        let kubeconfig_path = target.kubeconfig_path().ok()?;
+        let envs = target.kubectl_credentials();
+
+        let pvcs = kubectl_get_pvc(&kubeconfig_path, &namespace, envs.clone())
+            .ok()?
+            .items?;
+        let events = kubectl_exec_get_json_events(&kubeconfig_path, &namespace, envs)
+            .ok()?
+            .items;
+
+        find_pvc_binding_failure(&pvcs, &events, selector, since)
+    }
+
+    fn node_not_ready(&self, target: &DeploymentTarget) -> Option<NodeNotReady> {
+        let selector = self.helm_chart.k8s_selector.as_ref()?;
+        let namespace = self.helm_chart.get_namespace_string();
+        let kubeconfig_path = target.kubeconfig_path().ok()?;
+        let envs = target.kubectl_credentials();
+
+        let KubernetesList { items: pods } =
+            kubectl_exec_get_pods(&kubeconfig_path, Some(&namespace), Some(selector), envs.clone()).ok()?;
+        if pods.is_empty() {
+            return None;
         }
+        let KubernetesList { items: nodes } = kubectl_exec_get_node(&kubeconfig_path, envs).ok()?;
+
+        find_pod_on_not_ready_node(&pods, &nodes)
+    }
+
+    fn scheduling_gated_pods(&self, target: &DeploymentTarget) -> Vec<SchedulingGated> {
+        let selector = match self.helm_chart.k8s_selector.as_ref() {
+            Some(selector) => selector,
+            None => return vec![],
+        };
+        let namespace = self.helm_chart.get_namespace_string();
+        let kubeconfig_path = match target.kubeconfig_path() {
+            Ok(path) => path,
+            Err(_) => return vec![],
+        };
+        let envs = target.kubectl_credentials();
+
+        match kubectl_exec_get_pods(&kubeconfig_path, Some(&namespace), Some(selector), envs) {
+            Ok(KubernetesList { items: pods }) => find_scheduling_gated_pods(&pods),
+            Err(_) => vec![],
+        }
+    }
+
+    // None if the workloads cannot be retrieved or do not exist yet, to keep waiting for them
+    fn not_ready_workloads(&self, target: &DeploymentTarget) -> Option<Vec<String>> {
+        let selector = self.helm_chart.k8s_selector.as_ref()?;
+        let namespace = self.helm_chart.get_namespace_string();
+
+        block_on(async {
+            let params = ListParams::default().labels(selector);
+            let deployments: Api<Deployment> = Api::namespaced(target.kube.clone(), &namespace);
+            let statefulsets: Api<StatefulSet> = Api::namespaced(target.kube.clone(), &namespace);
+            let deployments = deployments.list(&params).await.ok()?.items;
+            let statefulsets = statefulsets.list(&params).await.ok()?.items;
+            if deployments.is_empty() && statefulsets.is_empty() {
+                return None;
+            }
+
+            Some(find_not_ready_workloads(&deployments, &statefulsets))
+        })
     }
 
     pub fn prepare_helm_chart(&self) -> Result<(), Box<EngineError>> {
@@ -86,20 +495,413 @@ impl HelmDeployment {
 
         Ok(())
     }
+
+    /// Estimate the size of the values helm is going to store in the release, once the chart is rendered
+    pub fn rendered_values_size_in_bytes(&self) -> u64 {
+        let file_size = |path: &Path| fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+
+        let mut size = file_size(&Path::new(&self.helm_chart.path).join("values.yaml"));
+        size += self
+            .helm_chart
+            .values_files
+            .iter()
+            .map(|f| file_size(Path::new(f)))
+            .sum::<u64>();
+        size += self
+            .helm_chart
+            .values
+            .iter()
+            .chain(self.helm_chart.values_string.iter())
+            .map(|v| (v.key.len() + v.value.len()) as u64)
+            .sum::<u64>();
+        size += self
+            .helm_chart
+            .yaml_files_content
+            .iter()
+            .map(|f| f.yaml_content.len() as u64)
+            .sum::<u64>();
+
+        size
+    }
+
+    /// Refuse to upgrade a release of the same name installed outside of the engine, unless the context allows
+    /// taking it over. The check is skipped when the ownership cannot be determined
+    fn check_release_ownership(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        let chart_name = match chart_name(Path::new(&self.helm_chart.path)) {
+            Ok(chart_name) => chart_name,
+            Err(err) => {
+                warn!("cannot read name of chart {}: {}", self.helm_chart.name, err.message_safe());
+                return Ok(());
+            }
+        };
+
+        match release_ownership(&target.kube, &self.helm_chart, &chart_name) {
+            Ok(ReleaseOwnership::Foreign(_)) if target.kubernetes.context().adopt_existing_helm_releases() => {
+                info!(
+                    "Adopting helm release {} in namespace {}",
+                    self.helm_chart.name,
+                    self.helm_chart.get_namespace_string()
+                );
+                Ok(())
+            }
+            Ok(ReleaseOwnership::Foreign(existing_chart_name)) => {
+                Err(Box::new(EngineError::new_helm_release_not_managed(
+                    self.event_details.clone(),
+                    self.helm_chart.name.clone(),
+                    self.helm_chart.get_namespace_string(),
+                    existing_chart_name,
+                )))
+            }
+            Ok(ReleaseOwnership::NotInstalled) | Ok(ReleaseOwnership::Engine) => Ok(()),
+            Err(err) => {
+                warn!("cannot check ownership of release {}: {}", self.helm_chart.name, err);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn check_values_size(&self) -> Result<(), Box<EngineError>> {
+        let max_size = default_helm_values_max_size_in_bytes();
+        let values_size = self.rendered_values_size_in_bytes();
+        if values_size > max_size {
+            return Err(Box::new(EngineError::new_helm_values_too_large(
+                self.event_details.clone(),
+                self.helm_chart.name.clone(),
+                values_size,
+                max_size,
+            )));
+        }
+
+        Ok(())
+    }
+
+    // helm waits for the resources to be ready, abort it early if a volume will never be bound,
+    // if a pod is stuck on a node which is not ready or has been held by scheduling gates for too long
+    fn should_abort(&self, target: &DeploymentTarget, watch: &ReadinessWatch) -> bool {
+        if (target.should_abort)() {
+            return true;
+        }
+
+        if self.pvc_binding_check && watch.last_pvc_binding_check.get().elapsed() >= PVC_BINDING_CHECK_INTERVAL {
+            watch.last_pvc_binding_check.set(Instant::now());
+            if let Some(failure) = self.pvc_binding_failure(target, watch.started_at) {
+                *watch.pvc_binding_failure.borrow_mut() = Some(failure);
+                return true;
+            }
+        }
+
+        if self.node_readiness_check && watch.last_node_readiness_check.get().elapsed() >= NODE_READINESS_CHECK_INTERVAL
+        {
+            watch.last_node_readiness_check.set(Instant::now());
+            if let Some(not_ready) = self.node_not_ready(target) {
+                *watch.node_not_ready.borrow_mut() = Some(not_ready);
+                return true;
+            }
+        }
+
+        if let Some(max_wait) = self.scheduling_gate_max_wait {
+            if watch.last_scheduling_gate_check.get().elapsed() >= SCHEDULING_GATE_CHECK_INTERVAL {
+                watch.last_scheduling_gate_check.set(Instant::now());
+                let gated_pods = self.scheduling_gated_pods(target);
+                if gated_pods.is_empty() {
+                    watch.scheduling_gated_since.set(None);
+                }
+                for gated in &gated_pods {
+                    info!("Pod {} awaiting scheduling gate {}", gated.pod_name, gated.gates.join(", "));
+                }
+
+                // gated pods are not scheduled by design, only give up once they have been held for too long
+                let gated_since = watch.scheduling_gated_since.get().unwrap_or_else(Instant::now);
+                if let Some(gated) = gated_pods.into_iter().next() {
+                    watch.scheduling_gated_since.set(Some(gated_since));
+                    if gated_since.elapsed() >= max_wait {
+                        *watch.scheduling_gated.borrow_mut() = Some(gated);
+                        return true;
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Readiness phase: wait for the workloads of the chart to be rolled out, when helm does not wait for them
+    fn wait_for_workloads_readiness(
+        &self,
+        target: &DeploymentTarget,
+        watch: &ReadinessWatch,
+        started_at: Instant,
+        timeout: Duration,
+    ) -> Result<(), HelmError> {
+        if self.helm_chart.k8s_selector.is_none() {
+            warn!("chart {} has no selector, cannot wait for its workloads", self.helm_chart.name);
+            return Ok(());
+        }
+
+        let mut not_ready = vec![];
+        while started_at.elapsed() < timeout {
+            if self.should_abort(target, watch) {
+                return Err(HelmError::Killed(self.helm_chart.name.clone(), HelmCommand::UPGRADE));
+            }
+
+            match self.not_ready_workloads(target) {
+                Some(workloads) if workloads.is_empty() => return Ok(()),
+                Some(workloads) => not_ready = workloads,
+                None => {}
+            }
+            std::thread::sleep(WORKLOAD_READINESS_CHECK_INTERVAL);
+        }
+
+        *watch.not_ready_workloads.borrow_mut() = Some(not_ready);
+        Err(HelmError::Timeout(
+            self.helm_chart.name.clone(),
+            HelmCommand::UPGRADE,
+            "workloads are not ready".to_string(),
+        ))
+    }
+
+    /// Retry phase: run helm upgrade, and the readiness phase if the engine waits for the workloads,
+    /// again when it fails on a transient error. Each attempt gets the whole timeout
+    fn upgrade_with_retry(
+        &self,
+        target: &DeploymentTarget,
+        helm_chart: &ChartInfo,
+        watch: &ReadinessWatch,
+    ) -> Result<(), HelmError> {
+        let should_abort = || self.should_abort(target, watch);
+        let mut upgrade_attempt = 1;
+        loop {
+            let started_at = Instant::now();
+            let ret = target
+                .cluster_executor
+                .helm_upgrade(helm_chart, &CommandKiller::from_cancelable(&should_abort));
+            let ret = match (ret, self.readiness_timeout) {
+                // nothing to wait for, a dry run does not deploy anything
+                (Ok(()), Some(timeout)) if !self.helm_chart.dry_run => {
+                    self.wait_for_workloads_readiness(target, watch, started_at, timeout)
+                }
+                (ret, _) => ret,
+            };
+
+            // A registry rate limiting the requests recovers by itself, so give the upgrade a few more chances.
+            // Workloads known to never be ready would fail the same way
+            let transient_pattern = match &ret {
+                Err(err) if !watch.has_failure() => transient_helm_error(err, &self.transient_error_patterns),
+                _ => None,
+            };
+            match transient_pattern {
+                Some(pattern) if upgrade_attempt < HELM_UPGRADE_MAX_ATTEMPTS && !should_abort() => {
+                    let backoff = HELM_UPGRADE_RETRY_BACKOFF * 2u32.pow(upgrade_attempt - 1);
+                    warn!(
+                        "helm upgrade of {} failed on a transient error ({}), retrying in {}s (attempt {}/{})",
+                        self.helm_chart.name,
+                        pattern,
+                        backoff.as_secs(),
+                        upgrade_attempt + 1,
+                        HELM_UPGRADE_MAX_ATTEMPTS
+                    );
+                    std::thread::sleep(backoff);
+                    upgrade_attempt += 1;
+                }
+                _ => return ret,
+            }
+        }
+    }
+
+    /// Adoption phase: take over a resource helm refuses to manage as it has not created it.
+    /// Resources owned by another helm release are never taken over
+    fn adopt_resource(
+        &self,
+        target: &DeploymentTarget,
+        conflict: HelmError,
+        adopted_resources: usize,
+    ) -> Result<(), Box<EngineError>> {
+        let (release, kind, name, namespace, owner) = match conflict {
+            HelmError::InvalidOwnershipMetadata(release, kind, name, namespace, owner) => {
+                (release, kind, name, namespace, owner)
+            }
+            err => return Err(Box::new(EngineError::new_helm_error(self.event_details.clone(), err))),
+        };
+
+        let resource = format!("{}/{}", kind, name);
+        if owner.is_some()
+            || !target.kubernetes.context().adopt_existing_helm_releases()
+            || self.helm_chart.dry_run
+            || adopted_resources >= HELM_MAX_ADOPTED_RESOURCES
+        {
+            return Err(Box::new(EngineError::new_helm_ownership_conflict(
+                self.event_details.clone(),
+                release,
+                resource,
+                namespace,
+                owner,
+                None,
+            )));
+        }
+
+        info!(
+            "Adopting resource {} in namespace {} into helm release {}",
+            resource, namespace, release
+        );
+        target
+            .cluster_executor
+            .kubectl_adopt_resource_in_helm_release(&namespace, &kind, &name, &self.helm_chart)
+            .map_err(|e| {
+                Box::new(EngineError::new_helm_ownership_conflict(
+                    self.event_details.clone(),
+                    release,
+                    resource,
+                    namespace.clone(),
+                    None,
+                    Some(e),
+                ))
+            })
+    }
+
+    /// Report why the workloads are not ready instead of the helm error, when it is known
+    fn upgrade_error(&self, target: &DeploymentTarget, watch: &ReadinessWatch, err: HelmError) -> Box<EngineError> {
+        if let Some(failure) = watch.pvc_binding_failure.borrow_mut().take() {
+            return Box::new(EngineError::new_pvc_binding_failed(
+                self.event_details.clone(),
+                failure.pvc_name,
+                failure.storage_class,
+                failure.requested_size,
+                CommandError::new_from_safe_message(failure.reason),
+            ));
+        }
+
+        // The node may have gone NotReady between two checks, so look one last time when helm gave up waiting
+        let stuck_on_node = match &err {
+            HelmError::Killed(..) => watch.node_not_ready.borrow_mut().take(),
+            HelmError::Timeout(..) | HelmError::Rollbacked(..) if self.node_readiness_check => {
+                self.node_not_ready(target)
+            }
+            _ => None,
+        };
+        if let Some(not_ready) = stuck_on_node {
+            return Box::new(EngineError::new_node_not_ready(
+                self.event_details.clone(),
+                not_ready.node_name,
+                not_ready.pod_name,
+            ));
+        }
+
+        // Pods still held when helm gave up waiting are not failing, report the gate blocking them instead
+        let held_by_gate = match &err {
+            HelmError::Killed(..) => watch.scheduling_gated.borrow_mut().take(),
+            HelmError::Timeout(..) | HelmError::Rollbacked(..) if self.scheduling_gate_max_wait.is_some() => {
+                self.scheduling_gated_pods(target).into_iter().next()
+            }
+            _ => None,
+        };
+        if let Some(gated) = held_by_gate {
+            return Box::new(EngineError::new_scheduling_gate_timeout(
+                self.event_details.clone(),
+                gated.pod_name,
+                gated.gates,
+                self.scheduling_gate_max_wait.unwrap_or_default(),
+            ));
+        }
+
+        if let (HelmError::Timeout(..), Some(not_ready)) = (&err, watch.not_ready_workloads.borrow_mut().take()) {
+            return Box::new(EngineError::new_workload_readiness_timeout(
+                self.event_details.clone(),
+                self.helm_chart.name.clone(),
+                not_ready,
+                self.readiness_timeout.unwrap_or_default(),
+            ));
+        }
+
+        Box::new(EngineError::new_helm_error(self.event_details.clone(), err))
+    }
+
+    fn on_upgrade_succeeded(&self, target: &DeploymentTarget) {
+        // a dry run creates no revision to tag
+        if self.helm_chart.dry_run {
+            return;
+        }
+        if let Some(tag) = &self.helm_chart.release_tag {
+            // the deployment succeeded, failing to tag it only prevents rolling back to it by name
+            if let Err(err) = tag_release(&target.kube, &self.helm_chart, tag) {
+                warn!("cannot tag release {} with {}: {}", self.helm_chart.name, tag, err);
+            }
+        }
+        if let Err(err) = mark_release_managed(&target.kube, &self.helm_chart) {
+            warn!("cannot mark release {} as managed by the engine: {}", self.helm_chart.name, err);
+        }
+    }
 }
 
 impl DeploymentAction for HelmDeployment {
     fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
-        self.prepare_helm_chart()?;
+        // manifests are applied by kubectl instead of helm, with server side apply if requested
+        if target.kubernetes.context().is_deploy_without_helm() && default_kubectl_server_side_apply() {
+            target.require(KubernetesFeature::ServerSideApply, self.event_details.clone())?;
+        }
 
-        // print diff in logs
-        let _ = target.helm.upgrade_diff(&self.helm_chart, &[]);
+        if !self.chart_prepared {
+            self.prepare_helm_chart()?;
+        }
+        self.check_values_size()?;
+        if let Some(export_path) = self.export_merged_values() {
+            debug!(
+                "merged values of chart {} exported to {}",
+                self.helm_chart.name,
+                export_path.display()
+            );
+        }
 
-        //upgrade
+        // subcharts are not shipped with the chart, fetch them before helm needs them
         target
-            .helm
-            .upgrade(&self.helm_chart, &[], &CommandKiller::from_cancelable(target.should_abort))
-            .map_err(|e| Box::new(EngineError::new_helm_error(self.event_details.clone(), e)))
+            .cluster_executor
+            .helm_dependency_build(&self.helm_chart)
+            .map_err(|e| match e {
+                HelmError::DependencyFailed(chart_name, dependency_name, raw_error) => {
+                    EngineError::new_helm_dependency_failed(
+                        self.event_details.clone(),
+                        chart_name,
+                        dependency_name,
+                        raw_error,
+                    )
+                }
+                e => EngineError::new_helm_error(self.event_details.clone(), e),
+            })?;
+
+        // releases are not stored when deploying without helm, there is nothing to collide with
+        if !target.kubernetes.context().is_deploy_without_helm() {
+            self.check_release_ownership(target)?;
+        }
+
+        // print diff in logs
+        let _ = target.cluster_executor.helm_upgrade_diff(&self.helm_chart);
+
+        // helm only applies the manifests when the engine waits for the readiness, within the same overall timeout
+        let helm_chart = match self.readiness_timeout {
+            Some(timeout) => ChartInfo {
+                wait: false,
+                atomic: false,
+                timeout_in_seconds: std::cmp::min(default_helm_operation_timeout(), timeout).as_secs() as i64,
+                ..self.helm_chart.clone()
+            },
+            None => self.helm_chart.clone(),
+        };
+
+        // Helm reports conflicting resources one by one, so adopt them until the upgrade succeeds
+        let watch = ReadinessWatch::new();
+        let mut adopted_resources = 0;
+        loop {
+            match self.upgrade_with_retry(target, &helm_chart, &watch) {
+                Ok(()) => {
+                    self.on_upgrade_succeeded(target);
+                    return Ok(());
+                }
+                Err(conflict @ HelmError::InvalidOwnershipMetadata(..)) if !watch.has_failure() => {
+                    self.adopt_resource(target, conflict, adopted_resources)?;
+                    adopted_resources += 1;
+                }
+                Err(err) => return Err(self.upgrade_error(target, &watch, err)),
+            }
+        }
     }
 
     fn on_pause(&self, _target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
@@ -108,8 +910,8 @@ impl DeploymentAction for HelmDeployment {
 
     fn on_delete(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
         target
-            .helm
-            .uninstall(&self.helm_chart, &[])
+            .cluster_executor
+            .helm_uninstall(&self.helm_chart)
             .map_err(|e| EngineError::new_helm_error(self.event_details.clone(), e))?;
 
         // helm does not wait for pod to terminate https://github.com/helm/helm/issues/10586
@@ -118,7 +920,7 @@ impl DeploymentAction for HelmDeployment {
             block_on(async {
                 let started = Instant::now();
 
-                let pods: Api<Pod> = Api::namespaced(target.kube.clone(), target.environment.namespace());
+                let pods: Api<Pod> = Api::namespaced(target.kube.clone(), &self.helm_chart.get_namespace_string());
                 while let Ok(pod) = pods.list(&ListParams::default().labels(pod_selector)).await {
                     if pod.items.is_empty() {
                         break;
@@ -137,23 +939,32 @@ impl DeploymentAction for HelmDeployment {
     }
 }
 
-#[cfg(feature = "test-local-kube")]
 #[cfg(test)]
 mod tests {
-    use crate::cloud_provider::helm::ChartInfo;
-    use crate::cmd::helm::Helm;
-    use crate::deployment_action::deploy_helm::{default_helm_timeout, HelmDeployment};
-    use crate::events::{EventDetails, InfrastructureStep, Stage, Transmitter};
-    use crate::io_models::QoveryIdentifier;
-    use function_name::named;
-
-    use std::path::PathBuf;
-    use std::time::{SystemTime, UNIX_EPOCH};
-    use uuid::Uuid;
+    use crate::cmd::helm::{HelmCommand, HelmError};
+    use crate::cmd::structs::{KubernetesEvent, KubernetesNode, KubernetesPod, PVCItem};
+    use crate::deployment_action::deploy_helm::{
+        default_helm_transient_error_patterns, find_not_ready_workloads, find_pod_on_not_ready_node,
+        find_pvc_binding_failure, find_scheduling_gated_pods, transient_helm_error, NodeNotReady, PvcBindingFailure,
+        SchedulingGated,
+    };
+    use crate::errors::CommandError;
+    use chrono::{DateTime, Utc};
+    use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
 
     #[test]
-    #[named]
+    #[cfg(feature = "test-local-kube")]
+    #[function_name::named]
     fn test_helm_deployment() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::cloud_provider::helm::ChartInfo;
+        use crate::cmd::helm::Helm;
+        use crate::deployment_action::deploy_helm::{default_helm_timeout, HelmDeployment};
+        use crate::events::{EventDetails, InfrastructureStep, Stage, Transmitter};
+        use crate::io_models::QoveryIdentifier;
+        use std::path::PathBuf;
+        use std::time::{SystemTime, UNIX_EPOCH};
+        use uuid::Uuid;
+
         let namespace = format!(
             "{}-{:?}",
             function_name!().replace('_', "-"),
@@ -204,4 +1015,270 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_find_scheduling_gated_pods() {
+        let pod = |name: &str, spec: serde_json::Value| -> KubernetesPod {
+            serde_json::from_value(serde_json::json!({
+                "metadata": { "name": name, "namespace": "default" },
+                "status": { "phase": "Pending" },
+                "spec": spec,
+            }))
+            .unwrap()
+        };
+
+        let pods = vec![
+            pod("scheduled", serde_json::json!({ "nodeName": "node-1" })),
+            pod("pending", serde_json::json!({})),
+            pod(
+                "gated",
+                serde_json::json!({ "schedulingGates": [{ "name": "acme.io/quota" }, { "name": "acme.io/approval" }] }),
+            ),
+        ];
+
+        assert_eq!(
+            find_scheduling_gated_pods(&pods),
+            vec![SchedulingGated {
+                pod_name: "gated".to_string(),
+                gates: vec!["acme.io/quota".to_string(), "acme.io/approval".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_find_pod_on_not_ready_node() {
+        let pod = |name: &str, node_name: &str| -> KubernetesPod {
+            serde_json::from_value(serde_json::json!({
+                "metadata": { "name": name, "namespace": "default" },
+                "status": { "phase": "Running" },
+                "spec": { "nodeName": node_name },
+            }))
+            .unwrap()
+        };
+        let node = |name: &str, ready: &str| -> KubernetesNode {
+            let resources = serde_json::json!({ "cpu": "2", "memory": "4Gi", "pods": "110" });
+            serde_json::from_value(serde_json::json!({
+                "metadata": { "name": name },
+                "status": {
+                    "allocatable": resources,
+                    "capacity": resources,
+                    "nodeInfo": { "kubeProxyVersion": "v1.23.0", "kubeletVersion": "v1.23.0" },
+                    "conditions": [{ "type": "Ready", "status": ready }],
+                },
+            }))
+            .unwrap()
+        };
+
+        let nodes = vec![node("node-1", "True"), node("node-2", "Unknown")];
+        assert_eq!(find_pod_on_not_ready_node(&[pod("app-1", "node-1")], &nodes), None);
+        assert_eq!(
+            find_pod_on_not_ready_node(&[pod("app-1", "node-1"), pod("app-2", "node-2")], &nodes),
+            Some(NodeNotReady {
+                node_name: "node-2".to_string(),
+                pod_name: "app-2".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_find_pvc_binding_failure() {
+        let pvc = |phase: &str, app: &str| -> PVCItem {
+            serde_json::from_value(serde_json::json!({
+                "apiVersion": "v1",
+                "kind": "PersistentVolumeClaim",
+                "metadata": { "resourceVersion": "1", "name": "data-app-0", "labels": { "app": app } },
+                "spec": {
+                    "resources": { "requests": { "storage": "10Gi" } },
+                    "storageClassName": "aws-ebs-gp2-0",
+                    "volumeMode": "Filesystem",
+                },
+                "status": { "phase": phase },
+            }))
+            .unwrap()
+        };
+        let event = |reason: &str, last_timestamp: Option<&str>| -> KubernetesEvent {
+            serde_json::from_value(serde_json::json!({
+                "type": "Warning",
+                "message": "volume quota exceeded",
+                "lastTimestamp": last_timestamp,
+                "reason": reason,
+                "involvedObject": { "kind": "PersistentVolumeClaim", "name": "data-app-0" },
+            }))
+            .unwrap()
+        };
+        let failure = |reason: &str| PvcBindingFailure {
+            pvc_name: "data-app-0".to_string(),
+            storage_class: "aws-ebs-gp2-0".to_string(),
+            requested_size: "10Gi".to_string(),
+            reason: format!("{}: volume quota exceeded", reason),
+        };
+        let deployment_start = DateTime::parse_from_rfc3339("2022-10-16T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let test_cases = vec![
+            (
+                "provisioning failure during the deployment",
+                pvc("Pending", "app"),
+                event("ProvisioningFailed", Some("2022-10-16T10:00:30Z")),
+                Some(failure("ProvisioningFailed")),
+            ),
+            (
+                "binding failure during the deployment",
+                pvc("Pending", "app"),
+                event("FailedBinding", Some("2022-10-16T10:00:30Z")),
+                Some(failure("FailedBinding")),
+            ),
+            (
+                "failure raised before the deployment",
+                pvc("Pending", "app"),
+                event("ProvisioningFailed", Some("2022-10-16T09:59:30Z")),
+                None,
+            ),
+            (
+                "failure without timestamp",
+                pvc("Pending", "app"),
+                event("ProvisioningFailed", None),
+                None,
+            ),
+            (
+                "claim already bound",
+                pvc("Bound", "app"),
+                event("ProvisioningFailed", Some("2022-10-16T10:00:30Z")),
+                None,
+            ),
+            (
+                "claim of another service",
+                pvc("Pending", "other-app"),
+                event("ProvisioningFailed", Some("2022-10-16T10:00:30Z")),
+                None,
+            ),
+            (
+                "provisioning still in progress",
+                pvc("Pending", "app"),
+                event("ExternalProvisioning", Some("2022-10-16T10:00:30Z")),
+                None,
+            ),
+        ];
+
+        for (description, pvc, event, expected) in test_cases {
+            assert_eq!(
+                find_pvc_binding_failure(&[pvc], &[event], "app=app", deployment_start),
+                expected,
+                "{}",
+                description
+            );
+        }
+    }
+
+    #[test]
+    fn test_transient_helm_error() {
+        let patterns = vec!["toomanyrequests".to_string(), "TLS handshake timeout".to_string()];
+        let cmd_error = |message: &str| {
+            HelmError::CmdError(
+                "app".to_string(),
+                HelmCommand::UPGRADE,
+                CommandError::new("Helm upgrade error".to_string(), Some(message.to_string()), None),
+            )
+        };
+
+        assert_eq!(
+            transient_helm_error(&cmd_error("toomanyrequests: You have reached your pull rate limit"), &patterns),
+            Some("toomanyrequests")
+        );
+        assert_eq!(
+            transient_helm_error(
+                &HelmError::Timeout(
+                    "app".to_string(),
+                    HelmCommand::UPGRADE,
+                    "net/http: TLS handshake timeout".to_string()
+                ),
+                &patterns
+            ),
+            Some("TLS handshake timeout")
+        );
+        assert_eq!(transient_helm_error(&cmd_error("invalid chart values"), &patterns), None);
+        assert_eq!(
+            transient_helm_error(&HelmError::Rollbacked("app".to_string(), HelmCommand::UPGRADE), &patterns),
+            None
+        );
+        assert_eq!(transient_helm_error(&cmd_error("toomanyrequests"), &[]), None);
+        // a wrong image name or missing credentials do not fix themselves
+        assert_eq!(
+            transient_helm_error(&cmd_error("ImagePullBackOff"), &default_helm_transient_error_patterns()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_find_not_ready_workloads() {
+        let deployment = |name: &str, status: serde_json::Value| -> Deployment {
+            serde_json::from_value(serde_json::json!({
+                "metadata": { "name": name, "generation": 2 },
+                "spec": { "replicas": 2, "selector": {}, "template": {} },
+                "status": status,
+            }))
+            .unwrap()
+        };
+        let statefulset = |name: &str, status: serde_json::Value| -> StatefulSet {
+            serde_json::from_value(serde_json::json!({
+                "metadata": { "name": name, "generation": 1 },
+                "spec": { "replicas": 1, "selector": {}, "serviceName": name, "template": {} },
+                "status": status,
+            }))
+            .unwrap()
+        };
+
+        let deployments = vec![
+            deployment(
+                "rolled-out",
+                serde_json::json!({
+                    "observedGeneration": 2,
+                    "replicas": 2,
+                    "updatedReplicas": 2,
+                    "availableReplicas": 2,
+                }),
+            ),
+            // the controller has not seen the new spec yet
+            deployment(
+                "not-observed",
+                serde_json::json!({
+                    "observedGeneration": 1,
+                    "replicas": 2,
+                    "updatedReplicas": 2,
+                    "availableReplicas": 2,
+                }),
+            ),
+            // an old replica is still running
+            deployment(
+                "old-replica",
+                serde_json::json!({
+                    "observedGeneration": 2,
+                    "replicas": 3,
+                    "updatedReplicas": 2,
+                    "availableReplicas": 2,
+                }),
+            ),
+        ];
+        let statefulsets = vec![
+            statefulset(
+                "ready",
+                serde_json::json!({ "observedGeneration": 1, "replicas": 1, "updatedReplicas": 1, "readyReplicas": 1 }),
+            ),
+            statefulset(
+                "starting",
+                serde_json::json!({ "observedGeneration": 1, "replicas": 1, "updatedReplicas": 1 }),
+            ),
+        ];
+
+        assert_eq!(
+            find_not_ready_workloads(&deployments, &statefulsets),
+            vec![
+                "deployment/not-observed 2/2 ready".to_string(),
+                "deployment/old-replica 2/2 ready".to_string(),
+                "statefulset/starting 0/1 ready".to_string(),
+            ]
+        );
+        assert!(find_not_ready_workloads(&deployments[..1], &statefulsets[..1]).is_empty());
+    }
 }
@@ -27,11 +27,25 @@ use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::time::Duration;
 
 impl<T: CloudProvider> DeploymentAction for Job<T>
 where
     Job<T>: ToTeraContext,
 {
+    fn helm_release(&self) -> Option<String> {
+        Some(self.helm_release_name())
+    }
+
+    // jobs with slow probes need more time to be ready
+    fn helm_timeout(&self) -> Duration {
+        self.startup_timeout()
+    }
+
+    fn service_workspace_directory(&self) -> Option<&str> {
+        Some(self.workspace_directory())
+    }
+
     fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
         let event_details = self.get_event_details(Stage::Environment(self.action().to_environment_step()));
 
@@ -174,7 +188,7 @@ where
             path: job.workspace_directory().to_string(),
             namespace: HelmChartNamespaces::Custom,
             custom_namespace: Some(target.environment.namespace().to_string()),
-            timeout_in_seconds: job.startup_timeout().as_secs() as i64,
+            timeout_in_seconds: job.helm_timeout().as_secs() as i64,
             k8s_selector: Some(job.selector()),
             ..Default::default()
         };
@@ -197,7 +211,7 @@ where
         // For cronjob we dont care as we don't control when it is executed
         if job.schedule().is_job() {
             // Get kube config file
-            let kubernetes_config_file_path = target.kubernetes.get_kubeconfig_file_path()?;
+            let kubernetes_config_file_path = target.kubeconfig_path()?;
             let job_pod_selector = format!("job-name={}", job.kube_service_name());
             let kube_pod_api: Api<Pod> = Api::namespaced(target.kube.clone(), target.environment.namespace());
 
@@ -229,7 +243,7 @@ where
                 // Get JSON output from shared volume
                 let result_json_output = kubectl_get_job_pod_output(
                     kubernetes_config_file_path.clone(),
-                    target.kubernetes.cloud_provider().credentials_environment_variables(),
+                    target.kubectl_credentials(),
                     target.environment.namespace(),
                     &pod_name,
                 );
@@ -395,7 +409,7 @@ where
             path: job.workspace_directory().to_string(),
             namespace: HelmChartNamespaces::Custom,
             custom_namespace: Some(target.environment.namespace().to_string()),
-            timeout_in_seconds: job.startup_timeout().as_secs() as i64,
+            timeout_in_seconds: job.helm_timeout().as_secs() as i64,
             k8s_selector: Some(job.selector()),
             ..Default::default()
         };
@@ -443,14 +457,14 @@ where
     (pre_run, task, post_run)
 }
 
-enum JobStatus {
+pub(super) enum JobStatus {
     NotRunning,
     Running,
     Success,
     Failure { reason: String, message: String },
 }
 
-fn job_status(job: &Option<&K8sJob>) -> JobStatus {
+pub(super) fn job_status(job: &Option<&K8sJob>) -> JobStatus {
     if let Some(pod) = job {
         if let Some(status) = &pod.status {
             if status.succeeded.is_some() {
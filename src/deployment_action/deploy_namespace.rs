@@ -9,41 +9,181 @@ use crate::runtime::block_on;
 use k8s_openapi::api::core::v1::Namespace;
 use kube::api::DeleteParams;
 use kube::Api;
+use regex::Regex;
 use std::collections::BTreeMap;
+use std::env;
+use std::thread;
 use std::time::Duration;
 
+// Admission webhooks are often unavailable only while their controller restarts
+const ADMISSION_WEBHOOK_DEFAULT_MAX_RETRIES: u32 = 3;
+
+pub fn default_admission_webhook_max_retries() -> u32 {
+    match env::var("ADMISSION_WEBHOOK_MAX_RETRIES") {
+        Ok(env_var) => env_var.parse::<u32>().unwrap_or(ADMISSION_WEBHOOK_DEFAULT_MAX_RETRIES),
+        Err(_) => ADMISSION_WEBHOOK_DEFAULT_MAX_RETRIES,
+    }
+}
+
+/// Returns the name of the admission webhook which failed to be called, if the error is due to one
+fn failing_admission_webhook(error_message: &str) -> Option<String> {
+    let webhook_re = Regex::new(r#"failed calling webhook \\?"(?P<name>[^"\\]+)\\?""#).ok()?;
+    webhook_re
+        .captures(error_message)
+        .and_then(|cap| cap.name("name"))
+        .map(|name| name.as_str().to_string())
+}
+
 pub struct NamespaceDeployment {
     pub resource_expiration: Option<Duration>,
     pub event_details: EventDetails,
+    // Namespace to manage, if none the environment namespace is used
+    pub namespace: Option<String>,
+    pub labels: BTreeMap<String, String>,
+    // Label identifying the service the namespace belongs to, see `with_owner_label`
+    owner_label: Option<(String, String)>,
+}
+
+impl NamespaceDeployment {
+    /// The namespace expires after the resources TTL of the cluster, if any.
+    /// Labels common to every namespace managed by the engine belong here
+    pub fn new(
+        target: &DeploymentTarget,
+        namespace: Option<String>,
+        labels: BTreeMap<String, String>,
+        event_details: EventDetails,
+    ) -> NamespaceDeployment {
+        NamespaceDeployment {
+            resource_expiration: target
+                .kubernetes
+                .context()
+                .resource_expiration_in_seconds()
+                .map(|ttl| Duration::from_secs(ttl as u64)),
+            event_details,
+            namespace,
+            labels,
+            owner_label: None,
+        }
+    }
+
+    /// The namespace belongs to the service carrying this label, which is set on the namespace when creating it.
+    /// An existing namespace without the label is refused, and the namespace is only deleted if it carries it
+    pub fn with_owner_label(mut self, key: &str, value: &str) -> NamespaceDeployment {
+        self.labels.insert(key.to_string(), value.to_string());
+        self.owner_label = Some((key.to_string(), value.to_string()));
+        self
+    }
+
+    // None if the namespace does not exist
+    fn is_owned(&self, target: &DeploymentTarget) -> Result<Option<bool>, kube::Error> {
+        let (key, value) = match &self.owner_label {
+            Some(owner_label) => owner_label,
+            None => return Ok(Some(true)),
+        };
+
+        let api: Api<Namespace> = Api::all(target.kube.clone());
+        let namespace = match block_on(api.get(self.namespace(target))) {
+            Ok(namespace) => namespace,
+            Err(kube::Error::Api(api_err)) if api_err.code == 404 => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        Ok(Some(
+            namespace
+                .metadata
+                .labels
+                .unwrap_or_default()
+                .get(key)
+                .map(|label_value| label_value == value)
+                .unwrap_or(false),
+        ))
+    }
+
+    fn namespace<'a>(&'a self, target: &'a DeploymentTarget) -> &'a str {
+        self.namespace
+            .as_deref()
+            .unwrap_or_else(|| target.environment.namespace())
+    }
 }
 
 impl DeploymentAction for NamespaceDeployment {
     fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
-        let mut namespace_labels: Option<BTreeMap<String, String>> = None;
+        let namespace = self.namespace(target);
+        if let Some((key, value)) = &self.owner_label {
+            match self.is_owned(target) {
+                Ok(Some(false)) => {
+                    return Err(Box::new(EngineError::new_namespace_not_owned(
+                        self.event_details.clone(),
+                        namespace.to_string(),
+                        format!("{}={}", key, value),
+                    )))
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    return Err(Box::new(EngineError::new_k8s_create_namespace(
+                        self.event_details.clone(),
+                        namespace.to_string(),
+                        CommandError::new(
+                            format!("Can't check owner of namespace {}", namespace),
+                            Some(err.to_string()),
+                            None,
+                        ),
+                    )))
+                }
+            }
+        }
+
+        let mut namespace_labels = self.labels.clone();
         if let Some(resource_expiration) = &self.resource_expiration {
-            namespace_labels = Some(BTreeMap::from([(
-                "ttl".to_string(),
-                format!("{}", resource_expiration.as_secs()),
-            )]));
+            namespace_labels.insert("ttl".to_string(), format!("{}", resource_expiration.as_secs()));
+            namespace_labels.insert(
+                "ttl_action".to_string(),
+                target.kubernetes.context().resource_expiration_action().to_string(),
+            );
         };
 
         // create a namespace with labels if it does not exist
-        block_on(kube_create_namespace_if_not_exists(
+        let namespace_labels = if namespace_labels.is_empty() {
+            None
+        } else {
+            Some(namespace_labels)
+        };
+        let max_retries = default_admission_webhook_max_retries();
+        let mut retry = 0;
+        while let Err(e) = block_on(kube_create_namespace_if_not_exists(
             &target.kube,
-            target.environment.namespace(),
-            namespace_labels,
-        ))
-        .map_err(|e| {
-            EngineError::new_k8s_create_namespace(
-                self.event_details.clone(),
-                target.environment.namespace().to_string(),
-                CommandError::new(
-                    format!("Can't create namespace {}", target.environment.namespace()),
-                    Some(e.to_string()),
-                    None,
-                ),
-            )
-        })?;
+            namespace,
+            namespace_labels.clone(),
+        )) {
+            let raw_error =
+                CommandError::new(format!("Can't create namespace {}", namespace), Some(e.to_string()), None);
+            let webhook_name = match failing_admission_webhook(&e.to_string()) {
+                Some(webhook_name) => webhook_name,
+                None => {
+                    return Err(Box::new(EngineError::new_k8s_create_namespace(
+                        self.event_details.clone(),
+                        namespace.to_string(),
+                        raw_error,
+                    )))
+                }
+            };
+
+            if retry >= max_retries || (target.should_abort)() {
+                return Err(Box::new(EngineError::new_admission_webhook_unavailable(
+                    self.event_details.clone(),
+                    namespace.to_string(),
+                    webhook_name,
+                    raw_error,
+                )));
+            }
+
+            retry += 1;
+            warn!(
+                "Admission webhook {} unavailable while creating namespace {}, retrying ({}/{})",
+                webhook_name, namespace, retry, max_retries
+            );
+            thread::sleep(Duration::from_secs(10 * retry as u64));
+        }
 
         // upmc-enterprises/registry-creds sometimes is too long to copy the secret to the namespace
         // this workaround speed up the process to avoid application fails with ImagePullError on the first deployment
@@ -55,14 +195,14 @@ impl DeploymentAction for NamespaceDeployment {
                         &target.kube,
                         "awsecr-cred",
                         from_namespace,
-                        target.environment.namespace(),
+                        namespace,
                     ))
                     .map_err(|e| {
                         EngineError::new_copy_secrets_to_another_namespace_error(
                             self.event_details.clone(),
                             e,
                             from_namespace,
-                            target.environment.namespace(),
+                            namespace,
                         )
                     })?;
                 }
@@ -78,16 +218,45 @@ impl DeploymentAction for NamespaceDeployment {
     }
 
     fn on_delete(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        // never delete a namespace the service does not own, it may hold anything
+        if self.owner_label.is_some() {
+            match self.is_owned(target) {
+                Ok(Some(true)) => {}
+                Ok(None) => return Ok(()),
+                Ok(Some(false)) | Err(_) => {
+                    warn!("namespace {} is not owned by the service, keeping it", self.namespace(target));
+                    return Ok(());
+                }
+            }
+        }
+
         block_on(async {
             let api: Api<Namespace> = Api::all(target.kube.clone());
-            if api.get(target.environment.namespace()).await.is_ok() {
+            if api.get(self.namespace(target)).await.is_ok() {
                 // do not catch potential error - to confirm
-                let _ = api
-                    .delete(target.environment.namespace(), &DeleteParams::foreground())
-                    .await;
+                let _ = api.delete(self.namespace(target), &DeleteParams::foreground()).await;
             }
         });
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::deployment_action::deploy_namespace::failing_admission_webhook;
+
+    #[test]
+    fn test_failing_admission_webhook() {
+        // setup:
+        let webhook_error = r#"ApiError: Internal error occurred: failed calling webhook "namespace.sidecar-injector.io": failed to call webhook: Post "https://sidecar-injector.default.svc:443/mutate?timeout=10s": context deadline exceeded: InternalError (ErrorResponse { status: "Failure", code: 500 })"#;
+        let other_error = r#"ApiError: namespaces is forbidden: User "qovery" cannot create resource "namespaces": Forbidden (ErrorResponse { status: "Failure", code: 403 })"#;
+
+        // execute & verify:
+        assert_eq!(
+            Some("namespace.sidecar-injector.io".to_string()),
+            failing_admission_webhook(webhook_error)
+        );
+        assert_eq!(None, failing_admission_webhook(other_error));
+    }
+}
@@ -9,20 +9,69 @@ use crate::deployment_report::router::reporter::RouterDeploymentReporter;
 use crate::deployment_report::{execute_long_deployment, DeploymentTaskImpl};
 use crate::errors::EngineError;
 use crate::events::{EnvironmentStep, Stage};
-use crate::models::router::Router;
+use crate::models::router::{Router, RouterService};
 use crate::models::types::{CloudProvider, ToTeraContext};
 
 use crate::deployment_report::logger::{EnvProgressLogger, EnvSuccessLogger};
+use crate::runtime::block_on;
+use k8s_openapi::api::networking::v1::IngressClass;
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use kube::api::{ApiResource, DynamicObject, GroupVersionKind};
+use kube::Api;
 use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const CERTIFICATE_READY_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const CERTIFICATE_READY_POLL_INTERVAL: Duration = Duration::from_secs(5);
 
 impl<T: CloudProvider> DeploymentAction for Router<T>
 where
     Router<T>: ToTeraContext,
 {
+    fn helm_release(&self) -> Option<String> {
+        Some(self.helm_release_name())
+    }
+
+    fn service_workspace_directory(&self) -> Option<&str> {
+        Some(self.workspace_directory())
+    }
+
     fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
         let event_details = self.get_event_details(Stage::Environment(EnvironmentStep::Deploy));
-        let pre_run = |_: &EnvProgressLogger| -> Result<(), Box<EngineError>> { Ok(()) };
-        let run = |_logger: &EnvProgressLogger, _: ()| -> Result<(), Box<EngineError>> {
+        let pre_run = |logger: &EnvProgressLogger| -> Result<(), Box<EngineError>> {
+            // An unknown ingress class is not an error, the controller may be installed after the router
+            if let Some(ingress_class_name) = self.ingress_class_name() {
+                let ingress_classes: Api<IngressClass> = Api::all(target.kube.clone());
+                match block_on(ingress_classes.get(&ingress_class_name)) {
+                    Ok(_) => {}
+                    Err(kube::Error::Api(err)) if err.code == 404 => logger.warning(format!(
+                        "IngressClass `{}` does not exist, the router will not be served until it is created",
+                        ingress_class_name
+                    )),
+                    Err(err) => logger.warning(format!("Cannot check IngressClass `{}`: {}", ingress_class_name, err)),
+                }
+            }
+
+            // custom domains certificates are issued by cert-manager, it must be installed
+            if self.custom_domain_certificate_name().is_some() {
+                let crds: Api<CustomResourceDefinition> = Api::all(target.kube.clone());
+                match block_on(crds.get("certificates.cert-manager.io")) {
+                    Ok(_) => {}
+                    Err(kube::Error::Api(err)) if err.code == 404 => {
+                        return Err(Box::new(EngineError::new_router_tls_certificate_failed(
+                            event_details.clone(),
+                            self.name().to_string(),
+                            "cert-manager is not installed in the cluster".to_string(),
+                        )))
+                    }
+                    Err(err) => logger.warning(format!("Cannot check that cert-manager is installed: {}", err)),
+                }
+            }
+
+            Ok(())
+        };
+        let run = |logger: &EnvProgressLogger, _: ()| -> Result<(), Box<EngineError>> {
             let chart = ChartInfo {
                 name: self.helm_release_name(),
                 path: self.workspace_directory().to_string(),
@@ -39,7 +88,22 @@ where
                 chart,
             );
 
-            helm.on_create(target)
+            helm.on_create(target)?;
+
+            match self.custom_domain_certificate_name() {
+                Some(certificate_name) if self.custom_domain_tls_wait_ready() && !target.is_dry_run_deploy => {
+                    logger.info(format!("Waiting for TLS certificate `{}` to be issued", certificate_name));
+                    self.wait_for_certificate_ready(target, &certificate_name)
+                        .map_err(|reason| {
+                            Box::new(EngineError::new_router_tls_certificate_failed(
+                                event_details.clone(),
+                                self.name().to_string(),
+                                reason,
+                            ))
+                        })
+                }
+                _ => Ok(()),
+            }
         };
 
         let post_run = |logger: &EnvSuccessLogger, _: ()| {
@@ -101,3 +165,111 @@ where
         )
     }
 }
+
+impl<T: CloudProvider> Router<T> {
+    fn custom_domain_tls_wait_ready(&self) -> bool {
+        self.advanced_settings
+            .custom_domain_tls
+            .as_ref()
+            .map(|tls| tls.wait_ready)
+            .unwrap_or(false)
+    }
+
+    fn wait_for_certificate_ready(&self, target: &DeploymentTarget, certificate_name: &str) -> Result<(), String> {
+        let resource = ApiResource::from_gvk(&GroupVersionKind::gvk("cert-manager.io", "v1", "Certificate"));
+        let certificates: Api<DynamicObject> =
+            Api::namespaced_with(target.kube.clone(), target.environment.namespace(), &resource);
+
+        let started = Instant::now();
+        loop {
+            // the certificate may not be created yet by cert-manager, keep waiting
+            if let Ok(certificate) = block_on(certificates.get(certificate_name)) {
+                match certificate_status(&certificate.data) {
+                    CertificateStatus::Ready => return Ok(()),
+                    CertificateStatus::Failed(reason) => return Err(reason),
+                    CertificateStatus::Pending => {}
+                }
+            }
+
+            if (target.should_abort)() {
+                return Err("deployment has been aborted".to_string());
+            }
+
+            if started.elapsed() > CERTIFICATE_READY_TIMEOUT {
+                return Err(format!(
+                    "certificate `{}` is not ready after {} seconds",
+                    certificate_name,
+                    CERTIFICATE_READY_TIMEOUT.as_secs()
+                ));
+            }
+
+            thread::sleep(CERTIFICATE_READY_POLL_INTERVAL);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum CertificateStatus {
+    Ready,
+    Pending,
+    Failed(String),
+}
+
+// cert-manager keeps Ready to False while issuing, failures are reported by the Issuing condition
+fn certificate_status(certificate: &serde_json::Value) -> CertificateStatus {
+    let conditions = match certificate["status"]["conditions"].as_array() {
+        Some(conditions) => conditions,
+        None => return CertificateStatus::Pending,
+    };
+    let condition = |condition_type: &str| {
+        conditions
+            .iter()
+            .find(|condition| condition["type"].as_str() == Some(condition_type))
+    };
+
+    if let Some(ready) = condition("Ready") {
+        if ready["status"].as_str() == Some("True") {
+            return CertificateStatus::Ready;
+        }
+    }
+
+    match condition("Issuing") {
+        Some(issuing)
+            if issuing["status"].as_str() == Some("False") && issuing["reason"].as_str() == Some("Failed") =>
+        {
+            CertificateStatus::Failed(issuing["message"].as_str().unwrap_or("issuance failed").to_string())
+        }
+        _ => CertificateStatus::Pending,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_certificate_status() {
+        let certificate = |conditions: serde_json::Value| json!({ "status": { "conditions": conditions } });
+
+        assert_eq!(certificate_status(&json!({})), CertificateStatus::Pending);
+        assert_eq!(
+            certificate_status(&certificate(json!([{ "type": "Ready", "status": "True" }]))),
+            CertificateStatus::Ready
+        );
+        assert_eq!(
+            certificate_status(&certificate(json!([
+                { "type": "Ready", "status": "False", "reason": "DoesNotExist" },
+                { "type": "Issuing", "status": "True", "reason": "DoesNotExist" },
+            ]))),
+            CertificateStatus::Pending
+        );
+        assert_eq!(
+            certificate_status(&certificate(json!([
+                { "type": "Ready", "status": "False", "reason": "DoesNotExist" },
+                { "type": "Issuing", "status": "False", "reason": "Failed", "message": "challenge failed" },
+            ]))),
+            CertificateStatus::Failed("challenge failed".to_string())
+        );
+    }
+}
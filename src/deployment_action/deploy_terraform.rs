@@ -1,12 +1,14 @@
-use crate::cloud_provider::kubernetes::Kubernetes;
+use crate::cloud_provider::service::TFSTATE_DEFAULT_WORKSPACE;
 use crate::cloud_provider::DeploymentTarget;
-use crate::cmd;
-use crate::cmd::kubectl::kubectl_exec_delete_secret;
+use crate::cmd::kubectl::{kubectl_exec_delete_secret, kubectl_exec_get_secrets};
+use crate::cmd::terraform::{TerraformPlanSummary, TERRAFORM_WORKSPACE_FILE};
 use crate::deployment_action::DeploymentAction;
-use crate::errors::EngineError;
+use crate::errors::{CommandError, EngineError};
 use crate::events::EventDetails;
 use crate::template::generate_and_copy_all_files_into_dir;
 use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
 use std::path::PathBuf;
 use tera::Context as TeraContext;
 
@@ -38,7 +40,8 @@ impl TerraformDeployment {
         }
     }
 
-    fn prepare_terraform_files(&self) -> Result<(), Box<EngineError>> {
+    /// Common files are copied first, so the resource ones can override them
+    pub fn prepare_terraform_files(&self) -> Result<(), Box<EngineError>> {
         // Copy the root folder
         generate_and_copy_all_files_into_dir(
             &self.terraform_common_folder,
@@ -69,41 +72,130 @@ impl TerraformDeployment {
             )
         })?;
 
+        // the state is stored in the secret of the workspace terraform runs into
+        if let Some(workspace) = self.tera_context.get("tfstate_workspace").and_then(Value::as_str) {
+            fs::write(self.destination_folder.join(TERRAFORM_WORKSPACE_FILE), workspace).map_err(|e| {
+                EngineError::new_cannot_create_file(
+                    self.event_details.clone(),
+                    CommandError::new_from_safe_message(format!("Cannot write terraform workspace: {}", e)),
+                )
+            })?;
+        }
+
         Ok(())
     }
 
+    /// Preview what applying the prepared files would change, None when the plan output cannot be understood
+    pub fn plan(&self, target: &DeploymentTarget) -> Result<Option<TerraformPlanSummary>, Box<EngineError>> {
+        target
+            .cluster_executor
+            .terraform_plan(&self.destination_folder.to_string_lossy())
+            .map(|output| TerraformPlanSummary::from_plan_output(&output))
+            .map_err(|err| Box::new(EngineError::new_terraform_error(self.event_details.clone(), err)))
+    }
+
+    /// Apply exactly what the last plan previewed, it must have been run first
+    pub fn apply_plan(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        target
+            .cluster_executor
+            .terraform_apply_plan(&self.destination_folder.to_string_lossy())
+            .map(|_| ())
+            .map_err(|err| Box::new(EngineError::new_terraform_error(self.event_details.clone(), err)))
+    }
+
+    /// Preview what destroying the resources of the prepared files would remove
+    pub fn plan_destroy(&self, target: &DeploymentTarget) -> Result<Option<TerraformPlanSummary>, Box<EngineError>> {
+        target
+            .cluster_executor
+            .terraform_plan_destroy(&self.destination_folder.to_string_lossy())
+            .map(|output| TerraformPlanSummary::from_plan_output(&output))
+            .map_err(|err| Box::new(EngineError::new_terraform_error(self.event_details.clone(), err)))
+    }
+
+    /// Apply the terraform files previously prepared into the destination folder
+    pub fn apply(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        let ret = target
+            .cluster_executor
+            .terraform_apply(&self.destination_folder.to_string_lossy(), self.is_dry_run);
+
+        if let Err(err) = ret {
+            Err(Box::new(EngineError::new_terraform_error(self.event_details.clone(), err)))
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn delete_tfstate_secret(
-        kubernetes: &dyn Kubernetes,
+        target: &DeploymentTarget,
         namespace: &str,
         secret_name: &str,
     ) -> Result<(), Box<EngineError>> {
-        let config_file_path = kubernetes.get_kubeconfig_file_path()?;
+        let config_file_path = target.kubeconfig_path()?;
 
         // create the namespace to insert the tfstate in secrets
-        let _ = kubectl_exec_delete_secret(
-            config_file_path,
-            namespace,
-            secret_name,
-            kubernetes.cloud_provider().credentials_environment_variables(),
-        );
+        let _ = kubectl_exec_delete_secret(config_file_path, namespace, secret_name, target.kubectl_credentials());
 
         Ok(())
     }
+
+    /// Delete the terraform states of the namespace which do not belong to any of `live_service_ids`.
+    /// All the services of the namespace must be given, the states of the missing ones are lost.
+    /// Best effort, returns the names of the deleted secrets
+    pub fn cleanup_orphaned_tfstate_secrets(
+        target: &DeploymentTarget,
+        namespace: &str,
+        live_service_ids: &HashSet<String>,
+    ) -> Result<Vec<String>, Box<EngineError>> {
+        let config_file_path = target.kubeconfig_path()?;
+
+        // terraform kubernetes backend labels the secrets it stores states into
+        let secrets = match kubectl_exec_get_secrets(
+            &config_file_path,
+            namespace,
+            "tfstate=true",
+            target.kubectl_credentials(),
+        ) {
+            Ok(secrets) => secrets,
+            Err(err) => {
+                warn!("Cannot list tfstates of namespace {}: {}", namespace, err);
+                return Ok(vec![]);
+            }
+        };
+
+        let mut deleted_secrets = vec![];
+        for secret_name in orphaned_tfstate_secrets(
+            secrets.items.iter().map(|secret| secret.metadata.name.as_str()),
+            live_service_ids,
+        ) {
+            match kubectl_exec_delete_secret(&config_file_path, namespace, &secret_name, target.kubectl_credentials()) {
+                Ok(()) => deleted_secrets.push(secret_name),
+                Err(err) => warn!("Cannot delete orphaned tfstate {}: {}", secret_name, err),
+            }
+        }
+
+        Ok(deleted_secrets)
+    }
+}
+
+// Secrets of the default workspace whose suffix, the service id, is not one of the live services
+fn orphaned_tfstate_secrets<'a>(
+    secret_names: impl Iterator<Item = &'a str>,
+    live_service_ids: &HashSet<String>,
+) -> Vec<String> {
+    let prefix = format!("tfstate-{}-", TFSTATE_DEFAULT_WORKSPACE);
+    secret_names
+        .filter(|name| match name.strip_prefix(&prefix) {
+            Some(service_id) => !live_service_ids.contains(service_id),
+            None => false,
+        })
+        .map(|name| name.to_string())
+        .collect()
 }
 
 impl DeploymentAction for TerraformDeployment {
-    fn on_create(&self, _target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+    fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
         self.prepare_terraform_files()?;
-        let ret = cmd::terraform::terraform_init_validate_plan_apply(
-            &self.destination_folder.to_string_lossy(),
-            self.is_dry_run,
-        );
-
-        if let Err(err) = ret {
-            Err(Box::new(EngineError::new_terraform_error(self.event_details.clone(), err)))
-        } else {
-            Ok(())
-        }
+        self.apply(target)
     }
 
     fn on_pause(&self, _target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
@@ -112,10 +204,20 @@ impl DeploymentAction for TerraformDeployment {
 
     fn on_delete(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
         self.prepare_terraform_files()?;
-        match cmd::terraform::terraform_init_validate_destroy(&self.destination_folder.to_string_lossy(), false) {
+        // On dry run, the resources and the tfstate are kept
+        if self.is_dry_run {
+            if let Some(plan) = self.plan_destroy(target)? {
+                info!("Dry run, terraform destroy skipped: {}", plan);
+            }
+            return Ok(());
+        }
+
+        match target
+            .cluster_executor
+            .terraform_destroy(&self.destination_folder.to_string_lossy(), false)
+        {
             Ok(_) => {
-                if let Err(err) = TerraformDeployment::delete_tfstate_secret(
-                    target.kubernetes,
+                if let Err(err) = target.cluster_executor.kubectl_delete_secret(
                     target.environment.namespace(),
                     self.tera_context.get("tfstate_name").and_then(Value::as_str).unwrap(),
                 ) {
@@ -127,3 +229,25 @@ impl DeploymentAction for TerraformDeployment {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::deployment_action::deploy_terraform::orphaned_tfstate_secrets;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_orphaned_tfstate_secrets() {
+        let live_service_ids: HashSet<String> = vec!["zd8a4b2c1".to_string()].into_iter().collect();
+        let secret_names = vec![
+            "tfstate-default-zd8a4b2c1",
+            "tfstate-default-z5e6f7a8b",
+            "tfstate-preview-z5e6f7a8b",
+            "db-credentials",
+        ];
+
+        assert_eq!(
+            orphaned_tfstate_secrets(secret_names.into_iter(), &live_service_ids),
+            vec!["tfstate-default-z5e6f7a8b".to_string()]
+        );
+    }
+}
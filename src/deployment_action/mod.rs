@@ -1,8 +1,11 @@
 use crate::cloud_provider::service::Action;
 use crate::cloud_provider::DeploymentTarget;
+use crate::deployment_action::deploy_helm::default_helm_timeout;
 use crate::errors::EngineError;
+use std::time::Duration;
 
 mod check_dns;
+mod database_init_scripts;
 mod deploy_application;
 mod deploy_container;
 mod deploy_database;
@@ -11,21 +14,189 @@ pub mod deploy_helm;
 mod deploy_job;
 pub mod deploy_namespace;
 mod deploy_router;
-mod deploy_terraform;
+pub mod deploy_terraform;
 mod pause_service;
+mod pre_deploy_job;
+mod readiness_probe;
+mod release_ownership;
+pub mod release_tag;
+pub mod summary;
 #[cfg(test)]
 mod test_utils;
 mod utils;
+mod warmup;
 
 pub trait DeploymentAction {
     fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>>;
     fn on_pause(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>>;
     fn on_delete(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>>;
+
+    /// Confirm the service is actually stopped once paused, scaling down only updates the workloads spec
+    fn on_pause_check(&self, _target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        Ok(())
+    }
+
+    /// Roll the pods of the service without redeploying it, services without pods have nothing to restart
+    fn on_restart(&self, _target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        Ok(())
+    }
+
+    /// Name of the helm release backing the service, if any
+    fn helm_release(&self) -> Option<String> {
+        None
+    }
+
+    /// Directory the service files are rendered into, if any
+    fn service_workspace_directory(&self) -> Option<&str> {
+        None
+    }
+
+    /// How long the helm upgrade of the service may take, readiness included
+    fn helm_timeout(&self) -> Duration {
+        default_helm_timeout()
+    }
+
     fn exec_action(&self, deployment_target: &DeploymentTarget, action: Action) -> Result<(), Box<EngineError>> {
         match action {
             Action::Create => self.on_create(deployment_target),
             Action::Delete => self.on_delete(deployment_target),
-            Action::Pause => self.on_pause(deployment_target),
+            Action::Pause => {
+                self.on_pause(deployment_target)?;
+                self.on_pause_check(deployment_target)
+            }
+            Action::Restart => self.on_restart(deployment_target),
         }
     }
+
+    /// Same as exec_action, but the whole action is run again when failing on a transient error.
+    /// Actions are idempotent (i.e: helm upgrade), so re-running them is safe
+    fn exec_action_with_retry(
+        &self,
+        deployment_target: &DeploymentTarget,
+        action: Action,
+        max_attempts: u32,
+        backoff: Duration,
+    ) -> Result<(), Box<EngineError>> {
+        retry_on_transient_error(max_attempts, backoff, deployment_target.should_abort, || {
+            self.exec_action(deployment_target, action)
+        })
+    }
+}
+
+/// Runs `action` again while it fails on a transient error, at most `max_attempts` times in total.
+/// The wait before each new attempt grows linearly with `backoff`
+fn retry_on_transient_error(
+    max_attempts: u32,
+    backoff: Duration,
+    should_abort: &dyn Fn() -> bool,
+    mut action: impl FnMut() -> Result<(), Box<EngineError>>,
+) -> Result<(), Box<EngineError>> {
+    let mut attempt = 1;
+    loop {
+        let err = match action() {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+
+        if attempt >= max_attempts || !err.tag().is_transient() || should_abort() {
+            return Err(match attempt {
+                1 => err,
+                _ => Box::new(err.with_attempts(attempt)),
+            });
+        }
+
+        warn!(
+            "Attempt {}/{} failed with a transient error, retrying: {}",
+            attempt, max_attempts, err
+        );
+        std::thread::sleep(backoff * attempt);
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cloud_provider::Kind;
+    use crate::deployment_action::retry_on_transient_error;
+    use crate::errors::{CommandError, EngineError};
+    use crate::events::{EnvironmentStep, EventDetails, Stage, Transmitter};
+    use crate::io_models::QoveryIdentifier;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    fn event_details() -> EventDetails {
+        EventDetails::new(
+            Some(Kind::Aws),
+            QoveryIdentifier::new_random(),
+            QoveryIdentifier::new_random(),
+            "".to_string(),
+            Stage::Environment(EnvironmentStep::Deploy),
+            Transmitter::Application(Uuid::new_v4(), "app".to_string()),
+        )
+    }
+
+    fn transient_error() -> Box<EngineError> {
+        Box::new(EngineError::new_k8s_scale_replicas(
+            event_details(),
+            "app=my-app".to_string(),
+            "my-namespace".to_string(),
+            1,
+            CommandError::new_from_safe_message("connection reset".to_string()),
+        ))
+    }
+
+    fn invalid_spec_error() -> Box<EngineError> {
+        Box::new(EngineError::new_unknown(
+            event_details(),
+            "invalid spec".to_string(),
+            None,
+            None,
+            None,
+        ))
+    }
+
+    #[test]
+    fn test_retry_on_transient_error() {
+        // transient errors are retried until the action succeeds
+        let mut attempts = 0;
+        let ret = retry_on_transient_error(3, Duration::ZERO, &|| false, || {
+            attempts += 1;
+            match attempts {
+                1 | 2 => Err(transient_error()),
+                _ => Ok(()),
+            }
+        });
+        assert!(ret.is_ok());
+        assert_eq!(attempts, 3);
+
+        // up to the max attempts, which are reported in the final error
+        let mut attempts = 0;
+        let ret = retry_on_transient_error(3, Duration::ZERO, &|| false, || {
+            attempts += 1;
+            Err(transient_error())
+        });
+        assert_eq!(attempts, 3);
+        assert_eq!(
+            ret.unwrap_err().user_log_message(),
+            "Unable to scale Kubernetes `app=my-app` replicas to `1` in namespace `my-namespace`. (failed after 3 attempts)"
+        );
+
+        // non transient errors are returned as is
+        let mut attempts = 0;
+        let ret = retry_on_transient_error(3, Duration::ZERO, &|| false, || {
+            attempts += 1;
+            Err(invalid_spec_error())
+        });
+        assert_eq!(attempts, 1);
+        assert_eq!(ret.unwrap_err().user_log_message(), "invalid spec");
+
+        // nor when the deployment is aborted
+        let mut attempts = 0;
+        let ret = retry_on_transient_error(3, Duration::ZERO, &|| true, || {
+            attempts += 1;
+            Err(transient_error())
+        });
+        assert_eq!(attempts, 1);
+        assert!(ret.is_err());
+    }
 }
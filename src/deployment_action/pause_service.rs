@@ -1,16 +1,28 @@
 use crate::cloud_provider::DeploymentTarget;
+use crate::deployment_action::utils::wait_until_scaled_to;
 use crate::deployment_action::DeploymentAction;
 use crate::errors::{CommandError, EngineError};
 use crate::events::EventDetails;
 use crate::runtime::block_on;
 use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
 use k8s_openapi::api::autoscaling::v1::{Scale, ScaleSpec};
+use k8s_openapi::api::autoscaling::v2beta2::HorizontalPodAutoscaler;
 use k8s_openapi::api::core::v1::Pod;
-use kube::api::{ListParams, Patch, PatchParams};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{DeleteParams, ListParams, Patch, PatchParams, PostParams};
 use kube::runtime::wait::{await_condition, Condition};
 use kube::Api;
+use std::collections::BTreeMap;
 use std::time::Duration;
 
+// Annotation of a paused deployment holding the autoscalers removed while it is paused, to restore them on resume
+const PAUSED_HPAS_ANNOTATION: &str = "qovery.com/paused-hpas";
+// Label set on the pods of a service about to be paused, to spot them while draining.
+// The service selectors don't exclude it, so the pods keep receiving requests until they are scaled down
+const DRAINING_LABEL: &str = "qovery.com/draining";
+// Pod template annotation bumped to roll the pods of a deployment, same as kubectl rollout restart
+const RESTARTED_AT_ANNOTATION: &str = "kubectl.kubernetes.io/restartedAt";
+
 fn has_deployment_ready_replicas(nb_ready_replicas: usize) -> impl Condition<Deployment> {
     move |deployment: Option<&Deployment>| {
         deployment
@@ -31,13 +43,150 @@ fn has_statefulset_ready_replicas(nb_ready_replicas: usize) -> impl Condition<St
     }
 }
 
+fn is_deployment_rolled_out() -> impl Condition<Deployment> {
+    move |deployment: Option<&Deployment>| {
+        let (generation, status) = match deployment {
+            Some(Deployment {
+                metadata,
+                status: Some(status),
+                ..
+            }) => (metadata.generation, status),
+            _ => return false,
+        };
+        let replicas = status.replicas.unwrap_or(0);
+
+        status.observed_generation >= generation
+            && status.updated_replicas.unwrap_or(0) == replicas
+            && status.ready_replicas.unwrap_or(0) == replicas
+    }
+}
+
+/// Returns the autoscalers stored on a paused deployment, empty when none have been removed
+fn paused_hpas(annotations: Option<&BTreeMap<String, String>>) -> Vec<HorizontalPodAutoscaler> {
+    annotations
+        .and_then(|annotations| annotations.get(PAUSED_HPAS_ANNOTATION))
+        .and_then(|hpas| serde_json::from_str(hpas).ok())
+        .unwrap_or_default()
+}
+
+/// Replicas to resume a deployment with, the autoscalers take over from their lower bound
+fn resume_replicas(hpas: &[HorizontalPodAutoscaler]) -> i32 {
+    hpas.iter()
+        .map(|hpa| hpa.spec.as_ref().and_then(|spec| spec.min_replicas).unwrap_or(1))
+        .max()
+        .unwrap_or(1)
+}
+
+/// Removes the autoscalers targeting the deployment, so they don't scale it back up while paused.
+/// Their definition is stored on the deployment to restore them with their original bounds on resume
+async fn suspend_deployment_hpas(
+    deployments: &Api<Deployment>,
+    hpas: &Api<HorizontalPodAutoscaler>,
+    deployment_name: &str,
+) -> Result<(), kube::Error> {
+    let targeting_hpas: Vec<HorizontalPodAutoscaler> = hpas
+        .list(&ListParams::default())
+        .await?
+        .into_iter()
+        .filter(|hpa| {
+            hpa.spec.as_ref().map_or(false, |spec| {
+                spec.scale_target_ref.kind == "Deployment" && spec.scale_target_ref.name == deployment_name
+            })
+        })
+        .collect();
+
+    // Already suspended by a previous pause, keep the stored autoscalers
+    if targeting_hpas.is_empty() {
+        return Ok(());
+    }
+
+    // Only keep what is needed to re-create them, server side fields would be rejected
+    let stored_hpas: Vec<HorizontalPodAutoscaler> = targeting_hpas
+        .iter()
+        .map(|hpa| HorizontalPodAutoscaler {
+            metadata: ObjectMeta {
+                name: hpa.metadata.name.clone(),
+                labels: hpa.metadata.labels.clone(),
+                annotations: hpa.metadata.annotations.clone(),
+                ..Default::default()
+            },
+            spec: hpa.spec.clone(),
+            status: None,
+        })
+        .collect();
+    let stored_hpas = serde_json::to_string(&stored_hpas).unwrap_or_default();
+    let patch = serde_json::json!({ "metadata": { "annotations": { PAUSED_HPAS_ANNOTATION: stored_hpas } } });
+    deployments
+        .patch(deployment_name, &PatchParams::default(), &Patch::Merge(patch))
+        .await?;
+
+    for hpa in targeting_hpas {
+        if let Some(name) = hpa.metadata.name {
+            hpas.delete(&name, &DeleteParams::default()).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Re-creates the autoscalers removed while the deployment was paused
+async fn resume_deployment_hpas(
+    deployments: &Api<Deployment>,
+    hpas: &Api<HorizontalPodAutoscaler>,
+    deployment_name: &str,
+    paused_hpas: Vec<HorizontalPodAutoscaler>,
+) -> Result<(), kube::Error> {
+    for hpa in paused_hpas {
+        match hpas.create(&PostParams::default(), &hpa).await {
+            Ok(_) => {}
+            // Re-created in the meantime, i.e: by the chart
+            Err(kube::Error::Api(err)) if err.code == 409 => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    let patch = serde_json::json!({ "metadata": { "annotations": { PAUSED_HPAS_ANNOTATION: null } } });
+    deployments
+        .patch(deployment_name, &PatchParams::default(), &Patch::Merge(patch))
+        .await?;
+
+    Ok(())
+}
+
+/// Flags the pods of the service as draining, then leaves them the drain timeout to complete in-flight requests
+async fn drain_pods(
+    kube: &kube::Client,
+    namespace: &str,
+    selector: &str,
+    drain_timeout: Duration,
+) -> Result<(), kube::Error> {
+    let pods: Api<Pod> = Api::namespaced(kube.clone(), namespace);
+    let patch = serde_json::json!({ "metadata": { "labels": { DRAINING_LABEL: "true" } } });
+    let running_pods = pods.list(&ListParams::default().labels(selector)).await?;
+    // Already paused, nothing to drain
+    if running_pods.items.is_empty() {
+        return Ok(());
+    }
+
+    for pod in running_pods {
+        if let Some(name) = pod.metadata.name {
+            pods.patch(&name, &PatchParams::default(), &Patch::Merge(&patch))
+                .await?;
+        }
+    }
+
+    tokio::time::sleep(drain_timeout).await;
+    Ok(())
+}
+
 async fn pause_service(
     kube: &kube::Client,
     namespace: &str,
     selector: &str,
     desired_size: usize, // only for test, normal behavior assume 0
     is_statefulset: bool,
-) -> Result<(), kube::Error> {
+    suspend_hpa: bool,
+) -> Result<usize, kube::Error> {
     let list_params = ListParams::default().labels(selector);
     let patch_params = PatchParams::default();
     let new_scale = Scale {
@@ -49,23 +198,31 @@ async fn pause_service(
     };
     let patch = Patch::Merge(new_scale);
 
-    // We don't need to remove HPA, if we set desired replicas to 0, hpa disable itself until we change it back
+    // If we set desired replicas to 0, hpa disable itself until we change it back
     // https://kubernetes.io/docs/tasks/run-application/horizontal-pod-autoscale/#implicit-maintenance-mode-deactivation
+    // But an hpa with a lower bound still fights the scale down until it notices, so it can be removed while paused
 
+    let mut nb_workloads = 0;
     if is_statefulset {
         let statefulsets: Api<StatefulSet> = Api::namespaced(kube.clone(), namespace);
         for statefulset in statefulsets.list(&list_params).await? {
             if let Some(name) = statefulset.metadata.name {
                 statefulsets.patch_scale(&name, &patch_params, &patch).await?;
                 let _ = await_condition(statefulsets.clone(), &name, has_statefulset_ready_replicas(0)).await;
+                nb_workloads += 1;
             }
         }
     } else {
         let deployments: Api<Deployment> = Api::namespaced(kube.clone(), namespace);
+        let hpas: Api<HorizontalPodAutoscaler> = Api::namespaced(kube.clone(), namespace);
         for deployment in deployments.list(&list_params).await? {
             if let Some(name) = deployment.metadata.name {
+                if suspend_hpa && desired_size == 0 {
+                    suspend_deployment_hpas(&deployments, &hpas, &name).await?;
+                }
                 deployments.patch_scale(&name, &patch_params, &patch).await?;
                 let _ = await_condition(deployments.clone(), &name, has_deployment_ready_replicas(0)).await;
+                nb_workloads += 1;
             }
         }
     };
@@ -81,6 +238,63 @@ async fn pause_service(
         tokio::time::sleep(Duration::from_secs(10)).await;
     }
 
+    Ok(nb_workloads)
+}
+
+/// Rolls the pods of the service without redeploying it.
+/// Deployments are rolled one pod at a time by bumping their pod template, statefulsets are scaled down and back up
+async fn restart_service(
+    kube: &kube::Client,
+    namespace: &str,
+    selector: &str,
+    is_statefulset: bool,
+) -> Result<(), kube::Error> {
+    let list_params = ListParams::default().labels(selector);
+    let patch_params = PatchParams::default();
+
+    if is_statefulset {
+        let statefulsets: Api<StatefulSet> = Api::namespaced(kube.clone(), namespace);
+        for statefulset in statefulsets.list(&list_params).await? {
+            let replicas = statefulset.spec.as_ref().and_then(|spec| spec.replicas).unwrap_or(1);
+            // A paused service stays paused
+            if replicas == 0 {
+                continue;
+            }
+
+            if let Some(name) = statefulset.metadata.name {
+                for desired_replicas in [0, replicas] {
+                    let scale = Scale {
+                        metadata: Default::default(),
+                        spec: Some(ScaleSpec {
+                            replicas: Some(desired_replicas),
+                        }),
+                        status: None,
+                    };
+                    statefulsets
+                        .patch_scale(&name, &patch_params, &Patch::Merge(scale))
+                        .await?;
+                    let _ = await_condition(
+                        statefulsets.clone(),
+                        &name,
+                        has_statefulset_ready_replicas(desired_replicas as usize),
+                    )
+                    .await;
+                }
+            }
+        }
+    } else {
+        let deployments: Api<Deployment> = Api::namespaced(kube.clone(), namespace);
+        let patch = serde_json::json!({ "spec": { "template": { "metadata": { "annotations": {
+            RESTARTED_AT_ANNOTATION: chrono::Utc::now().to_rfc3339()
+        } } } } });
+        for deployment in deployments.list(&list_params).await? {
+            if let Some(name) = deployment.metadata.name {
+                deployments.patch(&name, &patch_params, &Patch::Merge(&patch)).await?;
+                let _ = await_condition(deployments.clone(), &name, is_deployment_rolled_out()).await;
+            }
+        }
+    };
+
     Ok(())
 }
 
@@ -110,10 +324,25 @@ async fn unpause_service_if_needed(
         }
     } else {
         let deployments: Api<Deployment> = Api::namespaced(kube.clone(), namespace);
+        let hpas: Api<HorizontalPodAutoscaler> = Api::namespaced(kube.clone(), namespace);
         for deployment in deployments.list(&list_params).await? {
             if deployment.status.and_then(|s| s.replicas).unwrap_or(0) == 0 {
                 if let Some(name) = deployment.metadata.name {
-                    deployments.patch_scale(&name, &patch_params, &patch).await?;
+                    // Resume from the autoscalers lower bound, as they were before the pause
+                    let paused_hpas = paused_hpas(deployment.metadata.annotations.as_ref());
+                    let resume_scale = Scale {
+                        metadata: Default::default(),
+                        spec: Some(ScaleSpec {
+                            replicas: Some(resume_replicas(&paused_hpas)),
+                        }),
+                        status: None,
+                    };
+                    deployments
+                        .patch_scale(&name, &patch_params, &Patch::Merge(resume_scale))
+                        .await?;
+                    if !paused_hpas.is_empty() {
+                        resume_deployment_hpas(&deployments, &hpas, &name, paused_hpas).await?;
+                    }
                 }
             }
         }
@@ -127,6 +356,10 @@ pub struct PauseServiceAction {
     is_statefulset: bool,
     event_details: EventDetails,
     timeout: Duration,
+    namespace: Option<String>,
+    suspend_hpa: bool,
+    graceful_drain_timeout: Option<Duration>,
+    workload_required: bool,
 }
 
 impl PauseServiceAction {
@@ -141,16 +374,45 @@ impl PauseServiceAction {
             is_statefulset,
             timeout,
             event_details,
+            namespace: None,
+            suspend_hpa: false,
+            graceful_drain_timeout: None,
+            workload_required: false,
         }
     }
 
+    /// Target a namespace other than the environment one (i.e: service with a dedicated namespace)
+    pub fn with_namespace(mut self, namespace: String) -> PauseServiceAction {
+        self.namespace = Some(namespace);
+        self
+    }
+
+    /// Remove the autoscalers of the service while it is paused, they are restored when it is resumed
+    pub fn with_hpa_suspension(mut self, suspend_hpa: bool) -> PauseServiceAction {
+        self.suspend_hpa = suspend_hpa;
+        self
+    }
+
+    /// Drain the pods for this long before scaling them down, without it they are stopped right away
+    pub fn with_graceful_drain_timeout(mut self, drain_timeout: Option<Duration>) -> PauseServiceAction {
+        self.graceful_drain_timeout = drain_timeout;
+        self
+    }
+
+    /// Fail the pause when the selector matches no workload, instead of silently doing nothing
+    pub fn with_workload_required(mut self, workload_required: bool) -> PauseServiceAction {
+        self.workload_required = workload_required;
+        self
+    }
+
+    fn namespace<'a>(&'a self, target: &'a DeploymentTarget) -> &'a str {
+        self.namespace
+            .as_deref()
+            .unwrap_or_else(|| target.environment.namespace())
+    }
+
     pub fn unpause_if_needed(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
-        let fut = unpause_service_if_needed(
-            &target.kube,
-            target.environment.namespace(),
-            &self.selector,
-            self.is_statefulset,
-        );
+        let fut = unpause_service_if_needed(&target.kube, self.namespace(target), &self.selector, self.is_statefulset);
 
         match block_on(async { tokio::time::timeout(self.timeout, fut).await }) {
             // Happy path
@@ -162,7 +424,7 @@ impl PauseServiceAction {
                 return Err(Box::new(EngineError::new_k8s_scale_replicas(
                     self.event_details.clone(),
                     self.selector.clone(),
-                    target.environment.namespace().to_string(),
+                    self.namespace(target).to_string(),
                     0,
                     command_error,
                 )));
@@ -176,7 +438,7 @@ impl PauseServiceAction {
                 return Err(Box::new(EngineError::new_k8s_scale_replicas(
                     self.event_details.clone(),
                     self.selector.clone(),
-                    target.environment.namespace().to_string(),
+                    self.namespace(target).to_string(),
                     0,
                     command_error,
                 )));
@@ -193,21 +455,51 @@ impl DeploymentAction for PauseServiceAction {
     }
 
     fn on_pause(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
-        let fut = pause_service(
-            &target.kube,
-            target.environment.namespace(),
-            &self.selector,
-            0,
-            self.is_statefulset,
-        );
+        let fut = async {
+            if let Some(drain_timeout) = self.graceful_drain_timeout {
+                drain_pods(&target.kube, self.namespace(target), &self.selector, drain_timeout).await?;
+            }
+
+            pause_service(
+                &target.kube,
+                self.namespace(target),
+                &self.selector,
+                0,
+                self.is_statefulset,
+                self.suspend_hpa,
+            )
+            .await
+        };
 
         // Async block is necessary because tokio::time::timeout require a living tokio runtime, which does not exist
         // outside of the block_on. So must wrap it in an async task that will be exec inside the block_on
-        let ret = block_on(async { tokio::time::timeout(self.timeout, fut).await });
+        // The drain is not part of the scale down, so it does not eat its timeout
+        let timeout = self.timeout + self.graceful_drain_timeout.unwrap_or_default();
+        let ret = block_on(async { tokio::time::timeout(timeout, fut).await });
 
         match ret {
+            // Nothing has been scaled down, the service is not running as expected
+            Ok(Ok(0)) if self.workload_required => {
+                let command_error = CommandError::new_from_safe_message(format!(
+                    "No {} matches selector {}, nothing has been scaled down",
+                    if self.is_statefulset {
+                        "statefulset"
+                    } else {
+                        "deployment"
+                    },
+                    self.selector
+                ));
+                return Err(Box::new(EngineError::new_k8s_scale_replicas(
+                    self.event_details.clone(),
+                    self.selector.clone(),
+                    self.namespace(target).to_string(),
+                    0,
+                    command_error,
+                )));
+            }
+
             // Happy path
-            Ok(Ok(())) => {}
+            Ok(Ok(_)) => {}
 
             // error during scaling
             Ok(Err(kube_err)) => {
@@ -215,7 +507,7 @@ impl DeploymentAction for PauseServiceAction {
                 return Err(Box::new(EngineError::new_k8s_scale_replicas(
                     self.event_details.clone(),
                     self.selector.clone(),
-                    target.environment.namespace().to_string(),
+                    self.namespace(target).to_string(),
                     0,
                     command_error,
                 )));
@@ -224,12 +516,12 @@ impl DeploymentAction for PauseServiceAction {
             Err(_) => {
                 let command_error = CommandError::new_from_safe_message(format!(
                     "Timout of {}s exceeded while scaling down service",
-                    self.timeout.as_secs()
+                    timeout.as_secs()
                 ));
                 return Err(Box::new(EngineError::new_k8s_scale_replicas(
                     self.event_details.clone(),
                     self.selector.clone(),
-                    target.environment.namespace().to_string(),
+                    self.namespace(target).to_string(),
                     0,
                     command_error,
                 )));
@@ -239,16 +531,63 @@ impl DeploymentAction for PauseServiceAction {
         Ok(())
     }
 
+    fn on_pause_check(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        wait_until_scaled_to(
+            target,
+            self.namespace(target),
+            &self.selector,
+            0,
+            self.timeout,
+            self.event_details.clone(),
+        )
+    }
+
     fn on_delete(&self, _target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
         Ok(())
     }
+
+    fn on_restart(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        let fut = restart_service(&target.kube, self.namespace(target), &self.selector, self.is_statefulset);
+
+        match block_on(async { tokio::time::timeout(self.timeout, fut).await }) {
+            // Happy path
+            Ok(Ok(())) => {}
+
+            // error during restart
+            Ok(Err(kube_err)) => {
+                let command_error = CommandError::new_from_safe_message(kube_err.to_string());
+                return Err(Box::new(EngineError::new_k8s_restart_service(
+                    self.event_details.clone(),
+                    self.selector.clone(),
+                    self.namespace(target).to_string(),
+                    command_error,
+                )));
+            }
+            // timeout
+            Err(_) => {
+                let command_error = CommandError::new_from_safe_message(format!(
+                    "Timout of {}s exceeded while restarting service",
+                    self.timeout.as_secs()
+                ));
+                return Err(Box::new(EngineError::new_k8s_restart_service(
+                    self.event_details.clone(),
+                    self.selector.clone(),
+                    self.namespace(target).to_string(),
+                    command_error,
+                )));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(feature = "test-local-kube")]
 #[cfg(test)]
 mod tests {
     use crate::deployment_action::pause_service::{
-        has_deployment_ready_replicas, has_statefulset_ready_replicas, pause_service, unpause_service_if_needed,
+        drain_pods, has_deployment_ready_replicas, has_statefulset_ready_replicas, is_deployment_rolled_out,
+        pause_service, restart_service, unpause_service_if_needed, DRAINING_LABEL, RESTARTED_AT_ANNOTATION,
     };
     use crate::deployment_action::test_utils::{
         get_simple_deployment, get_simple_hpa, get_simple_statefulset, NamespaceForTest,
@@ -256,7 +595,8 @@ mod tests {
     use function_name::named;
     use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
     use k8s_openapi::api::autoscaling::v1::HorizontalPodAutoscaler;
-    use kube::api::PostParams;
+    use k8s_openapi::api::core::v1::Pod;
+    use kube::api::{ListParams, PostParams};
     use kube::runtime::wait::await_condition;
     use kube::Api;
     use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -291,10 +631,29 @@ mod tests {
         .await??;
 
         // Scaling a service that does not exist should not fail
-        tokio::time::timeout(timeout, pause_service(&kube_client, &namespace, "app=totototo", 0, false)).await??;
+        tokio::time::timeout(
+            timeout,
+            pause_service(&kube_client, &namespace, "app=totototo", 0, false, false),
+        )
+        .await??;
+
+        // Pods are flagged before being scaled down
+        tokio::time::timeout(timeout, drain_pods(&kube_client, &namespace, &selector, Duration::from_secs(1)))
+            .await??;
+        let pods: Api<Pod> = Api::namespaced(kube_client.clone(), &namespace);
+        for pod in pods.list(&ListParams::default().labels(&selector)).await? {
+            assert_eq!(
+                pod.metadata
+                    .labels
+                    .unwrap_or_default()
+                    .get(DRAINING_LABEL)
+                    .map(String::as_str),
+                Some("true")
+            );
+        }
 
         // Try to scale down our deployment
-        tokio::time::timeout(timeout, pause_service(&kube_client, &namespace, &selector, 0, false)).await??;
+        tokio::time::timeout(timeout, pause_service(&kube_client, &namespace, &selector, 0, false, false)).await??;
         tokio::time::timeout(
             timeout,
             await_condition(deployments.clone(), &app_name, has_deployment_ready_replicas(0)),
@@ -302,7 +661,7 @@ mod tests {
         .await??;
 
         // Try to scale up our deployment
-        tokio::time::timeout(timeout, pause_service(&kube_client, &namespace, &selector, 1, false)).await??;
+        tokio::time::timeout(timeout, pause_service(&kube_client, &namespace, &selector, 1, false, false)).await??;
         tokio::time::timeout(
             timeout,
             await_condition(deployments.clone(), &app_name, has_deployment_ready_replicas(1)),
@@ -338,11 +697,16 @@ mod tests {
         )
         .await??;
 
-        // Scaling a service that does not exist should not fail
-        tokio::time::timeout(timeout, pause_service(&kube_client, &namespace, "app=totototo", 0, true)).await??;
+        // Scaling a service that does not exist should not fail, but scales nothing
+        let nb_workloads =
+            tokio::time::timeout(timeout, pause_service(&kube_client, &namespace, "app=totototo", 0, true, false))
+                .await??;
+        assert_eq!(nb_workloads, 0);
 
         // Try to scale down our deployment
-        tokio::time::timeout(timeout, pause_service(&kube_client, &namespace, &selector, 0, true)).await??;
+        let nb_workloads =
+            tokio::time::timeout(timeout, pause_service(&kube_client, &namespace, &selector, 0, true, false)).await??;
+        assert_eq!(nb_workloads, 1);
         tokio::time::timeout(
             timeout,
             await_condition(statefulsets.clone(), &app_name, has_statefulset_ready_replicas(0)),
@@ -350,7 +714,7 @@ mod tests {
         .await??;
 
         // Try to scale up our deployment
-        tokio::time::timeout(timeout, pause_service(&kube_client, &namespace, &selector, 1, true)).await??;
+        tokio::time::timeout(timeout, pause_service(&kube_client, &namespace, &selector, 1, true, false)).await??;
         tokio::time::timeout(
             timeout,
             await_condition(statefulsets.clone(), &app_name, has_statefulset_ready_replicas(1)),
@@ -390,7 +754,7 @@ mod tests {
         .await??;
 
         // Try to scale down our deployment
-        tokio::time::timeout(timeout, pause_service(&kube_client, &namespace, &selector, 0, false)).await??;
+        tokio::time::timeout(timeout, pause_service(&kube_client, &namespace, &selector, 0, false, false)).await??;
         tokio::time::timeout(
             timeout,
             await_condition(deployments.clone(), &app_name, has_deployment_ready_replicas(0)),
@@ -406,4 +770,96 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[named]
+    async fn test_pause_deployment_with_hpa_suspension() -> Result<(), Box<dyn std::error::Error>> {
+        let kube_client = kube::Client::try_default().await.unwrap();
+        let namespace = format!(
+            "{}-{:?}",
+            function_name!().replace('_', "-"),
+            SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs()
+        );
+        let timeout = Duration::from_secs(30);
+        let deployments: Api<Deployment> = Api::namespaced(kube_client.clone(), &namespace);
+        let deployment: Deployment = get_simple_deployment();
+        let hpas: Api<HorizontalPodAutoscaler> = Api::namespaced(kube_client.clone(), &namespace);
+        let mut hpa = get_simple_hpa();
+        hpa.spec.as_mut().unwrap().min_replicas = Some(2);
+
+        let app_name = deployment.metadata.name.clone().unwrap_or_default();
+        let hpa_name = hpa.metadata.name.clone().unwrap_or_default();
+        let selector = format!("app={}", app_name);
+
+        // create simple deployment and wait for it to be ready
+        let _ns = NamespaceForTest::new(kube_client.clone(), namespace.to_string()).await?;
+
+        hpas.create(&PostParams::default(), &hpa).await.unwrap();
+        deployments.create(&PostParams::default(), &deployment).await.unwrap();
+        tokio::time::timeout(
+            timeout,
+            await_condition(deployments.clone(), &app_name, has_deployment_ready_replicas(1)),
+        )
+        .await??;
+
+        // The hpa is removed while paused
+        tokio::time::timeout(timeout, pause_service(&kube_client, &namespace, &selector, 0, false, true)).await??;
+        assert!(hpas.get_opt(&hpa_name).await?.is_none());
+
+        // And restored on resume, with the deployment resumed from the hpa lower bound
+        tokio::time::timeout(timeout, unpause_service_if_needed(&kube_client, &namespace, &selector, false)).await??;
+        tokio::time::timeout(
+            timeout,
+            await_condition(deployments.clone(), &app_name, has_deployment_ready_replicas(2)),
+        )
+        .await??;
+        let restored_hpa = hpas.get(&hpa_name).await?;
+        assert_eq!(restored_hpa.spec.and_then(|spec| spec.min_replicas), Some(2));
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[named]
+    async fn test_restart_deployment() -> Result<(), Box<dyn std::error::Error>> {
+        let kube_client = kube::Client::try_default().await.unwrap();
+        let namespace = format!(
+            "{}-{:?}",
+            function_name!().replace('_', "-"),
+            SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs()
+        );
+        let timeout = Duration::from_secs(60);
+        let deployments: Api<Deployment> = Api::namespaced(kube_client.clone(), &namespace);
+        let deployment: Deployment = get_simple_deployment();
+
+        let app_name = deployment.metadata.name.clone().unwrap_or_default();
+        let selector = format!("app={}", app_name);
+
+        // create simple deployment and wait for it to be ready
+        let _ns = NamespaceForTest::new(kube_client.clone(), namespace.to_string()).await?;
+
+        deployments.create(&PostParams::default(), &deployment).await.unwrap();
+        tokio::time::timeout(
+            timeout,
+            await_condition(deployments.clone(), &app_name, has_deployment_ready_replicas(1)),
+        )
+        .await??;
+
+        // Pods are rolled and the deployment is back to ready
+        tokio::time::timeout(timeout, restart_service(&kube_client, &namespace, &selector, false)).await??;
+        tokio::time::timeout(
+            timeout,
+            await_condition(deployments.clone(), &app_name, is_deployment_rolled_out()),
+        )
+        .await??;
+        let restarted = deployments.get(&app_name).await?;
+        assert!(restarted
+            .spec
+            .and_then(|spec| spec.template.metadata)
+            .and_then(|metadata| metadata.annotations)
+            .unwrap_or_default()
+            .contains_key(RESTARTED_AT_ANNOTATION));
+
+        Ok(())
+    }
 }
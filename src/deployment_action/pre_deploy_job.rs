@@ -0,0 +1,373 @@
+use crate::cloud_provider::models::EnvironmentVariable;
+use crate::cloud_provider::DeploymentTarget;
+use crate::deployment_action::deploy_job::{job_status, JobStatus};
+use crate::deployment_action::DeploymentAction;
+use crate::errors::EngineError;
+use crate::events::EventDetails;
+use crate::runtime::block_on;
+use k8s_openapi::api::batch::v1::Job;
+use k8s_openapi::api::core::v1::{Pod, Secret};
+use kube::api::{DeleteParams, ListParams, LogParams, PostParams, PropagationPolicy};
+use kube::Api;
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PRE_DEPLOY_JOB_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const PRE_DEPLOY_JOB_OUTPUT_MAX_LINES: i64 = 100;
+// Key of the marker secret holding the run key of the last successful run
+const PRE_DEPLOY_JOB_RUN_KEY: &str = "run-key";
+
+/// Run a one-shot job (i.e: a data backfill) with the image of a service, before the service is deployed.
+/// A marker secret records the run key once it succeeded, so it only runs again when the run key changes.
+pub struct PreDeployJob {
+    service_name: String,
+    service_id: String,
+    resource_name: String,
+    namespace: String,
+    image: String,
+    image_pull_secret: Option<String>,
+    registry_docker_json_config: Option<String>,
+    command: Vec<String>,
+    environment_variables: Vec<EnvironmentVariable>,
+    run_key: String,
+    timeout: Duration,
+    event_details: EventDetails,
+}
+
+impl PreDeployJob {
+    pub fn new(
+        service_name: String,
+        service_id: String,
+        sanitized_name: &str,
+        namespace: String,
+        image: String,
+        command: Vec<String>,
+        run_key: String,
+        timeout: Duration,
+        event_details: EventDetails,
+    ) -> PreDeployJob {
+        PreDeployJob {
+            service_name,
+            service_id,
+            resource_name: format!("{}-pre-deploy", sanitized_name),
+            namespace,
+            image,
+            image_pull_secret: None,
+            registry_docker_json_config: None,
+            command,
+            environment_variables: vec![],
+            run_key,
+            timeout,
+            event_details,
+        }
+    }
+
+    /// Environment variables of the service, with their values base64 encoded
+    pub fn with_environment_variables(mut self, environment_variables: Vec<EnvironmentVariable>) -> PreDeployJob {
+        self.environment_variables = environment_variables;
+        self
+    }
+
+    /// Pull the image with a secret already present in the namespace
+    pub fn with_image_pull_secret(mut self, secret_name: String) -> PreDeployJob {
+        self.image_pull_secret = Some(secret_name);
+        self
+    }
+
+    /// Pull the image with these registry credentials, stored in a secret only living along the job
+    pub fn with_registry_docker_json_config(mut self, docker_json_config: String) -> PreDeployJob {
+        self.registry_docker_json_config = Some(docker_json_config);
+        self
+    }
+
+    fn marker_name(&self) -> String {
+        format!("{}-done", self.resource_name)
+    }
+
+    fn registry_secret_name(&self) -> String {
+        format!("{}-registry", self.resource_name)
+    }
+
+    fn error(&self, reason: String, output: Option<String>) -> Box<EngineError> {
+        Box::new(EngineError::new_pre_deploy_job_failed(
+            self.event_details.clone(),
+            self.service_name.clone(),
+            reason,
+            output,
+        ))
+    }
+
+    fn environment_secret(&self) -> Result<Secret, serde_json::Error> {
+        let data: BTreeMap<&str, &str> = self
+            .environment_variables
+            .iter()
+            .map(|env_var| (env_var.key.as_str(), env_var.value.as_str()))
+            .collect();
+
+        serde_json::from_value(serde_json::json!({
+            "metadata": {
+                "name": self.resource_name,
+                "labels": { "serviceId": self.service_id },
+            },
+            "data": data,
+        }))
+    }
+
+    fn registry_secret(&self, docker_json_config: &str) -> Result<Secret, serde_json::Error> {
+        serde_json::from_value(serde_json::json!({
+            "metadata": {
+                "name": self.registry_secret_name(),
+                "labels": { "serviceId": self.service_id },
+            },
+            "type": "kubernetes.io/dockerconfigjson",
+            "data": { ".dockerconfigjson": docker_json_config },
+        }))
+    }
+
+    fn marker_secret(&self) -> Result<Secret, serde_json::Error> {
+        serde_json::from_value(serde_json::json!({
+            "metadata": {
+                "name": self.marker_name(),
+                "labels": { "serviceId": self.service_id },
+            },
+            "stringData": { PRE_DEPLOY_JOB_RUN_KEY: self.run_key },
+        }))
+    }
+
+    fn job(&self) -> Result<Job, serde_json::Error> {
+        let image_pull_secrets: Vec<serde_json::Value> = self
+            .image_pull_secret
+            .clone()
+            .or_else(|| {
+                self.registry_docker_json_config
+                    .as_ref()
+                    .map(|_| self.registry_secret_name())
+            })
+            .into_iter()
+            .map(|name| serde_json::json!({ "name": name }))
+            .collect();
+
+        serde_json::from_value(serde_json::json!({
+            "metadata": {
+                "name": self.resource_name,
+                "labels": { "serviceId": self.service_id },
+            },
+            "spec": {
+                "backoffLimit": 0,
+                "activeDeadlineSeconds": self.timeout.as_secs(),
+                "template": {
+                    "metadata": {
+                        "labels": { "serviceId": self.service_id },
+                    },
+                    "spec": {
+                        "restartPolicy": "Never",
+                        "automountServiceAccountToken": false,
+                        "imagePullSecrets": image_pull_secrets,
+                        "containers": [{
+                            "name": "pre-deploy",
+                            "image": self.image,
+                            "command": self.command,
+                            "envFrom": [{ "secretRef": { "name": self.resource_name } }],
+                        }],
+                    },
+                },
+            },
+        }))
+    }
+
+    /// Run key of the last successful run, None if the job never succeeded
+    fn last_run_key(&self, secrets: &Api<Secret>) -> Result<Option<String>, kube::Error> {
+        match block_on(secrets.get(&self.marker_name())) {
+            Ok(marker) => Ok(marker
+                .data
+                .and_then(|data| data.get(PRE_DEPLOY_JOB_RUN_KEY).cloned())
+                .and_then(|run_key| String::from_utf8(run_key.0).ok())),
+            Err(kube::Error::Api(err)) if err.code == 404 => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn cleanup(&self, jobs: &Api<Job>, secrets: &Api<Secret>) {
+        let delete_params = DeleteParams {
+            propagation_policy: Some(PropagationPolicy::Background),
+            ..Default::default()
+        };
+        let _ = block_on(jobs.delete(&self.resource_name, &delete_params));
+        let _ = block_on(secrets.delete(&self.resource_name, &DeleteParams::default()));
+        let _ = block_on(secrets.delete(&self.registry_secret_name(), &DeleteParams::default()));
+    }
+
+    fn output(&self, pods: &Api<Pod>) -> Option<String> {
+        let list_params = ListParams::default().labels(&format!("job-name={}", self.resource_name));
+        let pod_name = block_on(pods.list(&list_params))
+            .ok()?
+            .items
+            .into_iter()
+            .find_map(|pod| pod.metadata.name)?;
+        let log_params = LogParams {
+            tail_lines: Some(PRE_DEPLOY_JOB_OUTPUT_MAX_LINES),
+            ..Default::default()
+        };
+
+        block_on(pods.logs(&pod_name, &log_params)).ok()
+    }
+}
+
+impl DeploymentAction for PreDeployJob {
+    fn on_create(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        if self.command.is_empty() {
+            return Ok(());
+        }
+
+        let secrets: Api<Secret> = Api::namespaced(target.kube.clone(), &self.namespace);
+        let jobs: Api<Job> = Api::namespaced(target.kube.clone(), &self.namespace);
+        let pods: Api<Pod> = Api::namespaced(target.kube.clone(), &self.namespace);
+
+        let last_run_key = self
+            .last_run_key(&secrets)
+            .map_err(|err| self.error(format!("cannot check if the job already ran: {}", err), None))?;
+        if last_run_key.as_deref() == Some(self.run_key.as_str()) {
+            info!(
+                "Pre-deploy job of service {} already ran for run key {}",
+                self.service_name, self.run_key
+            );
+            return Ok(());
+        }
+
+        // leftovers of a previous failed run, the job must be gone before being created again
+        self.cleanup(&jobs, &secrets);
+        let cleanup_started = Instant::now();
+        while block_on(jobs.get(&self.resource_name)).is_ok() && cleanup_started.elapsed() < self.timeout {
+            thread::sleep(PRE_DEPLOY_JOB_POLL_INTERVAL);
+        }
+
+        let environment_secret = self
+            .environment_secret()
+            .map_err(|err| self.error(format!("cannot build environment secret: {}", err), None))?;
+        let job = self
+            .job()
+            .map_err(|err| self.error(format!("cannot build job: {}", err), None))?;
+        // built before creating anything, so a failure leaves nothing to clean up
+        let registry_secret = match &self.registry_docker_json_config {
+            Some(docker_json_config) => Some(
+                self.registry_secret(docker_json_config)
+                    .map_err(|err| self.error(format!("cannot build registry secret: {}", err), None))?,
+            ),
+            None => None,
+        };
+        block_on(secrets.create(&PostParams::default(), &environment_secret))
+            .map_err(|err| self.error(format!("cannot create environment secret: {}", err), None))?;
+        if let Some(registry_secret) = &registry_secret {
+            if let Err(err) = block_on(secrets.create(&PostParams::default(), registry_secret)) {
+                self.cleanup(&jobs, &secrets);
+                return Err(self.error(format!("cannot create registry secret: {}", err), None));
+            }
+        }
+        if let Err(err) = block_on(jobs.create(&PostParams::default(), &job)) {
+            self.cleanup(&jobs, &secrets);
+            return Err(self.error(format!("cannot create job: {}", err), None));
+        }
+
+        let started = Instant::now();
+        let result = loop {
+            match block_on(jobs.get(&self.resource_name))
+                .as_ref()
+                .map(|job| job_status(&Some(job)))
+            {
+                Ok(JobStatus::Success) => break Ok(()),
+                Ok(JobStatus::Failure { reason, message }) => {
+                    break Err(self.error(format!("{} {}", reason, message), self.output(&pods)))
+                }
+                Ok(JobStatus::NotRunning | JobStatus::Running) | Err(_) => {}
+            }
+
+            if (target.should_abort)() {
+                break Err(self.error("deployment has been aborted".to_string(), self.output(&pods)));
+            }
+
+            if started.elapsed() > self.timeout {
+                break Err(self.error(
+                    format!("job did not complete after {} seconds", self.timeout.as_secs()),
+                    self.output(&pods),
+                ));
+            }
+
+            thread::sleep(PRE_DEPLOY_JOB_POLL_INTERVAL);
+        };
+
+        let result = result.and_then(|_| {
+            let marker = self
+                .marker_secret()
+                .map_err(|err| self.error(format!("cannot build marker secret: {}", err), None))?;
+            // the marker of a previous run key is replaced
+            let _ = block_on(secrets.delete(&self.marker_name(), &DeleteParams::default()));
+            block_on(secrets.create(&PostParams::default(), &marker))
+                .map_err(|err| self.error(format!("cannot mark the job as done: {}", err), None))?;
+            Ok(())
+        });
+
+        // the environment secret holds the service secrets, never keep it around
+        self.cleanup(&jobs, &secrets);
+
+        result
+    }
+
+    fn on_pause(&self, _target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        Ok(())
+    }
+
+    fn on_delete(&self, target: &DeploymentTarget) -> Result<(), Box<EngineError>> {
+        let secrets: Api<Secret> = Api::namespaced(target.kube.clone(), &self.namespace);
+        let jobs: Api<Job> = Api::namespaced(target.kube.clone(), &self.namespace);
+
+        self.cleanup(&jobs, &secrets);
+        let _ = block_on(secrets.delete(&self.marker_name(), &DeleteParams::default()));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{EnvironmentStep, Stage, Transmitter};
+    use crate::io_models::QoveryIdentifier;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_pre_deploy_job_pulls_with_its_own_registry_secret() {
+        let event_details = EventDetails::new(
+            None,
+            QoveryIdentifier::new_random(),
+            QoveryIdentifier::new_random(),
+            "".to_string(),
+            Stage::Environment(EnvironmentStep::Deploy),
+            Transmitter::Container(Uuid::new_v4(), "my-container".to_string()),
+        );
+        let job = PreDeployJob::new(
+            "my-container".to_string(),
+            "service-id".to_string(),
+            "container-1234",
+            "my-namespace".to_string(),
+            "registry.io/qovery-mirror:tag".to_string(),
+            vec!["/bin/backfill".to_string()],
+            "v2".to_string(),
+            Duration::from_secs(60),
+            event_details,
+        )
+        .with_registry_docker_json_config("e30=".to_string());
+
+        let pod_spec = job.job().unwrap().spec.unwrap().template.spec.unwrap();
+        assert_eq!(
+            pod_spec
+                .image_pull_secrets
+                .unwrap()
+                .into_iter()
+                .map(|secret| secret.name)
+                .collect::<Vec<_>>(),
+            vec![Some("container-1234-pre-deploy-registry".to_string())]
+        );
+        assert_eq!(pod_spec.containers[0].command, Some(vec!["/bin/backfill".to_string()]));
+    }
+}
@@ -0,0 +1,114 @@
+use crate::cloud_provider::service::ReadinessProbe;
+use crate::deployment_action::utils::is_pod_ready;
+use crate::deployment_action::warmup::forward_pod_port;
+use crate::runtime::block_on;
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::ListParams;
+use kube::Api;
+use std::time::{Duration, Instant};
+
+const READINESS_PROBE_INTERVAL: Duration = Duration::from_secs(5);
+const READINESS_PROBE_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn probe_url(local_port: u16, probe: &ReadinessProbe) -> String {
+    format!("http://127.0.0.1:{}{}", local_port, probe.path)
+}
+
+async fn probe_pod(
+    client: &reqwest::Client,
+    pods: &Api<Pod>,
+    pod_name: &str,
+    probe: &ReadinessProbe,
+) -> Result<(), String> {
+    let port_forward = forward_pod_port(pods.clone(), pod_name.to_string(), probe.port)
+        .await
+        .map_err(|err| format!("{}: cannot forward port {}: {}", pod_name, probe.port, err))?;
+
+    match client.get(&probe_url(port_forward.local_port, probe)).send().await {
+        Ok(response) if response.status().as_u16() == probe.expected_status => Ok(()),
+        Ok(response) => Err(format!(
+            "{}: {} answered status {} instead of {}",
+            pod_name,
+            probe.path,
+            response.status().as_u16(),
+            probe.expected_status
+        )),
+        Err(err) => Err(format!("{}: {} cannot be reached: {}", pod_name, probe.path, err)),
+    }
+}
+
+/// Poll the probe endpoint of every ready pod matching the selector until they all answer the expected status,
+/// or the probe timeout elapses. Requests go through a port-forward, as the engine may not resolve the cluster DNS.
+/// Returns the last failure when the endpoint did not become ready in time
+pub fn wait_for_readiness_probe(
+    kube: &kube::Client,
+    namespace: &str,
+    selector: &str,
+    probe: &ReadinessProbe,
+    should_abort: &dyn Fn() -> bool,
+) -> Result<(), String> {
+    block_on(async {
+        let client = reqwest::Client::builder()
+            .timeout(READINESS_PROBE_REQUEST_TIMEOUT)
+            .build()
+            .map_err(|err| format!("cannot create http client: {}", err))?;
+        let pods: Api<Pod> = Api::namespaced(kube.clone(), namespace);
+
+        let started = Instant::now();
+        loop {
+            let last_failure = match pods.list(&ListParams::default().labels(selector)).await {
+                Ok(pod_list) => {
+                    let pod_names: Vec<String> = pod_list
+                        .items
+                        .into_iter()
+                        .filter(is_pod_ready)
+                        .filter_map(|pod| pod.metadata.name)
+                        .collect();
+
+                    let mut failure = None;
+                    for pod_name in &pod_names {
+                        if let Err(err) = probe_pod(&client, &pods, pod_name, probe).await {
+                            failure = Some(err);
+                            break;
+                        }
+                    }
+
+                    match (pod_names.is_empty(), failure) {
+                        (true, _) => format!("no ready pod matches {}", selector),
+                        (false, Some(failure)) => failure,
+                        (false, None) => return Ok(()),
+                    }
+                }
+                Err(err) => format!("cannot list pods to probe: {}", err),
+            };
+            info!("Readiness probe not passing yet: {}", last_failure);
+
+            if should_abort() || started.elapsed() >= probe.timeout {
+                return Err(format!(
+                    "readiness probe did not pass after {} seconds, {}",
+                    started.elapsed().as_secs(),
+                    last_failure
+                ));
+            }
+
+            tokio::time::sleep(READINESS_PROBE_INTERVAL).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readiness_probe_url() {
+        let probe = ReadinessProbe {
+            path: "/health/ready".to_string(),
+            port: 8080,
+            expected_status: 200,
+            timeout: Duration::from_secs(60),
+        };
+
+        assert_eq!(probe_url(34567, &probe), "http://127.0.0.1:34567/health/ready");
+    }
+}
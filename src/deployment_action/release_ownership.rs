@@ -0,0 +1,130 @@
+use crate::cloud_provider::helm::ChartInfo;
+use crate::deployment_action::release_tag::{release_revisions, ReleaseRevision};
+use crate::runtime::block_on;
+use flate2::read::GzDecoder;
+use k8s_openapi::api::core::v1::Secret;
+use kube::api::{Patch, PatchParams};
+use kube::Api;
+use std::io::Read;
+
+// Set on the secret helm stores each revision of a release into, once the engine deployed it
+pub const HELM_RELEASE_MANAGED_BY_ANNOTATION: &str = "qovery.com/managed-by";
+pub(super) const HELM_RELEASE_MANAGED_BY: &str = "qovery-engine";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReleaseOwnership {
+    NotInstalled,
+    Engine,
+    /// Installed by someone else, from the given chart if it can be read
+    Foreign(Option<String>),
+}
+
+// The release stored by helm is a base64 encoded gzipped json document
+pub(super) fn release_chart_name(secret: &Secret) -> Option<String> {
+    let encoded = secret.data.as_ref()?.get("release")?;
+    let compressed = base64::decode(&encoded.0).ok()?;
+    let mut release = String::new();
+    GzDecoder::new(compressed.as_slice())
+        .read_to_string(&mut release)
+        .ok()?;
+    let release: serde_json::Value = serde_json::from_str(&release).ok()?;
+
+    release["chart"]["metadata"]["name"].as_str().map(str::to_string)
+}
+
+// Releases deployed before the engine annotated them are recognized by the chart they were installed from
+fn ownership(revisions: &[ReleaseRevision], chart_name: &str) -> ReleaseOwnership {
+    let last_revision = match revisions.last() {
+        Some(revision) => revision,
+        None => return ReleaseOwnership::NotInstalled,
+    };
+
+    if revisions.iter().any(|revision| revision.managed_by_engine)
+        || last_revision.chart_name.as_deref() == Some(chart_name)
+    {
+        ReleaseOwnership::Engine
+    } else {
+        ReleaseOwnership::Foreign(last_revision.chart_name.clone())
+    }
+}
+
+/// Whether the release the chart is deployed as has been installed by the engine, from `chart_name`
+pub fn release_ownership(
+    kube: &kube::Client,
+    chart: &ChartInfo,
+    chart_name: &str,
+) -> Result<ReleaseOwnership, kube::Error> {
+    let revisions = block_on(release_revisions(kube, &chart.get_namespace_string(), &chart.name))?;
+    Ok(ownership(&revisions, chart_name))
+}
+
+/// Flag the last revision of the release as deployed by the engine
+pub fn mark_release_managed(kube: &kube::Client, chart: &ChartInfo) -> Result<(), kube::Error> {
+    let namespace = chart.get_namespace_string();
+    let revisions = block_on(release_revisions(kube, &namespace, &chart.name))?;
+    let last_revision = match revisions.last() {
+        Some(revision) if !revision.managed_by_engine => revision,
+        _ => return Ok(()),
+    };
+
+    let secrets: Api<Secret> = Api::namespaced(kube.clone(), &namespace);
+    let patch = serde_json::json!({
+        "metadata": { "annotations": { HELM_RELEASE_MANAGED_BY_ANNOTATION: HELM_RELEASE_MANAGED_BY } }
+    });
+    block_on(secrets.patch(&last_revision.secret_name, &PatchParams::default(), &Patch::Merge(patch)))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    #[test]
+    fn test_release_chart_name() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(br#"{"name":"app-z1234","chart":{"metadata":{"name":"q-application","version":"0.1.0"}}}"#)
+            .unwrap();
+        let release = base64::encode(encoder.finish().unwrap());
+        let secret: Secret = serde_json::from_value(serde_json::json!({
+            "metadata": { "name": "sh.helm.release.v1.app-z1234.v1" },
+            "data": { "release": base64::encode(release) }
+        }))
+        .unwrap();
+
+        assert_eq!(release_chart_name(&secret), Some("q-application".to_string()));
+    }
+
+    #[test]
+    fn test_release_ownership() {
+        let revision = |revision: u32, managed_by_engine: bool, chart_name: &str| ReleaseRevision {
+            revision,
+            secret_name: format!("sh.helm.release.v1.app.v{}", revision),
+            tag: None,
+            managed_by_engine,
+            chart_name: Some(chart_name.to_string()),
+        };
+
+        assert_eq!(ownership(&[], "q-application"), ReleaseOwnership::NotInstalled);
+        assert_eq!(
+            ownership(
+                &[revision(1, true, "q-application"), revision(2, false, "q-application")],
+                "q-application"
+            ),
+            ReleaseOwnership::Engine
+        );
+        // deployed before the releases were annotated
+        assert_eq!(
+            ownership(&[revision(1, false, "q-application")], "q-application"),
+            ReleaseOwnership::Engine
+        );
+        assert_eq!(
+            ownership(&[revision(1, false, "nginx")], "q-application"),
+            ReleaseOwnership::Foreign(Some("nginx".to_string()))
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use crate::cloud_provider::helm::ChartInfo;
+use crate::cloud_provider::service::Service;
+use crate::cloud_provider::DeploymentTarget;
+use crate::cmd::helm::{HelmCommand, HelmError};
+use crate::deployment_action::release_ownership::{
+    release_chart_name, HELM_RELEASE_MANAGED_BY, HELM_RELEASE_MANAGED_BY_ANNOTATION,
+};
+use crate::deployment_action::DeploymentAction;
+use crate::errors::{CommandError, EngineError};
+use crate::events::{EnvironmentStep, Stage};
+use crate::runtime::block_on;
+use k8s_openapi::api::core::v1::Secret;
+use kube::api::{ListParams, Patch, PatchParams};
+use kube::Api;
+
+// Set on the secret helm stores each revision of a release into
+pub const HELM_RELEASE_TAG_ANNOTATION: &str = "qovery.com/release-tag";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct ReleaseRevision {
+    pub revision: u32,
+    pub secret_name: String,
+    pub tag: Option<String>,
+    pub managed_by_engine: bool,
+    pub chart_name: Option<String>,
+}
+
+/// List the revisions of a release still in its history, from the secrets helm stores them into
+pub(super) async fn release_revisions(
+    kube: &kube::Client,
+    namespace: &str,
+    release_name: &str,
+) -> Result<Vec<ReleaseRevision>, kube::Error> {
+    let secrets: Api<Secret> = Api::namespaced(kube.clone(), namespace);
+    let list_params = ListParams::default().labels(&format!("owner=helm,name={}", release_name));
+
+    let mut revisions: Vec<ReleaseRevision> = secrets
+        .list(&list_params)
+        .await?
+        .items
+        .into_iter()
+        .filter_map(|secret| {
+            let revision = secret.metadata.labels.as_ref()?.get("version")?.parse::<u32>().ok()?;
+            let annotation = |key: &str| {
+                secret
+                    .metadata
+                    .annotations
+                    .as_ref()
+                    .and_then(|annotations| annotations.get(key).cloned())
+            };
+            Some(ReleaseRevision {
+                revision,
+                tag: annotation(HELM_RELEASE_TAG_ANNOTATION),
+                managed_by_engine: annotation(HELM_RELEASE_MANAGED_BY_ANNOTATION).as_deref()
+                    == Some(HELM_RELEASE_MANAGED_BY),
+                chart_name: release_chart_name(&secret),
+                secret_name: secret.metadata.name?,
+            })
+        })
+        .collect();
+    revisions.sort_by_key(|revision| revision.revision);
+
+    Ok(revisions)
+}
+
+/// Returns the revision carrying the tag, or the tags available in the release history if none does
+fn resolve_tag(revisions: &[ReleaseRevision], tag: &str) -> Result<u32, Vec<String>> {
+    revisions
+        .iter()
+        .rev()
+        .find(|revision| revision.tag.as_deref() == Some(tag))
+        .map(|revision| revision.revision)
+        .ok_or_else(|| revisions.iter().filter_map(|revision| revision.tag.clone()).collect())
+}
+
+/// Tag the last revision of the release. A tag designates a single revision, so it is moved if already set
+pub fn tag_release(kube: &kube::Client, chart: &ChartInfo, tag: &str) -> Result<(), kube::Error> {
+    let namespace = chart.get_namespace_string();
+    let revisions = block_on(release_revisions(kube, &namespace, &chart.name))?;
+    let last_revision = match revisions.last() {
+        Some(revision) => revision,
+        None => return Ok(()),
+    };
+
+    let secrets: Api<Secret> = Api::namespaced(kube.clone(), &namespace);
+    let set_tag = |secret_name: &str, tag: Option<&str>| -> Result<(), kube::Error> {
+        let patch = serde_json::json!({ "metadata": { "annotations": { HELM_RELEASE_TAG_ANNOTATION: tag } } });
+        block_on(secrets.patch(secret_name, &PatchParams::default(), &Patch::Merge(patch)))?;
+        Ok(())
+    };
+
+    for revision in revisions.iter().filter(|revision| revision.tag.as_deref() == Some(tag)) {
+        if revision.revision != last_revision.revision {
+            set_tag(&revision.secret_name, None)?;
+        }
+    }
+
+    set_tag(&last_revision.secret_name, Some(tag))
+}
+
+/// Rollback the helm release of the service to the revision tagged at deploy time.
+/// Returns the revision the release has been rolled back to
+pub fn rollback_to_tag<S: Service + DeploymentAction + ?Sized>(
+    target: &DeploymentTarget,
+    service: &S,
+    tag: &str,
+) -> Result<u32, Box<EngineError>> {
+    let event_details = service.get_event_details(Stage::Environment(EnvironmentStep::Deploy));
+    let release_name = match service.helm_release() {
+        Some(release_name) => release_name,
+        None => {
+            return Err(Box::new(EngineError::new_helm_error(
+                event_details,
+                HelmError::ReleaseDoesNotExist(service.name().to_string()),
+            )))
+        }
+    };
+
+    let namespace = target.environment.namespace();
+    let revisions = block_on(release_revisions(&target.kube, namespace, &release_name)).map_err(|err| {
+        EngineError::new_helm_error(
+            event_details.clone(),
+            HelmError::CmdError(
+                release_name.clone(),
+                HelmCommand::ROLLBACK,
+                CommandError::new_from_safe_message(format!("Cannot list release revisions: {}", err)),
+            ),
+        )
+    })?;
+
+    let revision = resolve_tag(&revisions, tag).map_err(|available_tags| {
+        EngineError::new_helm_release_tag_not_found(
+            event_details.clone(),
+            release_name.clone(),
+            tag.to_string(),
+            available_tags,
+        )
+    })?;
+
+    let chart = ChartInfo::new_from_release_name(&release_name, namespace);
+    target
+        .cluster_executor
+        .helm_rollback(&chart, revision)
+        .map_err(|err| EngineError::new_helm_error(event_details, err))?;
+
+    Ok(revision)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_tag() {
+        let revision = |revision: u32, tag: Option<&str>| ReleaseRevision {
+            revision,
+            secret_name: format!("sh.helm.release.v1.app.v{}", revision),
+            tag: tag.map(|tag| tag.to_string()),
+            managed_by_engine: false,
+            chart_name: None,
+        };
+        let revisions = vec![
+            revision(1, Some("initial")),
+            revision(2, None),
+            revision(3, Some("known-good")),
+            revision(4, None),
+        ];
+
+        assert_eq!(resolve_tag(&revisions, "known-good"), Ok(3));
+        assert_eq!(resolve_tag(&revisions, "initial"), Ok(1));
+        assert_eq!(
+            resolve_tag(&revisions, "unknown"),
+            Err(vec!["initial".to_string(), "known-good".to_string()])
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use crate::cloud_provider::service::Action;
+use crate::errors::{EngineError, Tag};
+use crate::runtime::block_on;
+use k8s_openapi::api::core::v1::{Event, Pod, Secret};
+use kube::api::ListParams;
+use kube::Api;
+use std::collections::HashSet;
+use std::time::Duration;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeploymentOutcome {
+    Success,
+    Failure { tag: Tag, message: String },
+}
+
+/// Final status of a service once its deployment action is over, whatever the result
+#[derive(Clone, Debug)]
+pub struct DeploymentSummary {
+    pub service_id: Uuid,
+    pub action: Action,
+    pub outcome: DeploymentOutcome,
+    pub duration: Duration,
+    pub helm_revision: Option<u64>,
+    pub ready_replicas: Option<u32>,
+    pub warnings: Vec<String>,
+}
+
+impl DeploymentSummary {
+    pub fn new(
+        service_id: Uuid,
+        action: Action,
+        result: &Result<(), Box<EngineError>>,
+        duration: Duration,
+    ) -> DeploymentSummary {
+        let outcome = match result {
+            Ok(()) => DeploymentOutcome::Success,
+            Err(err) => DeploymentOutcome::Failure {
+                tag: err.tag().clone(),
+                message: err.user_log_message().to_string(),
+            },
+        };
+
+        DeploymentSummary {
+            service_id,
+            action,
+            outcome,
+            duration,
+            helm_revision: None,
+            ready_replicas: None,
+            warnings: vec![],
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.outcome == DeploymentOutcome::Success
+    }
+
+    /// Complete the summary with what is currently running in the cluster.
+    /// This is best effort, a summary must never fail a deployment
+    pub fn with_runtime_status(
+        mut self,
+        kube: &kube::Client,
+        namespace: &str,
+        helm_release: Option<String>,
+        selector: Option<String>,
+    ) -> DeploymentSummary {
+        match block_on(fetch_runtime_status(
+            kube,
+            namespace,
+            helm_release.as_deref(),
+            selector.as_deref(),
+        )) {
+            Ok((helm_revision, pods, events)) => {
+                self.helm_revision = helm_revision;
+                self.ready_replicas = ready_replicas(&pods);
+                self.warnings = warning_messages(&pods, &events);
+            }
+            Err(err) => warn!("cannot fetch runtime status of service {}: {}", self.service_id, err),
+        }
+
+        self
+    }
+}
+
+// Read from the kubernetes api only, the summary is computed for every service and must stay cheap
+async fn fetch_runtime_status(
+    kube: &kube::Client,
+    namespace: &str,
+    helm_release: Option<&str>,
+    selector: Option<&str>,
+) -> Result<(Option<u64>, Vec<Pod>, Vec<Event>), kube::Error> {
+    let secrets_api: Api<Secret> = Api::namespaced(kube.clone(), namespace);
+    let pods_api: Api<Pod> = Api::namespaced(kube.clone(), namespace);
+    let event_api: Api<Event> = Api::namespaced(kube.clone(), namespace);
+
+    // helm stores the deployed revision of a release into a secret, no need to spawn helm to get it
+    let helm_revision = async {
+        let release = match helm_release {
+            Some(release) => release,
+            None => return Ok(None),
+        };
+        let list_params = ListParams::default()
+            .labels(&format!("owner=helm,name={},status=deployed", release))
+            .timeout(15);
+        let secrets = secrets_api.list(&list_params).await?;
+        Ok::<_, kube::Error>(
+            secrets
+                .items
+                .iter()
+                .filter_map(|secret| secret.metadata.labels.as_ref()?.get("version")?.parse::<u64>().ok())
+                .max(),
+        )
+    };
+    let pods = async {
+        match selector {
+            Some(selector) => pods_api
+                .list(&ListParams::default().labels(selector).timeout(15))
+                .await
+                .map(|pods| pods.items),
+            None => Ok(vec![]),
+        }
+    };
+    let events = async {
+        match selector {
+            Some(_) => event_api
+                .list(&ListParams::default().fields("type=Warning").timeout(15))
+                .await
+                .map(|events| events.items),
+            None => Ok(vec![]),
+        }
+    };
+
+    futures::future::try_join3(helm_revision, pods, events).await
+}
+
+fn ready_replicas(pods: &[Pod]) -> Option<u32> {
+    if pods.is_empty() {
+        return None;
+    }
+
+    let ready = pods
+        .iter()
+        .filter(|pod| {
+            pod.status
+                .as_ref()
+                .and_then(|status| status.conditions.as_ref())
+                .map(|conditions| {
+                    conditions
+                        .iter()
+                        .any(|condition| condition.type_ == "Ready" && condition.status == "True")
+                })
+                .unwrap_or(false)
+        })
+        .count();
+
+    Some(ready as u32)
+}
+
+// Only keep warnings raised for the pods of the service, the namespace is shared with the rest of the environment
+fn warning_messages(pods: &[Pod], events: &[Event]) -> Vec<String> {
+    let pod_names: HashSet<&str> = pods.iter().filter_map(|pod| pod.metadata.name.as_deref()).collect();
+
+    events
+        .iter()
+        .filter(|ev| ev.type_.as_deref() == Some("Warning"))
+        .filter(|ev| {
+            ev.involved_object
+                .name
+                .as_deref()
+                .map(|name| pod_names.contains(name))
+                .unwrap_or(false)
+        })
+        .filter_map(|ev| ev.message.as_ref().map(|msg| msg.replace('\n', "")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_runtime_status() {
+        let pod = |name: &str, ready: &str| -> Pod {
+            serde_json::from_value(serde_json::json!({
+                "metadata": { "name": name },
+                "status": { "conditions": [{ "type": "Ready", "status": ready }] },
+            }))
+            .unwrap()
+        };
+
+        assert_eq!(ready_replicas(&[]), None);
+        assert_eq!(ready_replicas(&[pod("app-0", "True"), pod("app-1", "False")]), Some(1));
+        assert_eq!(ready_replicas(&[pod("app-0", "True"), pod("app-1", "True")]), Some(2));
+
+        let pods = vec![pod("app-0", "False")];
+        let event = |type_: &str, pod: &str, msg: &str| -> Event {
+            serde_json::from_value(serde_json::json!({
+                "metadata": {},
+                "type": type_,
+                "involvedObject": { "kind": "Pod", "name": pod },
+                "message": msg,
+            }))
+            .unwrap()
+        };
+        let events = vec![
+            event("Warning", "app-0", "Back-off restarting failed container"),
+            event("Normal", "app-0", "Pulled"),
+            event("Warning", "other-0", "FailedScheduling"),
+        ];
+
+        assert_eq!(
+            warning_messages(&pods, &events),
+            vec!["Back-off restarting failed container".to_string()]
+        );
+    }
+}
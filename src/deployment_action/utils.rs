@@ -2,17 +2,40 @@ use crate::build_platform::Image;
 use crate::cloud_provider::DeploymentTarget;
 use crate::cmd::command::CommandKiller;
 use crate::cmd::docker::ContainerImage;
+use crate::cmd::kubectl::{kubectl_exec_get_deployments, kubectl_exec_get_pods, kubectl_exec_get_statefulsets};
+use crate::cmd::structs::{KubernetesPod, KubernetesPodStatusPhase};
 use crate::container_registry::errors::ContainerRegistryError;
 use crate::deployment_report::logger::{EnvProgressLogger, EnvSuccessLogger};
-use crate::errors::EngineError;
+use crate::errors::{CommandError, EngineError};
 use crate::events::EventDetails;
+use crate::io_models::application::{PolicyExemption, Toleration};
 use crate::io_models::container::Registry;
+use crate::kubers_utils::kube_list_names_from_selector;
 use crate::models::container::QOVERY_MIRROR_REPOSITORY_NAME;
+use crate::runtime::block_on;
+use crate::unit_conversion::{any_to_mi, cpu_string_to_float};
+use k8s_openapi::api::admissionregistration::v1::MutatingWebhookConfiguration;
 use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
 use k8s_openapi::api::batch::v1::CronJob;
-use kube::api::ListParams;
+use k8s_openapi::api::core::v1::{ConfigMap, Node, PersistentVolumeClaim, Pod, Secret, Service};
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use kube::api::{ListParams, LogParams};
 use kube::Api;
-use std::time::Duration;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::time::{Duration, Instant};
+
+/// Relaxing cluster policies must never go unnoticed, so it is reported on every deployment
+pub fn log_policy_exemption(policy_exemption: Option<&PolicyExemption>, logger: &EnvProgressLogger) {
+    if let Some(exemption) = policy_exemption {
+        let exempted_keys = exemption.exempted_keys().join(", ");
+        warn!("Cluster policies exemption ({}) applied: {}", exempted_keys, exemption.reason);
+        logger.warning(format!(
+            "⚠️ This service is exempted from cluster policies ({}): {}",
+            exempted_keys, exemption.reason
+        ));
+    }
+}
 
 pub fn delete_cached_image(
     current_image_tag: String,
@@ -208,3 +231,684 @@ pub async fn get_last_deployed_image(
         }
     }
 }
+
+/// Cpu (in milli) and memory (in MiB) requests of the first container of the deployed service
+pub async fn get_last_deployed_resources(
+    client: kube::Client,
+    selector: &str,
+    service_type: KubeObjectKind,
+    namespace: &str,
+) -> Option<(u32, u32)> {
+    let list_params = ListParams::default().labels(selector);
+
+    let pod_spec = match service_type {
+        KubeObjectKind::Deployment => {
+            let api: Api<Deployment> = Api::namespaced(client, namespace);
+            api.list(&list_params)
+                .await
+                .ok()?
+                .items
+                .into_iter()
+                .next()?
+                .spec?
+                .template
+                .spec?
+        }
+        KubeObjectKind::Statefulset => {
+            let api: Api<StatefulSet> = Api::namespaced(client, namespace);
+            api.list(&list_params)
+                .await
+                .ok()?
+                .items
+                .into_iter()
+                .next()?
+                .spec?
+                .template
+                .spec?
+        }
+        KubeObjectKind::Job | KubeObjectKind::CronJob => return None,
+    };
+
+    let requests = pod_spec.containers.first()?.resources.as_ref()?.requests.as_ref()?;
+    let cpu = requests.get("cpu")?;
+    let memory = requests.get("memory")?;
+
+    Some((
+        (cpu_string_to_float(cpu.0.as_str()) * 1000.0).round() as u32,
+        any_to_mi(memory.0.as_str()),
+    ))
+}
+
+/// Resource requests drifting by more than the threshold from the deployed ones, when the image changes as well.
+/// Returns None if the deployment does not combine a version change with a significant resource change
+pub fn combined_change_drift(
+    last_image: &str,
+    new_image_tag: &str,
+    last_resources: (u32, u32),
+    new_resources: (u32, u32),
+    threshold_percent: u32,
+) -> Option<Vec<String>> {
+    if last_image.ends_with(&format!(":{}", new_image_tag)) {
+        return None;
+    }
+
+    let exceeds_threshold = |last: u32, new: u32| -> bool {
+        let diff = if new > last { new - last } else { last - new };
+        last > 0 && (diff as u64) * 100 > (last as u64) * (threshold_percent as u64)
+    };
+
+    let (last_cpu, last_ram) = last_resources;
+    let (new_cpu, new_ram) = new_resources;
+    let mut drift = vec![];
+    if exceeds_threshold(last_cpu, new_cpu) {
+        drift.push(format!("cpu request {}m -> {}m", last_cpu, new_cpu));
+    }
+    if exceeds_threshold(last_ram, new_ram) {
+        drift.push(format!("memory request {}Mi -> {}Mi", last_ram, new_ram));
+    }
+
+    if drift.is_empty() {
+        None
+    } else {
+        Some(drift)
+    }
+}
+
+const READINESS_ANNOTATION_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Whether a mutating webhook whose name starts with the prefix is registered in the cluster.
+/// Used to probe for optional controllers injecting sidecars, as they can be installed under a revision suffix
+pub fn is_mutating_webhook_installed(kube: &kube::Client, name_prefix: &str) -> Result<bool, kube::Error> {
+    let webhooks: Api<MutatingWebhookConfiguration> = Api::all(kube.clone());
+    let webhooks = block_on(webhooks.list(&ListParams::default()))?;
+
+    Ok(webhooks.items.iter().any(|webhook| {
+        webhook
+            .metadata
+            .name
+            .as_deref()
+            .map(|name| name.starts_with(name_prefix))
+            .unwrap_or(false)
+    }))
+}
+
+/// Whether the custom resource definition, i.e `verticalpodautoscalers.autoscaling.k8s.io`, exists in the cluster
+pub fn is_crd_installed(kube: &kube::Client, crd_name: &str) -> Result<bool, kube::Error> {
+    let crds: Api<CustomResourceDefinition> = Api::all(kube.clone());
+    match block_on(crds.get(crd_name)) {
+        Ok(_) => Ok(true),
+        Err(kube::Error::Api(err)) if err.code == 404 => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Whether the API group is served by the cluster, i.e by an aggregated API server such as a metrics adapter
+pub fn is_api_group_available(kube: &kube::Client, group_name: &str) -> Result<bool, kube::Error> {
+    let groups = block_on(kube.list_api_groups())?;
+
+    Ok(groups.groups.iter().any(|group| group.name == group_name))
+}
+
+/// Wait for an annotation, set by an external controller, to reach its expected value on the workloads matching the selector.
+/// On timeout, the error reports the value the annotation had at that time
+pub fn wait_for_readiness_annotation(
+    target: &DeploymentTarget,
+    namespace: &str,
+    selector: &str,
+    kind: KubeObjectKind,
+    (annotation, expected_value): (&str, &str),
+    timeout: Duration,
+    event_details: EventDetails,
+) -> Result<(), Box<EngineError>> {
+    let kubeconfig_path = target.kubeconfig_path()?;
+    let envs = target.kubectl_credentials();
+    let started_at = Instant::now();
+    let mut current_value = None;
+
+    while started_at.elapsed() < timeout {
+        if (target.should_abort)() {
+            return Err(Box::new(EngineError::new_task_cancellation_requested(event_details)));
+        }
+
+        let workloads_annotations: Result<Vec<HashMap<String, String>>, CommandError> = match kind {
+            KubeObjectKind::Statefulset => {
+                kubectl_exec_get_statefulsets(&kubeconfig_path, Some(namespace), Some(selector), envs.clone())
+                    .map(|list| list.items.into_iter().map(|s| s.metadata.annotations).collect())
+            }
+            _ => kubectl_exec_get_deployments(&kubeconfig_path, Some(namespace), Some(selector), envs.clone())
+                .map(|list| list.items.into_iter().map(|d| d.metadata.annotations).collect()),
+        };
+
+        match workloads_annotations {
+            Ok(workloads_annotations) => {
+                match readiness_annotation_state(&workloads_annotations, annotation, expected_value) {
+                    Ok(()) => return Ok(()),
+                    Err(value) => current_value = value,
+                }
+            }
+            Err(err) => warn!("cannot retrieve annotations of workloads {}: {}", selector, err),
+        }
+
+        std::thread::sleep(READINESS_ANNOTATION_CHECK_INTERVAL);
+    }
+
+    Err(Box::new(EngineError::new_readiness_annotation_timeout(
+        event_details,
+        annotation.to_string(),
+        expected_value.to_string(),
+        current_value,
+        timeout,
+    )))
+}
+
+// Ready once every workload holds the expected value, otherwise returns the first value not matching it
+fn readiness_annotation_state(
+    workloads_annotations: &[HashMap<String, String>],
+    annotation: &str,
+    expected_value: &str,
+) -> Result<(), Option<String>> {
+    if workloads_annotations.is_empty() {
+        return Err(None);
+    }
+
+    match workloads_annotations
+        .iter()
+        .map(|annotations| annotations.get(annotation))
+        .find(|value| value.map(|v| v.as_str()) != Some(expected_value))
+    {
+        None => Ok(()),
+        Some(value) => Err(value.cloned()),
+    }
+}
+
+const SCALING_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+// Terminating pods are still running and billed, only completed ones are gone
+fn nb_running_pods(pods: &[KubernetesPod]) -> usize {
+    pods.iter()
+        .filter(|pod| {
+            !matches!(
+                pod.status.phase,
+                KubernetesPodStatusPhase::Succeeded | KubernetesPodStatusPhase::Failed
+            )
+        })
+        .count()
+}
+
+/// Wait for the pods matching the selector to reach the expected number, terminating ones included.
+/// Scaling a workload only updates its spec, this confirms the pods have actually been stopped or started
+pub fn wait_until_scaled_to(
+    target: &DeploymentTarget,
+    namespace: &str,
+    selector: &str,
+    expected_replicas: usize,
+    timeout: Duration,
+    event_details: EventDetails,
+) -> Result<(), Box<EngineError>> {
+    let kubeconfig_path = target.kubeconfig_path()?;
+    let envs = target.kubectl_credentials();
+    let started_at = Instant::now();
+    let mut nb_pods = None;
+
+    while started_at.elapsed() < timeout {
+        if (target.should_abort)() {
+            return Err(Box::new(EngineError::new_task_cancellation_requested(event_details)));
+        }
+
+        match kubectl_exec_get_pods(&kubeconfig_path, Some(namespace), Some(selector), envs.clone()) {
+            Ok(pods) if nb_running_pods(&pods.items) == expected_replicas => return Ok(()),
+            Ok(pods) => nb_pods = Some(nb_running_pods(&pods.items)),
+            Err(err) => warn!("cannot retrieve pods {}: {}", selector, err),
+        }
+
+        std::thread::sleep(SCALING_CHECK_INTERVAL);
+    }
+
+    let reason = match nb_pods {
+        Some(nb_pods) => format!(
+            "{} pods still running after {}s, expected {}",
+            nb_pods,
+            timeout.as_secs(),
+            expected_replicas
+        ),
+        None => format!("cannot retrieve pods within {}s", timeout.as_secs()),
+    };
+    Err(Box::new(EngineError::new_k8s_scale_replicas(
+        event_details,
+        selector.to_string(),
+        namespace.to_string(),
+        expected_replicas as u32,
+        CommandError::new_from_safe_message(reason),
+    )))
+}
+
+// Finalizers and pods termination can take a while, resources are expected to be gone within this delay
+const CLEANUP_VERIFICATION_DEFAULT_TIMEOUT_SECONDS: u64 = 60;
+const CLEANUP_VERIFICATION_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Delay to wait for resources of a deleted service to be gone, 0 disables the verification
+pub fn default_cleanup_verification_timeout() -> Duration {
+    let timeout_seconds = match env::var("CLEANUP_VERIFICATION_TIMEOUT_SECONDS") {
+        Ok(env_var) => env_var
+            .parse::<u64>()
+            .unwrap_or(CLEANUP_VERIFICATION_DEFAULT_TIMEOUT_SECONDS),
+        Err(_) => CLEANUP_VERIFICATION_DEFAULT_TIMEOUT_SECONDS,
+    };
+
+    Duration::from_secs(timeout_seconds)
+}
+
+fn remaining_resources(kube: &kube::Client, namespace: &str, selector: &str) -> Result<Vec<String>, kube::Error> {
+    block_on(async {
+        let mut resources = vec![];
+        resources.extend(kube_list_names_from_selector::<Deployment>(kube, selector, namespace).await?);
+        resources.extend(kube_list_names_from_selector::<StatefulSet>(kube, selector, namespace).await?);
+        resources.extend(kube_list_names_from_selector::<Pod>(kube, selector, namespace).await?);
+        resources.extend(kube_list_names_from_selector::<Service>(kube, selector, namespace).await?);
+        resources.extend(kube_list_names_from_selector::<Secret>(kube, selector, namespace).await?);
+        resources.extend(kube_list_names_from_selector::<ConfigMap>(kube, selector, namespace).await?);
+        resources.extend(kube_list_names_from_selector::<PersistentVolumeClaim>(kube, selector, namespace).await?);
+
+        Ok(resources)
+    })
+}
+
+// Number of log lines of the failing replica reported in the error
+const STATEFULSET_ROLLOUT_LOG_LINES: i64 = 50;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PartialRollout {
+    statefulset_name: String,
+    ready_replicas: u32,
+    replicas: u32,
+    failed_ordinal: u32,
+}
+
+pub fn is_pod_ready(pod: &Pod) -> bool {
+    pod.status
+        .as_ref()
+        .and_then(|status| status.conditions.as_ref())
+        .map(|conditions| conditions.iter().any(|c| c.type_ == "Ready" && c.status == "True"))
+        .unwrap_or(false)
+}
+
+// Replicas are rolled in order, so the first replica which is not ready is the one blocking the rollout
+fn partial_rollout(statefulset: &StatefulSet, pods: &[Pod]) -> Option<PartialRollout> {
+    let statefulset_name = statefulset.metadata.name.clone()?;
+    let replicas = statefulset
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.replicas)
+        .unwrap_or(1)
+        .max(0) as u32;
+    let ready_replicas = statefulset
+        .status
+        .as_ref()
+        .and_then(|status| status.ready_replicas)
+        .unwrap_or(0)
+        .max(0) as u32;
+    if ready_replicas >= replicas {
+        return None;
+    }
+
+    let failed_ordinal = (0..replicas).find(|ordinal| {
+        let pod_name = format!("{}-{}", statefulset_name, ordinal);
+        !pods
+            .iter()
+            .any(|pod| pod.metadata.name.as_deref() == Some(pod_name.as_str()) && is_pod_ready(pod))
+    })?;
+
+    Some(PartialRollout {
+        statefulset_name,
+        ready_replicas,
+        replicas,
+        failed_ordinal,
+    })
+}
+
+/// Verify that all the replicas of the statefulsets matching the selector are ready.
+/// On a partial rollout, the error reports the first replica which is not ready, along with its logs
+pub fn check_statefulset_rollout(
+    target: &DeploymentTarget,
+    namespace: &str,
+    selector: &str,
+    event_details: EventDetails,
+) -> Result<(), Box<EngineError>> {
+    let statefulsets_api: Api<StatefulSet> = Api::namespaced(target.kube.clone(), namespace);
+    let pods_api: Api<Pod> = Api::namespaced(target.kube.clone(), namespace);
+    let list_params = ListParams::default().labels(selector);
+    let (statefulsets, pods) = match block_on(futures::future::try_join(
+        statefulsets_api.list(&list_params),
+        pods_api.list(&list_params),
+    )) {
+        Ok((statefulsets, pods)) => (statefulsets.items, pods.items),
+        Err(err) => {
+            warn!("cannot check rollout of statefulsets {}: {}", selector, err);
+            return Ok(());
+        }
+    };
+
+    let rollout = match statefulsets.iter().find_map(|sts| partial_rollout(sts, &pods)) {
+        Some(rollout) => rollout,
+        None => return Ok(()),
+    };
+
+    let pod_name = format!("{}-{}", rollout.statefulset_name, rollout.failed_ordinal);
+    let log_params = LogParams {
+        tail_lines: Some(STATEFULSET_ROLLOUT_LOG_LINES),
+        ..Default::default()
+    };
+    let pod_logs = match block_on(pods_api.logs(&pod_name, &log_params)) {
+        Ok(logs) => logs,
+        Err(err) => format!("cannot retrieve logs of pod {}: {}", pod_name, err),
+    };
+
+    Err(Box::new(EngineError::new_statefulset_partial_rollout(
+        event_details,
+        rollout.statefulset_name,
+        rollout.failed_ordinal,
+        rollout.ready_replicas,
+        rollout.replicas,
+        pod_logs,
+    )))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NodePoolIssue {
+    NoNode,
+    AllNodesCordoned,
+    UntoleratedTaints(Vec<String>),
+}
+
+// Only taints with a NoSchedule or NoExecute effect keep the pods out of a node
+fn node_pool_issue(nodes: &[Node], tolerations: &[Toleration]) -> Option<NodePoolIssue> {
+    if nodes.is_empty() {
+        return Some(NodePoolIssue::NoNode);
+    }
+
+    let schedulable_nodes: Vec<&Node> = nodes
+        .iter()
+        .filter(|node| !node.spec.as_ref().and_then(|spec| spec.unschedulable).unwrap_or(false))
+        .collect();
+    if schedulable_nodes.is_empty() {
+        return Some(NodePoolIssue::AllNodesCordoned);
+    }
+
+    let untolerated_taints = |node: &Node| -> Vec<String> {
+        node.spec
+            .as_ref()
+            .and_then(|spec| spec.taints.as_ref())
+            .map(|taints| {
+                taints
+                    .iter()
+                    .filter(|taint| taint.effect == "NoSchedule" || taint.effect == "NoExecute")
+                    .filter(|taint| {
+                        let value = taint.value.as_deref().unwrap_or_default();
+                        !tolerations
+                            .iter()
+                            .any(|t| t.tolerates(&taint.key, value, &taint.effect))
+                    })
+                    .map(|taint| match &taint.value {
+                        Some(value) => format!("{}={}:{}", taint.key, value, taint.effect),
+                        None => format!("{}:{}", taint.key, taint.effect),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let mut taints: Vec<String> = vec![];
+    for node in schedulable_nodes {
+        let node_taints = untolerated_taints(node);
+        if node_taints.is_empty() {
+            return None;
+        }
+        taints.extend(node_taints);
+    }
+    taints.sort();
+    taints.dedup();
+
+    Some(NodePoolIssue::UntoleratedTaints(taints))
+}
+
+/// Warn when no node of the pool targeted by the node selector can currently run the service pods.
+/// Pods then stay pending until the cluster autoscaler scales the pool up, which can take several minutes,
+/// or forever if the pool cannot host them. The check never fails the deployment, as the pool may scale up
+pub fn check_node_pool_capacity(
+    kube: &kube::Client,
+    node_selector: &BTreeMap<String, String>,
+    tolerations: &[Toleration],
+    logger: &EnvProgressLogger,
+) {
+    if node_selector.is_empty() {
+        return;
+    }
+
+    let selector = node_selector
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<String>>()
+        .join(",");
+    let nodes_api: Api<Node> = Api::all(kube.clone());
+    let nodes = match block_on(nodes_api.list(&ListParams::default().labels(&selector))) {
+        Ok(nodes) => nodes.items,
+        Err(err) => {
+            warn!("cannot list nodes matching {}: {}", selector, err);
+            return;
+        }
+    };
+
+    match node_pool_issue(&nodes, tolerations) {
+        None => {}
+        Some(NodePoolIssue::NoNode) => logger.warning(format!(
+            "⏳ No node currently matches the node selector `{}`. Pods will stay pending until the node pool is scaled up by the cluster autoscaler, which can take several minutes",
+            selector
+        )),
+        Some(NodePoolIssue::AllNodesCordoned) => logger.warning(format!(
+            "⏳ Every node matching the node selector `{}` is cordoned. Pods will stay pending until a new node joins the pool",
+            selector
+        )),
+        Some(NodePoolIssue::UntoleratedTaints(taints)) => logger.warning(format!(
+            "⚠️ Nodes matching the node selector `{}` have taints not tolerated by the service: {}. Pods will stay pending unless matching tolerations are added",
+            selector,
+            taints.join(", ")
+        )),
+    }
+}
+
+/// Verify that no resource matching the selectors remains once a service has been deleted.
+/// Resources can be left behind by finalizers or orphaned volumes, and conflict with a future deployment.
+/// If the resources cannot be listed, the verification is skipped as the deletion itself succeeded
+pub fn verify_cleanup(
+    target: &DeploymentTarget,
+    namespace: &str,
+    selectors: &[&str],
+    event_details: EventDetails,
+) -> Result<(), Box<EngineError>> {
+    let timeout = default_cleanup_verification_timeout();
+    if timeout.is_zero() || target.is_dry_run_deploy {
+        return Ok(());
+    }
+
+    let started_at = Instant::now();
+    loop {
+        let mut remaining = vec![];
+        for selector in selectors {
+            match remaining_resources(&target.kube, namespace, selector) {
+                Ok(resources) => remaining.extend(resources),
+                Err(err) => {
+                    warn!("cannot verify cleanup of resources {}: {}", selector, err);
+                    return Ok(());
+                }
+            }
+        }
+
+        if remaining.is_empty() {
+            return Ok(());
+        }
+
+        if started_at.elapsed() >= timeout || (target.should_abort)() {
+            remaining.sort();
+            remaining.dedup();
+            return Err(Box::new(EngineError::new_incomplete_cleanup(event_details, remaining)));
+        }
+
+        std::thread::sleep(CLEANUP_VERIFICATION_CHECK_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deployment_action::test_utils::get_simple_statefulset;
+
+    #[test]
+    fn test_nb_running_pods() {
+        let pod = |name: &str, phase: &str| -> KubernetesPod {
+            serde_json::from_value(serde_json::json!({
+                "metadata": { "name": name, "namespace": "default" },
+                "status": { "phase": phase },
+                "spec": {},
+            }))
+            .unwrap()
+        };
+
+        assert_eq!(nb_running_pods(&[]), 0);
+        assert_eq!(
+            nb_running_pods(&[
+                pod("terminating", "Running"),
+                pod("starting", "Pending"),
+                pod("completed", "Succeeded"),
+                pod("crashed", "Failed"),
+            ]),
+            2
+        );
+    }
+
+    #[test]
+    fn test_readiness_annotation_state() {
+        let annotations = |value: Option<&str>| -> HashMap<String, String> {
+            value
+                .map(|v| HashMap::from([("example.com/ready".to_string(), v.to_string())]))
+                .unwrap_or_default()
+        };
+
+        assert_eq!(readiness_annotation_state(&[], "example.com/ready", "true"), Err(None));
+        assert_eq!(
+            readiness_annotation_state(&[annotations(None)], "example.com/ready", "true"),
+            Err(None)
+        );
+        assert_eq!(
+            readiness_annotation_state(
+                &[annotations(Some("true")), annotations(Some("progressing"))],
+                "example.com/ready",
+                "true"
+            ),
+            Err(Some("progressing".to_string()))
+        );
+        assert_eq!(
+            readiness_annotation_state(
+                &[annotations(Some("true")), annotations(Some("true"))],
+                "example.com/ready",
+                "true"
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_statefulset_partial_rollout() {
+        let mut statefulset = get_simple_statefulset();
+        statefulset.spec.as_mut().unwrap().replicas = Some(3);
+        statefulset.status = serde_json::from_value(serde_json::json!({ "replicas": 3, "readyReplicas": 1 })).unwrap();
+        let pod = |name: &str, ready: &str| -> Pod {
+            serde_json::from_value(serde_json::json!({
+                "metadata": { "name": name },
+                "status": { "conditions": [{ "type": "Ready", "status": ready }] },
+            }))
+            .unwrap()
+        };
+
+        assert_eq!(
+            partial_rollout(&statefulset, &[pod("pause-0", "True"), pod("pause-1", "False")]),
+            Some(PartialRollout {
+                statefulset_name: "pause".to_string(),
+                ready_replicas: 1,
+                replicas: 3,
+                failed_ordinal: 1,
+            })
+        );
+
+        statefulset.status = serde_json::from_value(serde_json::json!({ "replicas": 3, "readyReplicas": 3 })).unwrap();
+        assert_eq!(partial_rollout(&statefulset, &[]), None);
+    }
+
+    #[test]
+    fn test_combined_change_drift() {
+        let last_image = "registry.local/qovery-mirror:nginx.1.0";
+
+        // same version, only resources change
+        assert_eq!(
+            combined_change_drift(last_image, "nginx.1.0", (500, 256), (2000, 1024), 20),
+            None
+        );
+        // new version, resources change below the threshold
+        assert_eq!(combined_change_drift(last_image, "nginx.1.1", (500, 256), (550, 256), 20), None);
+        // new version and significant resources change
+        assert_eq!(
+            combined_change_drift(last_image, "nginx.1.1", (500, 256), (1000, 256), 20),
+            Some(vec!["cpu request 500m -> 1000m".to_string()])
+        );
+        assert_eq!(
+            combined_change_drift(last_image, "nginx.1.1", (500, 256), (100, 512), 20),
+            Some(vec![
+                "cpu request 500m -> 100m".to_string(),
+                "memory request 256Mi -> 512Mi".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_node_pool_issue() {
+        let node = |unschedulable: bool, taints: serde_json::Value| -> Node {
+            serde_json::from_value(serde_json::json!({
+                "metadata": { "name": "gpu-node" },
+                "spec": { "unschedulable": unschedulable, "taints": taints }
+            }))
+            .unwrap()
+        };
+        let gpu_taint = serde_json::json!([{ "key": "dedicated", "value": "gpu", "effect": "NoSchedule" }]);
+        let gpu_toleration = Toleration {
+            key: "dedicated".to_string(),
+            operator: "Equal".to_string(),
+            value: "gpu".to_string(),
+            effect: "NoSchedule".to_string(),
+        };
+
+        assert_eq!(node_pool_issue(&[], &[]), Some(NodePoolIssue::NoNode));
+        assert_eq!(
+            node_pool_issue(&[node(true, serde_json::json!([]))], &[]),
+            Some(NodePoolIssue::AllNodesCordoned)
+        );
+        assert_eq!(
+            node_pool_issue(&[node(false, gpu_taint.clone())], &[]),
+            Some(NodePoolIssue::UntoleratedTaints(vec!["dedicated=gpu:NoSchedule".to_string()]))
+        );
+        assert_eq!(node_pool_issue(&[node(false, gpu_taint.clone())], &[gpu_toleration]), None);
+        assert_eq!(
+            node_pool_issue(&[node(true, serde_json::json!([])), node(false, gpu_taint)], &[]),
+            Some(NodePoolIssue::UntoleratedTaints(vec!["dedicated=gpu:NoSchedule".to_string()]))
+        );
+        // PreferNoSchedule taints do not keep the pods out
+        assert_eq!(
+            node_pool_issue(
+                &[node(
+                    false,
+                    serde_json::json!([{ "key": "spot", "effect": "PreferNoSchedule" }])
+                )],
+                &[]
+            ),
+            None
+        );
+    }
+}
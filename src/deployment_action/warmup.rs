@@ -0,0 +1,117 @@
+use crate::deployment_action::utils::is_pod_ready;
+use crate::io_models::application::WarmupRequest;
+use crate::runtime::block_on;
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::ListParams;
+use kube::Api;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+/// Local port forwarding every accepted connection to a pod port, stopped when dropped
+pub(super) struct PodPortForward {
+    pub(super) local_port: u16,
+    listener_task: JoinHandle<()>,
+}
+
+impl Drop for PodPortForward {
+    fn drop(&mut self) {
+        self.listener_task.abort();
+    }
+}
+
+pub(super) async fn forward_pod_port(
+    pods: Api<Pod>,
+    pod_name: String,
+    port: u16,
+) -> Result<PodPortForward, std::io::Error> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let local_port = listener.local_addr()?.port();
+
+    // a kube port-forward only carries a single connection, so open a new one for each of them
+    let listener_task = tokio::spawn(async move {
+        while let Ok((mut connection, _)) = listener.accept().await {
+            let pods = pods.clone();
+            let pod_name = pod_name.clone();
+            tokio::spawn(async move {
+                let mut forwarder = match pods.portforward(&pod_name, &[port]).await {
+                    Ok(forwarder) => forwarder,
+                    Err(err) => {
+                        warn!("cannot forward port {} of pod {}: {}", port, pod_name, err);
+                        return;
+                    }
+                };
+                if let Some(mut upstream) = forwarder.take_stream(port) {
+                    let _ = tokio::io::copy_bidirectional(&mut connection, &mut upstream).await;
+                }
+            });
+        }
+    });
+
+    Ok(PodPortForward {
+        local_port,
+        listener_task,
+    })
+}
+
+async fn send_warmup_request(client: &reqwest::Client, local_port: u16, request: &WarmupRequest) -> Result<(), String> {
+    let method = reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|e| e.to_string())?;
+    let response = client
+        .request(method, format!("http://127.0.0.1:{}{}", local_port, request.path))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match response.status().is_success() {
+        true => Ok(()),
+        false => Err(format!("unexpected status {}", response.status())),
+    }
+}
+
+/// Send the warmup requests to every ready pod matching the selector. Requests go through a port-forward
+/// so each new pod is warmed up, instead of the ones the load balancer would pick.
+/// Returns the description of every request which failed
+pub fn warmup_pods(
+    kube: &kube::Client,
+    namespace: &str,
+    selector: &str,
+    port: u16,
+    requests: &[WarmupRequest],
+    timeout: Duration,
+) -> Vec<String> {
+    block_on(async {
+        let client = match reqwest::Client::builder().timeout(timeout).build() {
+            Ok(client) => client,
+            Err(err) => return vec![format!("cannot create http client: {}", err)],
+        };
+        let pods: Api<Pod> = Api::namespaced(kube.clone(), namespace);
+        let pod_names: Vec<String> = match pods.list(&ListParams::default().labels(selector)).await {
+            Ok(pods) => pods
+                .items
+                .into_iter()
+                .filter(is_pod_ready)
+                .filter_map(|pod| pod.metadata.name)
+                .collect(),
+            Err(err) => return vec![format!("cannot list pods to warm up: {}", err)],
+        };
+
+        let mut failures = vec![];
+        for pod_name in pod_names {
+            let port_forward = match forward_pod_port(pods.clone(), pod_name.clone(), port).await {
+                Ok(port_forward) => port_forward,
+                Err(err) => {
+                    failures.push(format!("{}: cannot forward port {}: {}", pod_name, port, err));
+                    continue;
+                }
+            };
+
+            for request in requests {
+                if let Err(err) = send_warmup_request(&client, port_forward.local_port, request).await {
+                    failures.push(format!("{}: {} {}: {}", pod_name, request.method, request.path, err));
+                }
+            }
+        }
+
+        failures
+    })
+}
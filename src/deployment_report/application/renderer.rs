@@ -2,9 +2,11 @@ use crate::cloud_provider::service::ServiceType;
 use crate::deployment_report::application::reporter::AppDeploymentReport;
 use crate::deployment_report::utils::{
     get_tera_instance, to_pods_render_context, to_pvc_render_context, to_services_render_context, PodRenderContext,
-    PvcRenderContext, ServiceRenderContext,
+    PvcRenderContext, QPodExt, ServiceRenderContext,
 };
 use crate::utilities::to_short_id;
+use itertools::Itertools;
+use k8s_openapi::api::core::v1::Pod;
 use serde::Serialize;
 
 #[derive(Debug, Serialize)]
@@ -18,6 +20,7 @@ pub struct AppDeploymentRenderContext {
     pub pods_failing: Vec<PodRenderContext>,
     pub pods_starting: Vec<PodRenderContext>,
     pub pods_terminating: Vec<PodRenderContext>,
+    pub pods_restarted: Vec<PodRenderContext>,
     pub pvcs: Vec<PvcRenderContext>,
 }
 
@@ -41,6 +44,9 @@ const REPORT_TEMPLATE: &str = r#"
 ┃     |__ {{ event.type_ | fmt_event_type }} {{ event.message }}
 {%- endfor -%}
 {%- endfor %}
+{%- for pod in pods_restarted %}
+┃  |__ ⚠️ Pod {{ pod.name }} restarted {{ pod.restart_count }} times {{ pod.message }}
+{%- endfor %}
 ┃
 {%- for pvc in pvcs %}
 ┃ 💽 Network volume {{ pvc.name }} is {{ pvc.state | upper }}
@@ -58,8 +64,9 @@ pub(super) fn render_app_deployment_report(
     deployment_info: &AppDeploymentReport,
 ) -> Result<String, tera::Error> {
     let services_ctx = to_services_render_context(&deployment_info.services, &deployment_info.events);
-    let (pods_starting, pods_terminating, pods_failing, _) =
+    let (pods_starting, pods_terminating, pods_failing, pods_running) =
         to_pods_render_context(&deployment_info.pods, &deployment_info.events);
+    let pods_restarted = to_pods_restarted_render_context(pods_running, &deployment_info.pods);
     let pvcs_ctx = to_pvc_render_context(&deployment_info.pvcs, &deployment_info.events);
     let render_ctx = AppDeploymentRenderContext {
         name: to_short_id(&deployment_info.id),
@@ -76,22 +83,46 @@ pub(super) fn render_app_deployment_report(
         pods_failing,
         pods_starting,
         pods_terminating,
+        pods_restarted,
         pvcs: pvcs_ctx,
     };
     let ctx = tera::Context::from_serialize(render_ctx)?;
     get_tera_instance().render_str(REPORT_TEMPLATE, &ctx)
 }
 
+// A crash looping pod is seen running between two restarts, so running pods are reported as soon as they restarted
+fn to_pods_restarted_render_context(pods_running: Vec<PodRenderContext>, pods: &[Pod]) -> Vec<PodRenderContext> {
+    pods_running
+        .into_iter()
+        .filter(|pod_ctx| pod_ctx.restart_count > 0)
+        .map(|mut pod_ctx| {
+            let containers = pods
+                .iter()
+                .find(|pod| pod.metadata.name.as_deref() == Some(pod_ctx.name.as_str()))
+                .map(|pod| pod.containers_restart_count())
+                .unwrap_or_default();
+            pod_ctx.message = Some(
+                containers
+                    .iter()
+                    .map(|(container, restart_count)| format!("(container {}: {})", container, restart_count))
+                    .join(" "),
+            );
+            pod_ctx
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use crate::cloud_provider::service::ServiceType;
     use crate::deployment_report::application::renderer::{
-        AppDeploymentRenderContext, ServiceRenderContext, REPORT_TEMPLATE,
+        to_pods_restarted_render_context, AppDeploymentRenderContext, ServiceRenderContext, REPORT_TEMPLATE,
     };
     use crate::deployment_report::utils::{
         fmt_event_type, DeploymentState, EventRenderContext, PodRenderContext, PvcRenderContext,
     };
     use crate::utilities::to_short_id;
+    use k8s_openapi::api::core::v1::Pod;
     use tera::Tera;
     use uuid::Uuid;
 
@@ -168,6 +199,13 @@ mod test {
                 restart_count: 0,
                 events: vec![],
             }],
+            pods_restarted: vec![PodRenderContext {
+                name: "app-pod-5".to_string(),
+                state: DeploymentState::Starting,
+                message: Some("(container app: 2)".to_string()),
+                restart_count: 2,
+                events: vec![],
+            }],
             pvcs: vec![
                 PvcRenderContext {
                 name: "pvc-1212".to_string(),
@@ -209,6 +247,7 @@ mod test {
 ┃     |__ 💢 Pod crashed 1 times
 ┃     |__ ℹ️ Pulling image :P
 ┃     |__ ⚠️ Container started
+┃  |__ ⚠️ Pod app-pod-5 restarted 2 times (container app: 2)
 ┃
 ┃ 💽 Network volume pvc-1212 is STARTING
 ┃  |__ ⚠️ Failed to provision volume with StorageClass "aws-ebs-io1-0": InvalidParameterValue: The volume size is invalid for io1 volumes: 1 GiB. io1 volumes must be at least 4 GiB in size. Please specify a volume size above the minimum limit
@@ -221,4 +260,31 @@ mod test {
             assert_eq!(rendered_line.trim_end(), gold_line);
         }
     }
+
+    #[test]
+    fn test_pods_restarted_render_context() {
+        let pod: Pod = serde_json::from_value(serde_json::json!({
+            "metadata": { "name": "app-pod-1" },
+            "status": {
+                "containerStatuses": [
+                    { "name": "app", "image": "app:1.0", "imageID": "", "ready": true, "restartCount": 3 },
+                    { "name": "sidecar", "image": "sidecar:1.0", "imageID": "", "ready": true, "restartCount": 0 }
+                ]
+            }
+        }))
+        .unwrap();
+        let pod_ctx = |name: &str, restart_count: u32| PodRenderContext {
+            name: name.to_string(),
+            state: DeploymentState::Starting,
+            message: None,
+            restart_count,
+            events: vec![],
+        };
+
+        let pods_restarted =
+            to_pods_restarted_render_context(vec![pod_ctx("app-pod-1", 3), pod_ctx("app-pod-2", 0)], &[pod]);
+        assert_eq!(pods_restarted.len(), 1);
+        assert_eq!(pods_restarted[0].name, "app-pod-1");
+        assert_eq!(pods_restarted[0].message.as_deref(), Some("(container app: 3)"));
+    }
 }
@@ -1,6 +1,9 @@
 use crate::cloud_provider::service::{Action, ServiceType};
 use crate::cloud_provider::DeploymentTarget;
 use crate::deployment_report::application::renderer::render_app_deployment_report;
+use crate::deployment_report::diagnostics::{
+    describe_non_ready_pods, describe_pods_in_debug_logs, fetch_pod_diagnostics,
+};
 use crate::deployment_report::logger::EnvLogger;
 use crate::deployment_report::DeploymentReporter;
 use crate::errors::EngineError;
@@ -10,9 +13,10 @@ use crate::models::container::ContainerService;
 use crate::runtime::block_on;
 use crate::utilities::to_short_id;
 use k8s_openapi::api::core::v1::{Event, PersistentVolumeClaim, Pod, Service};
-use kube::api::ListParams;
-use kube::Api;
-
+use kube::api::{ListParams, ObjectList};
+use kube::{Api, Resource};
+use std::collections::HashSet;
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 use uuid::Uuid;
 
@@ -24,7 +28,10 @@ pub struct ApplicationDeploymentReporter<T> {
     tag: String,
     namespace: String,
     kube_client: kube::Client,
-    selector: String,
+    // to describe the pods which are not ready when the deployment fails
+    kubeconfig_path: Option<PathBuf>,
+    kubectl_credentials: Vec<(String, String)>,
+    selectors: Vec<String>,
     logger: EnvLogger,
     _tag: std::marker::PhantomData<T>,
 }
@@ -39,9 +46,11 @@ impl<T> ApplicationDeploymentReporter<T> {
             long_id: *app.long_id(),
             service_type: ServiceType::Application,
             tag: app.get_build().git_repository.commit_id.clone(),
-            namespace: deployment_target.environment.namespace().to_string(),
+            namespace: app.namespace(deployment_target.environment),
             kube_client: deployment_target.kube.clone(),
-            selector: app.selector().unwrap_or_default(),
+            kubeconfig_path: deployment_target.kubeconfig_path().ok(),
+            kubectl_credentials: owned_credentials(deployment_target),
+            selectors: app.selectors(),
             logger: deployment_target.env_logger(app, action.to_environment_step()),
             _tag: Default::default(),
         }
@@ -58,7 +67,9 @@ impl<T> ApplicationDeploymentReporter<T> {
             tag: container.image_full(),
             namespace: deployment_target.environment.namespace().to_string(),
             kube_client: deployment_target.kube.clone(),
-            selector: container.selector().unwrap_or_default(),
+            kubeconfig_path: deployment_target.kubeconfig_path().ok(),
+            kubectl_credentials: owned_credentials(deployment_target),
+            selectors: container.selectors(),
             logger: deployment_target.env_logger(container, action.to_environment_step()),
             _tag: Default::default(),
         }
@@ -82,7 +93,7 @@ impl<T: Send + Sync> DeploymentReporter for ApplicationDeploymentReporter<T> {
         if let Ok(deployment_info) = block_on(fetch_app_deployment_report(
             &self.kube_client,
             &self.long_id,
-            &self.selector,
+            &self.selectors,
             &self.namespace,
         )) {
             self.logger.send_progress(format!(
@@ -102,7 +113,7 @@ impl<T: Send + Sync> DeploymentReporter for ApplicationDeploymentReporter<T> {
         let report = match block_on(fetch_app_deployment_report(
             &self.kube_client,
             &self.long_id,
-            &self.selector,
+            &self.selectors,
             &self.namespace,
         )) {
             Ok(deployment_info) => deployment_info,
@@ -175,6 +186,26 @@ Look at the report from above to understand why, and check your applications log
                 None,
             ));
         } else {
+            // best effort, the deployment already failed
+            let mut debug_lines =
+                match block_on(fetch_pod_diagnostics(&self.kube_client, &self.namespace, &self.selectors)) {
+                    Ok(diagnostics) => diagnostics.to_lines(),
+                    Err(_) => vec![],
+                };
+            if let (true, Some(kubeconfig_path)) = (describe_pods_in_debug_logs(), &self.kubeconfig_path) {
+                let kubectl_credentials = self
+                    .kubectl_credentials
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.as_str()))
+                    .collect();
+                debug_lines.extend(describe_non_ready_pods(
+                    kubeconfig_path,
+                    kubectl_credentials,
+                    &self.namespace,
+                    &self.selectors,
+                ));
+            }
+            self.logger.send_debug_lines(&debug_lines);
             self.logger.send_error(*error.clone());
             self.logger.send_error(EngineError::new_engine_error(
                 *error.clone(),
@@ -188,6 +219,14 @@ Look at the report from above to understand why, and check your applications log
     }
 }
 
+fn owned_credentials(deployment_target: &DeploymentTarget) -> Vec<(String, String)> {
+    deployment_target
+        .kubectl_credentials()
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
 #[derive(Debug)]
 pub(super) struct AppDeploymentReport {
     pub id: Uuid,
@@ -197,10 +236,23 @@ pub(super) struct AppDeploymentReport {
     pub events: Vec<Event>,
 }
 
+// Objects matched by several selectors are kept once, so their events are not reported twice
+fn dedup_by_uid<K: Resource>(objects: Vec<ObjectList<K>>) -> Vec<K> {
+    let mut uids = HashSet::new();
+    objects
+        .into_iter()
+        .flat_map(|list| list.items)
+        .filter(|object| match &object.meta().uid {
+            Some(uid) => uids.insert(uid.clone()),
+            None => true,
+        })
+        .collect()
+}
+
 async fn fetch_app_deployment_report(
     kube: &kube::Client,
     service_id: &Uuid,
-    selector: &str,
+    selectors: &[String],
     namespace: &str,
 ) -> Result<AppDeploymentReport, kube::Error> {
     let pods_api: Api<Pod> = Api::namespaced(kube.clone(), namespace);
@@ -208,19 +260,42 @@ async fn fetch_app_deployment_report(
     let pvc_api: Api<PersistentVolumeClaim> = Api::namespaced(kube.clone(), namespace);
     let event_api: Api<Event> = Api::namespaced(kube.clone(), namespace);
 
-    let list_params = ListParams::default().labels(selector).timeout(15);
-    let pods = pods_api.list(&list_params);
-    let services = svc_api.list(&list_params);
-    let pvcs = pvc_api.list(&list_params);
+    let list_params: Vec<ListParams> = selectors
+        .iter()
+        .map(|selector| ListParams::default().labels(selector).timeout(15))
+        .collect();
+    let pods = futures::future::try_join_all(list_params.iter().map(|params| pods_api.list(params)));
+    let services = futures::future::try_join_all(list_params.iter().map(|params| svc_api.list(params)));
+    let pvcs = futures::future::try_join_all(list_params.iter().map(|params| pvc_api.list(params)));
     let events_params = ListParams::default().timeout(15);
     let events = event_api.list(&events_params);
     let (pods, services, pvcs, events) = futures::future::try_join4(pods, services, pvcs, events).await?;
 
     Ok(AppDeploymentReport {
         id: *service_id,
-        pods: pods.items,
-        services: services.items,
-        pvcs: pvcs.items,
+        pods: dedup_by_uid(pods),
+        services: dedup_by_uid(services),
+        pvcs: dedup_by_uid(pvcs),
         events: events.items,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_by_uid() {
+        let pods = |uids: &[&str]| -> ObjectList<Pod> {
+            serde_json::from_value(serde_json::json!({
+                "metadata": {},
+                "items": uids.iter().map(|uid| serde_json::json!({ "metadata": { "uid": uid } })).collect::<Vec<_>>(),
+            }))
+            .unwrap()
+        };
+
+        let deduped = dedup_by_uid(vec![pods(&["web-1", "worker-1"]), pods(&["worker-1", "standby-1"])]);
+        let uids: Vec<&str> = deduped.iter().filter_map(|pod| pod.metadata.uid.as_deref()).collect();
+        assert_eq!(uids, vec!["web-1", "worker-1", "standby-1"]);
+    }
+}
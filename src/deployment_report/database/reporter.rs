@@ -102,7 +102,7 @@ impl DatabaseDeploymentReporter {
     ) -> DatabaseDeploymentReporter {
         DatabaseDeploymentReporter {
             long_id: *db.long_id(),
-            namespace: deployment_target.environment.namespace().to_string(),
+            namespace: deployment_target.environment.service_namespace(db.long_id()),
             is_managed: db.is_managed_service(),
             type_: db.db_type(),
             version: db.version(),
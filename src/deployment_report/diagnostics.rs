@@ -0,0 +1,373 @@
+use crate::cmd::kubectl::{kubectl_exec_describe_pod_by_name, kubectl_exec_get_pods};
+use crate::cmd::structs::{KubernetesPod, KubernetesPodStatusPhase};
+use crate::deployment_report::utils::get_last_events_for;
+use k8s_openapi::api::core::v1::{Event, Pod};
+use kube::api::{ListParams, LogParams};
+use kube::Api;
+use std::collections::HashSet;
+use std::env;
+use std::path::Path;
+
+const DIAGNOSTICS_LOG_LINES: i64 = 20;
+const DIAGNOSTICS_MAX_EVENTS_PER_POD: usize = 5;
+// Each line is sent as its own event, a crash dump must not flood the log sinks
+const DEBUG_LOGS_MAX_EVENTS: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConditionInfo {
+    pub pod_name: String,
+    pub type_: String,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerminatedInfo {
+    pub pod_name: String,
+    pub container_name: String,
+    pub exit_code: i32,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventInfo {
+    pub object_name: String,
+    pub type_: String,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+}
+
+/// What went wrong with the pods of a service, for programmatic consumers.
+/// Use `to_lines` to get the same information as human readable debug logs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PodDiagnostics {
+    pub logs: Vec<String>,
+    pub unmet_conditions: Vec<ConditionInfo>,
+    pub terminated: Vec<TerminatedInfo>,
+    pub events: Vec<EventInfo>,
+}
+
+impl PodDiagnostics {
+    /// Logs are fetched separately, as they require a call per container
+    pub fn from_pods(pods: &[Pod], events: &[Event], logs: Vec<String>) -> PodDiagnostics {
+        let mut diagnostics = PodDiagnostics {
+            logs,
+            ..Default::default()
+        };
+
+        for pod in pods {
+            let pod_name = pod.metadata.name.clone().unwrap_or_default();
+            let status = match &pod.status {
+                Some(status) => status,
+                None => continue,
+            };
+
+            for condition in status.conditions.iter().flatten().filter(|c| c.status != "True") {
+                diagnostics.unmet_conditions.push(ConditionInfo {
+                    pod_name: pod_name.clone(),
+                    type_: condition.type_.clone(),
+                    reason: condition.reason.clone(),
+                    message: condition.message.clone(),
+                });
+            }
+
+            // A crash looping container is waiting to restart, its termination is in its last state
+            for container in status.container_statuses.iter().flatten() {
+                let terminated = container
+                    .state
+                    .as_ref()
+                    .and_then(|state| state.terminated.as_ref())
+                    .or_else(|| {
+                        container
+                            .last_state
+                            .as_ref()
+                            .and_then(|state| state.terminated.as_ref())
+                    });
+                if let Some(terminated) = terminated {
+                    diagnostics.terminated.push(TerminatedInfo {
+                        pod_name: pod_name.clone(),
+                        container_name: container.name.clone(),
+                        exit_code: terminated.exit_code,
+                        reason: terminated.reason.clone(),
+                        message: terminated.message.clone(),
+                    });
+                }
+            }
+
+            if let Some(uid) = &pod.metadata.uid {
+                for event in get_last_events_for(events.iter(), uid, DIAGNOSTICS_MAX_EVENTS_PER_POD) {
+                    diagnostics.events.push(EventInfo {
+                        object_name: pod_name.clone(),
+                        type_: event.type_.clone().unwrap_or_default(),
+                        reason: event.reason.clone(),
+                        message: event.message.clone(),
+                    });
+                }
+            }
+        }
+
+        diagnostics
+    }
+
+    pub fn to_lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+        for condition in &self.unmet_conditions {
+            lines.push(format!(
+                "Pod {} condition {} is not met: {} {}",
+                condition.pod_name,
+                condition.type_,
+                condition.reason.as_deref().unwrap_or_default(),
+                condition.message.as_deref().unwrap_or_default()
+            ));
+        }
+        for terminated in &self.terminated {
+            lines.push(format!(
+                "Container {} of pod {} terminated with exit code {}: {} {}",
+                terminated.container_name,
+                terminated.pod_name,
+                terminated.exit_code,
+                terminated.reason.as_deref().unwrap_or_default(),
+                terminated.message.as_deref().unwrap_or_default()
+            ));
+        }
+        for event in &self.events {
+            lines.push(format!(
+                "{} event on {}: {} {}",
+                event.type_,
+                event.object_name,
+                event.reason.as_deref().unwrap_or_default(),
+                event.message.as_deref().unwrap_or_default()
+            ));
+        }
+        lines.extend(self.logs.iter().cloned());
+
+        lines.into_iter().map(|line| line.trim_end().to_string()).collect()
+    }
+}
+
+/// Diagnostics of the pods matching any of the selectors, along with the last lines of their containers logs
+pub async fn fetch_pod_diagnostics(
+    kube: &kube::Client,
+    namespace: &str,
+    selectors: &[String],
+) -> Result<PodDiagnostics, kube::Error> {
+    let pods_api: Api<Pod> = Api::namespaced(kube.clone(), namespace);
+    let events_api: Api<Event> = Api::namespaced(kube.clone(), namespace);
+
+    let mut uids = HashSet::new();
+    let mut pods = vec![];
+    for selector in selectors {
+        for pod in pods_api.list(&ListParams::default().labels(selector)).await? {
+            if uids.insert(pod.metadata.uid.clone()) {
+                pods.push(pod);
+            }
+        }
+    }
+    let events = events_api.list(&ListParams::default()).await?.items;
+
+    let mut logs = vec![];
+    for pod in &pods {
+        let pod_name = pod.metadata.name.clone().unwrap_or_default();
+        let containers = pod.spec.iter().flat_map(|spec| spec.containers.iter());
+        for container in containers {
+            let log_params = LogParams {
+                container: Some(container.name.clone()),
+                tail_lines: Some(DIAGNOSTICS_LOG_LINES),
+                ..Default::default()
+            };
+            // A container which never started has no logs, its other diagnostics tell why
+            if let Ok(container_logs) = pods_api.logs(&pod_name, &log_params).await {
+                logs.extend(
+                    container_logs
+                        .lines()
+                        .map(|line| format!("{}/{}: {}", pod_name, container.name, line)),
+                );
+            }
+        }
+    }
+
+    Ok(PodDiagnostics::from_pods(&pods, &events, logs))
+}
+
+/// A describe dump is long, so it is only added to the debug logs when `DEBUG_LOGS_DESCRIBE_PODS=true`
+pub fn describe_pods_in_debug_logs() -> bool {
+    match env::var("DEBUG_LOGS_DESCRIBE_PODS") {
+        Ok(value) => value.eq_ignore_ascii_case("true"),
+        Err(_) => false,
+    }
+}
+
+fn is_pod_ready(pod: &KubernetesPod) -> bool {
+    match pod.status.phase {
+        KubernetesPodStatusPhase::Succeeded => true,
+        KubernetesPodStatusPhase::Running => pod
+            .status
+            .container_statuses
+            .iter()
+            .flatten()
+            .all(|container| container.ready),
+        _ => false,
+    }
+}
+
+/// `kubectl describe` output of the pods matching any of the selectors which are not ready
+pub fn describe_non_ready_pods(
+    kubeconfig_path: &Path,
+    kubectl_credentials: Vec<(&str, &str)>,
+    namespace: &str,
+    selectors: &[String],
+) -> Vec<String> {
+    let mut pod_names = HashSet::new();
+    let mut lines = vec![];
+    for selector in selectors {
+        let pods = match kubectl_exec_get_pods(
+            kubeconfig_path,
+            Some(namespace),
+            Some(selector),
+            kubectl_credentials.clone(),
+        ) {
+            Ok(pods) => pods.items,
+            Err(err) => {
+                lines.push(format!("Cannot list pods to describe: {}", err.message_safe()));
+                continue;
+            }
+        };
+
+        for pod in pods.iter().filter(|pod| !is_pod_ready(pod)) {
+            if !pod_names.insert(pod.metadata.name.clone()) {
+                continue;
+            }
+
+            match kubectl_exec_describe_pod_by_name(
+                kubeconfig_path,
+                namespace,
+                &pod.metadata.name,
+                kubectl_credentials.clone(),
+            ) {
+                Ok(describe) => {
+                    lines.push(format!("Describe of pod {}:", pod.metadata.name));
+                    lines.extend(describe.lines().map(str::to_string));
+                }
+                Err(err) => lines.push(format!("Cannot describe pod {}: {}", pod.metadata.name, err.message_safe())),
+            }
+        }
+    }
+
+    lines
+}
+
+/// Debug logs to send one event each, so sinks limiting the size of a message do not truncate them.
+/// Past the maximum number of events, the remaining lines are only counted
+pub fn debug_log_events(lines: &[String]) -> Vec<String> {
+    if lines.len() <= DEBUG_LOGS_MAX_EVENTS {
+        return lines.to_vec();
+    }
+
+    let mut events = lines[..DEBUG_LOGS_MAX_EVENTS - 1].to_vec();
+    events.push(format!(
+        "... {} more debug lines truncated",
+        lines.len() - (DEBUG_LOGS_MAX_EVENTS - 1)
+    ));
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_log_events() {
+        let lines: Vec<String> = (0..DEBUG_LOGS_MAX_EVENTS).map(|i| format!("line {}", i)).collect();
+        assert_eq!(debug_log_events(&lines), lines);
+
+        let lines: Vec<String> = (0..DEBUG_LOGS_MAX_EVENTS + 10).map(|i| format!("line {}", i)).collect();
+        let events = debug_log_events(&lines);
+        assert_eq!(events.len(), DEBUG_LOGS_MAX_EVENTS);
+        assert_eq!(events[..DEBUG_LOGS_MAX_EVENTS - 1], lines[..DEBUG_LOGS_MAX_EVENTS - 1]);
+        assert_eq!(events.last().unwrap(), "... 11 more debug lines truncated");
+    }
+
+    #[test]
+    fn test_pod_diagnostics() {
+        let pod: Pod = serde_json::from_value(serde_json::json!({
+            "metadata": { "name": "app-7d9f", "uid": "pod-uid" },
+            "status": {
+                "conditions": [
+                    { "type": "PodScheduled", "status": "True" },
+                    { "type": "Ready", "status": "False", "reason": "ContainersNotReady" }
+                ],
+                "containerStatuses": [{
+                    "name": "app",
+                    "image": "app:1.0",
+                    "imageID": "",
+                    "ready": false,
+                    "restartCount": 3,
+                    "state": { "waiting": { "reason": "CrashLoopBackOff" } },
+                    "lastState": { "terminated": { "exitCode": 137, "reason": "OOMKilled" } }
+                }]
+            }
+        }))
+        .unwrap();
+        let event: Event = serde_json::from_value(serde_json::json!({
+            "metadata": { "name": "app-7d9f.1" },
+            "involvedObject": { "uid": "pod-uid" },
+            "type": "Warning",
+            "reason": "BackOff",
+            "message": "Back-off restarting failed container"
+        }))
+        .unwrap();
+
+        let diagnostics = PodDiagnostics::from_pods(&[pod], &[event], vec!["app-7d9f/app: starting".to_string()]);
+
+        assert_eq!(
+            diagnostics.unmet_conditions,
+            vec![ConditionInfo {
+                pod_name: "app-7d9f".to_string(),
+                type_: "Ready".to_string(),
+                reason: Some("ContainersNotReady".to_string()),
+                message: None,
+            }]
+        );
+        assert_eq!(
+            diagnostics.terminated,
+            vec![TerminatedInfo {
+                pod_name: "app-7d9f".to_string(),
+                container_name: "app".to_string(),
+                exit_code: 137,
+                reason: Some("OOMKilled".to_string()),
+                message: None,
+            }]
+        );
+        assert_eq!(diagnostics.events.len(), 1);
+        assert_eq!(
+            diagnostics.to_lines(),
+            vec![
+                "Pod app-7d9f condition Ready is not met: ContainersNotReady".to_string(),
+                "Container app of pod app-7d9f terminated with exit code 137: OOMKilled".to_string(),
+                "Warning event on app-7d9f: BackOff Back-off restarting failed container".to_string(),
+                "app-7d9f/app: starting".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_pod_ready() {
+        let pod = |phase: &str, ready: bool| -> KubernetesPod {
+            serde_json::from_value(serde_json::json!({
+                "metadata": { "name": "app-7d9f", "namespace": "z1234" },
+                "status": {
+                    "phase": phase,
+                    "containerStatuses": [{ "state": {}, "ready": ready, "restartCount": 0 }]
+                }
+            }))
+            .unwrap()
+        };
+
+        assert!(is_pod_ready(&pod("Running", true)));
+        assert!(is_pod_ready(&pod("Succeeded", false)));
+        assert!(!is_pod_ready(&pod("Running", false)));
+        assert!(!is_pod_ready(&pod("Pending", false)));
+        assert!(!is_pod_ready(&pod("Failed", false)));
+    }
+}
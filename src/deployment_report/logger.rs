@@ -1,4 +1,5 @@
 use crate::cloud_provider::service::Service;
+use crate::deployment_report::diagnostics::debug_log_events;
 use crate::errors::EngineError;
 use crate::events::{EngineEvent, EnvironmentStep, EventDetails, EventMessage, Stage};
 use crate::logger::Logger;
@@ -24,6 +25,7 @@ impl EnvLogger {
             EnvironmentStep::Deploy => (EnvironmentStep::Deploy, EnvironmentStep::Deployed),
             EnvironmentStep::Pause => (EnvironmentStep::Pause, EnvironmentStep::Paused),
             EnvironmentStep::Delete => (EnvironmentStep::Delete, EnvironmentStep::Deleted),
+            EnvironmentStep::Restart => (EnvironmentStep::Restart, EnvironmentStep::Restarted),
             EnvironmentStep::Build => (EnvironmentStep::Build, EnvironmentStep::Built),
             _ => panic!("Invalid environment step for logger"),
         };
@@ -69,6 +71,16 @@ impl EnvLogger {
         ));
     }
 
+    /// Debug lines are sent as separate events, in order, instead of a single message joining them
+    pub fn send_debug_lines(&self, lines: &[String]) {
+        for line in debug_log_events(lines) {
+            self.logger.log(EngineEvent::Debug(
+                self.event_details_progress.clone(),
+                EventMessage::new_from_safe(line),
+            ));
+        }
+    }
+
     pub fn send_success(&self, msg: String) {
         #[cfg(feature = "env-logger-check")]
         {
@@ -260,7 +260,12 @@ pub fn to_pods_render_context(
             pods_starting.push(PodRenderContext {
                 name: pod_name.to_string(),
                 state: DeploymentState::Starting,
-                message: None,
+                // gated pods are not scheduled by design, they are not stuck
+                message: if pod.is_scheduling_gated() {
+                    Some("awaiting scheduling gate".to_string())
+                } else {
+                    None
+                },
                 restart_count: pod.restart_count(),
                 events: get_last_events_for(events.iter(), pod_uid, DEFAULT_MAX_EVENTS)
                     .flat_map(to_event_context)
@@ -349,8 +354,10 @@ pub fn to_pvc_render_context(pvcs: &[PersistentVolumeClaim], events: &[Event]) -
 
 pub trait QPodExt {
     fn restart_count(&self) -> u32;
+    fn containers_restart_count(&self) -> Vec<(&str, u32)>;
     fn is_starting(&self) -> bool;
     fn is_failing(&self) -> Option<&str>;
+    fn is_scheduling_gated(&self) -> bool;
 }
 
 impl QPodExt for Pod {
@@ -365,6 +372,15 @@ impl QPodExt for Pod {
         }
     }
 
+    fn containers_restart_count(&self) -> Vec<(&str, u32)> {
+        self.status
+            .iter()
+            .flat_map(|status| status.container_statuses.iter().flatten())
+            .filter(|status| status.restart_count > 0)
+            .map(|status| (status.name.as_str(), status.restart_count as u32))
+            .collect()
+    }
+
     fn is_starting(&self) -> bool {
         // If the pod is in pending phase, it means it starts
         if let Some("Pending") = self.status.as_ref().and_then(|x| x.phase.as_deref()) {
@@ -386,6 +402,21 @@ impl QPodExt for Pod {
         false
     }
 
+    fn is_scheduling_gated(&self) -> bool {
+        // https://kubernetes.io/docs/concepts/scheduling-eviction/pod-scheduling-readiness/
+        self.status
+            .as_ref()
+            .and_then(|status| status.conditions.as_ref())
+            .map(|conditions| {
+                conditions.iter().any(|condition| {
+                    condition.type_ == "PodScheduled"
+                        && condition.status == "False"
+                        && condition.reason.as_deref() == Some("SchedulingGated")
+                })
+            })
+            .unwrap_or(false)
+    }
+
     fn is_failing<'a>(&'a self) -> Option<&'a str> {
         // https://stackoverflow.com/questions/57821723/list-of-all-reasons-for-container-states-in-kubernetes
         let is_error_reason = |reason: &str| {
@@ -58,5 +58,6 @@ pub trait DnsProvider {
             Stage::Infrastructure(InfrastructureStep::ValidateSystemRequirements),
             Transmitter::DnsProvider(*self.long_id(), self.provider_name().to_string()),
         )
+        .with_transaction_id(*self.context().transaction_id())
     }
 }
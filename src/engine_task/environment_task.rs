@@ -5,10 +5,12 @@ use crate::cloud_provider::aws::regions::AwsRegion;
 use crate::cloud_provider::environment::Environment;
 use crate::cloud_provider::service;
 use crate::cloud_provider::service::Service;
+use crate::cmd::command::set_command_log_tee;
 use crate::cmd::docker::Docker;
 use crate::container_registry::errors::ContainerRegistryError;
 use crate::container_registry::to_engine_error;
 use crate::deployment_action::deploy_environment::EnvironmentDeployment;
+use crate::deployment_action::summary::DeploymentSummary;
 use crate::deployment_report::logger::EnvLogger;
 use crate::engine::InfrastructureContext;
 use crate::errors::EngineError;
@@ -16,12 +18,13 @@ use crate::events::{EngineEvent, EnvironmentStep, EventDetails, EventMessage, St
 use crate::io_models::context::Context;
 use crate::io_models::engine_request::EnvironmentEngineRequest;
 use crate::io_models::Action;
-use crate::logger::Logger;
+use crate::logger::{LogTee, Logger, TeeLogger, LOG_TEE_DEFAULT_CAPACITY};
 use crate::transaction::DeploymentOption;
 use chrono::{DateTime, Utc};
 use std::collections::HashSet;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
 use std::{env, fs};
 use url::Url;
 use uuid::Uuid;
@@ -35,6 +38,12 @@ pub struct EnvironmentTask {
     request: EnvironmentEngineRequest,
     cancel_requested: Arc<AtomicBool>,
     logger: Box<dyn Logger>,
+    // correlates all the events of this task
+    transaction_id: Uuid,
+    // raw log of the deployment, engine events and commands output
+    log_tee: Option<LogTee>,
+    // one per service handled by the last run, whatever its outcome
+    deployment_summaries: Arc<Mutex<Vec<DeploymentSummary>>>,
 }
 
 impl EnvironmentTask {
@@ -54,9 +63,32 @@ impl EnvironmentTask {
             request,
             logger,
             cancel_requested: Arc::new(AtomicBool::from(false)),
+            transaction_id: Uuid::new_v4(),
+            log_tee: None,
+            deployment_summaries: Arc::new(Mutex::new(vec![])),
         }
     }
 
+    /// Stream the raw log of the deployment into `sender`, without ever blocking the deployment
+    pub fn with_log_tee(mut self, sender: Sender<String>) -> std::io::Result<Self> {
+        let log_tee = LogTee::new(sender, LOG_TEE_DEFAULT_CAPACITY)?;
+        self.logger = Box::new(TeeLogger::new(self.logger, log_tee.clone()));
+        self.log_tee = Some(log_tee);
+        Ok(self)
+    }
+
+    /// Summary of every service handled by the last run of the task, available once it is over
+    pub fn deployment_summaries(&self) -> Vec<DeploymentSummary> {
+        self.deployment_summaries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn event_details(&self) -> EventDetails {
+        self.request.event_details().with_transaction_id(self.transaction_id)
+    }
+
     fn info_context(&self) -> Context {
         Context::new(
             self.request.organization_long_id,
@@ -69,15 +101,16 @@ impl EnvironmentTask {
             self.request.features.clone(),
             self.request.metadata.clone(),
             self.docker.clone(),
-            self.request.event_details(),
+            self.event_details(),
         )
+        .with_cancel_requested(self.cancel_requested.clone())
     }
 
     // FIXME: Remove EngineConfig type, there is no use for it
     // merge it with DeploymentTarget type
     fn infrastructure_context(&self) -> Result<InfrastructureContext, Box<EngineError>> {
         self.request
-            .engine(&self.info_context(), self.request.event_details(), self.logger.clone())
+            .engine(&self.info_context(), self.event_details(), self.logger.clone())
     }
 
     fn _is_canceled(&self) -> bool {
@@ -85,7 +118,7 @@ impl EnvironmentTask {
     }
 
     fn get_event_details(&self, step: EnvironmentStep) -> EventDetails {
-        EventDetails::clone_changing_stage(self.request.event_details(), Stage::Environment(step))
+        EventDetails::clone_changing_stage(self.event_details(), Stage::Environment(step))
     }
 
     pub fn build_and_push_services(
@@ -174,9 +207,20 @@ impl EnvironmentTask {
     }
 
     pub fn deploy_environment(
+        environment: Environment,
+        infra_ctx: &InfrastructureContext,
+        should_abort: &dyn Fn() -> bool,
+    ) -> Result<(), Box<EngineError>> {
+        Self::deploy_environment_with_summaries(environment, infra_ctx, should_abort, &mut vec![])
+    }
+
+    /// Same as deploy_environment, the summary of every service handled is pushed into `summaries`,
+    /// whether the deployment succeeds or not
+    pub fn deploy_environment_with_summaries(
         mut environment: Environment,
         infra_ctx: &InfrastructureContext,
         should_abort: &dyn Fn() -> bool,
+        summaries: &mut Vec<DeploymentSummary>,
     ) -> Result<(), Box<EngineError>> {
         let mut deployed_services: HashSet<Uuid> = HashSet::new();
         let event_details = environment.event_details().clone();
@@ -213,8 +257,10 @@ impl EnvironmentTask {
                 service::Action::Create => env_deployment.on_create(),
                 service::Action::Pause => env_deployment.on_pause(),
                 service::Action::Delete => env_deployment.on_delete(),
+                service::Action::Restart => env_deployment.on_restart(),
             };
             deployed_services = env_deployment.deployed_services;
+            summaries.extend(env_deployment.summaries);
 
             deployment_ret
         };
@@ -234,6 +280,7 @@ impl EnvironmentTask {
                 service::Action::Create => Stage::Environment(EnvironmentStep::DeployedError),
                 service::Action::Pause => Stage::Environment(EnvironmentStep::PausedError),
                 service::Action::Delete => Stage::Environment(EnvironmentStep::DeletedError),
+                service::Action::Restart => Stage::Environment(EnvironmentStep::RestartedError),
             }
         };
 
@@ -274,6 +321,8 @@ impl Task for EnvironmentTask {
             self.get_event_details(EnvironmentStep::Start),
             EventMessage::new("🚀 Qovery Engine starts to execute the deployment".to_string(), None),
         ));
+        // commands are spawned from everywhere in the engine, they pick the tee of the task up from its thread
+        let _log_tee_guard = set_command_log_tee(self.log_tee.clone());
         let guard = scopeguard::guard((), |_| {
             self.logger.log(EngineEvent::Info(
                 self.get_event_details(EnvironmentStep::Terminated),
@@ -311,7 +360,14 @@ impl Task for EnvironmentTask {
         };
 
         // run the actions
-        let deployment_ret = EnvironmentTask::deploy_environment(environment, &infra_context, &self.cancel_checker());
+        let mut summaries = vec![];
+        let deployment_ret = EnvironmentTask::deploy_environment_with_summaries(
+            environment,
+            &infra_context,
+            &self.cancel_checker(),
+            &mut summaries,
+        );
+        *self.deployment_summaries.lock().unwrap_or_else(|e| e.into_inner()) = summaries;
         match (&self.request.action, deployment_ret) {
             (Action::Create, Ok(())) => self.logger.log(EngineEvent::Info(
                 self.get_event_details(EnvironmentStep::Deployed),
@@ -381,6 +437,14 @@ impl Task for EnvironmentTask {
             };
         };
 
+        if let Some(max_age) = crate::fs::default_stale_workspace_max_age() {
+            match crate::fs::cleanup_stale_workspaces(infra_context.context().workspace_root_dir(), max_age) {
+                Ok(removed) if !removed.is_empty() => info!("Removed stale workspaces: {}", removed.join(", ")),
+                Ok(_) => {}
+                Err(err) => error!("Cannot clean up stale workspaces: {}", err),
+            }
+        }
+
         info!("environment task {} finished", self.id());
     }
 
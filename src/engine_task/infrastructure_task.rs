@@ -11,8 +11,11 @@ use crate::io_models::{Action, QoveryIdentifier};
 use crate::logger::Logger;
 use crate::transaction::{Transaction, TransactionResult};
 use chrono::{DateTime, Utc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::{env, fs};
 use url::Url;
+use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct InfrastructureTask {
@@ -21,7 +24,10 @@ pub struct InfrastructureTask {
     docker_host: Option<Url>,
     docker: Docker,
     request: InfrastructureEngineRequest,
+    cancel_requested: Arc<AtomicBool>,
     logger: Box<dyn Logger>,
+    // correlates all the events of this task
+    transaction_id: Uuid,
 }
 
 impl InfrastructureTask {
@@ -40,9 +46,15 @@ impl InfrastructureTask {
             docker,
             request,
             logger,
+            cancel_requested: Arc::new(AtomicBool::from(false)),
+            transaction_id: Uuid::new_v4(),
         }
     }
 
+    fn event_details(&self) -> EventDetails {
+        self.request.event_details().with_transaction_id(self.transaction_id)
+    }
+
     fn info_context(&self) -> Context {
         Context::new(
             self.request.organization_long_id,
@@ -55,8 +67,9 @@ impl InfrastructureTask {
             self.request.features.clone(),
             self.request.metadata.clone(),
             self.docker.clone(),
-            self.request.event_details(),
+            self.event_details(),
         )
+        .with_cancel_requested(self.cancel_requested.clone())
     }
 
     fn handle_transaction_result(&self, logger: Box<dyn Logger>, transaction_result: TransactionResult) {
@@ -107,7 +120,8 @@ impl InfrastructureTask {
                     self.request.id.to_string(),
                     Infrastructure(infrastructure_step),
                     Transmitter::Kubernetes(kubernetes.long_id, kubernetes.name.to_string()),
-                ),
+                )
+                .with_transaction_id(self.transaction_id),
                 event_message,
             );
 
@@ -136,7 +150,7 @@ impl Task for InfrastructureTask {
 
         let engine = match self
             .request
-            .engine(&self.info_context(), self.request.event_details(), self.logger.clone())
+            .engine(&self.info_context(), self.event_details(), self.logger.clone())
         {
             Ok(engine) => engine,
             Err(err) => {
@@ -195,10 +209,13 @@ impl Task for InfrastructureTask {
     }
 
     fn cancel(&self) -> bool {
-        false
+        // terraform actions cannot be interrupted, only the nodes checks stop on it
+        self.cancel_requested.store(true, Ordering::Relaxed);
+        true
     }
 
     fn cancel_checker(&self) -> Box<dyn Fn() -> bool> {
-        Box::new(|| false)
+        let cancel_requested = self.cancel_requested.clone();
+        Box::new(move || cancel_requested.load(Ordering::Relaxed))
     }
 }
@@ -49,6 +49,7 @@ pub enum Tag {
     K8sCannotGetPods,
     K8sUpgradeDeployedVsRequestedVersionsInconsistency,
     K8sScaleReplicas,
+    K8sRestartService,
     K8sLoadBalancerConfigurationIssue,
     K8sServiceError,
     K8sGetLogs,
@@ -188,6 +189,35 @@ pub enum Tag {
     JobFailure,
     DoNotRespectCloudProviderBestPractices,
     CannotListClusters,
+    HelmValuesTooLarge,
+    InvalidEnvVarName,
+    DuplicateCustomDomain,
+    HelmOwnershipConflict,
+    DatabaseExternalNameTargetNotFound,
+    TerraformIncompatibleVersion,
+    K8sPvcBindingFailed,
+    UnresolvedServiceReference,
+    AdmissionWebhookUnavailable,
+    KubeContextNotFound,
+    K8sReadinessAnnotationTimeout,
+    DatabaseInitScriptsFailed,
+    RouterTlsCertificateFailed,
+    K8sCannotEnableServiceMeshInjection,
+    IncompleteCleanup,
+    DatabaseCredentialsCannotBeStored,
+    HelmDependencyFailed,
+    K8sStatefulsetPartialRollout,
+    ApplicationWarmupFailed,
+    CombinedChangeRejected,
+    K8sSchedulingGateTimeout,
+    HelmReleaseTagNotFound,
+    HelmAdmissionRejected,
+    WorkloadReadinessTimeout,
+    UnsupportedKubernetesVersion,
+    PreDeployJobFailed,
+    HelmReleaseNotManaged,
+    DatabaseVersionDowngrade,
+    NamespaceNotOwned,
 }
 
 impl From<errors::Tag> for Tag {
@@ -223,6 +253,7 @@ impl From<errors::Tag> for Tag {
                 Tag::K8sUpgradeDeployedVsRequestedVersionsInconsistency
             }
             errors::Tag::K8sScaleReplicas => Tag::K8sScaleReplicas,
+            errors::Tag::K8sRestartService => Tag::K8sRestartService,
             errors::Tag::K8sLoadBalancerConfigurationIssue => Tag::K8sLoadBalancerConfigurationIssue,
             errors::Tag::K8sServiceError => Tag::K8sServiceError,
             errors::Tag::K8sGetLogs => Tag::K8sGetLogs,
@@ -377,6 +408,35 @@ impl From<errors::Tag> for Tag {
             errors::Tag::K8sCannotGetPVCs => Tag::K8sCannotGetPVCs,
             errors::Tag::K8sCannotBoundPVC => Tag::K8sCannotBoundPVC,
             errors::Tag::CannotListClusters => Tag::CannotListClusters,
+            errors::Tag::HelmValuesTooLarge => Tag::HelmValuesTooLarge,
+            errors::Tag::InvalidEnvVarName => Tag::InvalidEnvVarName,
+            errors::Tag::DuplicateCustomDomain => Tag::DuplicateCustomDomain,
+            errors::Tag::HelmOwnershipConflict => Tag::HelmOwnershipConflict,
+            errors::Tag::DatabaseExternalNameTargetNotFound => Tag::DatabaseExternalNameTargetNotFound,
+            errors::Tag::TerraformIncompatibleVersion => Tag::TerraformIncompatibleVersion,
+            errors::Tag::K8sPvcBindingFailed => Tag::K8sPvcBindingFailed,
+            errors::Tag::UnresolvedServiceReference => Tag::UnresolvedServiceReference,
+            errors::Tag::AdmissionWebhookUnavailable => Tag::AdmissionWebhookUnavailable,
+            errors::Tag::KubeContextNotFound => Tag::KubeContextNotFound,
+            errors::Tag::K8sReadinessAnnotationTimeout => Tag::K8sReadinessAnnotationTimeout,
+            errors::Tag::DatabaseInitScriptsFailed => Tag::DatabaseInitScriptsFailed,
+            errors::Tag::RouterTlsCertificateFailed => Tag::RouterTlsCertificateFailed,
+            errors::Tag::K8sCannotEnableServiceMeshInjection => Tag::K8sCannotEnableServiceMeshInjection,
+            errors::Tag::IncompleteCleanup => Tag::IncompleteCleanup,
+            errors::Tag::DatabaseCredentialsCannotBeStored => Tag::DatabaseCredentialsCannotBeStored,
+            errors::Tag::HelmDependencyFailed => Tag::HelmDependencyFailed,
+            errors::Tag::K8sStatefulsetPartialRollout => Tag::K8sStatefulsetPartialRollout,
+            errors::Tag::ApplicationWarmupFailed => Tag::ApplicationWarmupFailed,
+            errors::Tag::CombinedChangeRejected => Tag::CombinedChangeRejected,
+            errors::Tag::K8sSchedulingGateTimeout => Tag::K8sSchedulingGateTimeout,
+            errors::Tag::HelmReleaseTagNotFound => Tag::HelmReleaseTagNotFound,
+            errors::Tag::HelmAdmissionRejected => Tag::HelmAdmissionRejected,
+            errors::Tag::WorkloadReadinessTimeout => Tag::WorkloadReadinessTimeout,
+            errors::Tag::UnsupportedKubernetesVersion => Tag::UnsupportedKubernetesVersion,
+            errors::Tag::PreDeployJobFailed => Tag::PreDeployJobFailed,
+            errors::Tag::HelmReleaseNotManaged => Tag::HelmReleaseNotManaged,
+            errors::Tag::DatabaseVersionDowngrade => Tag::DatabaseVersionDowngrade,
+            errors::Tag::NamespaceNotOwned => Tag::NamespaceNotOwned,
         }
     }
 }
@@ -7,7 +7,7 @@ use crate::build_platform::BuildError;
 use crate::cloud_provider::Kind;
 use crate::cmd;
 use crate::cmd::docker::DockerError;
-use crate::cmd::helm::HelmError;
+use crate::cmd::helm::{is_policy_admission_webhook, HelmError};
 use crate::cmd::terraform::{QuotaExceededError, TerraformError};
 use crate::container_registry::errors::ContainerRegistryError;
 
@@ -19,6 +19,7 @@ use derivative::Derivative;
 use kube::error::Error as KubeError;
 use std::fmt::{Display, Formatter};
 use std::io::Error;
+use std::time::Duration;
 use thiserror::Error;
 use url::Url;
 
@@ -599,6 +600,8 @@ pub enum Tag {
     K8sUpgradeDeployedVsRequestedVersionsInconsistency,
     /// K8sScaleReplicas: represents an error while trying to scale replicas.
     K8sScaleReplicas,
+    /// K8sRestartService: represents an error while trying to roll the pods of a service.
+    K8sRestartService,
     /// K8sLoadBalancerConfigurationIssue: represents an error where loadbalancer has a configuration issue.
     K8sLoadBalancerConfigurationIssue,
     /// K8sServiceError: represents an error on a k8s service.
@@ -844,12 +847,90 @@ pub enum Tag {
     ObjectStorageCannotGetObjectFile,
     /// JobFailure: represents an error while indicating that the job failed to terminate properly
     JobFailure,
+    /// HelmValuesTooLarge: represents an error where rendered helm values exceed the size helm can store in a release.
+    HelmValuesTooLarge,
+    /// InvalidEnvVarName: represents an error where an environment variable name is not valid.
+    InvalidEnvVarName,
+    /// DuplicateCustomDomain: represents an error where a custom domain is claimed by several routers.
+    DuplicateCustomDomain,
+    /// HelmOwnershipConflict: represents an error where helm cannot manage a resource created outside of the release.
+    HelmOwnershipConflict,
+    /// DatabaseExternalNameTargetNotFound: represents an error where the service targeted by a database external-name alias doesn't exist.
+    DatabaseExternalNameTargetNotFound,
+    /// TerraformIncompatibleVersion: represents an error where the installed terraform binary doesn't satisfy the required version.
+    TerraformIncompatibleVersion,
+    /// K8sPvcBindingFailed: represents an error where a persistent volume claim cannot be bound to any volume.
+    K8sPvcBindingFailed,
+    /// UnresolvedServiceReference: represents an error where an environment variable references a service which cannot be found.
+    UnresolvedServiceReference,
+    /// AdmissionWebhookUnavailable: represents an error where a kubernetes admission webhook cannot be reached.
+    AdmissionWebhookUnavailable,
+    /// KubeContextNotFound: represents an error when the kubeconfig doesn't contain the context of the cluster.
+    KubeContextNotFound,
+    /// K8sReadinessAnnotationTimeout: represents an error where the readiness annotation of a workload did not reach its expected value in time.
+    K8sReadinessAnnotationTimeout,
+    /// DatabaseInitScriptsFailed: represents an error while running the initialization scripts of a database.
+    DatabaseInitScriptsFailed,
+    /// RouterTlsCertificateFailed: represents an error where the TLS certificate of a router custom domain cannot be provisioned.
+    RouterTlsCertificateFailed,
+    /// K8sCannotEnableServiceMeshInjection: represents an error while enabling the service mesh sidecar injection on a namespace.
+    K8sCannotEnableServiceMeshInjection,
+    /// IncompleteCleanup: represents an error where resources of a deleted service are still present.
+    IncompleteCleanup,
+    /// DatabaseCredentialsCannotBeStored: represents an error while storing the generated credentials of a database in a secret.
+    DatabaseCredentialsCannotBeStored,
+    /// HelmDependencyFailed: represents an error while fetching a subchart declared in the chart dependencies.
+    HelmDependencyFailed,
+    /// K8sStatefulsetPartialRollout: represents an error when some replicas of a statefulset do not become ready.
+    K8sStatefulsetPartialRollout,
+    /// ApplicationWarmupFailed: represents an error when the warmup requests sent to the application pods failed.
+    ApplicationWarmupFailed,
+    /// CombinedChangeRejected: represents an error when a deployment changes both the version and the resources.
+    CombinedChangeRejected,
+    /// K8sSchedulingGateTimeout: represents an error when a pod is held by scheduling gates for too long.
+    K8sSchedulingGateTimeout,
+    /// HelmReleaseTagNotFound: represents an error when no revision of a helm release is tagged with the requested tag.
+    HelmReleaseTagNotFound,
+    /// HelmAdmissionRejected: represents an error when an admission webhook denies the resources of a helm release.
+    HelmAdmissionRejected,
+    /// WorkloadReadinessTimeout: represents an error when the workloads of a release are not ready in time.
+    WorkloadReadinessTimeout,
+    /// UnsupportedKubernetesVersion: represents an error when a feature requires a more recent Kubernetes version.
+    UnsupportedKubernetesVersion,
+    /// PreDeployJobFailed: represents an error while running the job a service requires before being deployed.
+    PreDeployJobFailed,
+    /// HelmReleaseNotManaged: represents an error when a helm release with the same name has been installed outside of the engine.
+    HelmReleaseNotManaged,
+    /// DatabaseVersionDowngrade: represents an error when the only version matching the requested one is lower.
+    DatabaseVersionDowngrade,
+    /// NamespaceNotOwned: represents an error when a namespace a service should own already exists without belonging to it.
+    NamespaceNotOwned,
 }
 
 impl Tag {
     pub fn is_cancel(&self) -> bool {
         matches!(self, Tag::TaskCancellationRequested)
     }
+
+    /// Errors likely due to a temporary cluster or provider state, where retrying the same action may succeed
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Tag::CannotConnectK8sCluster
+                | Tag::K8sCannotReachToApi
+                | Tag::K8sCannotGetPods
+                | Tag::K8sScaleReplicas
+                | Tag::K8sRestartService
+                | Tag::K8sPodIsNotReady
+                | Tag::K8sNodeIsNotReady
+                | Tag::HelmDeployTimeout
+                | Tag::TerraformStateLocked
+                | Tag::TerraformWaitingTimeoutResource
+                | Tag::DockerPullImageError
+                | Tag::ContainerRegistryImageUnreachableAfterPush
+                | Tag::AdmissionWebhookUnavailable
+        )
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -875,6 +956,12 @@ impl EngineError {
         &self.tag
     }
 
+    /// Returns the same error, with the number of attempts made mentioned in its user message.
+    pub fn with_attempts(mut self, attempts: u32) -> EngineError {
+        self.user_log_message = format!("{} (failed after {} attempts)", self.user_log_message, attempts);
+        self
+    }
+
     /// Returns error's event details.
     pub fn event_details(&self) -> &EventDetails {
         &self.event_details
@@ -1937,6 +2024,25 @@ impl EngineError {
         EngineError::new(event_details, Tag::K8sScaleReplicas, message, Some(raw_error), None, None)
     }
 
+    /// Creates new error for kubernetes service restart.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `selector`: K8s selector.
+    /// * `namespace`: K8s namespace.
+    /// * `raw_error`: Raw error message.
+    pub fn new_k8s_restart_service(
+        event_details: EventDetails,
+        selector: String,
+        namespace: String,
+        raw_error: CommandError,
+    ) -> EngineError {
+        let message = format!("Unable to restart Kubernetes `{}` pods in namespace `{}`.", selector, namespace);
+
+        EngineError::new(event_details, Tag::K8sRestartService, message, Some(raw_error), None, None)
+    }
+
     /// Creates new error for kubernetes load balancer configuration issue.
     ///
     /// Arguments:
@@ -2489,6 +2595,11 @@ impl EngineError {
                 None,
                 Some("Your deployment failed because Terraform faced a state lock. Please contact Qovery team to get unlocked.".to_string()),
             ),
+            TerraformError::IncompatibleVersion {
+                required_version,
+                installed_version,
+                ..
+            } => EngineError::new_incompatible_terraform_version(event_details, required_version, installed_version),
         }
     }
 
@@ -2693,6 +2804,14 @@ impl EngineError {
     pub fn new_helm_error(event_details: EventDetails, error: HelmError) -> EngineError {
         let cmd_error = match &error {
             HelmError::Killed(_, _) => return EngineError::new_task_cancellation_requested(event_details),
+            HelmError::AdmissionRejected(release, webhook, reason) => {
+                return EngineError::new_helm_admission_rejected(
+                    event_details,
+                    release.to_string(),
+                    webhook.to_string(),
+                    reason.to_string(),
+                )
+            }
             HelmError::CmdError(_, _, cmd_error) => Some(cmd_error.clone()),
             _ => None,
         };
@@ -2845,6 +2964,35 @@ impl EngineError {
         EngineError::new(event_details, Tag::UnsupportedVersion, message, None, None, None)
     }
 
+    /// Creates new error when the version matching the requested one for a database is lower than it.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `product_name`: Database product name.
+    /// * `requested_version`: Version requested by the user.
+    /// * `matched_version`: Lower version it has been matched to.
+    pub fn new_database_version_downgrade(
+        event_details: EventDetails,
+        product_name: String,
+        requested_version: String,
+        matched_version: String,
+    ) -> EngineError {
+        let message = format!(
+            "Error, `{}` version `{}` has been requested but the matching version `{}` is lower.",
+            product_name, requested_version, matched_version
+        );
+
+        EngineError::new(
+            event_details,
+            Tag::DatabaseVersionDowngrade,
+            message,
+            None,
+            None,
+            Some("Request a supported version, or allow database version downgrades if this is expected.".to_string()),
+        )
+    }
+
     /// Creates new error while trying to get cluster.
     ///
     /// Arguments:
@@ -3842,144 +3990,981 @@ impl EngineError {
             None,
         )
     }
-}
-impl Display for EngineError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        // Note: just in case, env vars are not leaked since it can hold sensitive data such as secrets.
-        f.write_str(self.message(ErrorMessageVerbosity::FullDetailsWithoutEnvVars).as_str())
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use crate::cloud_provider::Kind;
-    use crate::errors::{CommandError, EngineError, ErrorMessageVerbosity};
-    use crate::events::{EventDetails, InfrastructureStep, Stage, Transmitter};
-    use crate::io_models::QoveryIdentifier;
-    use uuid::Uuid;
 
-    #[test]
-    fn test_command_error_test_hidding_env_vars_in_message_safe_only() {
-        // setup:
-        let command_err = CommandError::new(
-            "my safe message".to_string(),
-            Some("my raw message".to_string()),
-            Some(vec![("my_secret".to_string(), "my_secret_value".to_string())]),
+    /// Creates new error when rendered helm values are too large to be stored into helm release secret.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `helm_chart`: Helm chart name.
+    /// * `values_size_in_bytes`: Estimated size of the rendered values.
+    /// * `max_size_in_bytes`: Maximum allowed size for the rendered values.
+    pub fn new_helm_values_too_large(
+        event_details: EventDetails,
+        helm_chart: String,
+        values_size_in_bytes: u64,
+        max_size_in_bytes: u64,
+    ) -> EngineError {
+        let message = format!(
+            "Error, rendered values of helm chart `{}` are too large: `{}` bytes while maximum allowed is `{}` bytes.",
+            helm_chart, values_size_in_bytes, max_size_in_bytes
         );
 
-        // execute:
-        let res = command_err.message(ErrorMessageVerbosity::SafeOnly);
-
-        // verify:
-        assert!(!res.contains("my_secret"));
-        assert!(!res.contains("my_secret_value"));
+        EngineError::new(
+            event_details,
+            Tag::HelmValuesTooLarge,
+            message,
+            None,
+            None,
+            Some("Helm stores release values in a Kubernetes secret limited to 1MB. Please reduce your configuration size (i.e: environment variables) by moving it to an external configuration source.".to_string()),
+        )
     }
 
-    #[test]
-    fn test_command_error_test_hidding_env_vars_in_message_full_without_env_vars() {
-        // setup:
-        let command_err = CommandError::new(
-            "my safe message".to_string(),
-            Some("my raw message".to_string()),
-            Some(vec![("my_secret".to_string(), "my_secret_value".to_string())]),
+    /// Creates new error when an environment variable name doesn't match the allowed pattern.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `env_var_name`: Invalid environment variable name.
+    /// * `allowed_pattern`: Pattern environment variable names must match.
+    pub fn new_invalid_env_var_name(
+        event_details: EventDetails,
+        env_var_name: String,
+        allowed_pattern: String,
+    ) -> EngineError {
+        let message = format!(
+            "Error, environment variable name `{}` is invalid, it must match `{}`.",
+            env_var_name, allowed_pattern
         );
 
-        // execute:
-        let res = command_err.message(ErrorMessageVerbosity::FullDetailsWithoutEnvVars);
-
-        // verify:
-        assert!(!res.contains("my_secret"));
-        assert!(!res.contains("my_secret_value"));
+        EngineError::new(
+            event_details,
+            Tag::InvalidEnvVarName,
+            message,
+            None,
+            None,
+            Some("Please rename this environment variable, it should only contain letters, digits and `_` and must not start with a digit.".to_string()),
+        )
     }
 
-    #[test]
-    fn test_engine_error_test_hidding_env_vars_in_message_safe_only() {
-        // setup:
-        let command_err = CommandError::new(
-            "my safe message".to_string(),
-            Some("my raw message".to_string()),
-            Some(vec![("my_secret".to_string(), "my_secret_value".to_string())]),
+    /// Creates new error when the same custom domain is claimed by several routers of an environment.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `domain`: Duplicated custom domain.
+    /// * `router_name`: Name of the first router claiming the domain.
+    /// * `conflicting_router_name`: Name of the other router claiming the domain.
+    pub fn new_duplicate_custom_domain(
+        event_details: EventDetails,
+        domain: String,
+        router_name: String,
+        conflicting_router_name: String,
+    ) -> EngineError {
+        let message = format!(
+            "Error, custom domain `{}` is claimed by both routers `{}` and `{}`.",
+            domain, router_name, conflicting_router_name
         );
-        let cluster_id = QoveryIdentifier::new_random();
-        let engine_err = EngineError::new_unknown(
-            EventDetails::new(
-                Some(Kind::Scw),
-                QoveryIdentifier::new_random(),
-                QoveryIdentifier::new_random(),
-                Uuid::new_v4().to_string(),
-                Stage::Infrastructure(InfrastructureStep::Create),
-                Transmitter::Kubernetes(Uuid::new_v4(), cluster_id.to_string()),
-            ),
-            "user_log_message".to_string(),
-            Some(command_err),
+
+        EngineError::new(
+            event_details,
+            Tag::DuplicateCustomDomain,
+            message,
             None,
             None,
-        );
+            Some("A custom domain can only be attached to a single service of the environment, please remove one of them.".to_string()),
+        )
+    }
 
-        // execute:
-        let res = engine_err.message(ErrorMessageVerbosity::SafeOnly);
+    /// Creates new error when helm cannot manage an existing resource it doesn't own.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `helm_release`: Helm release name.
+    /// * `resource`: Conflicting resource, as `kind/name`.
+    /// * `namespace`: Namespace of the conflicting resource.
+    /// * `owner_release`: Helm release owning the resource, if any.
+    /// * `raw_error`: Raw error message, if any.
+    pub fn new_helm_ownership_conflict(
+        event_details: EventDetails,
+        helm_release: String,
+        resource: String,
+        namespace: String,
+        owner_release: Option<String>,
+        raw_error: Option<CommandError>,
+    ) -> EngineError {
+        let (message, hint) = match owner_release {
+            Some(owner_release) => (
+                format!(
+                    "Error, helm release `{}` cannot manage resource `{}` in namespace `{}`: it is owned by helm release `{}`.",
+                    helm_release, resource, namespace, owner_release
+                ),
+                "Two services cannot manage the same resource, please rename or delete one of them.".to_string(),
+            ),
+            None => (
+                format!(
+                    "Error, helm release `{}` cannot manage resource `{}` in namespace `{}`: it already exists and is not owned by this release.",
+                    helm_release, resource, namespace
+                ),
+                "This resource has been created outside of Qovery. Either delete it or enable the adoption of existing resources.".to_string(),
+            ),
+        };
 
-        // verify:
-        assert!(!res.contains("my_secret"));
-        assert!(!res.contains("my_secret_value"));
+        EngineError::new(event_details, Tag::HelmOwnershipConflict, message, raw_error, None, Some(hint))
     }
 
-    #[test]
-    fn test_engine_error_test_hidding_env_vars_in_message_full_without_env_vars() {
-        // setup:
-        let command_err = CommandError::new(
-            "my safe message".to_string(),
-            Some("my raw message".to_string()),
-            Some(vec![("my_secret".to_string(), "my_secret_value".to_string())]),
+    /// Creates new error when the service targeted by a database external-name alias cannot be found.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `database_name`: Database name.
+    /// * `service_name`: Name of the kubernetes service the alias should point to.
+    /// * `raw_error`: Raw error message, if the service could not be fetched.
+    pub fn new_database_external_name_target_not_found(
+        event_details: EventDetails,
+        database_name: String,
+        service_name: String,
+        raw_error: Option<CommandError>,
+    ) -> EngineError {
+        let message = format!(
+            "Cannot create external-name alias for database `{}`: target service `{}` cannot be found.",
+            database_name, service_name
         );
-        let cluster_id = QoveryIdentifier::new_random();
-        let engine_err = EngineError::new_unknown(
-            EventDetails::new(
-                Some(Kind::Scw),
-                QoveryIdentifier::new_random(),
-                QoveryIdentifier::new_random(),
-                Uuid::new_v4().to_string(),
-                Stage::Infrastructure(InfrastructureStep::Create),
-                Transmitter::Kubernetes(Uuid::new_v4(), cluster_id.to_string()),
-            ),
-            "user_log_message".to_string(),
-            Some(command_err),
-            None,
+
+        EngineError::new(
+            event_details,
+            Tag::DatabaseExternalNameTargetNotFound,
+            message,
+            raw_error,
             None,
-        );
+            Some("Ensure the database has been correctly deployed before setting an alias.".to_string()),
+        )
+    }
 
-        // execute:
-        let res = engine_err.message(ErrorMessageVerbosity::SafeOnly);
+    /// Creates new error when the installed terraform binary doesn't satisfy the version required by the templates.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `required_version`: Terraform version constraint declared in templates.
+    /// * `installed_version`: Installed terraform version.
+    pub fn new_incompatible_terraform_version(
+        event_details: EventDetails,
+        required_version: String,
+        installed_version: String,
+    ) -> EngineError {
+        let message = format!(
+            "Installed terraform version `{}` doesn't satisfy the required version `{}`.",
+            installed_version, required_version
+        );
 
-        // verify:
-        assert!(!res.contains("my_secret"));
-        assert!(!res.contains("my_secret_value"));
+        EngineError::new(
+            event_details,
+            Tag::TerraformIncompatibleVersion,
+            message.to_string(),
+            Some(CommandError::new_from_safe_message(message)),
+            None,
+            Some(format!(
+                "Please install a terraform version satisfying `{}` to run the engine.",
+                required_version
+            )),
+        )
     }
 
-    #[test]
-    fn test_command_error_test_hidding_env_vars_in_debug() {
-        // setup:
-        let command_err = CommandError::new(
-            "my safe message".to_string(),
-            Some("my raw message".to_string()),
-            Some(vec![("my_secret".to_string(), "my_secret_value".to_string())]),
+    /// Creates new error when a persistent volume claim cannot be bound to any volume.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `pvc_name`: Name of the pending persistent volume claim.
+    /// * `storage_class`: Storage class requested by the claim.
+    /// * `requested_size`: Storage size requested by the claim.
+    /// * `raw_error`: Raw error message, from kubernetes events.
+    pub fn new_pvc_binding_failed(
+        event_details: EventDetails,
+        pvc_name: String,
+        storage_class: String,
+        requested_size: String,
+        raw_error: CommandError,
+    ) -> EngineError {
+        let message = format!(
+            "Volume claim `{}` requesting {} of storage class `{}` cannot be bound to any volume.",
+            pvc_name, requested_size, storage_class
         );
 
-        // execute:
-        let res = format!("{}", command_err);
-
-        // verify:
-        assert!(!res.contains("my_secret"));
-        assert!(!res.contains("my_secret_value"));
+        EngineError::new(
+            event_details,
+            Tag::K8sPvcBindingFailed,
+            message,
+            Some(raw_error),
+            None,
+            Some("Please ensure the storage class exists and has enough capacity for the requested size.".to_string()),
+        )
     }
 
-    #[test]
-    fn test_engine_error_test_hidding_env_vars_in_debug() {
-        // setup:
-        let command_err = CommandError::new(
-            "my safe message".to_string(),
-            Some("my raw message".to_string()),
-            Some(vec![("my_secret".to_string(), "my_secret_value".to_string())]),
+    /// Creates new error when an environment variable references a service which cannot be found in the environment.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `env_var_name`: Name of the environment variable holding the reference.
+    /// * `service_reference`: Unresolved service reference.
+    pub fn new_unresolved_service_reference(
+        event_details: EventDetails,
+        env_var_name: String,
+        service_reference: String,
+    ) -> EngineError {
+        let message = format!(
+            "Error, environment variable `{}` references `{}` which cannot be found in the environment.",
+            env_var_name, service_reference
+        );
+
+        EngineError::new(
+            event_details,
+            Tag::UnresolvedServiceReference,
+            message,
+            None,
+            None,
+            Some("Please ensure the referenced service is part of the same environment.".to_string()),
+        )
+    }
+
+    /// Creates new error when an admission webhook is unavailable while creating a namespace.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `namespace`: Namespace name.
+    /// * `webhook_name`: Name of the failing admission webhook.
+    /// * `raw_error`: Raw error message.
+    pub fn new_admission_webhook_unavailable(
+        event_details: EventDetails,
+        namespace: String,
+        webhook_name: String,
+        raw_error: CommandError,
+    ) -> EngineError {
+        let message = format!(
+            "Error, unable to create namespace `{}`: admission webhook `{}` is unavailable.",
+            namespace, webhook_name
+        );
+
+        EngineError::new(
+            event_details,
+            Tag::AdmissionWebhookUnavailable,
+            message,
+            Some(raw_error),
+            None,
+            Some(format!(
+                "Please ensure the controller serving the `{}` admission webhook is running on the cluster.",
+                webhook_name
+            )),
+        )
+    }
+
+    /// Creates new error when the kubeconfig doesn't contain the context of the cluster.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `expected_context`: Context expected for the cluster.
+    /// * `available_contexts`: Contexts found in the kubeconfig.
+    pub fn new_kube_context_not_found(
+        event_details: EventDetails,
+        expected_context: String,
+        available_contexts: Vec<String>,
+    ) -> EngineError {
+        let message = format!(
+            "Kubeconfig doesn't contain expected context `{}`, available contexts: [{}]",
+            expected_context,
+            available_contexts.join(", ")
+        );
+
+        EngineError::new(
+            event_details,
+            Tag::KubeContextNotFound,
+            message,
+            None,
+            None,
+            Some("Ensure the kubeconfig stored for this cluster is the one generated for it.".to_string()),
+        )
+    }
+
+    /// Creates new error when the readiness annotation of a workload is not set to its expected value in time.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `annotation`: Name of the readiness annotation.
+    /// * `expected_value`: Value the annotation must have.
+    /// * `current_value`: Value of the annotation when the timeout was reached, if any.
+    /// * `timeout`: Time waited for the annotation.
+    pub fn new_readiness_annotation_timeout(
+        event_details: EventDetails,
+        annotation: String,
+        expected_value: String,
+        current_value: Option<String>,
+        timeout: Duration,
+    ) -> EngineError {
+        let message = format!(
+            "Annotation `{}` was expected to be `{}` after {} seconds, but its current value is {}",
+            annotation,
+            expected_value,
+            timeout.as_secs(),
+            match current_value {
+                Some(value) => format!("`{}`", value),
+                None => "not set".to_string(),
+            }
+        );
+
+        EngineError::new(
+            event_details,
+            Tag::K8sReadinessAnnotationTimeout,
+            message,
+            None,
+            None,
+            Some(
+                "Ensure the controller in charge of this annotation is running and watching your application."
+                    .to_string(),
+            ),
+        )
+    }
+
+    /// Creates new error when the initialization scripts of a database failed to run.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `database_name`: Name of the database.
+    /// * `reason`: Why the scripts failed.
+    /// * `output`: Output of the scripts, if they could be started.
+    pub fn new_database_init_scripts_failed(
+        event_details: EventDetails,
+        database_name: String,
+        reason: String,
+        output: Option<String>,
+    ) -> EngineError {
+        let message = match output {
+            Some(output) => format!(
+                "Initialization scripts of database `{}` failed: {}\nOutput:\n{}",
+                database_name, reason, output
+            ),
+            None => format!("Initialization scripts of database `{}` failed: {}", database_name, reason),
+        };
+
+        EngineError::new(
+            event_details,
+            Tag::DatabaseInitScriptsFailed,
+            message,
+            None,
+            None,
+            Some("Fix your initialization scripts, they will run again on next deployment.".to_string()),
+        )
+    }
+
+    /// Creates new error when the TLS certificate of a router custom domain cannot be provisioned.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `router_name`: Router name.
+    /// * `reason`: Why the certificate cannot be provisioned.
+    pub fn new_router_tls_certificate_failed(
+        event_details: EventDetails,
+        router_name: String,
+        reason: String,
+    ) -> EngineError {
+        EngineError::new(
+            event_details,
+            Tag::RouterTlsCertificateFailed,
+            format!("TLS certificate of router `{}` cannot be provisioned: {}", router_name, reason),
+            None,
+            None,
+            Some(
+                "Check that your custom domains point to the cluster and that the certificate issuer is valid."
+                    .to_string(),
+            ),
+        )
+    }
+
+    /// Creates new error when the service mesh injection cannot be enabled on a namespace.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `namespace`: Namespace to enable the injection on.
+    /// * `raw_error`: Raw error message.
+    pub fn new_k8s_cannot_enable_service_mesh_injection(
+        event_details: EventDetails,
+        namespace: String,
+        raw_error: CommandError,
+    ) -> EngineError {
+        EngineError::new(
+            event_details,
+            Tag::K8sCannotEnableServiceMeshInjection,
+            format!("Error, unable to enable service mesh injection on namespace `{}`.", namespace),
+            Some(raw_error),
+            None,
+            None,
+        )
+    }
+
+    /// Creates new error when resources of a deleted service are still present in the cluster.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `remaining_resources`: Resources still present, prefixed by their kind.
+    pub fn new_incomplete_cleanup(event_details: EventDetails, remaining_resources: Vec<String>) -> EngineError {
+        EngineError::new(
+            event_details,
+            Tag::IncompleteCleanup,
+            format!(
+                "Service has been deleted but some of its resources are still present: {}",
+                remaining_resources.join(", ")
+            ),
+            None,
+            None,
+            Some(
+                "Check for finalizers blocking their deletion, they may conflict with future deployments.".to_string(),
+            ),
+        )
+    }
+
+    /// Creates new error when the generated credentials of a database cannot be stored in a secret.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `database_name`: Name of the database.
+    /// * `raw_error`: Raw error message.
+    pub fn new_database_credentials_cannot_be_stored(
+        event_details: EventDetails,
+        database_name: String,
+        raw_error: CommandError,
+    ) -> EngineError {
+        EngineError::new(
+            event_details,
+            Tag::DatabaseCredentialsCannotBeStored,
+            format!("Generated credentials of database `{}` cannot be stored.", database_name),
+            Some(raw_error),
+            None,
+            None,
+        )
+    }
+
+    /// Creates new error when a pod of the service is stuck on a node which is not ready.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `node_name`: Name of the node which is not ready.
+    /// * `pod_name`: Name of the pod scheduled on this node.
+    pub fn new_node_not_ready(event_details: EventDetails, node_name: String, pod_name: String) -> EngineError {
+        let message = format!(
+            "Pod `{}` cannot become ready because its node `{}` is not ready.",
+            pod_name, node_name
+        );
+
+        EngineError::new(
+            event_details,
+            Tag::K8sNodeIsNotReady,
+            message,
+            None,
+            None,
+            Some("This is an infrastructure issue, retry once the node has recovered or been replaced.".to_string()),
+        )
+    }
+
+    /// Creates new error when a subchart declared in the chart dependencies cannot be fetched.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `chart_name`: Name of the chart declaring the dependency.
+    /// * `dependency_name`: Name of the dependency which cannot be fetched.
+    /// * `raw_error`: Raw error message.
+    pub fn new_helm_dependency_failed(
+        event_details: EventDetails,
+        chart_name: String,
+        dependency_name: String,
+        raw_error: CommandError,
+    ) -> EngineError {
+        let message = format!(
+            "Dependency `{}` of helm chart `{}` cannot be fetched.",
+            dependency_name, chart_name
+        );
+
+        EngineError::new(
+            event_details,
+            Tag::HelmDependencyFailed,
+            message,
+            Some(raw_error),
+            None,
+            Some("Please ensure the dependency repository is reachable and serves the requested version.".to_string()),
+        )
+    }
+
+    /// Creates new error when a statefulset rollout is stuck on one of its replicas.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `statefulset_name`: Name of the statefulset.
+    /// * `failed_ordinal`: Ordinal of the first replica which is not ready.
+    /// * `ready_replicas`: Number of ready replicas.
+    /// * `replicas`: Number of desired replicas.
+    /// * `pod_logs`: Last logs of the replica which is not ready.
+    pub fn new_statefulset_partial_rollout(
+        event_details: EventDetails,
+        statefulset_name: String,
+        failed_ordinal: u32,
+        ready_replicas: u32,
+        replicas: u32,
+        pod_logs: String,
+    ) -> EngineError {
+        let message = format!(
+            "Statefulset `{}` rollout is stuck on replica `{}-{}`, {}/{} replicas are ready.",
+            statefulset_name, statefulset_name, failed_ordinal, ready_replicas, replicas
+        );
+
+        EngineError::new(
+            event_details,
+            Tag::K8sStatefulsetPartialRollout,
+            message.clone(),
+            Some(CommandError::new(message, Some(pod_logs), None)),
+            None,
+            Some("Replicas are deployed in order, check the logs of the replica which is not ready.".to_string()),
+        )
+    }
+
+    /// Creates new error when the warmup requests sent to the pods of an application failed.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `application_name`: Name of the application.
+    /// * `failures`: Description of the failed requests.
+    pub fn new_application_warmup_failed(
+        event_details: EventDetails,
+        application_name: String,
+        failures: Vec<String>,
+    ) -> EngineError {
+        let message = format!("Warmup of application `{}` failed: {}.", application_name, failures.join(", "));
+
+        EngineError::new(
+            event_details,
+            Tag::ApplicationWarmupFailed,
+            message,
+            None,
+            None,
+            Some("Ensure the warmup endpoints answer with a success status.".to_string()),
+        )
+    }
+
+    /// Creates new error when a deployment combines a version change with a significant resource change.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `resource_changes`: Resource requests changing along with the version.
+    pub fn new_combined_change_rejected(event_details: EventDetails, resource_changes: Vec<String>) -> EngineError {
+        let message = format!(
+            "Deployment changes both the version and the resources ({}), which is rejected by the advanced settings.",
+            resource_changes.join(", ")
+        );
+
+        EngineError::new(
+            event_details,
+            Tag::CombinedChangeRejected,
+            message,
+            None,
+            None,
+            Some("Deploy the version and the resources change separately.".to_string()),
+        )
+    }
+
+    /// Creates new error when a pod is still held by scheduling gates once the deployment stops waiting for it.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `pod_name`: Name of the pod held by the scheduling gates.
+    /// * `gates`: Scheduling gates not removed yet.
+    /// * `max_wait`: How long pods are allowed to be held by scheduling gates.
+    pub fn new_scheduling_gate_timeout(
+        event_details: EventDetails,
+        pod_name: String,
+        gates: Vec<String>,
+        max_wait: Duration,
+    ) -> EngineError {
+        let message = format!(
+            "Pod `{}` is still awaiting scheduling gate `{}`, it has not been scheduled in time.",
+            pod_name,
+            gates.join(", ")
+        );
+
+        EngineError::new(
+            event_details,
+            Tag::K8sSchedulingGateTimeout,
+            message,
+            None,
+            None,
+            Some(format!(
+                "Check the controller removing the gate, or increase the max wait (currently {}s).",
+                max_wait.as_secs()
+            )),
+        )
+    }
+
+    /// Creates new error when a release has no revision tagged with the requested tag.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `release_name`: Name of the helm release.
+    /// * `tag`: Requested tag.
+    /// * `available_tags`: Tags set on the revisions still in the release history.
+    pub fn new_helm_release_tag_not_found(
+        event_details: EventDetails,
+        release_name: String,
+        tag: String,
+        available_tags: Vec<String>,
+    ) -> EngineError {
+        let message = format!("No revision of helm release `{}` is tagged `{}`.", release_name, tag);
+        let hint = match available_tags.is_empty() {
+            true => "No revision of this release is tagged, tag a deployment to be able to rollback to it.".to_string(),
+            false => format!("Available tags are: {}.", available_tags.join(", ")),
+        };
+
+        EngineError::new(event_details, Tag::HelmReleaseTagNotFound, message, None, None, Some(hint))
+    }
+
+    /// Creates new error when an admission webhook denies the resources of a helm release.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `helm_release`: Helm release name.
+    /// * `webhook`: Name of the admission webhook which denied the request.
+    /// * `reason`: Reason given by the webhook.
+    pub fn new_helm_admission_rejected(
+        event_details: EventDetails,
+        helm_release: String,
+        webhook: String,
+        reason: String,
+    ) -> EngineError {
+        let message = format!(
+            "Error, admission webhook `{}` denied the resources of helm release `{}`: {}",
+            webhook, helm_release, reason
+        );
+        // policy engines reject by design, retrying won't help but an explicit exemption might
+        let hint = match is_policy_admission_webhook(&webhook) {
+            true => "The service violates a cluster policy. If it is legitimate, exempt the service with the `deployment.policy_exemption.labels` advanced setting, as honored by the cluster constraint templates.",
+            false => "The cluster admission webhook rejected the deployment, check its policies against the service configuration.",
+        };
+
+        EngineError::new(
+            event_details,
+            Tag::HelmAdmissionRejected,
+            message,
+            None,
+            None,
+            Some(hint.to_string()),
+        )
+    }
+
+    /// Creates new error when the workloads of a release are not ready once the engine stops waiting for them.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `helm_release`: Name of the helm release.
+    /// * `not_ready_workloads`: Workloads not rolled out yet, with their ready replicas.
+    /// * `timeout`: How long the engine waited for the workloads to be ready.
+    pub fn new_workload_readiness_timeout(
+        event_details: EventDetails,
+        helm_release: String,
+        not_ready_workloads: Vec<String>,
+        timeout: Duration,
+    ) -> EngineError {
+        let message = format!(
+            "Workloads of release `{}` are not ready after {}s: {}.",
+            helm_release,
+            timeout.as_secs(),
+            match not_ready_workloads.is_empty() {
+                true => "no workload found".to_string(),
+                false => not_ready_workloads.join(", "),
+            }
+        );
+
+        EngineError::new(
+            event_details,
+            Tag::WorkloadReadinessTimeout,
+            message,
+            None,
+            None,
+            Some("Check the logs and events of the pods, or increase the probes delays to get more time.".to_string()),
+        )
+    }
+
+    /// Creates new error when a feature required by the deployment is not available on the cluster version.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `feature`: Name of the required feature.
+    /// * `required_version`: First Kubernetes version providing the feature.
+    /// * `cluster_version`: Kubernetes version running on the cluster.
+    pub fn new_unsupported_kubernetes_version(
+        event_details: EventDetails,
+        feature: String,
+        required_version: VersionsNumber,
+        cluster_version: VersionsNumber,
+    ) -> EngineError {
+        let message = format!(
+            "Kubernetes {} is required for {}, but the cluster runs Kubernetes {}.",
+            required_version, feature, cluster_version
+        );
+
+        EngineError::new(
+            event_details,
+            Tag::UnsupportedKubernetesVersion,
+            message,
+            None,
+            None,
+            Some("Upgrade the cluster, or disable the feature requiring it.".to_string()),
+        )
+    }
+
+    /// Creates new error when the job a service requires before being deployed did not complete.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `service_name`: Name of the service being deployed.
+    /// * `reason`: Why the job did not complete.
+    /// * `output`: Last lines of the job logs, if any.
+    pub fn new_pre_deploy_job_failed(
+        event_details: EventDetails,
+        service_name: String,
+        reason: String,
+        output: Option<String>,
+    ) -> EngineError {
+        let message = match output {
+            Some(output) => format!(
+                "Pre-deploy job of service `{}` failed: {}\nOutput:\n{}",
+                service_name, reason, output
+            ),
+            None => format!("Pre-deploy job of service `{}` failed: {}", service_name, reason),
+        };
+
+        EngineError::new(
+            event_details,
+            Tag::PreDeployJobFailed,
+            message,
+            None,
+            None,
+            Some("Fix your pre-deploy job, it will run again on next deployment.".to_string()),
+        )
+    }
+
+    /// Creates new error when the helm release a service is deployed as has not been installed by the engine.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `helm_release`: Helm release name.
+    /// * `namespace`: Namespace of the release.
+    /// * `chart_name`: Chart the existing release has been installed from, if it can be read.
+    pub fn new_helm_release_not_managed(
+        event_details: EventDetails,
+        helm_release: String,
+        namespace: String,
+        chart_name: Option<String>,
+    ) -> EngineError {
+        let message = match chart_name {
+            Some(chart_name) => format!(
+                "Error, helm release `{}` in namespace `{}` already exists and has not been deployed by Qovery (chart `{}`).",
+                helm_release, namespace, chart_name
+            ),
+            None => format!(
+                "Error, helm release `{}` in namespace `{}` already exists and has not been deployed by Qovery.",
+                helm_release, namespace
+            ),
+        };
+
+        EngineError::new(
+            event_details,
+            Tag::HelmReleaseNotManaged,
+            message,
+            None,
+            None,
+            Some("Rename or uninstall the existing release, or enable the adoption of existing helm releases to let Qovery take it over.".to_string()),
+        )
+    }
+
+    /// Creates new error when a namespace dedicated to a service already exists and does not carry its owner label.
+    ///
+    /// Arguments:
+    ///
+    /// * `event_details`: Error linked event details.
+    /// * `namespace`: Namespace name.
+    /// * `owner_label`: Label, as `key=value`, the namespace should carry.
+    pub fn new_namespace_not_owned(event_details: EventDetails, namespace: String, owner_label: String) -> EngineError {
+        let message = format!(
+            "Error, namespace `{}` already exists and does not belong to this service (missing label `{}`).",
+            namespace, owner_label
+        );
+
+        EngineError::new(
+            event_details,
+            Tag::NamespaceNotOwned,
+            message,
+            None,
+            None,
+            Some("Use another namespace, the service deletes its namespace along with it.".to_string()),
+        )
+    }
+}
+impl Display for EngineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Note: just in case, env vars are not leaked since it can hold sensitive data such as secrets.
+        f.write_str(self.message(ErrorMessageVerbosity::FullDetailsWithoutEnvVars).as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cloud_provider::Kind;
+    use crate::errors::{CommandError, EngineError, ErrorMessageVerbosity, Tag};
+    use crate::events::{EventDetails, InfrastructureStep, Stage, Transmitter};
+    use crate::io_models::QoveryIdentifier;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_command_error_test_hidding_env_vars_in_message_safe_only() {
+        // setup:
+        let command_err = CommandError::new(
+            "my safe message".to_string(),
+            Some("my raw message".to_string()),
+            Some(vec![("my_secret".to_string(), "my_secret_value".to_string())]),
+        );
+
+        // execute:
+        let res = command_err.message(ErrorMessageVerbosity::SafeOnly);
+
+        // verify:
+        assert!(!res.contains("my_secret"));
+        assert!(!res.contains("my_secret_value"));
+    }
+
+    #[test]
+    fn test_command_error_test_hidding_env_vars_in_message_full_without_env_vars() {
+        // setup:
+        let command_err = CommandError::new(
+            "my safe message".to_string(),
+            Some("my raw message".to_string()),
+            Some(vec![("my_secret".to_string(), "my_secret_value".to_string())]),
+        );
+
+        // execute:
+        let res = command_err.message(ErrorMessageVerbosity::FullDetailsWithoutEnvVars);
+
+        // verify:
+        assert!(!res.contains("my_secret"));
+        assert!(!res.contains("my_secret_value"));
+    }
+
+    #[test]
+    fn test_engine_error_test_hidding_env_vars_in_message_safe_only() {
+        // setup:
+        let command_err = CommandError::new(
+            "my safe message".to_string(),
+            Some("my raw message".to_string()),
+            Some(vec![("my_secret".to_string(), "my_secret_value".to_string())]),
+        );
+        let cluster_id = QoveryIdentifier::new_random();
+        let engine_err = EngineError::new_unknown(
+            EventDetails::new(
+                Some(Kind::Scw),
+                QoveryIdentifier::new_random(),
+                QoveryIdentifier::new_random(),
+                Uuid::new_v4().to_string(),
+                Stage::Infrastructure(InfrastructureStep::Create),
+                Transmitter::Kubernetes(Uuid::new_v4(), cluster_id.to_string()),
+            ),
+            "user_log_message".to_string(),
+            Some(command_err),
+            None,
+            None,
+        );
+
+        // execute:
+        let res = engine_err.message(ErrorMessageVerbosity::SafeOnly);
+
+        // verify:
+        assert!(!res.contains("my_secret"));
+        assert!(!res.contains("my_secret_value"));
+    }
+
+    #[test]
+    fn test_engine_error_test_hidding_env_vars_in_message_full_without_env_vars() {
+        // setup:
+        let command_err = CommandError::new(
+            "my safe message".to_string(),
+            Some("my raw message".to_string()),
+            Some(vec![("my_secret".to_string(), "my_secret_value".to_string())]),
+        );
+        let cluster_id = QoveryIdentifier::new_random();
+        let engine_err = EngineError::new_unknown(
+            EventDetails::new(
+                Some(Kind::Scw),
+                QoveryIdentifier::new_random(),
+                QoveryIdentifier::new_random(),
+                Uuid::new_v4().to_string(),
+                Stage::Infrastructure(InfrastructureStep::Create),
+                Transmitter::Kubernetes(Uuid::new_v4(), cluster_id.to_string()),
+            ),
+            "user_log_message".to_string(),
+            Some(command_err),
+            None,
+            None,
+        );
+
+        // execute:
+        let res = engine_err.message(ErrorMessageVerbosity::SafeOnly);
+
+        // verify:
+        assert!(!res.contains("my_secret"));
+        assert!(!res.contains("my_secret_value"));
+    }
+
+    #[test]
+    fn test_command_error_test_hidding_env_vars_in_debug() {
+        // setup:
+        let command_err = CommandError::new(
+            "my safe message".to_string(),
+            Some("my raw message".to_string()),
+            Some(vec![("my_secret".to_string(), "my_secret_value".to_string())]),
+        );
+
+        // execute:
+        let res = format!("{}", command_err);
+
+        // verify:
+        assert!(!res.contains("my_secret"));
+        assert!(!res.contains("my_secret_value"));
+    }
+
+    #[test]
+    fn test_engine_error_test_hidding_env_vars_in_debug() {
+        // setup:
+        let command_err = CommandError::new(
+            "my safe message".to_string(),
+            Some("my raw message".to_string()),
+            Some(vec![("my_secret".to_string(), "my_secret_value".to_string())]),
         );
         let cluster_id = QoveryIdentifier::new_random();
         let engine_err = EngineError::new_unknown(
@@ -4039,4 +5024,31 @@ mod tests {
             &Stage::Infrastructure(InfrastructureStep::CreateError)
         );
     }
+
+    #[test]
+    fn test_engine_error_with_attempts() {
+        // setup:
+        let engine_err = EngineError::new_unknown(
+            EventDetails::new(
+                Some(Kind::Scw),
+                QoveryIdentifier::new_random(),
+                QoveryIdentifier::new_random(),
+                "".to_string(),
+                Stage::Infrastructure(InfrastructureStep::Create),
+                Transmitter::Kubernetes(Uuid::new_v4(), QoveryIdentifier::new_random().to_string()),
+            ),
+            "user_log_message".to_string(),
+            None,
+            None,
+            None,
+        );
+
+        // execute:
+        let engine_err = engine_err.with_attempts(3);
+
+        // verify:
+        assert_eq!("user_log_message (failed after 3 attempts)", engine_err.user_log_message());
+        assert!(!engine_err.tag().is_transient());
+        assert!(Tag::HelmDeployTimeout.is_transient());
+    }
 }
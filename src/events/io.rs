@@ -171,6 +171,9 @@ pub enum EnvironmentStep {
     Delete,
     Deleted,
     DeletedError,
+    Restart,
+    Restarted,
+    RestartedError,
     LoadConfiguration,
     Start,
     Terminated,
@@ -180,6 +183,7 @@ pub enum EnvironmentStep {
     RetrieveClusterResources,
     UnderMigration,
     JobOutput,
+    VersionMismatch,
 }
 
 impl From<events::EnvironmentStep> for EnvironmentStep {
@@ -202,12 +206,16 @@ impl From<events::EnvironmentStep> for EnvironmentStep {
             events::EnvironmentStep::DeployedError => EnvironmentStep::DeployedError,
             events::EnvironmentStep::PausedError => EnvironmentStep::PausedError,
             events::EnvironmentStep::DeletedError => EnvironmentStep::DeletedError,
+            events::EnvironmentStep::Restart => EnvironmentStep::Restart,
+            events::EnvironmentStep::Restarted => EnvironmentStep::Restarted,
+            events::EnvironmentStep::RestartedError => EnvironmentStep::RestartedError,
             events::EnvironmentStep::ValidateApiInput => EnvironmentStep::ValidateApiInput,
             events::EnvironmentStep::ValidateSystemRequirements => EnvironmentStep::ValidateSystemRequirements,
             events::EnvironmentStep::RetrieveClusterConfig => EnvironmentStep::RetrieveClusterConfig,
             events::EnvironmentStep::RetrieveClusterResources => EnvironmentStep::RetrieveClusterResources,
             events::EnvironmentStep::UnderMigration => EnvironmentStep::UnderMigration,
             events::EnvironmentStep::JobOutput => EnvironmentStep::JobOutput,
+            events::EnvironmentStep::VersionMismatch => EnvironmentStep::VersionMismatch,
         }
     }
 }
@@ -261,6 +269,8 @@ pub struct EventDetails {
     execution_id: String,
     stage: Stage,
     transmitter: Transmitter,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    transaction_id: Option<String>,
 }
 
 impl From<events::EventDetails> for EventDetails {
@@ -271,6 +281,7 @@ impl From<events::EventDetails> for EventDetails {
             execution_id: details.execution_id.to_string(),
             stage: Stage::from(details.stage),
             transmitter: Transmitter::from(details.transmitter),
+            transaction_id: details.transaction_id.map(|id| id.to_string()),
         }
     }
 }
@@ -315,4 +326,24 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn should_serialize_transaction_id_when_set() {
+        let transaction_id = Uuid::new_v4();
+        let event_details = EventDetails::new(
+            Some(Kind::Scw),
+            QoveryIdentifier::new_random(),
+            QoveryIdentifier::new_random(),
+            QoveryIdentifier::new_random().to_string(),
+            Stage::Infrastructure(InfrastructureStep::Create),
+            Transmitter::Kubernetes(Uuid::new_v4(), "".to_string()),
+        );
+
+        let json = serde_json::to_string(&super::EventDetails::from(event_details.clone())).unwrap();
+        assert!(!json.contains("transaction_id"));
+
+        let json = serde_json::to_string(&super::EventDetails::from(event_details.with_transaction_id(transaction_id)))
+            .unwrap();
+        assert!(json.contains(&format!(r#""transaction_id":"{}""#, transaction_id)));
+    }
 }
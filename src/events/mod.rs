@@ -357,9 +357,17 @@ pub enum EnvironmentStep {
     Deleted,
     /// DeleteError: Terminal error on deleting an environment/service.
     DeletedError,
+    /// Restart: restart the pods of an environment/service, without redeploying it.
+    Restart,
+    /// Restarted: env/service has been restarted.
+    Restarted,
+    /// RestartedError: Terminal error on restarting an environment/service.
+    RestartedError,
     // Transfer data to core
     /// JobOutput: contains the environment variables to upsert
     JobOutput,
+    /// VersionMismatch: contains the version requested for a service and the one it has been matched to
+    VersionMismatch,
 }
 
 impl EnvironmentStep {
@@ -371,6 +379,7 @@ impl EnvironmentStep {
                 | EnvironmentStep::DeployedError
                 | EnvironmentStep::PausedError
                 | EnvironmentStep::DeletedError
+                | EnvironmentStep::RestartedError
         )
     }
 }
@@ -398,12 +407,16 @@ impl Display for EnvironmentStep {
                 EnvironmentStep::DeployedError => "deployed-error",
                 EnvironmentStep::PausedError => "paused-error",
                 EnvironmentStep::DeletedError => "deleted-error",
+                EnvironmentStep::Restart => "restart",
+                EnvironmentStep::Restarted => "restarted",
+                EnvironmentStep::RestartedError => "restarted-error",
                 EnvironmentStep::ValidateApiInput => "validate-api-input",
                 EnvironmentStep::ValidateSystemRequirements => "validate-system-requirements",
                 EnvironmentStep::RetrieveClusterConfig => "retrieve-cluster-config",
                 EnvironmentStep::RetrieveClusterResources => "retrieve-cluster-resources",
                 EnvironmentStep::UnderMigration => "under-migration",
                 EnvironmentStep::JobOutput => "job-output",
+                EnvironmentStep::VersionMismatch => "version-mismatch",
             },
         )
     }
@@ -484,6 +497,8 @@ pub struct EventDetails {
     stage: Stage,
     /// transmitter: source triggering the event.
     transmitter: Transmitter,
+    /// transaction_id: identifier shared by all the events of one engine task invocation.
+    transaction_id: Option<Uuid>,
 }
 
 impl EventDetails {
@@ -512,9 +527,16 @@ impl EventDetails {
             execution_id,
             stage,
             transmitter,
+            transaction_id: None,
         }
     }
 
+    /// Correlates this event with the other ones of the same engine task invocation.
+    pub fn with_transaction_id(mut self, transaction_id: Uuid) -> Self {
+        self.transaction_id = Some(transaction_id);
+        self
+    }
+
     pub(super) fn mut_to_error_stage(&mut self) {
         self.stage = match &self.stage {
             Stage::Infrastructure(step) => match step {
@@ -593,6 +615,11 @@ impl EventDetails {
     pub fn transmitter(&self) -> Transmitter {
         self.transmitter.clone()
     }
+
+    /// Returns event's engine task invocation identifier, if any.
+    pub fn transaction_id(&self) -> Option<&Uuid> {
+        self.transaction_id.as_ref()
+    }
 }
 
 #[cfg(test)]
@@ -1,8 +1,9 @@
 use std::collections::HashSet;
-use std::fs;
 use std::fs::{create_dir_all, File, OpenOptions};
 use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
 use std::path::Path;
+use std::time::{Duration, SystemTime};
+use std::{env, fs};
 
 use crate::cmd::structs::SecretItem;
 use crate::errors::CommandError;
@@ -148,6 +149,65 @@ pub fn cleanup_workspace_directory(working_root_dir: &str, execution_id: &str) -
     };
 }
 
+/// Remove the service workspace once successfully deployed, rendered files are kept unless explicitly enabled
+pub fn default_workspace_cleanup_after_deploy() -> bool {
+    match env::var("WORKSPACE_CLEANUP_AFTER_DEPLOY") {
+        Ok(env_var) => env_var.parse::<bool>().unwrap_or(false),
+        Err(_) => false,
+    }
+}
+
+/// Age after which execution workspaces are pruned, disabled unless explicitly set
+pub fn default_stale_workspace_max_age() -> Option<Duration> {
+    env::var("WORKSPACE_STALE_MAX_AGE_IN_SECS")
+        .ok()?
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+pub fn cleanup_service_workspace_directory(workspace_dir: &str) -> Result<(), Error> {
+    match fs::remove_dir_all(workspace_dir) {
+        Ok(_) => Ok(()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Remove the execution workspaces, and their archives, not modified for more than `max_age`.
+/// Returns the removed paths
+pub fn cleanup_stale_workspaces(working_root_dir: &str, max_age: Duration) -> Result<Vec<String>, Error> {
+    let workspaces_dir = Path::new(working_root_dir).join(".qovery-workspace");
+    if !workspaces_dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let now = SystemTime::now();
+    let mut removed = vec![];
+    for entry in fs::read_dir(&workspaces_dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let age = metadata
+            .modified()
+            .map(|modified| now.duration_since(modified).unwrap_or_default())?;
+        if age < max_age {
+            continue;
+        }
+
+        let path = entry.path();
+        let result = match metadata.is_dir() {
+            true => fs::remove_dir_all(&path),
+            false => fs::remove_file(&path),
+        };
+        match result {
+            Ok(_) => removed.push(path.to_string_lossy().to_string()),
+            Err(err) => error!("error trying to remove stale workspace '{}', error: {}", path.display(), err),
+        }
+    }
+
+    Ok(removed)
+}
+
 pub fn create_workspace_archive(working_root_dir: &str, execution_id: &str) -> Result<String, Error> {
     info!("archive workspace directory in progress");
 
@@ -491,6 +551,36 @@ mod tests {
         tmp_dir.close().expect("error closing temporary directory");
     }
 
+    #[test]
+    fn test_cleanup_stale_workspaces() {
+        // setup:
+        let tmp_dir = TempDir::new("stale_workspaces").expect("error creating temporary dir");
+        let root_dir = tmp_dir.path().to_str().expect("error getting file path string");
+        let workspace_dir =
+            workspace_directory(root_dir, "123", "applications/app-1").expect("error creating workspace");
+        File::create(format!("{}/.qovery-workspace/123.tgz", root_dir)).expect("error creating archive file");
+
+        // execute & verify:
+        let removed = cleanup_stale_workspaces(root_dir, Duration::from_secs(3600)).expect("error cleaning workspaces");
+        assert!(removed.is_empty());
+        assert!(Path::new(&workspace_dir).exists());
+
+        let mut removed = cleanup_stale_workspaces(root_dir, Duration::ZERO).expect("error cleaning workspaces");
+        removed.sort();
+        assert_eq!(
+            removed,
+            vec![
+                format!("{}/.qovery-workspace/123", root_dir),
+                format!("{}/.qovery-workspace/123.tgz", root_dir),
+            ]
+        );
+        assert!(!Path::new(&workspace_dir).exists());
+        assert!(cleanup_service_workspace_directory(&workspace_dir).is_ok());
+
+        // clean:
+        tmp_dir.close().expect("error closing temporary directory");
+    }
+
     #[test]
     fn test_backup_cleaning() {
         let content = r#"
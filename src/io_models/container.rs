@@ -2,7 +2,7 @@ use crate::cloud_provider::kubernetes::Kind as KubernetesKind;
 use crate::cloud_provider::{CloudProvider, Kind as CPKind};
 use crate::container_registry::ecr::ECR;
 use crate::container_registry::ContainerRegistry;
-use crate::io_models::application::{to_environment_variable, AdvancedSettingsProbeType, Port, Storage};
+use crate::io_models::application::{to_environment_variable, AdvancedSettingsProbeType, Port, Storage, Toleration};
 use crate::io_models::context::Context;
 use crate::io_models::Action;
 use crate::models;
@@ -156,6 +156,60 @@ pub struct ContainerAdvancedSettings {
     // Deployment
     #[serde(alias = "deployment.custom_domain_check_enabled")]
     pub deployment_custom_domain_check_enabled: bool,
+    // Off by default: pods of the previous revision are checked too, one on a NotReady node fails the deployment
+    #[serde(alias = "deployment.node_not_ready_fail_fast")]
+    pub deployment_node_not_ready_fail_fast: bool,
+    // Pods held by scheduling gates are expected to wait for an external controller, fail only past this delay
+    #[serde(alias = "deployment.scheduling_gate_max_wait_sec")]
+    pub deployment_scheduling_gate_max_wait_sec: u32,
+    // Wait for the rollout from the engine to report the workloads not ready. helm then neither waits nor rolls back
+    #[serde(alias = "deployment.engine_readiness_check")]
+    pub deployment_engine_readiness_check: bool,
+    #[serde(alias = "deployment.combined_change_threshold_percent")]
+    pub deployment_combined_change_threshold_percent: u32,
+    #[serde(alias = "deployment.reject_combined_changes")]
+    pub deployment_reject_combined_changes: bool,
+    // Labels and annotations the cluster policy constraints honor to exempt the service, a reason is required
+    #[serde(alias = "deployment.policy_exemption.labels")]
+    pub deployment_policy_exemption_labels: BTreeMap<String, String>,
+    #[serde(alias = "deployment.policy_exemption.annotations")]
+    pub deployment_policy_exemption_annotations: BTreeMap<String, String>,
+    #[serde(alias = "deployment.policy_exemption.reason")]
+    pub deployment_policy_exemption_reason: String,
+    // Idle pods kept on top of the service ones, preempted as soon as the service needs their room. 0 to disable
+    #[serde(alias = "deployment.standby_replicas")]
+    pub deployment_standby_replicas: u32,
+    // Run the service container as this user id and from this absolute directory. Unset to keep the image ones
+    #[serde(alias = "deployment.container_user")]
+    pub deployment_container_user: Option<u32>,
+    #[serde(alias = "deployment.working_dir")]
+    pub deployment_working_dir: String,
+    // Remove the autoscaler while the service is paused so it does not scale it back up, restored on resume
+    #[serde(alias = "deployment.pause_suspend_hpa")]
+    pub deployment_pause_suspend_hpa: bool,
+    // Job run to completion with the service image before deploying it, i.e: a data backfill. Empty to disable
+    #[serde(alias = "deployment.pre_deploy_job.command")]
+    pub deployment_pre_deploy_job_command: Vec<String>,
+    // The job runs again only when this key changes, empty to run it once per image
+    #[serde(alias = "deployment.pre_deploy_job.run_key")]
+    pub deployment_pre_deploy_job_run_key: String,
+    #[serde(alias = "deployment.pre_deploy_job.timeout_sec")]
+    pub deployment_pre_deploy_job_timeout_sec: u32,
+    // Endpoint polled through the service once pods are ready, until it answers the expected status. Empty to disable
+    #[serde(alias = "deployment.readiness_check.path")]
+    pub deployment_readiness_check_path: String,
+    // 0 to use the default port of the service
+    #[serde(alias = "deployment.readiness_check.port")]
+    pub deployment_readiness_check_port: u16,
+    #[serde(alias = "deployment.readiness_check.expected_status")]
+    pub deployment_readiness_check_expected_status: u16,
+    #[serde(alias = "deployment.readiness_check.timeout_sec")]
+    pub deployment_readiness_check_timeout_sec: u32,
+    // Schedule the service on a dedicated node pool, the tolerations must match the taints of its nodes
+    #[serde(alias = "deployment.node_selector")]
+    pub deployment_node_selector: BTreeMap<String, String>,
+    #[serde(alias = "deployment.tolerations")]
+    pub deployment_tolerations: Vec<Toleration>,
 
     // Ingress
     #[serde(alias = "network.ingress.proxy_body_size_mb")]
@@ -186,6 +240,17 @@ pub struct ContainerAdvancedSettings {
     pub network_ingress_proxy_buffer_size_kb: u32,
     #[serde(alias = "network.ingress.whitelist_source_range")]
     pub network_ingress_whitelist_source_range: String,
+    // IngressClass of the controller serving the router, empty to keep the qovery nginx controller
+    #[serde(alias = "network.ingress.class_name")]
+    pub network_ingress_class_name: String,
+    // TLS certificates of custom domains, issued by cert-manager
+    #[serde(alias = "network.ingress.custom_domain_tls.enabled")]
+    pub network_ingress_custom_domain_tls_enabled: bool,
+    // cert-manager ClusterIssuer name, empty to use a Let's Encrypt issuer dedicated to the router
+    #[serde(alias = "network.ingress.custom_domain_tls.issuer")]
+    pub network_ingress_custom_domain_tls_issuer: String,
+    #[serde(alias = "network.ingress.custom_domain_tls.wait_ready")]
+    pub network_ingress_custom_domain_tls_wait_ready: bool,
 
     // Readiness Probes
     #[serde(alias = "readiness_probe.type")]
@@ -228,6 +293,27 @@ impl Default for ContainerAdvancedSettings {
     fn default() -> Self {
         ContainerAdvancedSettings {
             deployment_custom_domain_check_enabled: true,
+            deployment_node_not_ready_fail_fast: false,
+            deployment_scheduling_gate_max_wait_sec: 600,
+            deployment_engine_readiness_check: false,
+            deployment_combined_change_threshold_percent: 20,
+            deployment_reject_combined_changes: false,
+            deployment_policy_exemption_labels: BTreeMap::new(),
+            deployment_policy_exemption_annotations: BTreeMap::new(),
+            deployment_policy_exemption_reason: "".to_string(),
+            deployment_standby_replicas: 0,
+            deployment_container_user: None,
+            deployment_working_dir: "".to_string(),
+            deployment_pause_suspend_hpa: true,
+            deployment_pre_deploy_job_command: vec![],
+            deployment_pre_deploy_job_run_key: "".to_string(),
+            deployment_pre_deploy_job_timeout_sec: 600,
+            deployment_readiness_check_path: "".to_string(),
+            deployment_readiness_check_port: 0,
+            deployment_readiness_check_expected_status: 200,
+            deployment_readiness_check_timeout_sec: 300,
+            deployment_node_selector: BTreeMap::new(),
+            deployment_tolerations: vec![],
             network_ingress_proxy_body_size_mb: 100,
             network_ingress_cors_enable: false,
             network_ingress_sticky_session_enable: false,
@@ -242,6 +328,10 @@ impl Default for ContainerAdvancedSettings {
             network_ingress_proxy_read_timeout_seconds: 60,
             network_ingress_proxy_buffer_size_kb: 4,
             network_ingress_whitelist_source_range: "0.0.0.0/0".to_string(),
+            network_ingress_class_name: "".to_string(),
+            network_ingress_custom_domain_tls_enabled: true,
+            network_ingress_custom_domain_tls_issuer: "".to_string(),
+            network_ingress_custom_domain_tls_wait_ready: false,
             readiness_probe_type: AdvancedSettingsProbeType::Tcp,
             readiness_probe_http_get_path: "/".to_string(),
             readiness_probe_initial_delay_seconds: 30,
@@ -4,9 +4,18 @@ use crate::utilities::to_short_id;
 use rand::distributions::Alphanumeric;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
 use url::Url;
 use uuid::Uuid;
 
+// Helm release names are limited to 53 chars, keep room for the service part
+const HELM_RELEASE_SUFFIX_MAX_LENGTH: usize = 12;
+
 #[derive(Clone)]
 pub struct Context {
     organization_id: Uuid,
@@ -22,6 +31,10 @@ pub struct Context {
     metadata: Option<Metadata>,
     pub docker: Docker,
     event_details: EventDetails,
+    // shared by all the events of the services built from this context
+    transaction_id: Uuid,
+    // set by the task when the user cancels it, long running operations poll it to stop early
+    cancel_requested: Arc<AtomicBool>,
 }
 
 impl Context {
@@ -38,6 +51,8 @@ impl Context {
         docker: Docker,
         event_details: EventDetails,
     ) -> Self {
+        let transaction_id = event_details.transaction_id().copied().unwrap_or_else(Uuid::new_v4);
+
         Context {
             organization_id,
             organization_short_id: to_short_id(&organization_id),
@@ -51,10 +66,25 @@ impl Context {
             features,
             metadata,
             docker,
-            event_details,
+            event_details: event_details.with_transaction_id(transaction_id),
+            transaction_id,
+            cancel_requested: Arc::default(),
         }
     }
 
+    pub fn with_cancel_requested(mut self, cancel_requested: Arc<AtomicBool>) -> Self {
+        self.cancel_requested = cancel_requested;
+        self
+    }
+
+    pub fn cancel_requested(&self) -> Arc<AtomicBool> {
+        self.cancel_requested.clone()
+    }
+
+    pub fn transaction_id(&self) -> &Uuid {
+        &self.transaction_id
+    }
+
     pub fn organization_short_id(&self) -> &str {
         &self.organization_short_id
     }
@@ -98,6 +128,67 @@ impl Context {
         }
     }
 
+    pub fn is_deploy_without_helm(&self) -> bool {
+        match &self.metadata {
+            Some(meta) => matches!(meta.deploy_without_helm, Some(true)),
+            _ => false,
+        }
+    }
+
+    /// Manifests rendered beforehand to deploy without helm, one directory per release name
+    pub fn rendered_charts_directory(&self) -> Option<PathBuf> {
+        let directory = self.metadata.as_ref()?.rendered_charts_directory.as_ref()?.trim();
+        match directory.is_empty() {
+            true => None,
+            false => Some(PathBuf::from(directory)),
+        }
+    }
+
+    pub fn adopt_existing_helm_releases(&self) -> bool {
+        match &self.metadata {
+            Some(meta) => matches!(meta.adopt_existing_helm_releases, Some(true)),
+            _ => false,
+        }
+    }
+
+    pub fn allow_database_version_downgrade(&self) -> bool {
+        match &self.metadata {
+            Some(meta) => matches!(meta.allow_database_version_downgrade, Some(true)),
+            _ => false,
+        }
+    }
+
+    pub fn cleanup_orphaned_tfstate_secrets(&self) -> bool {
+        match &self.metadata {
+            Some(meta) => matches!(meta.cleanup_orphaned_tfstate_secrets, Some(true)),
+            _ => false,
+        }
+    }
+
+    /// How many times a service action is run when it fails on a transient error, 1 to never retry
+    pub fn deployment_max_attempts(&self) -> u32 {
+        match &self.metadata {
+            Some(meta) => meta.deployment_max_attempts.unwrap_or(1).max(1),
+            _ => 1,
+        }
+    }
+
+    pub fn deployment_retry_backoff(&self) -> Duration {
+        let backoff_in_seconds = match &self.metadata {
+            Some(meta) => meta.deployment_retry_backoff_in_seconds.unwrap_or(10),
+            _ => 10,
+        };
+        Duration::from_secs(backoff_in_seconds as u64)
+    }
+
+    pub fn release_tag(&self) -> Option<String> {
+        let tag = self.metadata.as_ref()?.release_tag.as_ref()?.trim();
+        match tag.is_empty() {
+            true => None,
+            false => Some(tag.to_string()),
+        }
+    }
+
     pub fn disable_pleco(&self) -> bool {
         match &self.metadata {
             Some(meta) => meta.disable_pleco.unwrap_or(true),
@@ -123,6 +214,13 @@ impl Context {
         }
     }
 
+    pub fn resource_expiration_action(&self) -> ResourceExpirationAction {
+        match &self.metadata {
+            Some(meta) => meta.resource_expiration_action.unwrap_or_default(),
+            _ => ResourceExpirationAction::default(),
+        }
+    }
+
     pub fn is_first_cluster_deployment(&self) -> bool {
         match &self.metadata {
             Some(meta) => meta.is_first_cluster_deployment.unwrap_or(false),
@@ -130,6 +228,31 @@ impl Context {
         }
     }
 
+    pub fn git_metadata(&self) -> Option<&GitMetadata> {
+        match &self.metadata {
+            Some(meta) => meta.git_metadata.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Suffix to append to helm release names, so several previews of the same service can coexist
+    pub fn helm_release_suffix(&self) -> Option<String> {
+        let preview_id = self.metadata.as_ref()?.preview_id.as_ref()?;
+        let suffix = preview_id
+            .to_lowercase()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+            .take(HELM_RELEASE_SUFFIX_MAX_LENGTH)
+            .collect::<String>()
+            .trim_matches('-')
+            .to_string();
+
+        match suffix.is_empty() {
+            true => None,
+            false => Some(suffix),
+        }
+    }
+
     // Qovery features
     pub fn is_feature_enabled(&self, name: &Features) -> bool {
         for feature in &self.features {
@@ -152,28 +275,126 @@ pub struct Metadata {
     pub dry_run_deploy: Option<bool>,
     pub forced_upgrade: Option<bool>,
     pub resource_expiration_in_seconds: Option<u32>,
+    pub resource_expiration_action: Option<ResourceExpirationAction>,
     pub disable_pleco: Option<bool>,
     pub is_first_cluster_deployment: Option<bool>,
+    #[serde(default)]
+    pub git_metadata: Option<GitMetadata>,
+    // i.e: the pull request number, for preview environments sharing a namespace
+    #[serde(default)]
+    pub preview_id: Option<String>,
+    // apply rendered charts with kubectl instead of helm, when helm cannot be used on the cluster
+    #[serde(default)]
+    pub deploy_without_helm: Option<bool>,
+    // i.e: the output of `helm template --output-dir`, applied instead of rendering the charts when helm is not installed
+    #[serde(default)]
+    pub rendered_charts_directory: Option<String>,
+    // take over helm releases with the name of a service which have not been installed by the engine,
+    // and the resources of a release created outside of helm
+    #[serde(default)]
+    pub adopt_existing_helm_releases: Option<bool>,
+    // deploy a database with a lower version than the requested one, when it is the only one matching
+    #[serde(default)]
+    pub allow_database_version_downgrade: Option<bool>,
+    // delete the terraform states left by services which are not part of the environment anymore
+    #[serde(default)]
+    pub cleanup_orphaned_tfstate_secrets: Option<bool>,
+    // run again the action of a service failing on a transient error (i.e: cluster api unreachable)
+    #[serde(default)]
+    pub deployment_max_attempts: Option<u32>,
+    #[serde(default)]
+    pub deployment_retry_backoff_in_seconds: Option<u32>,
+    // tag the helm revisions of the services deployed by this request, to rollback to them by name later
+    #[serde(default)]
+    pub release_tag: Option<String>,
 }
 
 impl Metadata {
     pub fn new(
         dry_run_deploy: Option<bool>,
         resource_expiration_in_seconds: Option<u32>,
+        resource_expiration_action: Option<ResourceExpirationAction>,
         forced_upgrade: Option<bool>,
         disable_pleco: Option<bool>,
         is_first_cluster_deployment: Option<bool>,
+        git_metadata: Option<GitMetadata>,
+        preview_id: Option<String>,
+        deploy_without_helm: Option<bool>,
+        rendered_charts_directory: Option<String>,
+        adopt_existing_helm_releases: Option<bool>,
+        allow_database_version_downgrade: Option<bool>,
+        cleanup_orphaned_tfstate_secrets: Option<bool>,
+        deployment_max_attempts: Option<u32>,
+        deployment_retry_backoff_in_seconds: Option<u32>,
+        release_tag: Option<String>,
     ) -> Self {
         Metadata {
             dry_run_deploy,
             resource_expiration_in_seconds,
+            resource_expiration_action,
             forced_upgrade,
             disable_pleco,
             is_first_cluster_deployment,
+            git_metadata,
+            preview_id,
+            deploy_without_helm,
+            rendered_charts_directory,
+            adopt_existing_helm_releases,
+            allow_database_version_downgrade,
+            cleanup_orphaned_tfstate_secrets,
+            deployment_max_attempts,
+            deployment_retry_backoff_in_seconds,
+            release_tag,
         }
     }
 }
 
+/// What should happen to resources once their expiration (ttl) is reached
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceExpirationAction {
+    Pause,
+    Delete,
+}
+
+impl Default for ResourceExpirationAction {
+    fn default() -> Self {
+        ResourceExpirationAction::Delete
+    }
+}
+
+impl Display for ResourceExpirationAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ResourceExpirationAction::Pause => "pause",
+            ResourceExpirationAction::Delete => "delete",
+        })
+    }
+}
+
+/// Git information about the source being deployed, exposed as annotations on the deployed resources
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
+pub struct GitMetadata {
+    pub commit_id: String,
+    pub branch: Option<String>,
+    pub author: Option<String>,
+}
+
+impl GitMetadata {
+    pub fn to_annotations(&self) -> BTreeMap<String, String> {
+        let mut annotations = BTreeMap::new();
+        annotations.insert("qovery.com/git-commit".to_string(), self.commit_id.clone());
+        if let Some(branch) = &self.branch {
+            annotations.insert("qovery.com/git-branch".to_string(), branch.clone());
+        }
+        if let Some(author) = &self.author {
+            annotations.insert("qovery.com/git-author".to_string(), author.clone());
+        }
+
+        annotations
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Hash, Eq, PartialEq)]
 pub enum Features {
     LogsHistory,
@@ -204,7 +425,7 @@ impl CloneForTest for Context {
 
 #[cfg(test)]
 mod tests {
-    use crate::io_models::context::Metadata;
+    use crate::io_models::context::{GitMetadata, Metadata};
 
     #[test]
     /// Preventing empty / partially empty metadata input from triggering a deserialization error
@@ -215,9 +436,13 @@ mod tests {
         // verify:
         assert_eq!(None, result.is_first_cluster_deployment);
         assert_eq!(None, result.resource_expiration_in_seconds);
+        assert_eq!(None, result.resource_expiration_action);
         assert_eq!(None, result.forced_upgrade);
         assert_eq!(None, result.disable_pleco);
         assert_eq!(None, result.dry_run_deploy);
+        assert_eq!(None, result.git_metadata);
+        assert_eq!(None, result.preview_id);
+        assert_eq!(None, result.release_tag);
     }
 
     #[test]
@@ -281,4 +506,21 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_git_metadata_to_annotations() {
+        // setup:
+        let git_metadata: GitMetadata =
+            serde_json::from_str(r#"{"commit_id": "abc123", "branch": "main", "author": null}"#)
+                .expect("Error while trying to deserialize GitMetadata");
+
+        // execute:
+        let annotations = git_metadata.to_annotations();
+
+        // verify:
+        assert_eq!(2, annotations.len());
+        assert_eq!(Some(&"abc123".to_string()), annotations.get("qovery.com/git-commit"));
+        assert_eq!(Some(&"main".to_string()), annotations.get("qovery.com/git-branch"));
+        assert_eq!(None, annotations.get("qovery.com/git-author"));
+    }
 }
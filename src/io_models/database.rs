@@ -9,10 +9,21 @@ use crate::models::types::{AWSEc2, VersionsNumber, AWS, SCW};
 use chrono::{DateTime, Utc};
 use core::result::Result;
 use core::result::Result::{Err, Ok};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use uuid::Uuid;
 
+// Namespaces of the cluster components, on top of the `kube-` prefixed ones
+const RESERVED_NAMESPACES: [&str; 6] = [
+    "default",
+    "qovery",
+    "prometheus",
+    "logging",
+    "cert-manager",
+    "nginx-ingress",
+];
+
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
 pub enum DatabaseMode {
     MANAGED,
@@ -44,6 +55,177 @@ pub struct Database {
     pub activate_backups: bool,
     pub publicly_accessible: bool,
     pub mode: DatabaseMode,
+    // for non-managed databases only, name of an additional external-name service pointing to the database
+    #[serde(default)]
+    pub external_name_alias: Option<String>,
+    // run once, in order, when the database is created
+    #[serde(default)]
+    pub init_scripts: Vec<InitScript>,
+    // for managed databases only, the password is generated by the cloud provider instead of using the given one
+    #[serde(default)]
+    pub generated_credentials: bool,
+    // for non-managed databases only, number of read-only replicas streaming from the primary, 0 means no replica
+    #[serde(default)]
+    pub read_replicas: u32,
+    // for replicated non-managed databases only, deploy a proxy always routing connections to the current primary
+    #[serde(default)]
+    pub failover_proxy: bool,
+    // for non-managed databases only, namespace to deploy the database into instead of the environment one
+    #[serde(default)]
+    pub namespace: Option<String>,
+    // ignored when backups are not activated
+    #[serde(default)]
+    pub backup_policy: Option<DatabaseBackupPolicy>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct InitScript {
+    pub name: String,
+    pub content: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct DatabaseBackupPolicy {
+    pub schedule_cron: String,
+    pub retention_days: u32,
+    // daily time range backups are taken in, i.e: `03:00-04:00`
+    #[serde(default)]
+    pub window: Option<String>,
+}
+
+// (name, min, max) of the fields of a cron expression
+const CRON_FIELDS: [(&str, u32, u32); 5] = [
+    ("minute", 0, 59),
+    ("hour", 0, 23),
+    ("day of month", 1, 31),
+    ("month", 1, 12),
+    ("day of week", 0, 7),
+];
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+// (start, end) minutes of the day of the managed databases maintenance window, `Tue:02:00-Tue:04:00` in the templates
+const MAINTENANCE_WINDOW: (u32, u32) = (2 * 60, 4 * 60);
+// RDS rejects shorter backup windows
+const BACKUP_WINDOW_MIN_DURATION_MINUTES: u32 = 30;
+// of the window derived from the schedule when none is given
+const BACKUP_WINDOW_DEFAULT_DURATION_MINUTES: u32 = 60;
+
+/// Checks a value of a cron field, i.e: `*`, `5`, `1-5`, `*/15`, `0-30/10`
+fn validate_cron_value(value: &str, min: u32, max: u32) -> bool {
+    let (range, step) = match value.split_once('/') {
+        Some((range, step)) => (range, Some(step)),
+        None => (value, None),
+    };
+    if let Some(step) = step {
+        if !matches!(step.parse::<u32>(), Ok(step) if step > 0) {
+            return false;
+        }
+    }
+
+    let in_bounds = |bound: &str| matches!(bound.parse::<u32>(), Ok(bound) if (min..=max).contains(&bound));
+    match range.split_once('-') {
+        _ if range == "*" => true,
+        Some((start, end)) => in_bounds(start) && in_bounds(end) && start.parse::<u32>() <= end.parse::<u32>(),
+        None => in_bounds(range),
+    }
+}
+
+impl DatabaseBackupPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        let fields: Vec<&str> = self.schedule_cron.split_whitespace().collect();
+        if fields.len() != CRON_FIELDS.len() {
+            return Err(format!(
+                "backup schedule `{}` must be a cron expression with {} fields",
+                self.schedule_cron,
+                CRON_FIELDS.len()
+            ));
+        }
+
+        for (field, (name, min, max)) in fields.iter().zip(CRON_FIELDS.iter()) {
+            if !field.split(',').all(|value| validate_cron_value(value, *min, *max)) {
+                return Err(format!(
+                    "backup schedule `{}` has an invalid {} `{}`, expected values within [{}, {}]",
+                    self.schedule_cron, name, field, min, max
+                ));
+            }
+        }
+
+        // managed databases take a single automated backup a day, at a given time
+        let start = self.schedule_start().ok_or_else(|| {
+            format!(
+                "backup schedule `{}` must run once a day at a fixed time, i.e: `30 5 * * *`",
+                self.schedule_cron
+            )
+        })?;
+
+        if self.retention_days == 0 {
+            return Err("backup retention must be at least 1 day".to_string());
+        }
+
+        let window = self.backup_window();
+        let (window_start, window_end) = parse_window(&window)
+            .ok_or_else(|| format!("backup window `{}` must be formatted as `hh:mm-hh:mm`", window))?;
+        let duration = (window_end + MINUTES_PER_DAY - window_start) % MINUTES_PER_DAY;
+        if duration < BACKUP_WINDOW_MIN_DURATION_MINUTES {
+            return Err(format!(
+                "backup window `{}` must last at least {} minutes",
+                window, BACKUP_WINDOW_MIN_DURATION_MINUTES
+            ));
+        }
+        if (start + MINUTES_PER_DAY - window_start) % MINUTES_PER_DAY >= duration {
+            return Err(format!(
+                "backup schedule `{}` must start within the backup window `{}`",
+                self.schedule_cron, window
+            ));
+        }
+        let (maintenance_start, maintenance_end) = MAINTENANCE_WINDOW;
+        if overlaps(
+            (window_start, duration),
+            (maintenance_start, maintenance_end - maintenance_start),
+        ) {
+            return Err(format!(
+                "backup window `{}` cannot overlap the maintenance window Tue:02:00-Tue:04:00",
+                window
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Minute of the day the backup starts at, None unless the schedule runs once a day
+    fn schedule_start(&self) -> Option<u32> {
+        match self.schedule_cron.split_whitespace().collect::<Vec<&str>>()[..] {
+            [minute, hour, "*", "*", "*"] => Some(hour.parse::<u32>().ok()? * 60 + minute.parse::<u32>().ok()?),
+            _ => None,
+        }
+    }
+
+    /// Time range the provider takes the daily backup in, one hour from the schedule unless given
+    pub fn backup_window(&self) -> String {
+        if let Some(window) = &self.window {
+            return window.clone();
+        }
+
+        let start = self.schedule_start().unwrap_or_default();
+        let end = (start + BACKUP_WINDOW_DEFAULT_DURATION_MINUTES) % MINUTES_PER_DAY;
+        format!("{:02}:{:02}-{:02}:{:02}", start / 60, start % 60, end / 60, end % 60)
+    }
+}
+
+// (start, end) minutes of the day of a `hh:mm-hh:mm` window
+fn parse_window(window: &str) -> Option<(u32, u32)> {
+    let window_regex = Regex::new(r"^([01][0-9]|2[0-3]):([0-5][0-9])-([01][0-9]|2[0-3]):([0-5][0-9])$").ok()?;
+    let captures = window_regex.captures(window)?;
+    let minutes = |hour: usize, minute: usize| -> Option<u32> {
+        Some(captures[hour].parse::<u32>().ok()? * 60 + captures[minute].parse::<u32>().ok()?)
+    };
+
+    Some((minutes(1, 2)?, minutes(3, 4)?))
+}
+
+// daily (start, duration) ranges in minutes, which may wrap around midnight
+fn overlaps(a: (u32, u32), b: (u32, u32)) -> bool {
+    (b.0 + MINUTES_PER_DAY - a.0) % MINUTES_PER_DAY < a.1 || (a.0 + MINUTES_PER_DAY - b.0) % MINUTES_PER_DAY < b.1
 }
 
 impl Database {
@@ -64,8 +246,73 @@ impl Database {
             activate_high_availability: self.activate_high_availability,
             activate_backups: self.activate_backups,
             publicly_accessible: self.publicly_accessible,
+            external_name_alias: self.external_name_alias.clone(),
+            init_scripts: self.init_scripts.clone(),
+            generated_credentials: self.generated_credentials,
+            read_replicas: self.read_replicas,
+            failover_proxy: self.failover_proxy,
+            backup_policy: self.backup_policy.clone().filter(|_| self.activate_backups),
         };
 
+        if let Some(backup_policy) = &database_options.backup_policy {
+            backup_policy.validate().map_err(DatabaseError::InvalidConfig)?;
+
+            // scaleway only sets the backup frequency and retention, the time of the day cannot be chosen
+            if backup_policy.window.is_some() && cloud_provider.kind() == CPKind::Scw {
+                return Err(DatabaseError::InvalidConfig(
+                    "Backup window is not supported by Scaleway managed databases".to_string(),
+                ));
+            }
+        }
+
+        if self.generated_credentials && self.mode != DatabaseMode::MANAGED {
+            return Err(DatabaseError::InvalidConfig(
+                "Generated credentials are only supported by managed databases".to_string(),
+            ));
+        }
+
+        if self.read_replicas > 0
+            && (self.mode != DatabaseMode::CONTAINER
+                || !matches!(self.kind, DatabaseKind::Postgresql | DatabaseKind::Mysql))
+        {
+            return Err(DatabaseError::InvalidConfig(
+                "Read replicas are only supported by PostgreSQL and MySQL container databases".to_string(),
+            ));
+        }
+
+        // without replicas there is no primary change to follow
+        if self.failover_proxy && self.read_replicas == 0 {
+            return Err(DatabaseError::InvalidConfig(
+                "Failover proxy requires the database to have read replicas".to_string(),
+            ));
+        }
+
+        if let Some(namespace) = &self.namespace {
+            if self.mode != DatabaseMode::CONTAINER {
+                return Err(DatabaseError::InvalidConfig(
+                    "Namespace override is only supported by container databases".to_string(),
+                ));
+            }
+
+            // RFC 1123 label, as kubernetes expects
+            let namespace_regex = Regex::new(r"^[a-z0-9]([-a-z0-9]*[a-z0-9])?$")
+                .map_err(|e| DatabaseError::InvalidConfig(e.to_string()))?;
+            if namespace.len() > 63 || !namespace_regex.is_match(namespace) {
+                return Err(DatabaseError::InvalidConfig(format!(
+                    "Namespace `{}` is not a valid kubernetes namespace name",
+                    namespace
+                )));
+            }
+
+            // the database deletes its namespace, it must not be one the cluster relies on
+            if namespace.starts_with("kube-") || RESERVED_NAMESPACES.contains(&namespace.as_str()) {
+                return Err(DatabaseError::InvalidConfig(format!(
+                    "Namespace `{}` is reserved and cannot be used by a database",
+                    namespace
+                )));
+            }
+        }
+
         let version = VersionsNumber::from_str(self.version.as_str())
             .map_err(|_| DatabaseError::InvalidConfig(format!("Bad version number: {}", self.version)))?;
 
@@ -90,6 +337,7 @@ impl Database {
                         database_options.publicly_accessible,
                         database_options.port,
                         database_options,
+                        self.namespace.clone(),
                         |transmitter| context.get_event_details(transmitter),
                     )?))
                 } else {
@@ -108,6 +356,7 @@ impl Database {
                         database_options.publicly_accessible,
                         database_options.port,
                         database_options,
+                        self.namespace.clone(),
                         |transmitter| context.get_event_details(transmitter),
                     )?))
                 }
@@ -132,6 +381,7 @@ impl Database {
                         database_options.publicly_accessible,
                         database_options.port,
                         database_options,
+                        self.namespace.clone(),
                         |transmitter| context.get_event_details(transmitter),
                     )?))
                 } else {
@@ -150,6 +400,7 @@ impl Database {
                         database_options.publicly_accessible,
                         database_options.port,
                         database_options,
+                        self.namespace.clone(),
                         |transmitter| context.get_event_details(transmitter),
                     )?))
                 }
@@ -175,6 +426,7 @@ impl Database {
                         database_options.publicly_accessible,
                         database_options.port,
                         database_options,
+                        self.namespace.clone(),
                         |transmitter| context.get_event_details(transmitter),
                     )?))
                 } else {
@@ -193,6 +445,7 @@ impl Database {
                         database_options.publicly_accessible,
                         database_options.port,
                         database_options,
+                        self.namespace.clone(),
                         |transmitter| context.get_event_details(transmitter),
                     )?))
                 }
@@ -217,6 +470,7 @@ impl Database {
                         database_options.publicly_accessible,
                         database_options.port,
                         database_options,
+                        self.namespace.clone(),
                         |transmitter| context.get_event_details(transmitter),
                     )?))
                 } else {
@@ -235,6 +489,7 @@ impl Database {
                         database_options.publicly_accessible,
                         database_options.port,
                         database_options,
+                        self.namespace.clone(),
                         |transmitter| context.get_event_details(transmitter),
                     )?))
                 }
@@ -259,6 +514,7 @@ impl Database {
                         database_options.publicly_accessible,
                         database_options.port,
                         database_options,
+                        self.namespace.clone(),
                         |transmitter| context.get_event_details(transmitter),
                     )?))
                 } else {
@@ -277,6 +533,7 @@ impl Database {
                         database_options.publicly_accessible,
                         database_options.port,
                         database_options,
+                        self.namespace.clone(),
                         |transmitter| context.get_event_details(transmitter),
                     )?))
                 }
@@ -301,6 +558,7 @@ impl Database {
                         database_options.publicly_accessible,
                         database_options.port,
                         database_options,
+                        self.namespace.clone(),
                         |transmitter| context.get_event_details(transmitter),
                     )?))
                 } else {
@@ -319,6 +577,7 @@ impl Database {
                         database_options.publicly_accessible,
                         database_options.port,
                         database_options,
+                        self.namespace.clone(),
                         |transmitter| context.get_event_details(transmitter),
                     )?))
                 }
@@ -343,6 +602,7 @@ impl Database {
                         database_options.publicly_accessible,
                         database_options.port,
                         database_options,
+                        self.namespace.clone(),
                         |transmitter| context.get_event_details(transmitter),
                     )?))
                 } else {
@@ -361,6 +621,7 @@ impl Database {
                         database_options.publicly_accessible,
                         database_options.port,
                         database_options,
+                        self.namespace.clone(),
                         |transmitter| context.get_event_details(transmitter),
                     )?))
                 }
@@ -385,6 +646,7 @@ impl Database {
                         database_options.publicly_accessible,
                         database_options.port,
                         database_options,
+                        self.namespace.clone(),
                         |transmitter| context.get_event_details(transmitter),
                     )?))
                 } else {
@@ -403,6 +665,7 @@ impl Database {
                         database_options.publicly_accessible,
                         database_options.port,
                         database_options,
+                        self.namespace.clone(),
                         |transmitter| context.get_event_details(transmitter),
                     )?))
                 }
@@ -424,6 +687,7 @@ impl Database {
                     database_options.publicly_accessible,
                     database_options.port,
                     database_options,
+                    self.namespace.clone(),
                     |transmitter| context.get_event_details(transmitter),
                 )?;
 
@@ -445,6 +709,7 @@ impl Database {
                     database_options.publicly_accessible,
                     database_options.port,
                     database_options,
+                    self.namespace.clone(),
                     |transmitter| context.get_event_details(transmitter),
                 )?;
 
@@ -466,6 +731,7 @@ impl Database {
                     database_options.publicly_accessible,
                     database_options.port,
                     database_options,
+                    self.namespace.clone(),
                     |transmitter| context.get_event_details(transmitter),
                 )?;
 
@@ -487,6 +753,7 @@ impl Database {
                     database_options.publicly_accessible,
                     database_options.port,
                     database_options,
+                    self.namespace.clone(),
                     |transmitter| context.get_event_details(transmitter),
                 )?;
 
@@ -508,6 +775,7 @@ impl Database {
                     database_options.publicly_accessible,
                     database_options.port,
                     database_options,
+                    self.namespace.clone(),
                     |transmitter| context.get_event_details(transmitter),
                 )?;
 
@@ -529,6 +797,7 @@ impl Database {
                     database_options.publicly_accessible,
                     database_options.port,
                     database_options,
+                    self.namespace.clone(),
                     |transmitter| context.get_event_details(transmitter),
                 )?;
 
@@ -579,4 +848,51 @@ pub struct DatabaseOptions {
     pub activate_high_availability: bool,
     pub activate_backups: bool,
     pub publicly_accessible: bool,
+    pub external_name_alias: Option<String>,
+    pub init_scripts: Vec<InitScript>,
+    pub generated_credentials: bool,
+    pub read_replicas: u32,
+    pub failover_proxy: bool,
+    pub backup_policy: Option<DatabaseBackupPolicy>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io_models::database::DatabaseBackupPolicy;
+
+    #[test]
+    fn test_backup_policy_validation() {
+        let policy = |schedule_cron: &str, window: Option<&str>| DatabaseBackupPolicy {
+            schedule_cron: schedule_cron.to_string(),
+            retention_days: 7,
+            window: window.map(str::to_string),
+        };
+
+        assert!(policy("0 5 * * *", None).validate().is_ok());
+        assert_eq!(policy("0 5 * * *", None).backup_window(), "05:00-06:00");
+        assert!(policy("30 5 * * *", Some("05:00-06:00")).validate().is_ok());
+        assert!(policy("30 23 * * *", Some("23:00-01:00")).validate().is_ok());
+        assert!(policy("0 5 * *", None).validate().is_err());
+        assert!(policy("60 5 * * *", None).validate().is_err());
+        assert!(policy("0 5 0 * *", None).validate().is_err());
+        assert!(policy("0 6-3 * * *", None).validate().is_err());
+        assert!(policy("*/0 5 * * *", None).validate().is_err());
+        assert!(policy("0 5 * * mon", None).validate().is_err());
+        assert!(policy("0 5 * * *", Some("5h-6h")).validate().is_err());
+        assert!(DatabaseBackupPolicy {
+            retention_days: 0,
+            ..policy("0 5 * * *", None)
+        }
+        .validate()
+        .is_err());
+
+        // a single backup a day, within a window the providers accept
+        assert!(policy("*/15 5 * * *", None).validate().is_err());
+        assert!(policy("0 5 * * 1-5", None).validate().is_err());
+        assert!(policy("0 5 * * *", Some("05:00-05:15")).validate().is_err());
+        assert!(policy("0 7 * * *", Some("05:00-06:00")).validate().is_err());
+        assert!(policy("0 3 * * *", None).validate().is_err());
+        assert!(policy("30 1 * * *", None).validate().is_err());
+        assert!(policy("0 1 * * *", Some("01:00-01:30")).validate().is_ok());
+    }
 }
@@ -12,8 +12,9 @@ use crate::models::application::ApplicationError;
 use crate::models::container::ContainerError;
 use crate::models::database::DatabaseError;
 use crate::models::job::JobError;
-use crate::models::router::RouterError;
+use crate::models::router::{RouterAdvancedSettings, RouterError, RouterTlsSettings};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use uuid::Uuid;
 
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
@@ -83,6 +84,8 @@ impl EnvironmentRequest {
         for router in &self.routers {
             let mut custom_domain_check_enabled = true;
             let mut whitelist_source_range = "0.0.0.0/0".to_string();
+            let mut ingress_class_name: Option<String> = None;
+            let mut custom_domain_tls: Option<RouterTlsSettings> = None;
 
             for app in &self.applications {
                 for route in &router.routes {
@@ -93,6 +96,12 @@ impl EnvironmentRequest {
                         }
 
                         whitelist_source_range = app.advanced_settings.network_ingress_whitelist_source_range.clone();
+                        ingress_class_name = Some(app.advanced_settings.network_ingress_class_name.clone());
+                        custom_domain_tls = router_tls_settings(
+                            app.advanced_settings.network_ingress_custom_domain_tls_enabled,
+                            &app.advanced_settings.network_ingress_custom_domain_tls_issuer,
+                            app.advanced_settings.network_ingress_custom_domain_tls_wait_ready,
+                        );
                     }
                 }
             }
@@ -109,12 +118,23 @@ impl EnvironmentRequest {
                             .advanced_settings
                             .network_ingress_whitelist_source_range
                             .clone();
+                        ingress_class_name = Some(container.advanced_settings.network_ingress_class_name.clone());
+                        custom_domain_tls = router_tls_settings(
+                            container.advanced_settings.network_ingress_custom_domain_tls_enabled,
+                            &container.advanced_settings.network_ingress_custom_domain_tls_issuer,
+                            container.advanced_settings.network_ingress_custom_domain_tls_wait_ready,
+                        );
                     }
                 }
             }
 
-            match router.to_router_domain(context, custom_domain_check_enabled, whitelist_source_range, cloud_provider)
-            {
+            let advanced_settings = RouterAdvancedSettings {
+                custom_domain_check_enabled,
+                whitelist_source_range,
+                ingress_class_name: ingress_class_name.filter(|class_name| !class_name.is_empty()),
+                custom_domain_tls,
+            };
+            match router.to_router_domain(context, advanced_settings, cloud_provider) {
                 Ok(router) => routers.push(router),
                 Err(err) => {
                     return Err(DomainError::RouterError(err));
@@ -140,7 +160,7 @@ impl EnvironmentRequest {
             }
         }
 
-        Ok(Environment::new(
+        let environment = Environment::new(
             self.long_id,
             self.name.clone(),
             self.project_long_id,
@@ -152,6 +172,32 @@ impl EnvironmentRequest {
             routers,
             databases,
             jobs,
-        ))
+        );
+
+        // A database deletes its own namespace, it must not take the environment or another database with it
+        let mut database_namespaces = HashSet::new();
+        for db in &environment.databases {
+            if let Some(namespace) = db.as_service().namespace_override() {
+                if namespace == environment.namespace() || !database_namespaces.insert(namespace.clone()) {
+                    return Err(DomainError::DatabaseError(DatabaseError::InvalidConfig(format!(
+                        "Namespace `{}` is already used by the environment or another database",
+                        namespace
+                    ))));
+                }
+            }
+        }
+
+        Ok(environment)
     }
 }
+
+fn router_tls_settings(enabled: bool, cluster_issuer: &str, wait_ready: bool) -> Option<RouterTlsSettings> {
+    if !enabled {
+        return None;
+    }
+
+    Some(RouterTlsSettings {
+        cluster_issuer: Some(cluster_issuer.to_string()).filter(|issuer| !issuer.is_empty()),
+        wait_ready,
+    })
+}
@@ -25,3 +25,26 @@ where
 
     Ok(())
 }
+
+/// Names, prefixed by their kind, of the resources matching the selector
+pub async fn kube_list_names_from_selector<K>(
+    client: &kube::Client,
+    selector: &str,
+    namespace: &str,
+) -> Result<Vec<String>, kube::Error>
+where
+    K: Clone + DeserializeOwned + Debug + Resource,
+    <K as Resource>::DynamicType: Default,
+{
+    let obj_name = K::kind(&K::DynamicType::default()).to_string();
+    let list_params = ListParams::default().labels(selector);
+
+    let api: Api<K> = Api::namespaced(client.clone(), namespace);
+    let objects = api.list(&list_params).await?;
+
+    Ok(objects
+        .items
+        .iter()
+        .map(|obj| format!("{}/{}", obj_name, obj.meta().name.as_deref().unwrap_or_default()))
+        .collect())
+}
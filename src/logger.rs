@@ -1,4 +1,9 @@
 use crate::events::{EngineEvent, EventMessageVerbosity};
+use std::collections::VecDeque;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
 use tracing;
 
 pub trait Logger: Send + Sync {
@@ -64,6 +69,102 @@ impl Logger for StdIoLogger {
     }
 }
 
+/// Default number of lines a LogTee keeps in memory while its consumer is lagging behind
+pub const LOG_TEE_DEFAULT_CAPACITY: usize = 10_000;
+
+struct LogTeeQueue {
+    lines: Mutex<VecDeque<String>>,
+    not_empty: Condvar,
+    capacity: usize,
+}
+
+/// Stream the raw operational log of a deployment (commands output and engine events) to a single consumer.
+/// Sending a line never blocks: lines are buffered in a bounded queue, forwarded to the consumer channel
+/// by a dedicated thread, and the oldest ones are dropped when that thread does not keep up.
+#[derive(Clone)]
+pub struct LogTee {
+    queue: Arc<LogTeeQueue>,
+}
+
+impl LogTee {
+    pub fn new(sender: Sender<String>, capacity: usize) -> std::io::Result<LogTee> {
+        let queue = Arc::new(LogTeeQueue {
+            lines: Mutex::new(VecDeque::with_capacity(capacity)),
+            not_empty: Condvar::new(),
+            capacity: capacity.max(1),
+        });
+
+        let forwarder_queue = queue.clone();
+        thread::Builder::new()
+            .name("log-tee".to_string())
+            .spawn(move || forward_log_tee_lines(forwarder_queue, sender))?;
+
+        Ok(LogTee { queue })
+    }
+
+    pub fn send(&self, line: String) {
+        let mut lines = self.queue.lines.lock().unwrap_or_else(|e| e.into_inner());
+        if lines.len() >= self.queue.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+        self.queue.not_empty.notify_one();
+    }
+}
+
+fn forward_log_tee_lines(queue: Arc<LogTeeQueue>, sender: Sender<String>) {
+    loop {
+        let line = {
+            let mut lines = queue.lines.lock().unwrap_or_else(|e| e.into_inner());
+            if lines.is_empty() {
+                // Every LogTee handle is gone and everything has been forwarded, nothing left to do
+                if Arc::strong_count(&queue) == 1 {
+                    return;
+                }
+                lines = queue
+                    .not_empty
+                    .wait_timeout(lines, Duration::from_millis(500))
+                    .map(|(lines, _)| lines)
+                    .unwrap_or_else(|e| e.into_inner().0);
+            }
+
+            match lines.pop_front() {
+                Some(line) => line,
+                None => continue,
+            }
+        };
+
+        // The consumer is gone, stop forwarding
+        if sender.send(line).is_err() {
+            return;
+        }
+    }
+}
+
+/// Logger forwarding every event to another logger, while also streaming it into a LogTee
+#[derive(Clone)]
+pub struct TeeLogger {
+    logger: Box<dyn Logger>,
+    tee: LogTee,
+}
+
+impl TeeLogger {
+    pub fn new(logger: Box<dyn Logger>, tee: LogTee) -> TeeLogger {
+        TeeLogger { logger, tee }
+    }
+}
+
+impl Logger for TeeLogger {
+    fn log(&self, event: EngineEvent) {
+        self.tee.send(event.message(EventMessageVerbosity::FullDetails));
+        self.logger.log(event);
+    }
+
+    fn clone_dyn(&self) -> Box<dyn Logger> {
+        Box::new(self.clone())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -237,4 +338,47 @@ mod tests {
             assert!(logs_contain(raw_message), "{}", tc.description);
         }
     }
+
+    #[test]
+    fn test_log_tee_forward_lines_in_order() {
+        // setup:
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let tee = LogTee::new(sender, LOG_TEE_DEFAULT_CAPACITY).expect("cannot spawn log tee forwarder");
+
+        // execute:
+        for i in 0..3 {
+            tee.send(format!("line {}", i));
+        }
+
+        // verify:
+        for i in 0..3 {
+            assert_eq!(
+                Ok(format!("line {}", i)),
+                receiver.recv_timeout(std::time::Duration::from_secs(5))
+            );
+        }
+    }
+
+    #[test]
+    fn test_log_tee_drop_oldest_lines_when_forwarder_is_late() {
+        // setup: no forwarder, lines pile up as if it could not keep up
+        let tee = LogTee {
+            queue: Arc::new(LogTeeQueue {
+                lines: Mutex::new(VecDeque::new()),
+                not_empty: Condvar::new(),
+                capacity: 2,
+            }),
+        };
+
+        // execute:
+        for i in 0..10 {
+            tee.send(format!("line {}", i));
+        }
+
+        // verify:
+        assert_eq!(
+            tee.queue.lines.lock().unwrap().iter().cloned().collect::<Vec<String>>(),
+            vec!["line 8".to_string(), "line 9".to_string()]
+        );
+    }
 }
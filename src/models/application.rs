@@ -1,21 +1,37 @@
 use crate::build_platform::Build;
-use crate::cloud_provider::environment::Environment;
-use crate::cloud_provider::kubernetes::Kubernetes;
-use crate::cloud_provider::models::{EnvironmentVariable, EnvironmentVariableDataTemplate, Storage};
-use crate::cloud_provider::service::{Action, Service, ServiceType};
+use crate::cloud_provider::environment::{resolve_service_references, Environment};
+use crate::cloud_provider::kubernetes::{overcommitted_cpu_request, overcommitted_ram_request_in_mib, Kubernetes};
+use crate::cloud_provider::models::{
+    environment_variables_checksum, EnvironmentVariable, EnvironmentVariableDataTemplate, Storage,
+};
+use crate::cloud_provider::service::{Action, HealthCheck, ReadinessProbe, Service, ServiceType};
 use crate::cloud_provider::utilities::sanitize_name;
+use crate::container_registry::ContainerRegistryInfo;
 use crate::deployment_action::DeploymentAction;
 use crate::events::{EventDetails, Stage, Transmitter};
-use crate::io_models::application::{AdvancedSettingsProbeType, ApplicationAdvancedSettings, Port};
+use crate::io_models::application::{
+    validate_node_selector, AdvancedSettingsProbeType, ApplicationAdvancedSettings, AutoscalingMetric, EgressProxy,
+    HostAlias, LogShippingDestination, PolicyExemption, Port, RollingUpdateStrategy, Toleration, WarmupRequest,
+};
 use crate::io_models::context::Context;
-
+use crate::models::container::QOVERY_MIRROR_REPOSITORY_NAME;
 use crate::models::types::{CloudProvider, ToTeraContext};
 use crate::utilities::to_short_id;
 use itertools::Itertools;
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
+use std::time::Duration;
 use tera::Context as TeraContext;
 use uuid::Uuid;
 
+const HELM_RELEASE_NAME_MAX_LENGTH: usize = 53;
+
+// Logging sidecar image, mirrored into the cluster registry so pods never pull it from docker hub
+pub const LOG_SHIPPING_IMAGE: &str = "fluent/fluent-bit";
+pub const LOG_SHIPPING_IMAGE_TAG: &str = "2.0.9";
+// Shared by the application and the logging sidecar, the application writes its *.log files into it
+const LOG_SHIPPING_DIR: &str = "/var/log/qovery";
+
 #[derive(thiserror::Error, Debug)]
 pub enum ApplicationError {
     #[error("Application invalid configuration: {0}")]
@@ -42,6 +58,27 @@ pub struct Application<T: CloudProvider> {
     pub(super) _extra_settings: T::AppExtraSettings,
     pub(super) workspace_directory: String,
     pub(super) lib_root_directory: String,
+    pub(super) release_suffix: Option<String>,
+    // given with the deployment request, not kept from one deployment to the next
+    pub(super) release_tag: Option<String>,
+}
+
+/// An autoscaler cannot be rendered from inconsistent bounds, helm would only fail with an opaque error later on
+/// Pausing a service scales its workloads down in the cluster and keeps the requested instances, so a paused service
+/// still carries max_instances > 0 and is not rejected here
+pub(super) fn validate_instances(service_name: &str, min_instances: u32, max_instances: u32) -> Result<(), String> {
+    if max_instances == 0 {
+        return Err(format!("{} max_instances must be greater than 0", service_name));
+    }
+
+    if min_instances > max_instances {
+        return Err(format!(
+            "{} min_instances ({}) must be less or equal to max_instances ({})",
+            service_name, min_instances, max_instances
+        ));
+    }
+
+    Ok(())
 }
 
 // Here we define the common behavior among all providers
@@ -65,6 +102,96 @@ impl<T: CloudProvider> Application<T> {
         mk_event_details: impl Fn(Transmitter) -> EventDetails,
     ) -> Result<Self, ApplicationError> {
         // TODO: Check that the information provided are coherent
+        validate_instances(name, min_instances, max_instances).map_err(ApplicationError::InvalidConfig)?;
+
+        if advanced_settings.resources_overcommit_ratio_percent == 0
+            || advanced_settings.resources_overcommit_ratio_percent > 100
+        {
+            return Err(ApplicationError::InvalidConfig(format!(
+                "resources overcommit ratio must be within (0, 100] percent, got {}",
+                advanced_settings.resources_overcommit_ratio_percent
+            )));
+        }
+
+        if advanced_settings.logging_sidecar_enabled {
+            LogShippingDestination::from_url(&advanced_settings.logging_sidecar_destination)
+                .map_err(ApplicationError::InvalidConfig)?;
+
+            if advanced_settings.logging_sidecar_cpu_milli == 0 || advanced_settings.logging_sidecar_ram_in_mib == 0 {
+                return Err(ApplicationError::InvalidConfig(
+                    "logging sidecar cpu and ram must be greater than 0".to_string(),
+                ));
+            }
+        }
+
+        if !advanced_settings.hpa_custom_metric_name.is_empty()
+            && advanced_settings.hpa_custom_metric_target_average_value.is_empty()
+        {
+            return Err(ApplicationError::InvalidConfig(format!(
+                "autoscaling custom metric `{}` requires a target average value",
+                advanced_settings.hpa_custom_metric_name
+            )));
+        }
+
+        for warmup_request in &advanced_settings.deployment_warmup_requests {
+            warmup_request.validate().map_err(ApplicationError::InvalidConfig)?;
+        }
+
+        for host_alias in &advanced_settings.network_host_aliases {
+            host_alias.validate().map_err(ApplicationError::InvalidConfig)?;
+        }
+
+        if !advanced_settings.network_egress_proxy_url.is_empty() {
+            EgressProxy::new(
+                &advanced_settings.network_egress_proxy_url,
+                &advanced_settings.network_egress_proxy_no_proxy,
+                advanced_settings.network_egress_proxy_restrict_egress,
+            )
+            .map_err(ApplicationError::InvalidConfig)?;
+        }
+
+        if !advanced_settings.deployment_pre_deploy_job_command.is_empty()
+            && advanced_settings.deployment_pre_deploy_job_timeout_sec == 0
+        {
+            return Err(ApplicationError::InvalidConfig(
+                "pre-deploy job timeout must be greater than 0".to_string(),
+            ));
+        }
+
+        validate_node_selector(&advanced_settings.deployment_node_selector).map_err(ApplicationError::InvalidConfig)?;
+        for toleration in &advanced_settings.deployment_tolerations {
+            toleration.validate().map_err(ApplicationError::InvalidConfig)?;
+        }
+
+        let readiness_check_path = &advanced_settings.deployment_readiness_check_path;
+        if !readiness_check_path.is_empty() && !readiness_check_path.starts_with('/') {
+            return Err(ApplicationError::InvalidConfig(format!(
+                "readiness check path must start with `/`, got `{}`",
+                readiness_check_path
+            )));
+        }
+
+        let working_dir = advanced_settings.deployment_working_dir.trim();
+        if !working_dir.is_empty() && !working_dir.starts_with('/') {
+            return Err(ApplicationError::InvalidConfig(format!(
+                "working directory must be an absolute path, got `{}`",
+                working_dir
+            )));
+        }
+
+        RollingUpdateStrategy::new(
+            &advanced_settings.deployment_update_strategy_max_surge,
+            &advanced_settings.deployment_update_strategy_max_unavailable,
+            max_instances,
+        )
+        .map_err(ApplicationError::InvalidConfig)?;
+
+        PolicyExemption::new(
+            &advanced_settings.deployment_policy_exemption_labels,
+            &advanced_settings.deployment_policy_exemption_annotations,
+            &advanced_settings.deployment_policy_exemption_reason,
+        )
+        .map_err(ApplicationError::InvalidConfig)?;
 
         let workspace_directory = crate::fs::workspace_directory(
             context.workspace_root_dir(),
@@ -95,6 +222,8 @@ impl<T: CloudProvider> Application<T> {
             _extra_settings: extra_settings,
             workspace_directory,
             lib_root_directory: context.lib_root_dir().to_string(),
+            release_suffix: context.helm_release_suffix(),
+            release_tag: context.release_tag(),
         })
     }
 
@@ -103,7 +232,16 @@ impl<T: CloudProvider> Application<T> {
     }
 
     pub fn helm_release_name(&self) -> String {
-        crate::string::cut(format!("application-{}-{}", self.id(), self.id()), 50)
+        let release_name = format!("application-{}-{}", self.id(), self.id());
+        match &self.release_suffix {
+            // helm rejects release names longer than 53 chars, the suffix must survive the truncation
+            Some(suffix) => format!(
+                "{}-{}",
+                crate::string::cut(release_name, HELM_RELEASE_NAME_MAX_LENGTH - suffix.len() - 1),
+                suffix
+            ),
+            None => crate::string::cut(release_name, 50),
+        }
     }
 
     pub fn helm_chart_dir(&self) -> String {
@@ -118,6 +256,7 @@ impl<T: CloudProvider> Application<T> {
         let mut context = TeraContext::new();
         context.insert("id", self.id());
         context.insert("long_id", &self.long_id);
+        context.insert("preview_id", &self.release_suffix);
         context.insert("owner_id", environment.owner_id.as_str());
         context.insert("project_id", environment.project_id.as_str());
         context.insert("project_long_id", &environment.project_long_id);
@@ -129,15 +268,25 @@ impl<T: CloudProvider> Application<T> {
         context.insert("zone", kubernetes.zone());
         context.insert("name", self.name());
         context.insert("sanitized_name", &self.sanitized_name());
-        context.insert("namespace", environment.namespace());
+        context.insert("namespace", &self.namespace(environment));
         context.insert("cluster_name", kubernetes.name());
         context.insert("total_cpus", &self.total_cpus());
         context.insert("total_ram_in_mib", &self.total_ram_in_mib());
+        context.insert("cpu_request", &self.cpu_request());
+        context.insert("ram_request_in_mib", &self.ram_request_in_mib());
         context.insert("min_instances", &self.min_instances());
         context.insert("max_instances", &self.max_instances());
+        context.insert("autoscaling_metrics", &self.autoscaling_metrics());
+        let rolling_update_strategy = self.rolling_update_strategy();
+        context.insert("max_surge", &rolling_update_strategy.max_surge);
+        context.insert("max_unavailable", &rolling_update_strategy.max_unavailable);
         context.insert(
-            "hpa_cpu_average_utilization_percent",
-            &self.advanced_settings.hpa_cpu_average_utilization_percent,
+            "git_annotations",
+            &kubernetes
+                .context()
+                .git_metadata()
+                .map(|git| git.to_annotations())
+                .unwrap_or_default(),
         );
 
         if let Some(default_port) = self.ports.iter().find(|p| p.is_default) {
@@ -246,8 +395,10 @@ impl<T: CloudProvider> Application<T> {
             }
         };
 
+        // unresolved references are reported before deploying, keep the raw values here
         let environment_variables = self
-            .environment_variables
+            .resolved_environment_variables(environment)
+            .unwrap_or_else(|_| self.environment_variables.clone())
             .iter()
             .map(|ev| EnvironmentVariableDataTemplate {
                 key: ev.key.clone(),
@@ -256,10 +407,56 @@ impl<T: CloudProvider> Application<T> {
             .collect::<Vec<_>>();
 
         context.insert("environment_variables", &environment_variables);
+        context.insert(
+            "environment_variables_checksum",
+            &environment_variables_checksum(
+                environment_variables
+                    .iter()
+                    .map(|ev| (ev.key.as_str(), ev.value.as_str())),
+            ),
+        );
         context.insert("ports", &self.ports);
         context.insert("is_registry_secret", &true);
         context.insert("registry_secret", self.build().image.registry_secret_name(kubernetes.kind()));
 
+        let log_shipping_destination = self.log_shipping_destination();
+        context.insert("log_shipping_enabled", &log_shipping_destination.is_some());
+        context.insert("log_shipping_destination", &log_shipping_destination);
+        context.insert("log_shipping_dir", LOG_SHIPPING_DIR);
+        context.insert("log_shipping_cpu_milli", &self.advanced_settings.logging_sidecar_cpu_milli);
+        context.insert("log_shipping_ram_in_mib", &self.advanced_settings.logging_sidecar_ram_in_mib);
+        context.insert("log_shipping_native_sidecar", &self.advanced_settings.logging_sidecar_native);
+
+        let service_mesh_annotations: BTreeMap<&str, &str> = self
+            .advanced_settings
+            .deployment_service_mesh
+            .pod_injection_annotation()
+            .into_iter()
+            .collect();
+        context.insert("service_mesh_annotations", &service_mesh_annotations);
+        context.insert("vpa_update_mode", &self.vpa_update_mode());
+        context.insert("host_aliases", &self.host_aliases());
+        context.insert("node_selector", self.node_selector());
+        context.insert("tolerations", self.tolerations());
+        let egress_proxy = self.egress_proxy();
+        context.insert("egress_proxy_enabled", &egress_proxy.is_some());
+        context.insert(
+            "egress_proxy_restricted",
+            &egress_proxy
+                .as_ref()
+                .map(|proxy| proxy.restrict_egress)
+                .unwrap_or(false),
+        );
+        context.insert(
+            "egress_proxy_host_cidr",
+            &egress_proxy.as_ref().and_then(|proxy| proxy.host_cidr()),
+        );
+        context.insert("egress_proxy", &egress_proxy);
+        context.insert("policy_exemption", &self.policy_exemption());
+        context.insert("standby_replicas", &self.advanced_settings.deployment_standby_replicas);
+        context.insert("container_user", &self.container_user());
+        context.insert("working_dir", &self.working_dir());
+
         context.insert(
             "resource_expiration_in_seconds",
             &kubernetes.advanced_settings().pleco_resources_ttl,
@@ -304,6 +501,19 @@ impl<T: CloudProvider> Application<T> {
         self.total_ram_in_mib
     }
 
+    /// Cpu requested to kubernetes, lowered by the overcommit ratio
+    pub fn cpu_request(&self) -> String {
+        overcommitted_cpu_request(self.total_cpus(), self.advanced_settings.resources_overcommit_ratio_percent)
+    }
+
+    /// Memory requested to kubernetes, lowered by the overcommit ratio
+    pub fn ram_request_in_mib(&self) -> u32 {
+        overcommitted_ram_request_in_mib(
+            self.total_ram_in_mib,
+            self.advanced_settings.resources_overcommit_ratio_percent,
+        )
+    }
+
     pub fn min_instances(&self) -> u32 {
         self.min_instances
     }
@@ -312,12 +522,63 @@ impl<T: CloudProvider> Application<T> {
         self.max_instances
     }
 
+    pub fn warmup_requests(&self) -> &[WarmupRequest] {
+        &self.advanced_settings.deployment_warmup_requests
+    }
+
+    pub fn warmup_timeout(&self) -> Duration {
+        Duration::from_secs(self.advanced_settings.deployment_warmup_timeout_sec as u64)
+    }
+
+    // Requests go straight to the pods, so they target the port the application listens on
+    pub fn warmup_port(&self) -> Option<u16> {
+        self.ports
+            .iter()
+            .find(|p| p.is_default)
+            .or_else(|| self.ports.first())
+            .map(|p| p.port)
+    }
+
+    // The settings are validated in `new()`
+    pub fn rolling_update_strategy(&self) -> RollingUpdateStrategy {
+        RollingUpdateStrategy::new(
+            &self.advanced_settings.deployment_update_strategy_max_surge,
+            &self.advanced_settings.deployment_update_strategy_max_unavailable,
+            self.max_instances,
+        )
+        .unwrap_or_else(|_| RollingUpdateStrategy::chart_default(self.max_instances))
+    }
+
+    pub fn host_aliases(&self) -> Vec<HostAlias> {
+        self.advanced_settings.network_host_aliases.clone()
+    }
+
+    /// Update mode of the vertical pod autoscaler deployed alongside the application, if any
+    pub fn vpa_update_mode(&self) -> Option<&'static str> {
+        match self.advanced_settings.vpa_enabled {
+            true => Some(self.advanced_settings.vpa_update_mode.to_k8s_update_mode()),
+            false => None,
+        }
+    }
+
+    // Without metrics, no autoscaler is deployed and the application keeps its min instances
+    pub fn autoscaling_metrics(&self) -> Vec<AutoscalingMetric> {
+        if self.is_stateful() || self.min_instances == self.max_instances {
+            return vec![];
+        }
+
+        self.advanced_settings.autoscaling_metrics()
+    }
+
     pub fn publicly_accessible(&self) -> bool {
         self.public_ports().count() > 0
     }
 
     pub fn selector(&self) -> String {
-        format!("appId={}", self.id())
+        match &self.release_suffix {
+            Some(suffix) => format!("appId={},previewId={}", self.id(), suffix),
+            None => format!("appId={}", self.id()),
+        }
     }
 
     pub fn build(&self) -> &Build {
@@ -328,13 +589,202 @@ impl<T: CloudProvider> Application<T> {
         &mut self.build
     }
 
+    /// Kubernetes resources of several previews of the application share the namespace, so they carry the suffix too
     pub fn sanitized_name(&self) -> String {
-        sanitize_name("app", self.id())
+        match &self.release_suffix {
+            Some(suffix) => format!("{}-{}", sanitize_name("app", self.id()), suffix),
+            None => sanitize_name("app", self.id()),
+        }
     }
 
     pub fn workspace_directory(&self) -> &str {
         &self.workspace_directory
     }
+
+    /// Return the first environment variable name not matching the configured validation, with the allowed pattern
+    pub fn invalid_environment_variable_name(&self) -> Option<(&str, &'static str)> {
+        let validation = &self.advanced_settings.deployment_env_var_name_validation;
+        let allowed_pattern = validation.allowed_pattern()?;
+        self.environment_variables
+            .iter()
+            .map(|env_var| env_var.key.as_str())
+            .find(|name| !validation.is_valid_name(name))
+            .map(|name| (name, allowed_pattern))
+    }
+
+    /// Return environment variables with references to other services of the environment replaced by their endpoint.
+    /// On failure, returns the environment variable name with its unresolved reference
+    pub fn resolved_environment_variables(
+        &self,
+        environment: &Environment,
+    ) -> Result<Vec<EnvironmentVariable>, (String, String)> {
+        self.environment_variables
+            .iter()
+            .map(|env_var| {
+                // values are base64 encoded, only the ones containing a reference need to be rewritten
+                let value = match base64::decode(&env_var.value).map(String::from_utf8) {
+                    Ok(Ok(value)) => value,
+                    _ => return Ok(env_var.clone()),
+                };
+                let resolved_value = resolve_service_references(&value, |long_id| environment.service_fqdn(long_id))
+                    .map_err(|reference| (env_var.key.clone(), reference))?;
+
+                Ok(EnvironmentVariable {
+                    key: env_var.key.clone(),
+                    value: match resolved_value == value {
+                        true => env_var.value.clone(),
+                        false => base64::encode(resolved_value),
+                    },
+                })
+            })
+            .collect()
+    }
+
+    // Only stateful applications have volume claims to bind
+    pub fn pvc_binding_fail_fast(&self) -> bool {
+        self.is_stateful() && self.advanced_settings.deployment_pvc_binding_fail_fast
+    }
+
+    pub fn node_not_ready_fail_fast(&self) -> bool {
+        self.advanced_settings.deployment_node_not_ready_fail_fast
+    }
+
+    pub fn engine_readiness_check(&self) -> bool {
+        self.advanced_settings.deployment_engine_readiness_check
+    }
+
+    pub fn scheduling_gate_max_wait(&self) -> Duration {
+        Duration::from_secs(self.advanced_settings.deployment_scheduling_gate_max_wait_sec as u64)
+    }
+
+    pub fn release_tag(&self) -> Option<String> {
+        self.release_tag.clone()
+    }
+
+    // None when the logging sidecar is disabled, the destination itself is validated in `new()`
+    pub fn log_shipping_destination(&self) -> Option<LogShippingDestination> {
+        if !self.advanced_settings.logging_sidecar_enabled {
+            return None;
+        }
+
+        LogShippingDestination::from_url(&self.advanced_settings.logging_sidecar_destination).ok()
+    }
+
+    // The sidecar image is the same for every application, it is mirrored once per cluster registry
+    pub fn log_shipping_tag_for_mirror(&self) -> String {
+        format!("{}.{}", LOG_SHIPPING_IMAGE.replace('/', "."), LOG_SHIPPING_IMAGE_TAG)
+    }
+
+    // Logging sidecar image pulled by the pods, mirrored into the cluster registry
+    pub fn log_shipping_mirrored_image(&self, registry_info: &ContainerRegistryInfo) -> String {
+        format!(
+            "{}/{}:{}",
+            registry_info.endpoint.host_str().unwrap_or_default(),
+            (registry_info.get_image_name)(QOVERY_MIRROR_REPOSITORY_NAME),
+            self.log_shipping_tag_for_mirror()
+        )
+    }
+
+    // Selector of the placeholder pods kept on standby for the application, None when there are none
+    pub fn standby_selector(&self) -> Option<String> {
+        match self.advanced_settings.deployment_standby_replicas {
+            0 => None,
+            _ => Some(format!("qovery.com/standby-of={}", self.long_id)),
+        }
+    }
+
+    pub fn pause_suspend_hpa(&self) -> bool {
+        self.advanced_settings.deployment_pause_suspend_hpa
+    }
+
+    pub fn pause_graceful_drain_timeout(&self) -> Option<Duration> {
+        self.advanced_settings
+            .deployment_pause_graceful_drain_timeout_sec
+            .map(|timeout| Duration::from_secs(timeout as u64))
+    }
+
+    // None when no pre-deploy job is configured
+    pub fn pre_deploy_job_command(&self) -> Option<Vec<String>> {
+        match self.advanced_settings.deployment_pre_deploy_job_command.is_empty() {
+            true => None,
+            false => Some(self.advanced_settings.deployment_pre_deploy_job_command.clone()),
+        }
+    }
+
+    // Without an explicit run key, the job runs once per image
+    pub fn pre_deploy_job_run_key(&self) -> String {
+        match self.advanced_settings.deployment_pre_deploy_job_run_key.trim() {
+            "" => self.build.image.full_image_name_with_tag(),
+            run_key => run_key.to_string(),
+        }
+    }
+
+    pub fn node_selector(&self) -> &BTreeMap<String, String> {
+        &self.advanced_settings.deployment_node_selector
+    }
+
+    pub fn tolerations(&self) -> &[Toleration] {
+        &self.advanced_settings.deployment_tolerations
+    }
+
+    pub fn pre_deploy_job_timeout(&self) -> Duration {
+        Duration::from_secs(self.advanced_settings.deployment_pre_deploy_job_timeout_sec as u64)
+    }
+
+    // None to run as the user set by the image
+    pub fn container_user(&self) -> Option<u32> {
+        self.advanced_settings.deployment_container_user
+    }
+
+    // None to run from the working directory set by the image, the path is validated in `new()`
+    pub fn working_dir(&self) -> Option<String> {
+        match self.advanced_settings.deployment_working_dir.trim() {
+            "" => None,
+            working_dir => Some(working_dir.to_string()),
+        }
+    }
+
+    // None when nothing is exempted, the settings themselves are validated in `new()`
+    pub fn policy_exemption(&self) -> Option<PolicyExemption> {
+        PolicyExemption::new(
+            &self.advanced_settings.deployment_policy_exemption_labels,
+            &self.advanced_settings.deployment_policy_exemption_annotations,
+            &self.advanced_settings.deployment_policy_exemption_reason,
+        )
+        .ok()
+        .flatten()
+    }
+
+    // None when no egress proxy is configured, the settings themselves are validated in `new()`
+    pub fn egress_proxy(&self) -> Option<EgressProxy> {
+        if self.advanced_settings.network_egress_proxy_url.is_empty() {
+            return None;
+        }
+
+        EgressProxy::new(
+            &self.advanced_settings.network_egress_proxy_url,
+            &self.advanced_settings.network_egress_proxy_no_proxy,
+            self.advanced_settings.network_egress_proxy_restrict_egress,
+        )
+        .ok()
+    }
+
+    pub fn has_dedicated_namespace(&self) -> bool {
+        self.advanced_settings.deployment_dedicated_namespace
+    }
+
+    /// Namespace the application is deployed into.
+    /// By default it is the environment one, unless the application asks for its own namespace
+    pub fn namespace(&self, environment: &Environment) -> String {
+        match self.has_dedicated_namespace() {
+            true => format!("{}-{}", environment.namespace(), self.id()),
+            false => environment.namespace().to_string(),
+        }
+    }
+
+    pub fn helm_chart_external_name_service_dir(&self) -> String {
+        format!("{}/common/charts/external-name-svc", self.lib_root_directory)
+    }
 }
 
 impl<T: CloudProvider> Service for Application<T> {
@@ -370,6 +820,12 @@ impl<T: CloudProvider> Service for Application<T> {
         Some(self.selector())
     }
 
+    fn selectors(&self) -> Vec<String> {
+        let mut selectors = vec![self.selector()];
+        selectors.extend(self.standby_selector());
+        selectors
+    }
+
     fn as_service(&self) -> &dyn Service {
         self
     }
@@ -400,6 +856,7 @@ pub trait ApplicationService: Service + DeploymentAction + ToTeraContext {
     fn get_build_mut(&mut self) -> &mut Build;
     fn public_ports(&self) -> Vec<&Port>;
     fn advanced_settings(&self) -> &ApplicationAdvancedSettings;
+    fn namespace(&self, environment: &Environment) -> String;
     fn startup_timeout(&self) -> std::time::Duration {
         let settings = self.advanced_settings();
         let readiness_probe_timeout = settings.readiness_probe_initial_delay_seconds
@@ -412,6 +869,19 @@ pub trait ApplicationService: Service + DeploymentAction + ToTeraContext {
         let startup_timeout = std::cmp::max(probe_timeout /* * 10 rolling restart percent */, 60 * 10);
         std::time::Duration::from_secs(startup_timeout as u64)
     }
+    /// Annotation (key, value) an external controller must set on the workload for it to be considered ready,
+    /// on top of pods readiness
+    fn readiness_annotation(&self) -> Option<(String, String)> {
+        let settings = self.advanced_settings();
+        if settings.deployment_readiness_annotation_key.is_empty() {
+            return None;
+        }
+
+        Some((
+            settings.deployment_readiness_annotation_key.clone(),
+            settings.deployment_readiness_annotation_value.clone(),
+        ))
+    }
 
     fn as_deployment_action(&self) -> &dyn DeploymentAction;
 }
@@ -436,7 +906,54 @@ where
         &self.advanced_settings
     }
 
+    fn namespace(&self, environment: &Environment) -> String {
+        self.namespace(environment)
+    }
+
     fn as_deployment_action(&self) -> &dyn DeploymentAction {
         self
     }
 }
+
+impl<T: CloudProvider> HealthCheck for Application<T> {
+    // Without an explicit port, the endpoint is polled on the default port of the application
+    fn readiness_probe(&self) -> Option<ReadinessProbe> {
+        let settings = &self.advanced_settings;
+        if settings.deployment_readiness_check_path.is_empty() {
+            return None;
+        }
+
+        let port = match settings.deployment_readiness_check_port {
+            0 => self.ports.iter().find_or_first(|p| p.is_default)?.port,
+            port => port,
+        };
+
+        Some(ReadinessProbe {
+            path: settings.deployment_readiness_check_path.clone(),
+            port,
+            expected_status: settings.deployment_readiness_check_expected_status,
+            timeout: Duration::from_secs(settings.deployment_readiness_check_timeout_sec as u64),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::application::validate_instances;
+
+    #[test]
+    fn test_validate_instances() {
+        assert!(validate_instances("my-app", 1, 1).is_ok());
+        assert!(validate_instances("my-app", 0, 2).is_ok());
+        assert!(validate_instances("my-app", 2, 4).is_ok());
+
+        assert_eq!(
+            validate_instances("my-app", 0, 0),
+            Err("my-app max_instances must be greater than 0".to_string())
+        );
+        assert_eq!(
+            validate_instances("my-app", 3, 2),
+            Err("my-app min_instances (3) must be less or equal to max_instances (2)".to_string())
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use crate::cloud_provider::service::{
-    check_service_version, default_tera_context, get_tfstate_name, get_tfstate_suffix, Service,
+    check_service_version, default_tera_context, get_tfstate_name, get_tfstate_suffix, get_tfstate_workspace, Service,
     ServiceVersionCheckResult,
 };
 use crate::cloud_provider::{service, DeploymentTarget};
@@ -8,7 +8,9 @@ use crate::events::{EventDetails, Stage};
 use crate::models::aws::database_utils::{
     get_managed_mongodb_version, get_managed_mysql_version, get_managed_postgres_version, get_managed_redis_version,
 };
-use crate::models::database::{Container, Database, DatabaseType, Managed, MongoDB, MySQL, PostgresSQL, Redis};
+use crate::models::database::{
+    insert_backup_policy, Container, Database, DatabaseType, Managed, MongoDB, MySQL, PostgresSQL, Redis,
+};
 
 use crate::io_models::database::DatabaseOptions;
 use crate::models::types::{ToTeraContext, AWS};
@@ -263,10 +265,9 @@ where
         context.insert("kubeconfig_path", &kube_config_file_path);
         context.insert("namespace", environment.namespace());
 
-        let version = self
-            .get_version_aws_managed(event_details.clone())?
-            .matched_version()
-            .to_string();
+        let version_check = self.get_version_aws_managed(event_details.clone())?;
+        version_check.log_mismatch(kubernetes.logger(), event_details.clone());
+        let version = version_check.matched_version().to_string();
         context.insert("version", &version);
 
         // Specific to mysql
@@ -337,6 +338,7 @@ where
         context.insert("database_db_name", self.name());
         context.insert("database_login", options.login.as_str());
         context.insert("database_password", options.password.as_str());
+        context.insert("database_generate_credentials", &options.generated_credentials);
         context.insert("database_port", &self.private_port);
         context.insert("database_disk_size_in_gib", &options.disk_size_in_gib);
         context.insert("database_instance_type", &self.database_instance_type);
@@ -348,9 +350,11 @@ where
         context.insert("database_id", &self.id());
         context.insert("tfstate_suffix_name", &get_tfstate_suffix(self));
         context.insert("tfstate_name", &get_tfstate_name(self));
+        context.insert("tfstate_workspace", &get_tfstate_workspace(self));
         context.insert("skip_final_snapshot", &false);
         context.insert("final_snapshot_name", &format!("qovery-{}-final-snap", self.id));
         context.insert("delete_automated_backups", &target.kubernetes.context().is_test_cluster());
+        insert_backup_policy(&mut context, options);
         context.insert("publicly_accessible", &options.publicly_accessible);
 
         context.insert(
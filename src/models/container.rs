@@ -1,18 +1,24 @@
 use crate::build_platform::Build;
-use crate::cloud_provider::models::{EnvironmentVariable, Storage, StorageDataTemplate};
-use crate::cloud_provider::service::{Action, Service, ServiceType};
+use crate::cloud_provider::models::{
+    environment_variables_checksum, EnvironmentVariable, Storage, StorageDataTemplate,
+};
+use crate::cloud_provider::service::{Action, HealthCheck, ReadinessProbe, Service, ServiceType};
 use crate::cloud_provider::DeploymentTarget;
+use crate::container_registry::ContainerRegistryInfo;
 use crate::deployment_action::DeploymentAction;
 use crate::events::{EventDetails, Stage, Transmitter};
-use crate::io_models::application::Port;
+use crate::io_models::application::{validate_node_selector, PolicyExemption, Port, Toleration};
 use crate::io_models::container::{ContainerAdvancedSettings, Registry};
 use crate::io_models::context::Context;
+use crate::models::application::validate_instances;
 use crate::models::types::{CloudProvider, ToTeraContext};
 use crate::string::cut;
 use crate::utilities::to_short_id;
 use itertools::Itertools;
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
+use std::time::Duration;
 use uuid::Uuid;
 
 #[derive(thiserror::Error, Debug)]
@@ -46,6 +52,8 @@ pub struct Container<T: CloudProvider> {
     pub(super) _extra_settings: T::AppExtraSettings,
     pub(super) workspace_directory: String,
     pub(super) lib_root_directory: String,
+    // given with the deployment request, not kept from one deployment to the next
+    pub(super) release_tag: Option<String>,
 }
 
 pub const QOVERY_MIRROR_REPOSITORY_NAME: &str = "qovery-mirror";
@@ -75,11 +83,7 @@ impl<T: CloudProvider> Container<T> {
         extra_settings: T::AppExtraSettings,
         mk_event_details: impl Fn(Transmitter) -> EventDetails,
     ) -> Result<Self, ContainerError> {
-        if min_instances > max_instances {
-            return Err(ContainerError::InvalidConfig(
-                "min_instances must be less or equal to max_instances".to_string(),
-            ));
-        }
+        validate_instances(&name, min_instances, max_instances).map_err(ContainerError::InvalidConfig)?;
 
         if min_instances == 0 {
             return Err(ContainerError::InvalidConfig(
@@ -111,6 +115,42 @@ impl<T: CloudProvider> Container<T> {
             ));
         }
 
+        if !advanced_settings.deployment_pre_deploy_job_command.is_empty()
+            && advanced_settings.deployment_pre_deploy_job_timeout_sec == 0
+        {
+            return Err(ContainerError::InvalidConfig(
+                "pre-deploy job timeout must be greater than 0".to_string(),
+            ));
+        }
+
+        validate_node_selector(&advanced_settings.deployment_node_selector).map_err(ContainerError::InvalidConfig)?;
+        for toleration in &advanced_settings.deployment_tolerations {
+            toleration.validate().map_err(ContainerError::InvalidConfig)?;
+        }
+
+        let readiness_check_path = &advanced_settings.deployment_readiness_check_path;
+        if !readiness_check_path.is_empty() && !readiness_check_path.starts_with('/') {
+            return Err(ContainerError::InvalidConfig(format!(
+                "readiness check path must start with `/`, got `{}`",
+                readiness_check_path
+            )));
+        }
+
+        let working_dir = advanced_settings.deployment_working_dir.trim();
+        if !working_dir.is_empty() && !working_dir.starts_with('/') {
+            return Err(ContainerError::InvalidConfig(format!(
+                "working directory must be an absolute path, got `{}`",
+                working_dir
+            )));
+        }
+
+        PolicyExemption::new(
+            &advanced_settings.deployment_policy_exemption_labels,
+            &advanced_settings.deployment_policy_exemption_annotations,
+            &advanced_settings.deployment_policy_exemption_reason,
+        )
+        .map_err(ContainerError::InvalidConfig)?;
+
         let workspace_directory = crate::fs::workspace_directory(
             context.workspace_root_dir(),
             context.execution_id(),
@@ -145,6 +185,7 @@ impl<T: CloudProvider> Container<T> {
             _extra_settings: extra_settings,
             workspace_directory,
             lib_root_directory: context.lib_root_dir().to_string(),
+            release_tag: context.release_tag(),
         })
     }
 
@@ -156,6 +197,98 @@ impl<T: CloudProvider> Container<T> {
         format!("container-{}", self.long_id)
     }
 
+    pub fn node_not_ready_fail_fast(&self) -> bool {
+        self.advanced_settings.deployment_node_not_ready_fail_fast
+    }
+
+    pub fn engine_readiness_check(&self) -> bool {
+        self.advanced_settings.deployment_engine_readiness_check
+    }
+
+    pub fn scheduling_gate_max_wait(&self) -> Duration {
+        Duration::from_secs(self.advanced_settings.deployment_scheduling_gate_max_wait_sec as u64)
+    }
+
+    pub fn release_tag(&self) -> Option<String> {
+        self.release_tag.clone()
+    }
+
+    // Selector of the placeholder pods kept on standby for the container, None when there are none
+    pub fn standby_selector(&self) -> Option<String> {
+        match self.advanced_settings.deployment_standby_replicas {
+            0 => None,
+            _ => Some(format!("qovery.com/standby-of={}", self.long_id)),
+        }
+    }
+
+    pub fn pause_suspend_hpa(&self) -> bool {
+        self.advanced_settings.deployment_pause_suspend_hpa
+    }
+
+    // None when no pre-deploy job is configured
+    pub fn pre_deploy_job_command(&self) -> Option<Vec<String>> {
+        match self.advanced_settings.deployment_pre_deploy_job_command.is_empty() {
+            true => None,
+            false => Some(self.advanced_settings.deployment_pre_deploy_job_command.clone()),
+        }
+    }
+
+    // Without an explicit run key, the job runs once per image
+    pub fn pre_deploy_job_run_key(&self) -> String {
+        match self.advanced_settings.deployment_pre_deploy_job_run_key.trim() {
+            "" => self.image_with_tag(),
+            run_key => run_key.to_string(),
+        }
+    }
+
+    pub fn node_selector(&self) -> &BTreeMap<String, String> {
+        &self.advanced_settings.deployment_node_selector
+    }
+
+    pub fn tolerations(&self) -> &[Toleration] {
+        &self.advanced_settings.deployment_tolerations
+    }
+
+    pub fn pre_deploy_job_timeout(&self) -> Duration {
+        Duration::from_secs(self.advanced_settings.deployment_pre_deploy_job_timeout_sec as u64)
+    }
+
+    // None to run as the user set by the image
+    pub fn container_user(&self) -> Option<u32> {
+        self.advanced_settings.deployment_container_user
+    }
+
+    // None to run from the working directory set by the image, the path is validated in `new()`
+    pub fn working_dir(&self) -> Option<String> {
+        match self.advanced_settings.deployment_working_dir.trim() {
+            "" => None,
+            working_dir => Some(working_dir.to_string()),
+        }
+    }
+
+    // None when nothing is exempted, the settings themselves are validated in `new()`
+    pub fn policy_exemption(&self) -> Option<PolicyExemption> {
+        PolicyExemption::new(
+            &self.advanced_settings.deployment_policy_exemption_labels,
+            &self.advanced_settings.deployment_policy_exemption_annotations,
+            &self.advanced_settings.deployment_policy_exemption_reason,
+        )
+        .ok()
+        .flatten()
+    }
+
+    pub fn resources_requests(&self) -> (u32, u32) {
+        (self.cpu_request_in_mili, self.ram_request_in_mib)
+    }
+
+    pub fn combined_change_threshold_percent(&self) -> u32 {
+        self.advanced_settings.deployment_combined_change_threshold_percent
+    }
+
+    pub fn reject_combined_changes(&self) -> bool {
+        self.advanced_settings.deployment_reject_combined_changes
+    }
+
     pub fn helm_chart_dir(&self) -> String {
         format!("{}/common/charts/q-container", self.lib_root_directory)
     }
@@ -195,12 +328,7 @@ impl<T: CloudProvider> Container<T> {
                 name: self.kube_service_name(),
                 user_unsafe_name: self.name.clone(),
                 // FIXME: We mirror images to cluster private registry
-                image_full: format!(
-                    "{}/{}:{}",
-                    registry_info.endpoint.host_str().unwrap_or_default(),
-                    (registry_info.get_image_name)(QOVERY_MIRROR_REPOSITORY_NAME),
-                    self.tag_for_mirror()
-                ),
+                image_full: self.mirrored_image_full(registry_info),
                 image_tag: self.tag_for_mirror(),
                 command_args: self.command_args.clone(),
                 entrypoint: self.entrypoint.clone(),
@@ -214,6 +342,9 @@ impl<T: CloudProvider> Container<T> {
                 default_port: self.ports.iter().find_or_first(|p| p.is_default).cloned(),
                 storages: vec![],
                 advanced_settings: self.advanced_settings.clone(),
+                policy_exemption: self.policy_exemption(),
+                container_user: self.container_user(),
+                working_dir: self.working_dir(),
             },
             registry: registry_info
                 .registry_docker_json_config
@@ -223,6 +354,11 @@ impl<T: CloudProvider> Container<T> {
                     docker_json_config: docker_json.to_string(),
                 }),
             environment_variables: self.environment_variables.clone(),
+            environment_variables_checksum: environment_variables_checksum(
+                self.environment_variables
+                    .iter()
+                    .map(|ev| (ev.key.as_str(), ev.value.as_str())),
+            ),
             resource_expiration_in_seconds: Some(kubernetes.advanced_settings().pleco_resources_ttl),
         };
 
@@ -263,6 +399,20 @@ impl<T: CloudProvider> Container<T> {
         cut(format!("{}.{}.{}", self.image.replace('/', "."), self.tag, self.long_id), 128)
     }
 
+    // Image pulled by the pods, mirrored into the cluster registry
+    pub fn mirrored_image_full(&self, registry_info: &ContainerRegistryInfo) -> String {
+        format!(
+            "{}/{}:{}",
+            registry_info.endpoint.host_str().unwrap_or_default(),
+            (registry_info.get_image_name)(QOVERY_MIRROR_REPOSITORY_NAME),
+            self.tag_for_mirror()
+        )
+    }
+
+    pub fn environment_variables(&self) -> &[EnvironmentVariable] {
+        &self.environment_variables
+    }
+
     pub fn selector(&self) -> String {
         format!("qovery.com/service-id={}", self.long_id)
     }
@@ -305,6 +455,12 @@ impl<T: CloudProvider> Service for Container<T> {
         Some(self.selector())
     }
 
+    fn selectors(&self) -> Vec<String> {
+        let mut selectors = vec![self.selector()];
+        selectors.extend(self.standby_selector());
+        selectors
+    }
+
     fn as_service(&self) -> &dyn Service {
         self
     }
@@ -373,6 +529,28 @@ where
     }
 }
 
+impl<T: CloudProvider> HealthCheck for Container<T> {
+    // Without an explicit port, the endpoint is polled on the default port of the container
+    fn readiness_probe(&self) -> Option<ReadinessProbe> {
+        let settings = &self.advanced_settings;
+        if settings.deployment_readiness_check_path.is_empty() {
+            return None;
+        }
+
+        let port = match settings.deployment_readiness_check_port {
+            0 => self.ports.iter().find_or_first(|p| p.is_default)?.port,
+            port => port,
+        };
+
+        Some(ReadinessProbe {
+            path: settings.deployment_readiness_check_path.clone(),
+            port,
+            expected_status: settings.deployment_readiness_check_expected_status,
+            timeout: Duration::from_secs(settings.deployment_readiness_check_timeout_sec as u64),
+        })
+    }
+}
+
 #[derive(Serialize, Debug, Clone)]
 pub(super) struct ClusterTeraContext {
     pub(super) long_id: Uuid,
@@ -401,6 +579,9 @@ pub(super) struct ServiceTeraContext {
     pub(super) default_port: Option<Port>,
     pub(super) storages: Vec<StorageDataTemplate>,
     pub(super) advanced_settings: ContainerAdvancedSettings,
+    pub(super) policy_exemption: Option<PolicyExemption>,
+    pub(super) container_user: Option<u32>,
+    pub(super) working_dir: Option<String>,
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -420,5 +601,6 @@ pub(super) struct ContainerTeraContext {
     pub(super) service: ServiceTeraContext,
     pub(super) registry: Option<RegistryTeraContext>,
     pub(super) environment_variables: Vec<EnvironmentVariable>,
+    pub(super) environment_variables_checksum: String,
     pub(super) resource_expiration_in_seconds: Option<i32>,
 }
@@ -1,14 +1,16 @@
 use crate::build_platform::Build;
+use crate::cloud_provider::environment::internal_service_fqdn;
 use crate::cloud_provider::service::{
     check_service_version, default_tera_context, Action, Service, ServiceType, ServiceVersionCheckResult,
 };
 use crate::cloud_provider::utilities::managed_db_name_sanitizer;
 use crate::cloud_provider::{service, DeploymentTarget};
+use crate::cmd::kubectl::ScalingKind;
 use crate::deployment_action::DeploymentAction;
 use crate::errors::EngineError;
-use crate::events::{EnvironmentStep, EventDetails, Stage, Transmitter};
+use crate::events::{EngineEvent, EnvironmentStep, EventDetails, EventMessage, Stage, Transmitter};
 use crate::io_models::context::Context;
-use crate::io_models::database::DatabaseOptions;
+use crate::io_models::database::{DatabaseOptions, InitScript};
 use crate::models::database_utils::{
     get_self_hosted_mongodb_version, get_self_hosted_mysql_version, get_self_hosted_postgres_version,
     get_self_hosted_redis_version,
@@ -94,6 +96,8 @@ pub struct Database<C: CloudProvider, M: DatabaseMode, T: DatabaseType<C, M>> {
     pub(crate) publicly_accessible: bool,
     pub(crate) private_port: u16,
     pub(crate) options: T::DatabaseOptions,
+    pub(crate) namespace_override: Option<String>,
+    pub(crate) allow_version_downgrade: bool,
     pub(crate) workspace_directory: String,
     pub(crate) lib_root_directory: String,
 }
@@ -114,6 +118,7 @@ impl<C: CloudProvider, M: DatabaseMode, T: DatabaseType<C, M>> Database<C, M, T>
         publicly_accessible: bool,
         private_port: u16,
         options: T::DatabaseOptions,
+        namespace_override: Option<String>,
         mk_event_details: impl Fn(Transmitter) -> EventDetails,
     ) -> Result<Self, DatabaseError> {
         // TODO: Implement domain constraint logic
@@ -144,6 +149,8 @@ impl<C: CloudProvider, M: DatabaseMode, T: DatabaseType<C, M>> Database<C, M, T>
             publicly_accessible,
             private_port,
             options,
+            namespace_override,
+            allow_version_downgrade: context.allow_database_version_downgrade(),
             workspace_directory,
             lib_root_directory: context.lib_root_dir().to_string(),
         })
@@ -160,12 +167,42 @@ impl<C: CloudProvider, M: DatabaseMode, T: DatabaseType<C, M>> Database<C, M, T>
     pub(super) fn fqdn(&self, target: &DeploymentTarget, fqdn: &str) -> String {
         match &self.publicly_accessible {
             true => fqdn.to_string(),
-            false => match M::is_managed() {
-                true => format!("{}-dns.{}.svc.cluster.local", self.id(), target.environment.namespace()),
-                false => format!("{}.{}.svc.cluster.local", self.sanitized_name(), target.environment.namespace()),
-            },
+            false => self.internal_fqdn(&target.environment.service_namespace(&self.long_id)),
         }
     }
+
+    fn internal_fqdn(&self, namespace: &str) -> String {
+        match M::is_managed() {
+            true => internal_service_fqdn(&format!("{}-dns", self.id()), namespace),
+            false => internal_service_fqdn(&self.sanitized_name(), namespace),
+        }
+    }
+
+    /// Internal endpoint spreading the connections across the read-only replicas, if any
+    fn read_replica_fqdn(&self, namespace: &str, read_replicas: u32) -> Option<String> {
+        if M::is_managed() || read_replicas == 0 {
+            return None;
+        }
+
+        let service_suffix = match T::db_type() {
+            service::DatabaseType::PostgreSQL => "read",
+            service::DatabaseType::MySQL => "slave",
+            service::DatabaseType::MongoDB | service::DatabaseType::Redis => return None,
+        };
+        Some(internal_service_fqdn(
+            &format!("{}-{}", self.sanitized_name(), service_suffix),
+            namespace,
+        ))
+    }
+
+    /// Internal endpoint of the failover proxy, always routing the connections to the current primary
+    fn failover_proxy_fqdn(&self, namespace: &str, failover_proxy: bool, read_replicas: u32) -> Option<String> {
+        if M::is_managed() || !failover_proxy || read_replicas == 0 {
+            return None;
+        }
+
+        Some(internal_service_fqdn(&format!("{}-proxy", self.sanitized_name()), namespace))
+    }
 }
 
 impl<C: CloudProvider, M: DatabaseMode, T: DatabaseType<C, M>> Service for Database<C, M, T> {
@@ -208,6 +245,10 @@ impl<C: CloudProvider, M: DatabaseMode, T: DatabaseType<C, M>> Service for Datab
         Some(self.selector())
     }
 
+    fn namespace_override(&self) -> Option<String> {
+        self.namespace_override.clone()
+    }
+
     fn as_service(&self) -> &dyn Service {
         self
     }
@@ -257,9 +298,12 @@ impl<C: CloudProvider, T: DatabaseType<C, Container>> Database<C, Container, T>
         // we need the kubernetes config file to store tfstates file in kube secrets
         let kube_config_file_path = kubernetes.get_kubeconfig_file_path()?;
         context.insert("kubeconfig_path", &kube_config_file_path);
-        context.insert("namespace", environment.namespace());
+        let service_namespace = environment.service_namespace(&self.long_id);
+        context.insert("namespace", &service_namespace);
 
-        let version = self.get_version(event_details)?.matched_version().to_string();
+        let version_check = self.get_version(event_details.clone())?;
+        version_check.log_mismatch(kubernetes.logger(), event_details.clone());
+        let version = version_check.matched_version().to_string();
         context.insert("version", &version);
 
         for (k, v) in kubernetes.cloud_provider().tera_context_environment_variables() {
@@ -285,6 +329,33 @@ impl<C: CloudProvider, T: DatabaseType<C, Container>> Database<C, Container, T>
         context.insert("database_fqdn", &options.host.as_str());
         context.insert("database_id", &self.id());
         context.insert("publicly_accessible", &self.publicly_accessible);
+        context.insert("read_replicas", &options.read_replicas);
+        if let Some(read_fqdn) = self.read_replica_fqdn(&service_namespace, options.read_replicas) {
+            context.insert("database_read_fqdn", &read_fqdn);
+        }
+        let failover_proxy_fqdn =
+            self.failover_proxy_fqdn(&service_namespace, options.failover_proxy, options.read_replicas);
+        context.insert("failover_proxy_enabled", &failover_proxy_fqdn.is_some());
+        if let Some(proxy_fqdn) = failover_proxy_fqdn {
+            context.insert("database_proxy_fqdn", &proxy_fqdn);
+        }
+
+        // the pods of the database are spread across zones on a best effort basis. Requiring it is opt-in,
+        // as pods would stay pending when no other zone has room for them
+        let zone_anti_affinity = kubernetes.zones_count() > 1;
+        let zone_anti_affinity_required = kubernetes.advanced_settings().database_zone_anti_affinity_required;
+        if zone_anti_affinity_required && !zone_anti_affinity {
+            kubernetes.logger().log(EngineEvent::Warning(
+                event_details,
+                EventMessage::new_from_safe(format!(
+                    "Zone anti-affinity is required for database `{}` but cluster `{}` spans a single zone, its pods can't be spread across zones",
+                    self.name,
+                    kubernetes.name()
+                )),
+            ));
+        }
+        context.insert("zone_anti_affinity", &zone_anti_affinity);
+        context.insert("zone_anti_affinity_required", &zone_anti_affinity_required);
 
         context.insert(
             "resource_expiration_in_seconds",
@@ -326,6 +397,15 @@ impl<C: CloudProvider, T: DatabaseType<C, Managed>> Database<C, Managed, T> {
     }
 }
 
+/// Backup settings of managed databases, the templates keep their provider defaults without a policy.
+/// The daily schedule is applied through the backup window, which starts at the scheduled time unless given
+pub(super) fn insert_backup_policy(context: &mut TeraContext, options: &DatabaseOptions) {
+    if let Some(backup_policy) = &options.backup_policy {
+        context.insert("backup_retention_days", &backup_policy.retention_days);
+        context.insert("backup_window", &backup_policy.backup_window());
+    }
+}
+
 pub trait DatabaseService: Service + DeploymentAction + ToTeraContext {
     fn is_managed_service(&self) -> bool;
 
@@ -333,10 +413,22 @@ pub trait DatabaseService: Service + DeploymentAction + ToTeraContext {
 
     fn version(&self) -> String;
 
+    /// Endpoint of the database, reachable from within the cluster
+    fn internal_fqdn(&self, namespace: &str) -> String;
+
+    /// Scripts to run, in order, once the database is created for the first time
+    fn init_scripts(&self) -> Vec<InitScript>;
+
+    /// Kind of the workload running a database deployed in the cluster, scaled down when it is paused
+    fn scaling_kind(&self) -> ScalingKind {
+        ScalingKind::Statefulset
+    }
+
     fn as_deployment_action(&self) -> &dyn DeploymentAction;
 }
 
-impl<C: CloudProvider, M: DatabaseMode, T: DatabaseType<C, M>> DatabaseService for Database<C, M, T>
+impl<C: CloudProvider, M: DatabaseMode, T: DatabaseType<C, M, DatabaseOptions = DatabaseOptions>> DatabaseService
+    for Database<C, M, T>
 where
     Database<C, M, T>: Service + DeploymentAction + ToTeraContext,
 {
@@ -352,6 +444,14 @@ where
         self.version.to_string()
     }
 
+    fn internal_fqdn(&self, namespace: &str) -> String {
+        self.internal_fqdn(namespace)
+    }
+
+    fn init_scripts(&self) -> Vec<InitScript> {
+        self.options.init_scripts.clone()
+    }
+
     fn as_deployment_action(&self) -> &dyn DeploymentAction {
         self
     }
@@ -23,6 +23,17 @@ pub enum RouterError {
 pub struct RouterAdvancedSettings {
     pub custom_domain_check_enabled: bool,
     pub whitelist_source_range: String,
+    // None to keep the default qovery nginx controller
+    pub ingress_class_name: Option<String>,
+    // None when custom domains should not get a certificate
+    pub custom_domain_tls: Option<RouterTlsSettings>,
+}
+
+/// cert-manager certificate issued for the router custom domains
+pub struct RouterTlsSettings {
+    // ClusterIssuer name, None to use a Let's Encrypt issuer dedicated to the router
+    pub cluster_issuer: Option<String>,
+    pub wait_ready: bool,
 }
 
 pub struct Router<T: CloudProvider> {
@@ -211,9 +222,16 @@ impl<T: CloudProvider> Router<T> {
             false => "https://acme-v02.api.letsencrypt.org/directory",
         };
         context.insert("spec_acme_server", lets_encrypt_url);
+        let custom_domain_tls = self.advanced_settings.custom_domain_tls.as_ref();
+        context.insert("custom_domain_tls_enabled", &custom_domain_tls.is_some());
+        context.insert(
+            "custom_domain_tls_cluster_issuer",
+            &custom_domain_tls.and_then(|tls| tls.cluster_issuer.as_ref()),
+        );
 
         // Nginx
         context.insert("sticky_sessions_enabled", &sticky_session_enabled);
+        context.insert("ingress_class_name", &self.advanced_settings.ingress_class_name);
 
         // ingress advanced settings
         // 1 app == 1 ingress, we filter only on the app to retrieve advanced settings
@@ -302,6 +320,11 @@ impl<T: CloudProvider> Service for Router<T> {
 pub trait RouterService: Service + DeploymentAction + ToTeraContext {
     /// all domains (auto-generated by Qovery and user custom domains) associated to the router
     fn has_custom_domains(&self) -> bool;
+    fn custom_domains(&self) -> &[CustomDomain];
+    /// IngressClass the router ingress targets, None for the default qovery nginx controller
+    fn ingress_class_name(&self) -> Option<String>;
+    /// Name of the cert-manager Certificate of the custom domains, None if no certificate is requested
+    fn custom_domain_certificate_name(&self) -> Option<String>;
 
     fn as_deployment_action(&self) -> &dyn DeploymentAction;
 }
@@ -314,6 +337,23 @@ where
         !self.custom_domains.is_empty()
     }
 
+    fn custom_domains(&self) -> &[CustomDomain] {
+        &self.custom_domains
+    }
+
+    fn ingress_class_name(&self) -> Option<String> {
+        self.advanced_settings.ingress_class_name.clone()
+    }
+
+    fn custom_domain_certificate_name(&self) -> Option<String> {
+        if self.custom_domains.is_empty() || self.advanced_settings.custom_domain_tls.is_none() {
+            return None;
+        }
+
+        // created by cert-manager after the ingress tls secret name
+        Some(format!("router-tls-{}", self.id))
+    }
+
     fn as_deployment_action(&self) -> &dyn DeploymentAction {
         self
     }
@@ -39,6 +39,10 @@ impl ToTeraContext for Application<SCW> {
             }
         };
         context.insert("cpu_burst", &cpu_limits.cpu_limit);
+        context.insert(
+            "log_shipping_image",
+            &self.log_shipping_mirrored_image(target.container_registry.registry_info()),
+        );
 
         let storage = self
             .storage
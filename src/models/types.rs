@@ -1,4 +1,5 @@
 use serde_derive::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Write;
 use std::str::FromStr;
@@ -65,6 +66,82 @@ impl VersionsNumber {
 
         test
     }
+
+    /// Compare major and minor versions numerically, as a string comparison would put 1.9 after 1.27.
+    /// Missing or unparsable parts count as 0
+    pub fn is_at_least_major_minor(&self, other: &VersionsNumber) -> bool {
+        let to_numbers = |version: &VersionsNumber| -> (u32, u32) {
+            (
+                version.major.parse::<u32>().unwrap_or(0),
+                version
+                    .minor
+                    .as_ref()
+                    .and_then(|minor| minor.parse::<u32>().ok())
+                    .unwrap_or(0),
+            )
+        };
+
+        to_numbers(self) >= to_numbers(other)
+    }
+
+    /// Compare versions as semver does, numerically part by part with a pre-release coming before its release.
+    /// Missing parts count as 0, so 13 and 13.0 have the same precedence. The suffix is not taken into account
+    pub fn cmp_precedence(&self, other: &VersionsNumber) -> Ordering {
+        let parts = |version: &VersionsNumber| -> Vec<(u64, Option<String>)> {
+            [Some(&version.major), version.minor.as_ref(), version.patch.as_ref()]
+                .iter()
+                .map(|part| version_part(part.map(|part| part.as_str())))
+                .collect()
+        };
+
+        for ((number, pre_release), (other_number, other_pre_release)) in parts(self).into_iter().zip(parts(other)) {
+            let ordering = number
+                .cmp(&other_number)
+                .then_with(|| match (pre_release, other_pre_release) {
+                    (None, None) => Ordering::Equal,
+                    (None, Some(_)) => Ordering::Greater,
+                    (Some(_), None) => Ordering::Less,
+                    (Some(pre_release), Some(other_pre_release)) => pre_release.cmp(&other_pre_release),
+                });
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+
+        Ordering::Equal
+    }
+}
+
+// Numeric value of a version part and its pre-release, if any (i.e: `3-rc1`).
+// Missing and non numeric parts (i.e: `x` of `6.x`) count as 0
+fn version_part(part: Option<&str>) -> (u64, Option<String>) {
+    let part = part.unwrap_or("0");
+    let (number, pre_release) = match part.split_once('-') {
+        Some((number, pre_release)) => (number, Some(pre_release.to_string())),
+        None => (part, None),
+    };
+
+    (number.parse::<u64>().unwrap_or(0), pre_release)
+}
+
+// Versions with the same precedence are ordered by their parts, to stay consistent with equality
+impl Ord for VersionsNumber {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp_precedence(other).then_with(|| {
+            (&self.major, &self.minor, &self.patch, &self.suffix).cmp(&(
+                &other.major,
+                &other.minor,
+                &other.patch,
+                &other.suffix,
+            ))
+        })
+    }
+}
+
+impl PartialOrd for VersionsNumber {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 impl FromStr for VersionsNumber {
@@ -128,3 +205,30 @@ impl fmt::Display for VersionsNumber {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::models::types::VersionsNumber;
+    use std::cmp::Ordering;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_versions_number_ordering() {
+        let version = |version: &str| VersionsNumber::from_str(version).unwrap();
+
+        assert!(version("1.10") > version("1.9"));
+        assert!(version("1.9.12") < version("1.10.0"));
+        assert!(version("v14.2") > version("13.8"));
+
+        // pre-releases come before their release
+        assert!(version("1.2.0-rc1") < version("1.2.0"));
+        assert!(version("1.2.0-rc1") < version("1.2.0-rc2"));
+        assert!(version("1.2.0-rc1") > version("1.1.9"));
+
+        // missing parts count as 0
+        assert_eq!(version("13").cmp_precedence(&version("13.0.0")), Ordering::Equal);
+        assert_eq!(version("13.4").cmp_precedence(&version("13")), Ordering::Greater);
+        assert!(version("13") < version("13.0"));
+        assert_eq!(version("6.x").cmp_precedence(&version("6.0")), Ordering::Equal);
+    }
+}
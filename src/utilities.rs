@@ -1,3 +1,4 @@
+use crate::constants::KUBECTL_CONTEXT;
 use kube::config::{KubeConfigOptions, Kubeconfig, KubeconfigError};
 use std::collections::hash_map::DefaultHasher;
 use std::collections::{BTreeMap, HashMap};
@@ -78,8 +79,15 @@ pub async fn create_kube_client<P: AsRef<Path>>(
         }
     }
 
-    // build kube client: the kube config must have already the good context selected
-    let kube_config = kube::Config::from_custom_kubeconfig(kubeconfig, &KubeConfigOptions::default())
+    // build kube client: use the expected context if provided, otherwise the kube config must have already the good context selected
+    let options = KubeConfigOptions {
+        context: envs
+            .iter()
+            .find(|(k, _)| k == KUBECTL_CONTEXT)
+            .map(|(_, context)| context.to_string()),
+        ..KubeConfigOptions::default()
+    };
+    let kube_config = kube::Config::from_custom_kubeconfig(kubeconfig, &options)
         .await
         .map_err(to_err)?;
     let kube_client = kube::Client::try_from(kube_config)?;
@@ -273,6 +273,13 @@ fn postgresql_deploy_a_working_environment_and_redeploy() {
             activate_backups: false,
             publicly_accessible: false,
             mode: CONTAINER,
+            external_name_alias: None,
+            init_scripts: vec![],
+            generated_credentials: false,
+            read_replicas: 0,
+            failover_proxy: false,
+            namespace: None,
+            backup_policy: None,
         }];
         environment.applications = environment
             .applications
@@ -14,6 +14,7 @@ use qovery_engine::io_models::context::CloneForTest;
 use qovery_engine::io_models::job::{Job, JobSchedule, JobSource};
 use qovery_engine::io_models::router::{CustomDomain, Route, Router};
 use qovery_engine::io_models::Action;
+use qovery_engine::models::router::{RouterAdvancedSettings, RouterTlsSettings};
 use qovery_engine::transaction::TransactionResult;
 use qovery_engine::utilities::to_short_id;
 use retry::delay::Fibonacci;
@@ -842,7 +843,19 @@ fn aws_eks_deploy_a_working_environment_with_sticky_session() {
             .routers
             .first()
             .unwrap()
-            .to_router_domain(infra_ctx.context(), true, "0.0.0.0/0".to_string(), infra_ctx.cloud_provider())
+            .to_router_domain(
+                infra_ctx.context(),
+                RouterAdvancedSettings {
+                    custom_domain_check_enabled: true,
+                    whitelist_source_range: "0.0.0.0/0".to_string(),
+                    ingress_class_name: None,
+                    custom_domain_tls: Some(RouterTlsSettings {
+                        cluster_issuer: None,
+                        wait_ready: false,
+                    }),
+                },
+                infra_ctx.cloud_provider(),
+            )
             .unwrap();
         let environment_domain = environment
             .to_environment_domain(infra_ctx.context(), infra_ctx.cloud_provider(), infra_ctx.container_registry())
@@ -108,6 +108,7 @@ fn q_ingress_test() {
             k8s_selector: None,
             backup_resources: None,
             crds_update: None,
+            release_tag: None,
         },
         chart_installation_checker: None,
     };
@@ -152,6 +153,7 @@ fn q_container_test() {
             k8s_selector: None,
             backup_resources: None,
             crds_update: None,
+            release_tag: None,
         },
         chart_installation_checker: None,
     };
@@ -192,6 +194,7 @@ fn q_application_test() {
             k8s_selector: None,
             backup_resources: None,
             crds_update: None,
+            release_tag: None,
         },
         chart_installation_checker: None,
     };
@@ -232,6 +235,7 @@ fn q_container_psql_test() {
             k8s_selector: None,
             backup_resources: None,
             crds_update: None,
+            release_tag: None,
         },
         chart_installation_checker: None,
     };
@@ -272,6 +276,7 @@ fn q_managed_psql_test() {
             k8s_selector: None,
             backup_resources: None,
             crds_update: None,
+            release_tag: None,
         },
         chart_installation_checker: None,
     };
@@ -312,6 +317,7 @@ fn q_job_test() {
             k8s_selector: None,
             backup_resources: None,
             crds_update: None,
+            release_tag: None,
         },
         chart_installation_checker: None,
     };
@@ -0,0 +1,95 @@
+use crate::helm::{deployment_target, infra_ctx, test_environment, test_kubernetes};
+use qovery_engine::cloud_provider::kubernetes::Kubernetes;
+use qovery_engine::cmd::cluster_executor::{ClusterCall, ClusterCallKind, MockClusterExecutor};
+use qovery_engine::deployment_action::deploy_terraform::TerraformDeployment;
+use qovery_engine::deployment_action::DeploymentAction;
+use qovery_engine::events::{EnvironmentStep, Stage};
+use std::fs;
+use tempdir::TempDir;
+use tera::Context as TeraContext;
+
+// terraform files are empty, the executor does not run them
+fn terraform_deployment(test_kube: &dyn Kubernetes, tmp_dir: &TempDir) -> (TerraformDeployment, String) {
+    let common_dir = tmp_dir.path().join("common");
+    let resource_dir = tmp_dir.path().join("resource");
+    let destination_dir = tmp_dir.path().join("destination");
+    for dir in [&common_dir, &resource_dir, &destination_dir] {
+        fs::create_dir_all(dir).expect("error creating terraform dir");
+    }
+
+    let mut tera_context = TeraContext::new();
+    tera_context.insert("tfstate_name", "tfstate-default-zd8a4b2c1");
+
+    (
+        TerraformDeployment::new(
+            tera_context,
+            common_dir,
+            resource_dir,
+            destination_dir.clone(),
+            test_kube.get_event_details(Stage::Environment(EnvironmentStep::Delete)),
+            false,
+        ),
+        destination_dir.to_string_lossy().to_string(),
+    )
+}
+
+#[test]
+fn test_terraform_delete_removes_tfstate_after_destroy() {
+    // setup:
+    let tmp_dir = TempDir::new("terraform_deployment").expect("error creating temporary dir");
+    let test_kube = test_kubernetes();
+    let (terraform_deploy, destination_dir) = terraform_deployment(test_kube.as_ref(), &tmp_dir);
+    let test_env = test_environment(test_kube.as_ref());
+    let infra_ctx = infra_ctx(test_kube.as_ref());
+    let mut target = deployment_target(test_kube.as_ref(), &test_env, &infra_ctx);
+    let executor = MockClusterExecutor::new();
+    target.cluster_executor = Box::new(executor.clone());
+
+    // execute:
+    let result = terraform_deploy.on_delete(&target);
+
+    // verify:
+    assert!(result.is_ok());
+    assert_eq!(
+        executor.calls(),
+        vec![
+            ClusterCall {
+                kind: ClusterCallKind::TerraformDestroy,
+                target: destination_dir,
+                namespace: None,
+            },
+            ClusterCall {
+                kind: ClusterCallKind::KubectlDeleteSecret,
+                target: "tfstate-default-zd8a4b2c1".to_string(),
+                namespace: Some(test_env.namespace().to_string()),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_terraform_delete_keeps_tfstate_when_destroy_fails() {
+    // setup:
+    let tmp_dir = TempDir::new("terraform_deployment").expect("error creating temporary dir");
+    let test_kube = test_kubernetes();
+    let (terraform_deploy, destination_dir) = terraform_deployment(test_kube.as_ref(), &tmp_dir);
+    let test_env = test_environment(test_kube.as_ref());
+    let infra_ctx = infra_ctx(test_kube.as_ref());
+    let mut target = deployment_target(test_kube.as_ref(), &test_env, &infra_ctx);
+    let executor = MockClusterExecutor::new().with_failing_call(ClusterCallKind::TerraformDestroy);
+    target.cluster_executor = Box::new(executor.clone());
+
+    // execute:
+    let result = terraform_deploy.on_delete(&target);
+
+    // verify:
+    assert!(result.is_err());
+    assert_eq!(
+        executor.calls(),
+        vec![ClusterCall {
+            kind: ClusterCallKind::TerraformDestroy,
+            target: destination_dir,
+            namespace: None,
+        }]
+    );
+}
@@ -17,7 +17,10 @@ use qovery_engine::cloud_provider::service::Action;
 use qovery_engine::cloud_provider::DeploymentTarget;
 use qovery_engine::engine::InfrastructureContext;
 use qovery_engine::events::{EnvironmentStep, EventDetails, Stage};
-use qovery_engine::io_models::application::{AdvancedSettingsProbeType, ApplicationAdvancedSettings, Port, Protocol};
+use qovery_engine::io_models::application::{
+    AdvancedSettingsEnvVarNameValidation, AdvancedSettingsProbeType, AdvancedSettingsServiceMesh,
+    AdvancedSettingsVpaUpdateMode, ApplicationAdvancedSettings, HostAlias, Port, Protocol, WarmupRequest,
+};
 use qovery_engine::io_models::container::{ContainerAdvancedSettings, Registry};
 use qovery_engine::io_models::database::{DatabaseMode, DatabaseOptions};
 use qovery_engine::io_models::job::{JobAdvancedSettings, JobSchedule};
@@ -26,7 +29,7 @@ use qovery_engine::models::aws::{AwsAppExtraSettings, AwsRouterExtraSettings, Aw
 use qovery_engine::models::container::Container;
 use qovery_engine::models::database::{Container as ContainerDB, Database, Managed, PostgresSQL};
 use qovery_engine::models::job::{ImageSource, Job, RegistryImageSource};
-use qovery_engine::models::router::{Router, RouterAdvancedSettings};
+use qovery_engine::models::router::{Router, RouterAdvancedSettings, RouterTlsSettings};
 use qovery_engine::models::types::{VersionsNumber, AWS as AWSType};
 use qovery_engine::utilities::to_short_id;
 use std::collections::{BTreeMap, HashMap};
@@ -42,6 +45,8 @@ use uuid::Uuid;
 
 mod cert_manager;
 mod chart_testing;
+#[cfg(feature = "test-local-kube")]
+mod deployment_action;
 
 fn lib_dir() -> String {
     env::var("LIB_ROOT_DIR").expect("Missing environment variable LIB_ROOT_DIR")
@@ -111,6 +116,8 @@ fn test_kubernetes() -> Box<dyn Kubernetes> {
                 loki_log_retention_in_week: 3,
                 aws_iam_user_mapper_group_name: "my_aws_iam_user_mapper_group_name".to_string(),
                 cloud_provider_container_registry_tags: HashMap::new(),
+                cluster_listen_probe_timeout_sec: 4,
+                database_zone_anti_affinity_required: false,
             },
         )
         .unwrap(),
@@ -249,6 +256,46 @@ pub fn test_application(test_kube: &dyn Kubernetes) -> Application<AWSType> {
         ApplicationAdvancedSettings {
             deployment_delay_start_time_sec: 0,
             deployment_custom_domain_check_enabled: true,
+            deployment_dedicated_namespace: false,
+            deployment_env_var_name_validation: AdvancedSettingsEnvVarNameValidation::Kubernetes,
+            deployment_pvc_binding_fail_fast: false,
+            deployment_node_not_ready_fail_fast: false,
+            deployment_scheduling_gate_max_wait_sec: 600,
+            deployment_engine_readiness_check: false,
+            deployment_policy_exemption_labels: BTreeMap::new(),
+            deployment_policy_exemption_annotations: BTreeMap::new(),
+            deployment_policy_exemption_reason: "".to_string(),
+            deployment_standby_replicas: 0,
+            deployment_container_user: None,
+            deployment_working_dir: "".to_string(),
+            deployment_update_strategy_max_surge: "".to_string(),
+            deployment_update_strategy_max_unavailable: "".to_string(),
+            deployment_pause_suspend_hpa: true,
+            deployment_pause_graceful_drain_timeout_sec: None,
+            deployment_pre_deploy_job_command: vec![],
+            deployment_pre_deploy_job_run_key: "".to_string(),
+            deployment_pre_deploy_job_timeout_sec: 600,
+            deployment_readiness_check_path: "".to_string(),
+            deployment_readiness_check_port: 0,
+            deployment_readiness_check_expected_status: 200,
+            deployment_readiness_check_timeout_sec: 300,
+            deployment_node_selector: BTreeMap::new(),
+            deployment_tolerations: vec![],
+            deployment_readiness_annotation_key: "".to_string(),
+            deployment_readiness_annotation_value: "".to_string(),
+            deployment_warmup_requests: vec![WarmupRequest {
+                method: "GET".to_string(),
+                path: "/warmup".to_string(),
+            }],
+            deployment_warmup_timeout_sec: 45,
+            deployment_warmup_fail_on_error: false,
+            deployment_service_mesh: AdvancedSettingsServiceMesh::None,
+            deployment_service_mesh_label_namespace: false,
+            logging_sidecar_enabled: false,
+            logging_sidecar_destination: "".to_string(),
+            logging_sidecar_cpu_milli: 50,
+            logging_sidecar_ram_in_mib: 64,
+            logging_sidecar_native: false,
             build_timeout_max_sec: 2,
             network_ingress_proxy_body_size_mb: 3,
             network_ingress_cors_enable: true,
@@ -264,6 +311,17 @@ pub fn test_application(test_kube: &dyn Kubernetes) -> Application<AWSType> {
             network_ingress_proxy_read_timeout_seconds: 9,
             network_ingress_proxy_buffer_size_kb: 10,
             network_ingress_whitelist_source_range: "my_network_ingress_whitelist_source_range".to_string(),
+            network_ingress_class_name: "my_network_ingress_class_name".to_string(),
+            network_ingress_custom_domain_tls_enabled: true,
+            network_ingress_custom_domain_tls_issuer: "my_network_ingress_custom_domain_tls_issuer".to_string(),
+            network_ingress_custom_domain_tls_wait_ready: false,
+            network_host_aliases: vec![HostAlias {
+                ip: "10.0.0.1".to_string(),
+                hostnames: vec!["legacy.internal".to_string()],
+            }],
+            network_egress_proxy_url: "http://10.1.2.3:3128".to_string(),
+            network_egress_proxy_no_proxy: vec![".svc".to_string(), "10.0.0.0/8".to_string()],
+            network_egress_proxy_restrict_egress: true,
             readiness_probe_type: AdvancedSettingsProbeType::Tcp,
             readiness_probe_http_get_path: "my_useless_readiness_probe_http_get_path".to_string(),
             readiness_probe_initial_delay_seconds: 11,
@@ -279,6 +337,12 @@ pub fn test_application(test_kube: &dyn Kubernetes) -> Application<AWSType> {
             liveness_probe_success_threshold: 24,
             liveness_probe_failure_threshold: 25,
             hpa_cpu_average_utilization_percent: 31,
+            hpa_memory_average_utilization_percent: 32,
+            hpa_custom_metric_name: "my_hpa_custom_metric_name".to_string(),
+            hpa_custom_metric_target_average_value: "33".to_string(),
+            vpa_enabled: true,
+            vpa_update_mode: AdvancedSettingsVpaUpdateMode::Initial,
+            resources_overcommit_ratio_percent: 100,
         },
         AwsAppExtraSettings {},
         |transmitter| test_kube.context().get_event_details(transmitter),
@@ -312,6 +376,27 @@ pub fn test_container(test_kube: &dyn Kubernetes) -> Container<AWSType> {
         vec![test_env_var()],
         ContainerAdvancedSettings {
             deployment_custom_domain_check_enabled: true,
+            deployment_node_not_ready_fail_fast: false,
+            deployment_scheduling_gate_max_wait_sec: 600,
+            deployment_engine_readiness_check: false,
+            deployment_policy_exemption_labels: BTreeMap::new(),
+            deployment_policy_exemption_annotations: BTreeMap::new(),
+            deployment_policy_exemption_reason: "".to_string(),
+            deployment_standby_replicas: 0,
+            deployment_container_user: None,
+            deployment_working_dir: "".to_string(),
+            deployment_pause_suspend_hpa: true,
+            deployment_pre_deploy_job_command: vec![],
+            deployment_pre_deploy_job_run_key: "".to_string(),
+            deployment_pre_deploy_job_timeout_sec: 600,
+            deployment_readiness_check_path: "".to_string(),
+            deployment_readiness_check_port: 0,
+            deployment_readiness_check_expected_status: 200,
+            deployment_readiness_check_timeout_sec: 300,
+            deployment_node_selector: BTreeMap::new(),
+            deployment_tolerations: vec![],
+            deployment_combined_change_threshold_percent: 20,
+            deployment_reject_combined_changes: false,
             network_ingress_proxy_body_size_mb: 11,
             network_ingress_cors_enable: true,
             network_ingress_sticky_session_enable: false,
@@ -326,6 +411,10 @@ pub fn test_container(test_kube: &dyn Kubernetes) -> Container<AWSType> {
             network_ingress_proxy_read_timeout_seconds: 17,
             network_ingress_proxy_buffer_size_kb: 18,
             network_ingress_whitelist_source_range: "my_network_ingress_whitelist_source_range".to_string(),
+            network_ingress_class_name: "my_network_ingress_class_name".to_string(),
+            network_ingress_custom_domain_tls_enabled: true,
+            network_ingress_custom_domain_tls_issuer: "my_network_ingress_custom_domain_tls_issuer".to_string(),
+            network_ingress_custom_domain_tls_wait_ready: false,
             readiness_probe_type: AdvancedSettingsProbeType::Http,
             readiness_probe_http_get_path: "my_readiness_probe_http_get_path".to_string(),
             readiness_probe_initial_delay_seconds: 21,
@@ -375,7 +464,14 @@ pub fn test_managed_database(test_kube: &dyn Kubernetes) -> Database<AWSType, Ma
             activate_high_availability: true,
             activate_backups: true,
             publicly_accessible: true,
+            external_name_alias: None,
+            init_scripts: vec![],
+            generated_credentials: false,
+            read_replicas: 0,
+            failover_proxy: false,
+            backup_policy: None,
         },
+        None,
         |transmitter| test_kube.context().get_event_details(transmitter),
     )
     .unwrap()
@@ -408,7 +504,14 @@ pub fn test_container_database(test_kube: &dyn Kubernetes) -> Database<AWSType,
             activate_high_availability: true,
             activate_backups: true,
             publicly_accessible: true,
+            external_name_alias: None,
+            init_scripts: vec![],
+            generated_credentials: false,
+            read_replicas: 0,
+            failover_proxy: false,
+            backup_policy: None,
         },
+        None,
         |transmitter| test_kube.context().get_event_details(transmitter),
     )
     .unwrap()
@@ -427,6 +530,11 @@ pub fn test_router(test_kube: &dyn Kubernetes) -> Router<AWSType> {
         RouterAdvancedSettings {
             custom_domain_check_enabled: true,
             whitelist_source_range: "my_whitelist_source_range".to_string(),
+            ingress_class_name: None,
+            custom_domain_tls: Some(RouterTlsSettings {
+                cluster_issuer: None,
+                wait_ready: false,
+            }),
         },
         |transmitter| test_kube.context().get_event_details(transmitter),
     )
@@ -333,6 +333,13 @@ pub fn environment_3_apps_3_databases(
                 activate_backups: false,
                 publicly_accessible: false,
                 mode: CONTAINER,
+                external_name_alias: None,
+                init_scripts: vec![],
+                generated_credentials: false,
+                read_replicas: 0,
+                failover_proxy: false,
+                namespace: None,
+                backup_policy: None,
             },
             Database {
                 kind: DatabaseKind::Postgresql,
@@ -356,6 +363,13 @@ pub fn environment_3_apps_3_databases(
                 activate_backups: false,
                 publicly_accessible: false,
                 mode: CONTAINER,
+                external_name_alias: None,
+                init_scripts: vec![],
+                generated_credentials: false,
+                read_replicas: 0,
+                failover_proxy: false,
+                namespace: None,
+                backup_policy: None,
             },
             Database {
                 kind: DatabaseKind::Mongodb,
@@ -379,6 +393,13 @@ pub fn environment_3_apps_3_databases(
                 activate_backups: false,
                 publicly_accessible: false,
                 mode: CONTAINER,
+                external_name_alias: None,
+                init_scripts: vec![],
+                generated_credentials: false,
+                read_replicas: 0,
+                failover_proxy: false,
+                namespace: None,
+                backup_policy: None,
             },
         ],
     }
@@ -562,6 +583,13 @@ pub fn test_db(
         activate_backups: false,
         publicly_accessible: is_public,
         mode: database_mode.clone(),
+        external_name_alias: None,
+        init_scripts: vec![],
+        generated_credentials: false,
+        read_replicas: 0,
+        failover_proxy: false,
+        namespace: None,
+        backup_policy: None,
     };
 
     environment.databases = vec![db.clone()];
@@ -851,6 +879,13 @@ pub fn test_pause_managed_db(
         activate_backups: false,
         publicly_accessible: is_public,
         mode: database_mode.clone(),
+        external_name_alias: None,
+        init_scripts: vec![],
+        generated_credentials: false,
+        read_replicas: 0,
+        failover_proxy: false,
+        namespace: None,
+        backup_policy: None,
     };
 
     environment.databases = vec![db];
@@ -1095,6 +1130,13 @@ pub fn test_db_on_upgrade(
         activate_backups: false,
         publicly_accessible: is_public,
         mode: database_mode.clone(),
+        external_name_alias: None,
+        init_scripts: vec![],
+        generated_credentials: false,
+        read_replicas: 0,
+        failover_proxy: false,
+        namespace: None,
+        backup_policy: None,
     };
 
     environment.databases = vec![db];
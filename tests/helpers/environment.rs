@@ -147,6 +147,13 @@ pub fn environment_2_app_2_routers_1_psql(
             activate_backups: false,
             publicly_accessible: false,
             mode: CONTAINER,
+            external_name_alias: None,
+            init_scripts: vec![],
+            generated_credentials: false,
+            read_replicas: 0,
+            failover_proxy: false,
+            namespace: None,
+            backup_policy: None,
         }],
         applications: vec![
             Application {
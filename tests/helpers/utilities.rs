@@ -68,9 +68,20 @@ fn context(organization_id: Uuid, cluster_id: Uuid, ttl: u32) -> Context {
                 None => Some(ttl),
             }
         },
+        resource_expiration_action: None,
         forced_upgrade: Option::from(env::var_os("forced_upgrade").is_some()),
         disable_pleco: Some(true),
         is_first_cluster_deployment: None,
+        git_metadata: None,
+        preview_id: None,
+        deploy_without_helm: Option::from(env::var_os("deploy_without_helm").is_some()),
+        rendered_charts_directory: env::var("rendered_charts_directory").ok(),
+        adopt_existing_helm_releases: None,
+        allow_database_version_downgrade: None,
+        cleanup_orphaned_tfstate_secrets: None,
+        deployment_max_attempts: None,
+        deployment_retry_backoff_in_seconds: None,
+        release_tag: None,
     };
     let enabled_features = vec![Features::LogsHistory, Features::MetricsHistory];
 
@@ -282,6 +282,13 @@ fn postgresql_deploy_a_working_environment_and_redeploy() {
             total_ram_in_mib: 512,
             disk_size_in_gib: 10,
             mode: database_mode.clone(),
+            external_name_alias: None,
+            init_scripts: vec![],
+            generated_credentials: false,
+            read_replicas: 0,
+            failover_proxy: false,
+            namespace: None,
+            backup_policy: None,
             database_instance_type: if database_mode == MANAGED {
                 SCW_MANAGED_DATABASE_INSTANCE_TYPE
             } else {